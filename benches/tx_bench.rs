@@ -0,0 +1,218 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use rust_week_4_exercises::*;
+
+const ITERATIONS: usize = 10_000;
+// Roughly the transaction count of a full mainnet block
+const BLOCK_TX_COUNT: usize = 2_000;
+
+// Mainnet transaction f4184fc5...e9e16 from block 170, the first transfer
+// between two people (Satoshi to Hal Finney): one P2PK input, two P2PK outputs
+const MAINNET_TX_HEX: &str = "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+
+fn fixture_bytes() -> Vec<u8> {
+    hex::decode(MAINNET_TX_HEX).unwrap()
+}
+
+const MAINNET_TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+fn fixture_tx() -> LegacyTransaction {
+    let tx = TransactionReader::new(&fixture_bytes()[..])
+        .read_transaction()
+        .unwrap();
+    assert_eq!(tx.txid_hex(), MAINNET_TXID);
+    tx
+}
+
+fn bench_serialize_legacy(c: &mut Criterion) {
+    let tx = fixture_tx();
+    c.bench_function("serialize_legacy", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(black_box(&tx).serialize());
+            }
+        })
+    });
+}
+
+fn bench_deserialize_legacy(c: &mut Criterion) {
+    let raw = fixture_bytes();
+    c.bench_function("deserialize_legacy", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                let tx = TransactionReader::new(black_box(&raw[..]))
+                    .read_transaction()
+                    .unwrap();
+                black_box(tx);
+            }
+        })
+    });
+}
+
+// Same fixture as `deserialize_legacy`, but scripts borrow from `raw`
+fn bench_deserialize_borrowed(c: &mut Criterion) {
+    let raw = fixture_bytes();
+    c.bench_function("deserialize_borrowed", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(TransactionRef::parse(black_box(&raw[..])).unwrap());
+            }
+        })
+    });
+}
+
+// A block-sized batch of transactions: the mainnet fixture with the previous
+// output varied, so every txid differs
+fn block_transactions() -> Vec<LegacyTransaction> {
+    (0..BLOCK_TX_COUNT as u32)
+        .map(|i| {
+            let mut tx = fixture_tx();
+            let input = &tx.inputs()[0];
+            let outpoint = OutPoint {
+                txid: input.outpoint().txid,
+                vout: i,
+            };
+            tx.inputs_mut()[0] =
+                TxInput::new(outpoint, input.script_sig().clone(), input.sequence());
+            tx
+        })
+        .collect()
+}
+
+fn bench_parse_transactions(c: &mut Criterion) {
+    let raw: Vec<u8> = block_transactions()
+        .iter()
+        .flat_map(|tx| tx.serialize())
+        .collect();
+    c.bench_function("parse_transactions", |b| {
+        b.iter(|| block::parse_transactions(black_box(&raw), BLOCK_TX_COUNT).unwrap())
+    });
+}
+
+fn bench_summarize_transactions(c: &mut Criterion) {
+    let txs = block_transactions();
+    c.bench_function("summarize_transactions", |b| {
+        b.iter(|| block::summarize_transactions(black_box(&txs)))
+    });
+}
+
+// Same batch as `summarize_transactions`; any gain depends on the cores
+// available, so compare the two on the target machine
+#[cfg(feature = "parallel")]
+fn bench_par_summarize_transactions(c: &mut Criterion) {
+    let txs = block_transactions();
+    c.bench_function("par_summarize_transactions", |b| {
+        b.iter(|| block::par_summarize_transactions(black_box(&txs)))
+    });
+}
+
+fn bench_txid(c: &mut Criterion) {
+    let tx = fixture_tx();
+    c.bench_function("txid", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(black_box(&tx).txid());
+            }
+        })
+    });
+}
+
+fn bench_script_classify(c: &mut Criterion) {
+    let outputs: Vec<TxOutput> = [
+        [vec![0x76, 0xa9, 0x14], vec![0x11; 20], vec![0x88, 0xac]].concat(),
+        [vec![0xa9, 0x14], vec![0x22; 20], vec![0x87]].concat(),
+        [vec![0x00, 0x14], vec![0x33; 20]].concat(),
+        [vec![0x51, 0x20], vec![0x44; 32]].concat(),
+        vec![0x6a, 0x04, 0xde, 0xad, 0xbe, 0xef],
+    ]
+    .into_iter()
+    .map(|script_pubkey| TxOutput::new(Amount::from_sat(0), Script(script_pubkey)))
+    .collect();
+
+    c.bench_function("script_classify", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                for output in &outputs {
+                    black_box(black_box(output).script_type());
+                }
+            }
+        })
+    });
+}
+
+// The fixed-size header encoding against the allocating `serialize`
+fn bench_serialize_header(c: &mut Criterion) {
+    let header = BlockHeader {
+        version: 1,
+        prev_blockhash: [0; 32],
+        merkle_root: fixture_tx().txid(),
+        time: 1_231_469_665,
+        bits: 0x1d00ffff,
+        nonce: 2_573_394_689,
+    };
+    c.bench_function("serialize_header_array", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(black_box(&header).serialize_header());
+            }
+        })
+    });
+    c.bench_function("serialize_header_vec", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(black_box(&header).serialize());
+            }
+        })
+    });
+}
+
+// Cloning a 10-input transaction with 107-byte scriptSigs: the derived deep
+// copy against the `Arc`-shared wrapper
+fn bench_clone(c: &mut Criterion) {
+    let tx = LegacyTransaction::builder()
+        .add_inputs((0..10).map(|vout| {
+            let outpoint = OutPoint {
+                txid: Txid([0x11; 32]),
+                vout,
+            };
+            TxInput::new(outpoint, Script(vec![0x42; 107]), 0xFFFFFFFF)
+        }))
+        .add_output(TxOutput::new(
+            Amount::from_sat(50_000),
+            Script([vec![0x00, 0x14], vec![0x33; 20]].concat()),
+        ))
+        .build();
+    c.bench_function("clone_legacy", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(black_box(&tx).clone());
+            }
+        })
+    });
+    let shared = tx.into_arc();
+    c.bench_function("clone_arc_legacy", |b| {
+        b.iter(|| {
+            for _ in 0..ITERATIONS {
+                black_box(black_box(&shared).clone());
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_serialize_legacy,
+    bench_deserialize_legacy,
+    bench_deserialize_borrowed,
+    bench_txid,
+    bench_script_classify,
+    bench_serialize_header,
+    bench_clone,
+    bench_parse_transactions,
+    bench_summarize_transactions
+);
+#[cfg(feature = "parallel")]
+criterion_group!(parallel_benches, bench_par_summarize_transactions);
+#[cfg(feature = "parallel")]
+criterion_main!(benches, parallel_benches);
+#[cfg(not(feature = "parallel"))]
+criterion_main!(benches);
@@ -0,0 +1,141 @@
+// Payment destinations and the output scripts they pay to
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::network::Network;
+use crate::script::{OutputScriptType, Script};
+use crate::{BitcoinError, base58, bech32};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Address {
+    P2PKH([u8; 20]),
+    P2SH([u8; 20]),
+    P2WPKH([u8; 20]),
+    P2WSH([u8; 32]),
+    P2TR([u8; 32]),
+}
+
+// Network-identifying part of an encoded address
+enum Prefix {
+    Base58(u8),
+    Hrp(String),
+}
+
+impl Prefix {
+    fn belongs_to(&self, address: &Address, network: Network) -> bool {
+        match (address, self) {
+            (Address::P2PKH(_), Prefix::Base58(version)) => *version == network.p2pkh_prefix(),
+            (Address::P2SH(_), Prefix::Base58(version)) => *version == network.p2sh_prefix(),
+            (_, Prefix::Hrp(hrp)) => hrp == network.bech32_hrp(),
+            _ => false,
+        }
+    }
+}
+
+impl Address {
+    // Parse a Base58Check (P2PKH/P2SH) or Bech32/Bech32m (SegWit) address,
+    // requiring it to belong to `network`
+    pub fn from_str_for_network(s: &str, network: Network) -> Result<Address, BitcoinError> {
+        let (address, prefix) = Address::decode(s)?;
+        if prefix.belongs_to(&address, network) {
+            Ok(address)
+        } else if Network::ALL.iter().any(|&n| prefix.belongs_to(&address, n)) {
+            Err(BitcoinError::InvalidNetwork)
+        } else {
+            Err(BitcoinError::InvalidAddress)
+        }
+    }
+
+    // Parse an address of any known network. Testnet and signet share
+    // prefixes, as do testnet and regtest Base58 addresses; the first match in
+    // `Network::ALL` wins.
+    pub(crate) fn from_str_any_network(s: &str) -> Result<(Address, Network), BitcoinError> {
+        let (address, prefix) = Address::decode(s)?;
+        let network = Network::ALL
+            .into_iter()
+            .find(|&n| prefix.belongs_to(&address, n))
+            .ok_or(BitcoinError::InvalidAddress)?;
+        Ok((address, network))
+    }
+
+    // Encode for `network`; the inverse of `from_str_for_network`
+    pub fn to_string_for_network(&self, network: Network) -> String {
+        let base58 = |version: u8, hash: &[u8]| base58::encode_check(&[&[version], hash].concat());
+        let hrp = network.bech32_hrp();
+        match self {
+            Address::P2PKH(hash) => base58(network.p2pkh_prefix(), hash),
+            Address::P2SH(hash) => base58(network.p2sh_prefix(), hash),
+            Address::P2WPKH(hash) => bech32::encode_segwit(hrp, 0, hash),
+            Address::P2WSH(hash) => bech32::encode_segwit(hrp, 0, hash),
+            Address::P2TR(output_key) => bech32::encode_segwit(hrp, 1, output_key),
+        }
+    }
+
+    fn decode(s: &str) -> Result<(Address, Prefix), BitcoinError> {
+        if let Ok((hrp, version, program)) = bech32::decode_segwit(s) {
+            let address = match (version, program.len()) {
+                (0, 20) => Address::P2WPKH(program.try_into().unwrap()),
+                (0, 32) => Address::P2WSH(program.try_into().unwrap()),
+                (1, 32) => Address::P2TR(program.try_into().unwrap()),
+                _ => return Err(BitcoinError::InvalidAddress),
+            };
+            return Ok((address, Prefix::Hrp(hrp)));
+        }
+
+        let data = base58::decode_check(s).map_err(|_| BitcoinError::InvalidAddress)?;
+        let [version, hash @ ..] = data.as_slice() else {
+            return Err(BitcoinError::InvalidAddress);
+        };
+        let hash: [u8; 20] = hash.try_into().map_err(|_| BitcoinError::InvalidAddress)?;
+        let address = if Network::ALL.iter().any(|n| n.p2sh_prefix() == *version) {
+            Address::P2SH(hash)
+        } else {
+            Address::P2PKH(hash)
+        };
+        Ok((address, Prefix::Base58(*version)))
+    }
+
+    // The address a standard scriptPubKey pays to; None for scripts with no
+    // address form (P2PK, bare multisig, OP_RETURN, ...)
+    pub fn from_script(script: &Script) -> Option<Address> {
+        let bytes = script.bytes();
+        let address = match script.classify() {
+            OutputScriptType::P2PKH => Address::P2PKH(bytes[3..23].try_into().unwrap()),
+            OutputScriptType::P2SH => Address::P2SH(bytes[2..22].try_into().unwrap()),
+            OutputScriptType::P2WPKH => Address::P2WPKH(bytes[2..].try_into().unwrap()),
+            OutputScriptType::P2WSH => Address::P2WSH(bytes[2..].try_into().unwrap()),
+            OutputScriptType::P2TR => Address::P2TR(script.p2tr_output_key()?),
+            _ => return None,
+        };
+        Some(address)
+    }
+
+    // scriptPubKey of an output paying to this address
+    pub fn script_pubkey(&self) -> Script {
+        match self {
+            Address::P2PKH(hash) => Script::new_p2pkh(hash),
+            Address::P2SH(hash) => Script::new_p2sh(hash),
+            Address::P2WPKH(hash) => Script::new_p2wpkh(hash),
+            Address::P2WSH(hash) => Script::new_p2wsh(hash),
+            Address::P2TR(output_key) => Script::new_p2tr(output_key),
+        }
+    }
+}
+
+// Accepts an address of any network; use `from_str_for_network` to require one
+impl FromStr for Address {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Address::from_str_any_network(s).map(|(address, _)| address)
+    }
+}
+
+// The address carries no network, so this is the mainnet encoding; use
+// `to_string_for_network` for the others
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_for_network(Network::Mainnet))
+    }
+}
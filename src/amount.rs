@@ -0,0 +1,198 @@
+// Monetary amounts and fee rates
+
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+use crate::BitcoinError;
+
+// Digits after the decimal point of a BTC amount
+const BTC_DECIMALS: usize = 8;
+
+// An amount of bitcoin, in satoshis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Amount(pub u64);
+
+// Total bitcoin that will ever exist: 21 million BTC
+pub const MAX_MONEY: Amount = Amount::MAX;
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const ONE_SAT: Amount = Amount(1);
+    pub const ONE_BTC: Amount = Amount(100_000_000);
+    // 21 million BTC, the same as `MAX_MONEY`
+    pub const MAX: Amount = Amount(21_000_000 * 100_000_000);
+    // `TxOutput::dust_threshold` of a P2PKH output at 1 sat/vB
+    pub const DUST_LIMIT: Amount = Amount(546);
+
+    pub const fn from_sat(sat: u64) -> Self {
+        Amount(sat)
+    }
+
+    pub const fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    // Rounded to the nearest satoshi; negative, non-finite and above-`MAX`
+    // values are rejected. Prefer parsing a string where exactness matters.
+    pub fn from_btc(btc: f64) -> Result<Self, BitcoinError> {
+        let sat = (btc * Amount::ONE_BTC.0 as f64).round();
+        if !sat.is_finite() || sat < 0.0 || sat > Amount::MAX.0 as f64 {
+            return Err(BitcoinError::InvalidAmount);
+        }
+        Ok(Amount(sat as u64))
+    }
+
+    pub fn to_btc(self) -> f64 {
+        self.0 as f64 / Amount::ONE_BTC.0 as f64
+    }
+
+    // Below the fixed P2PKH dust limit; `TxOutput::is_dust` computes the
+    // threshold for a specific script and fee rate
+    pub fn is_dust(&self) -> bool {
+        *self < Amount::DUST_LIMIT
+    }
+
+    // `None` on overflow; `+` follows u64 and panics in debug builds instead
+    pub const fn checked_add(self, rhs: Amount) -> Option<Amount> {
+        match self.0.checked_add(rhs.0) {
+            Some(sat) => Some(Amount(sat)),
+            None => None,
+        }
+    }
+
+    // `None` when `rhs` exceeds `self`
+    pub const fn checked_sub(self, rhs: Amount) -> Option<Amount> {
+        match self.0.checked_sub(rhs.0) {
+            Some(sat) => Some(Amount(sat)),
+            None => None,
+        }
+    }
+
+    pub const fn saturating_add(self, rhs: Amount) -> Amount {
+        Amount(self.0.saturating_add(rhs.0))
+    }
+
+    pub const fn saturating_sub(self, rhs: Amount) -> Amount {
+        Amount(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Add for Amount {
+    type Output = Amount;
+
+    fn add(self, rhs: Amount) -> Amount {
+        Amount(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+
+    fn sub(self, rhs: Amount) -> Amount {
+        Amount(self.0 - rhs.0)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sat", self.0)
+    }
+}
+
+// A number followed by its unit, "BTC" or "sat" (also "sats"), in any case
+// and optionally separated by whitespace: "0.001 BTC", "100000 sat". BTC
+// amounts are parsed exactly, with at most eight decimal places. Amounts
+// above `MAX` are rejected.
+impl FromStr for Amount {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or(BitcoinError::InvalidAmount)?;
+        let (number, unit) = (&s[..split], s[split..].trim_start());
+
+        let sat = if unit.eq_ignore_ascii_case("btc") {
+            let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+            if whole.is_empty() || fraction.len() > BTC_DECIMALS || fraction.contains('.') {
+                return Err(BitcoinError::InvalidAmount);
+            }
+            let fraction = format!("{fraction:0<BTC_DECIMALS$}");
+            whole
+                .parse::<u64>()
+                .ok()
+                .and_then(|whole| whole.checked_mul(Amount::ONE_BTC.0))
+                .zip(fraction.parse::<u64>().ok())
+                .and_then(|(whole, fraction)| whole.checked_add(fraction))
+        } else if unit.eq_ignore_ascii_case("sat") || unit.eq_ignore_ascii_case("sats") {
+            number.parse::<u64>().ok()
+        } else {
+            None
+        };
+
+        sat.map(Amount)
+            .filter(|amount| *amount <= Amount::MAX)
+            .ok_or(BitcoinError::InvalidAmount)
+    }
+}
+
+// Confirmation target in blocks and the sat/vB rate assumed to meet it,
+// ordered by target
+const FEE_RATE_TABLE: [(u32, u64); 4] = [(1, 50), (3, 20), (6, 10), (144, 2)];
+
+// Fee rate, stored in satoshis per 1000 virtual bytes for sub-sat/vB precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FeeRate(u64);
+
+impl FeeRate {
+    pub const fn from_sat_per_vb(sat_per_vb: u64) -> Self {
+        FeeRate(sat_per_vb * 1000)
+    }
+
+    pub const fn from_sat_per_kvb(sat_per_kvb: u64) -> Self {
+        FeeRate(sat_per_kvb)
+    }
+
+    /// Conservative rate for confirming within `target_blocks`, from a fixed
+    /// table rather than live mempool data, so this is for offline use only.
+    /// A target between table entries gets the rate of the shorter one.
+    pub fn from_target_blocks(target_blocks: u32) -> Self {
+        let sat_per_vb = FEE_RATE_TABLE
+            .iter()
+            .rev()
+            .find(|(blocks, _)| *blocks <= target_blocks)
+            .unwrap_or(&FEE_RATE_TABLE[0])
+            .1;
+        FeeRate::from_sat_per_vb(sat_per_vb)
+    }
+
+    // Fee rate paid by `fee` over `vsize` virtual bytes (rounded down)
+    pub fn from_fee_and_vsize(fee: Amount, vsize: u64) -> Self {
+        if vsize == 0 {
+            return FeeRate(0);
+        }
+        FeeRate(fee.to_sat().saturating_mul(1000) / vsize)
+    }
+
+    pub const fn to_sat_per_kvb(self) -> u64 {
+        self.0
+    }
+
+    // Whole satoshis per virtual byte (rounded down)
+    pub const fn to_sat_per_vb(self) -> u64 {
+        self.0 / 1000
+    }
+
+    // Fee for a transaction of `vsize` virtual bytes (rounded up)
+    pub fn fee_for_vsize(self, vsize: u64) -> Amount {
+        Amount(self.0.saturating_mul(vsize).div_ceil(1000))
+    }
+}
+
+impl fmt::Display for FeeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:03} sat/vB", self.0 / 1000, self.0 % 1000)
+    }
+}
@@ -0,0 +1,86 @@
+// Dependency-free Base58 and Base58Check codec
+
+use crate::hash::checksum;
+use crate::{BitcoinError, ParseErrorCode};
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// Encode bytes as Base58; each leading zero byte becomes a leading '1'
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+
+    // Base-58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in &mut digits {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let mut out = String::with_capacity(zeros + digits.len());
+    out.extend(std::iter::repeat_n('1', zeros));
+    out.extend(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char));
+    out
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    let zeros = s.bytes().take_while(|&c| c == b'1').count();
+
+    // Base-256 bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    for c in s.bytes().skip(zeros) {
+        let mut carry = ALPHABET.iter().position(|&a| a == c).ok_or_else(|| {
+            BitcoinError::cli_error(
+                ParseErrorCode::InvalidAddress,
+                format!("Invalid base58 character: {}", c as char),
+            )
+        })? as u32;
+        for byte in &mut bytes {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+// Base58 of `data` followed by its four-byte sha256d checksum
+pub fn encode_check(data: &[u8]) -> String {
+    let mut payload = data.to_vec();
+    payload.extend_from_slice(&checksum(data));
+    encode(&payload)
+}
+
+// Decode Base58Check, returning the payload without its checksum
+pub fn decode_check(s: &str) -> Result<Vec<u8>, BitcoinError> {
+    let mut data = decode(s)?;
+    if data.len() < 4 {
+        return Err(BitcoinError::cli_error(
+            ParseErrorCode::InvalidAddress,
+            "Base58Check data too short",
+        ));
+    }
+    let payload_len = data.len() - 4;
+    if checksum(&data[..payload_len]) != data[payload_len..] {
+        return Err(BitcoinError::cli_error(
+            ParseErrorCode::InvalidAddress,
+            "Base58Check checksum mismatch",
+        ));
+    }
+    data.truncate(payload_len);
+    Ok(data)
+}
@@ -0,0 +1,152 @@
+// Bech32 (BIP-173) and Bech32m (BIP-350) codec for SegWit addresses
+
+use crate::{BitcoinError, ParseErrorCode};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    // Witness version 0
+    Bech32,
+    // Witness versions 1 through 16
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|c| c & 31))
+}
+
+// Regroup `data` from `from`-bit to `to`-bit values. Encoding pads the last
+// group with zeros; decoding rejects any non-zero or over-long padding.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, BitcoinError> {
+    let invalid = || {
+        BitcoinError::cli_error(
+            ParseErrorCode::InvalidAddress,
+            "Invalid bech32 data padding",
+        )
+    };
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let max = (1 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(invalid());
+    }
+    Ok(out)
+}
+
+// Encode a SegWit output program as an address with human-readable part `hrp`
+pub fn encode_segwit(hrp: &str, witness_version: u8, program: &[u8]) -> String {
+    let variant = if witness_version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    let mut data = vec![witness_version];
+    data.extend(convert_bits(program, 8, 5, true).unwrap_or_default());
+
+    let checksum_input = hrp_expand(hrp).chain(data.iter().copied()).chain([0; 6]);
+    let checksum = polymod(checksum_input) ^ variant.constant();
+
+    let mut out = String::with_capacity(hrp.len() + 1 + data.len() + 6);
+    out.push_str(hrp);
+    out.push('1');
+    out.extend(data.iter().map(|&d| CHARSET[d as usize] as char));
+    out.extend((0..6).map(|i| CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char));
+    out
+}
+
+// Decode a SegWit address into (hrp, witness version, program), enforcing the
+// checksum variant and program length rules of BIP-173 and BIP-350
+pub fn decode_segwit(address: &str) -> Result<(String, u8, Vec<u8>), BitcoinError> {
+    let invalid = |reason: &str| {
+        BitcoinError::cli_error(
+            ParseErrorCode::InvalidAddress,
+            format!("Invalid bech32 address: {reason}"),
+        )
+    };
+
+    if address.len() > 90 {
+        return Err(invalid("too long"));
+    }
+    let has_lower = address.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = address.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(invalid("mixed case"));
+    }
+    let address = address.to_ascii_lowercase();
+    let separator = address
+        .rfind('1')
+        .ok_or_else(|| invalid("missing separator"))?;
+    let (hrp, rest) = (&address[..separator], &address[separator + 1..]);
+    // The data part needs a witness version character ahead of the checksum
+    if hrp.is_empty() || rest.len() < 7 || !hrp.bytes().all(|c| (33..=126).contains(&c)) {
+        return Err(invalid("malformed"));
+    }
+
+    let data = rest
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&x| x == c).map(|p| p as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| invalid("invalid character"))?;
+    let (&witness_version, _) = data.split_first().ok_or_else(|| invalid("empty data"))?;
+    if witness_version > 16 {
+        return Err(invalid("witness version above 16"));
+    }
+
+    let expected = if witness_version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    };
+    if polymod(hrp_expand(hrp).chain(data.iter().copied())) != expected.constant() {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let program = convert_bits(&data[1..data.len() - 6], 5, 8, false)?;
+    if !(2..=40).contains(&program.len())
+        || (witness_version == 0 && program.len() != 20 && program.len() != 32)
+    {
+        return Err(invalid("bad program length"));
+    }
+    Ok((hrp.to_string(), witness_version, program))
+}
@@ -0,0 +1,340 @@
+// BIP-32 hierarchical deterministic keys and derivation paths
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::keys::{PrivateKey, PublicKey};
+use crate::secp256k1::{self, AffinePoint, U256};
+use crate::{BitcoinError, Network, base58, hash};
+
+// Child numbers at or above this are hardened
+pub const HARDENED: u32 = 0x8000_0000;
+
+// Serialized size of an extended key, before Base58Check
+const EXTENDED_KEY_SIZE: usize = 78;
+
+// A sequence of child numbers, written `m/84'/0'/0'/0/5`. Hardened numbers
+// carry the `HARDENED` bit and are marked with `'` (or `h`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DerivationPath(pub Vec<u32>);
+
+impl DerivationPath {
+    // The same path followed by `index`
+    pub fn child(&self, index: u32) -> DerivationPath {
+        let mut path = self.0.clone();
+        path.push(index);
+        DerivationPath(path)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(BitcoinError::InvalidDerivationPath);
+        }
+        parts
+            .map(|part| {
+                let (number, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(number) => (number, true),
+                    None => (part, false),
+                };
+                // Digits only, so signs and whitespace are rejected
+                if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(BitcoinError::InvalidDerivationPath);
+                }
+                let index = number
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|index| *index < HARDENED)
+                    .ok_or(BitcoinError::InvalidDerivationPath)?;
+                Ok(if hardened { index | HARDENED } else { index })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index & HARDENED != 0 {
+                write!(f, "/{}'", index & !HARDENED)?;
+            } else {
+                write!(f, "/{index}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPrivKey {
+    pub network: Network,
+    pub depth: u8,
+    // First four bytes of the parent's key identifier; zero for the master
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub private_key: PrivateKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPubKey {
+    pub network: Network,
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    pub public_key: PublicKey,
+}
+
+impl ExtendedPrivKey {
+    // Master key from a 16 to 64 byte seed
+    pub fn new_master(network: Network, seed: &[u8]) -> Result<ExtendedPrivKey, BitcoinError> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(BitcoinError::InvalidKey);
+        }
+        let i = hash::hmac_sha512(b"Bitcoin seed", seed);
+        Ok(ExtendedPrivKey {
+            network,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key: PrivateKey::new(i[..32].try_into().unwrap(), network)?,
+        })
+    }
+
+    // CKDpriv. Fails for the (probability below 2^-127) indexes that give an
+    // invalid key; BIP-32 says to skip to the next index.
+    pub fn derive_child(&self, index: u32) -> Result<ExtendedPrivKey, BitcoinError> {
+        let secret = self.private_key.secret_bytes();
+        let mut data = Vec::with_capacity(37);
+        if index & HARDENED != 0 {
+            data.push(0);
+            data.extend_from_slice(&secret);
+        } else {
+            data.extend_from_slice(&self.private_key.public_key().to_bytes());
+        }
+        data.extend_from_slice(&index.to_be_bytes());
+        let i = hash::hmac_sha512(&self.chain_code, &data);
+
+        let tweak = U256::from_be_bytes(i[..32].try_into().unwrap());
+        if tweak >= secp256k1::N {
+            return Err(BitcoinError::InvalidKey);
+        }
+        let child = tweak.add_mod(U256::from_be_bytes(&secret), &secp256k1::N);
+        Ok(ExtendedPrivKey {
+            network: self.network,
+            depth: self.depth.checked_add(1).ok_or(BitcoinError::InvalidKey)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key: PrivateKey::new(child.to_be_bytes(), self.network)?,
+        })
+    }
+
+    // Derive every step of `path` from this key, which is taken as `m`
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<ExtendedPrivKey, BitcoinError> {
+        path.0
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    pub fn to_extended_pub_key(&self) -> ExtendedPubKey {
+        ExtendedPubKey {
+            network: self.network,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            public_key: self.private_key.public_key(),
+        }
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.to_extended_pub_key().fingerprint()
+    }
+
+    pub fn encode(&self) -> [u8; EXTENDED_KEY_SIZE] {
+        let mut key = [0u8; 33];
+        key[1..].copy_from_slice(&self.private_key.secret_bytes());
+        encode(
+            self.network.xprv_version(),
+            self.depth,
+            self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key,
+        )
+    }
+
+    pub fn decode(data: &[u8]) -> Result<ExtendedPrivKey, BitcoinError> {
+        let fields = decode(data, Network::xprv_version)?;
+        let [0, secret @ ..] = fields.key else {
+            return Err(BitcoinError::InvalidKey);
+        };
+        Ok(ExtendedPrivKey {
+            network: fields.network,
+            depth: fields.depth,
+            parent_fingerprint: fields.parent_fingerprint,
+            child_number: fields.child_number,
+            chain_code: fields.chain_code,
+            private_key: PrivateKey::new(secret, fields.network)?,
+        })
+    }
+}
+
+impl ExtendedPubKey {
+    // CKDpub, which only reaches non-hardened children
+    pub fn derive_child(&self, index: u32) -> Result<ExtendedPubKey, BitcoinError> {
+        if index & HARDENED != 0 {
+            return Err(BitcoinError::HardenedDerivationFromPublicKey(index));
+        }
+        let mut data = self.public_key.to_bytes();
+        data.extend_from_slice(&index.to_be_bytes());
+        let i = hash::hmac_sha512(&self.chain_code, &data);
+
+        let tweak: [u8; 32] = i[..32].try_into().unwrap();
+        let tweak_point = secp256k1::public_key(&tweak).map_err(|_| BitcoinError::InvalidKey)?;
+        let point = tweak_point
+            .add(&self.public_key.point)
+            .ok_or(BitcoinError::InvalidKey)?;
+        Ok(ExtendedPubKey {
+            network: self.network,
+            depth: self.depth.checked_add(1).ok_or(BitcoinError::InvalidKey)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            public_key: PublicKey {
+                point,
+                compressed: true,
+            },
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<ExtendedPubKey, BitcoinError> {
+        path.0
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.public_key.pubkey_hash()[..4].try_into().unwrap()
+    }
+
+    pub fn encode(&self) -> [u8; EXTENDED_KEY_SIZE] {
+        encode(
+            self.network.xpub_version(),
+            self.depth,
+            self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &self.public_key.point.to_compressed(),
+        )
+    }
+
+    pub fn decode(data: &[u8]) -> Result<ExtendedPubKey, BitcoinError> {
+        let fields = decode(data, Network::xpub_version)?;
+        let point = AffinePoint::from_sec1(&fields.key).map_err(|_| BitcoinError::InvalidKey)?;
+        Ok(ExtendedPubKey {
+            network: fields.network,
+            depth: fields.depth,
+            parent_fingerprint: fields.parent_fingerprint,
+            child_number: fields.child_number,
+            chain_code: fields.chain_code,
+            public_key: PublicKey {
+                point,
+                compressed: true,
+            },
+        })
+    }
+}
+
+impl fmt::Display for ExtendedPrivKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base58::encode_check(&self.encode()))
+    }
+}
+
+impl fmt::Display for ExtendedPubKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base58::encode_check(&self.encode()))
+    }
+}
+
+impl FromStr for ExtendedPrivKey {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = base58::decode_check(s).map_err(|_| BitcoinError::InvalidKey)?;
+        ExtendedPrivKey::decode(&data)
+    }
+}
+
+impl FromStr for ExtendedPubKey {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = base58::decode_check(s).map_err(|_| BitcoinError::InvalidKey)?;
+        ExtendedPubKey::decode(&data)
+    }
+}
+
+// The fields shared by both kinds of extended key
+struct ExtendedKeyFields {
+    network: Network,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+    chain_code: [u8; 32],
+    key: [u8; 33],
+}
+
+fn encode(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+    chain_code: &[u8; 32],
+    key: &[u8; 33],
+) -> [u8; EXTENDED_KEY_SIZE] {
+    let mut out = [0u8; EXTENDED_KEY_SIZE];
+    out[0..4].copy_from_slice(&version);
+    out[4] = depth;
+    out[5..9].copy_from_slice(&parent_fingerprint);
+    out[9..13].copy_from_slice(&child_number.to_be_bytes());
+    out[13..45].copy_from_slice(chain_code);
+    out[45..].copy_from_slice(key);
+    out
+}
+
+// Split an encoded key, matching its version bytes against `version` for
+// each network. Testnet versions are shared, so they decode as testnet.
+fn decode(
+    data: &[u8],
+    version: fn(&Network) -> [u8; 4],
+) -> Result<ExtendedKeyFields, BitcoinError> {
+    let data: &[u8; EXTENDED_KEY_SIZE] = data.try_into().map_err(|_| BitcoinError::InvalidKey)?;
+    let network = Network::ALL
+        .into_iter()
+        .find(|network| version(network) == data[0..4])
+        .ok_or(BitcoinError::InvalidNetwork)?;
+    let fields = ExtendedKeyFields {
+        network,
+        depth: data[4],
+        parent_fingerprint: data[5..9].try_into().unwrap(),
+        child_number: u32::from_be_bytes(data[9..13].try_into().unwrap()),
+        chain_code: data[13..45].try_into().unwrap(),
+        key: data[45..].try_into().unwrap(),
+    };
+    // A master key has no parent and no child number
+    if fields.depth == 0 && (fields.parent_fingerprint != [0; 4] || fields.child_number != 0) {
+        return Err(BitcoinError::InvalidKey);
+    }
+    Ok(fields)
+}
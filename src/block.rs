@@ -0,0 +1,303 @@
+// Blocks and block headers
+
+use std::io::{Read, Write};
+
+use crate::{
+    Amount, BitcoinDeserialize, BitcoinError, BitcoinSerialize, ByteCursor, LegacyTransaction,
+    OutputScriptType, Transaction, TransactionReader, Txid, UtxoSet, display_hex,
+    encode_compact_size, hash, merkle,
+};
+
+// Initial block subsidy of 50 BTC, in satoshis
+pub const INITIAL_SUBSIDY: u64 = 50 * 100_000_000;
+
+// Number of blocks between subsidy halvings
+pub const HALVING_INTERVAL: u32 = 210_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub header: BlockHeader,
+    // The first transaction is the coinbase
+    pub transactions: Vec<Transaction>,
+}
+
+// Serialized size of a block header
+pub const HEADER_SIZE: usize = 80;
+
+impl BlockHeader {
+    // Fixed-size serialization, without the allocation of `serialize`
+    pub fn serialize_header(&self) -> [u8; HEADER_SIZE] {
+        let mut out = [0u8; HEADER_SIZE];
+        out[0..4].copy_from_slice(&self.version.to_le_bytes());
+        out[4..36].copy_from_slice(&self.prev_blockhash);
+        out[36..68].copy_from_slice(&self.merkle_root);
+        out[68..72].copy_from_slice(&self.time.to_le_bytes());
+        out[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        out[76..80].copy_from_slice(&self.nonce.to_le_bytes());
+        out
+    }
+
+    pub fn try_from_bytes(bytes: &[u8; HEADER_SIZE]) -> Result<BlockHeader, BitcoinError> {
+        let mut cursor = ByteCursor::new(bytes);
+        Ok(BlockHeader {
+            version: cursor.read_i32_le()?,
+            prev_blockhash: cursor.read_array()?,
+            merkle_root: cursor.read_array()?,
+            time: cursor.read_u32_le()?,
+            bits: cursor.read_u32_le()?,
+            nonce: cursor.read_u32_le()?,
+        })
+    }
+
+    // Double SHA256 of the header, in internal byte order
+    pub fn block_hash(&self) -> [u8; 32] {
+        hash::sha256d(&self.serialize_header())
+    }
+
+    // Block hash in the byte order shown by explorers and RPC
+    pub fn block_hash_hex(&self) -> String {
+        display_hex(&self.block_hash())
+    }
+
+    pub fn target(&self) -> Result<[u8; 32], BitcoinError> {
+        target_from_bits(self.bits)
+    }
+
+    // Check the block hash, read as a little-endian number, does not exceed
+    // the target encoded in `bits`
+    pub fn validate_pow(&self) -> Result<(), BitcoinError> {
+        let target = self.target()?;
+        let mut hash = self.block_hash();
+        hash.reverse();
+        if hash > target {
+            return Err(BitcoinError::InvalidProofOfWork);
+        }
+        Ok(())
+    }
+}
+
+// Expand the compact `bits` encoding (a base-256 exponent byte and a 23-bit
+// mantissa) into a big-endian 256-bit target. Negative, zero and overflowing
+// targets are rejected, as by Bitcoin Core.
+pub fn target_from_bits(bits: u32) -> Result<[u8; 32], BitcoinError> {
+    let exponent = (bits >> 24) as usize;
+    let mantissa = bits & 0x007F_FFFF;
+    if bits & 0x0080_0000 != 0 || mantissa == 0 {
+        return Err(BitcoinError::InvalidTarget(bits));
+    }
+
+    // The mantissa's bytes, most significant first, sit just below 256^exponent
+    let mut target = [0u8; 32];
+    for (i, byte) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+        let Some(position) = exponent.checked_sub(i + 1) else {
+            continue;
+        };
+        if position >= 32 {
+            if *byte != 0 {
+                return Err(BitcoinError::InvalidTarget(bits));
+            }
+            continue;
+        }
+        target[31 - position] = *byte;
+    }
+    if target == [0; 32] {
+        return Err(BitcoinError::InvalidTarget(bits));
+    }
+    Ok(target)
+}
+
+impl BitcoinSerialize for BlockHeader {
+    fn serialize(&self) -> Vec<u8> {
+        self.serialize_header().to_vec()
+    }
+}
+
+impl BitcoinDeserialize for BlockHeader {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        let mut bytes = [0u8; HEADER_SIZE];
+        TransactionReader::new(r).read_exact(&mut bytes)?;
+        BlockHeader::try_from_bytes(&bytes)
+    }
+}
+
+// Header, transaction count, then each transaction in its own wire format
+impl BitcoinSerialize for Block {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = self.header_bytes().to_vec();
+        out.extend(self.transactions.serialize());
+        out
+    }
+
+    // Streams each transaction rather than buffering the whole block
+    fn serialize_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        writer.write_all(&self.header_bytes())?;
+        writer.write_all(&encode_compact_size(self.transactions.len() as u64))?;
+        for tx in &self.transactions {
+            tx.serialize_to_writer(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl BitcoinDeserialize for Block {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        let header = BlockHeader::deserialize_from(r)?;
+        let mut reader = TransactionReader::new(r);
+        let count = reader.read_compact_size()?;
+        let transactions = (0..count)
+            .map(|_| reader.read_any_transaction())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Block {
+            header,
+            transactions,
+        })
+    }
+}
+
+// Parse `count` legacy transactions laid end to end, as in a block body after
+// the transaction count; trailing bytes are rejected
+pub fn parse_transactions(
+    data: &[u8],
+    count: usize,
+) -> Result<Vec<LegacyTransaction>, BitcoinError> {
+    let mut rest = data;
+    let mut reader = TransactionReader::new(&mut rest);
+    let transactions = (0..count)
+        .map(|_| reader.read_transaction())
+        .collect::<Result<Vec<_>, _>>()?;
+    if !rest.is_empty() {
+        return Err(BitcoinError::InvalidTransaction);
+    }
+    Ok(transactions)
+}
+
+// The per-transaction work of indexing a block: its txid and the type of each
+// output script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+    pub txid: Txid,
+    pub output_types: Vec<OutputScriptType>,
+}
+
+impl TransactionSummary {
+    pub fn new(tx: &LegacyTransaction) -> TransactionSummary {
+        TransactionSummary {
+            txid: Txid(tx.txid()),
+            output_types: tx
+                .outputs()
+                .iter()
+                .map(|output| output.script_type())
+                .collect(),
+        }
+    }
+}
+
+pub fn summarize_transactions(txs: &[LegacyTransaction]) -> Vec<TransactionSummary> {
+    txs.iter().map(TransactionSummary::new).collect()
+}
+
+// Same result as `summarize_transactions`, with the transactions split into
+// one contiguous chunk per available core
+#[cfg(feature = "parallel")]
+pub fn par_summarize_transactions(txs: &[LegacyTransaction]) -> Vec<TransactionSummary> {
+    let threads = std::thread::available_parallelism().map_or(1, usize::from);
+    let chunk_size = txs.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = txs
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| summarize_transactions(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+// Legacy sigops of every transaction in a block, as counted against the
+// block limit by `LegacyTransaction::check_sigops_limit`
+pub fn block_sigops_total(txs: &[LegacyTransaction]) -> u32 {
+    txs.iter()
+        .fold(0, |total, tx| total.saturating_add(tx.legacy_sigops()))
+}
+
+// Block subsidy at `height`, halving every 210,000 blocks
+pub fn block_subsidy(height: u32) -> Amount {
+    let halvings = height / HALVING_INTERVAL;
+    if halvings >= 64 {
+        return Amount::from_sat(0);
+    }
+    Amount::from_sat(INITIAL_SUBSIDY >> halvings)
+}
+
+impl Block {
+    pub fn header_bytes(&self) -> [u8; HEADER_SIZE] {
+        self.header.serialize_header()
+    }
+
+    pub fn block_hash(&self) -> [u8; 32] {
+        self.header.block_hash()
+    }
+
+    // Merkle root of the block's txids, `None` for a block with no transactions
+    pub fn compute_merkle_root(&self) -> Option<[u8; 32]> {
+        let txids: Vec<[u8; 32]> = self.transactions.iter().map(|tx| tx.txid()).collect();
+        merkle::merkle_root(&txids)
+    }
+
+    // Sum of the fees paid by every non-coinbase transaction
+    pub fn total_fees(&self, utxo_set: &UtxoSet) -> Result<Amount, BitcoinError> {
+        self.transactions
+            .iter()
+            .skip(1)
+            .try_fold(Amount::from_sat(0), |total, tx| {
+                let fee = tx.absolute_fee(utxo_set)?;
+                total.checked_add(fee).ok_or(BitcoinError::InvalidAmount)
+            })
+    }
+
+    // Total output value of the coinbase transaction
+    pub fn coinbase_claim(&self) -> Result<Amount, BitcoinError> {
+        let coinbase = self
+            .transactions
+            .first()
+            .filter(|tx| tx.is_coinbase())
+            .ok_or(BitcoinError::InvalidTransaction)?;
+
+        coinbase
+            .outputs()
+            .iter()
+            .try_fold(Amount::from_sat(0), |total, output| {
+                total.checked_add(output.value)
+            })
+            .ok_or(BitcoinError::InvalidAmount)
+    }
+
+    // Check the coinbase claims no more than the subsidy plus collected fees
+    pub fn validate_subsidy(
+        &self,
+        block_height: u32,
+        utxo_set: &UtxoSet,
+    ) -> Result<(), BitcoinError> {
+        let claim = self.coinbase_claim()?;
+        let fees = self.total_fees(utxo_set)?;
+        let allowed = block_subsidy(block_height)
+            .checked_add(fees)
+            .ok_or(BitcoinError::InvalidAmount)?;
+
+        if claim > allowed {
+            return Err(BitcoinError::InvalidAmount);
+        }
+        Ok(())
+    }
+}
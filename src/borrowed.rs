@@ -0,0 +1,190 @@
+// Zero-copy transaction parsing: scripts and witness items borrow from the
+// input buffer instead of being copied, which avoids an allocation per script
+// when scanning large blocks. `to_owned` converts to the owned types.
+
+use crate::segwit::{SEGWIT_FLAG, SEGWIT_MARKER, SegWitTransaction};
+use crate::transaction::Transaction;
+use crate::{
+    Amount, BitcoinError, ByteCursor, LegacyTransactionBuilder, LockTime, OutPoint, Script,
+    Sequence, TxInput, TxOutput,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxInputRef<'a> {
+    previous_output: OutPoint,
+    script_sig: &'a [u8],
+    sequence: Sequence,
+}
+
+impl<'a> TxInputRef<'a> {
+    pub fn outpoint(&self) -> &OutPoint {
+        &self.previous_output
+    }
+
+    pub fn script_sig(&self) -> &'a [u8] {
+        self.script_sig
+    }
+
+    pub fn sequence(&self) -> Sequence {
+        self.sequence
+    }
+
+    pub fn to_owned(&self) -> TxInput {
+        TxInput::new(
+            self.previous_output.clone(),
+            Script(self.script_sig.to_vec()),
+            self.sequence,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxOutputRef<'a> {
+    value: Amount,
+    script_pubkey: &'a [u8],
+}
+
+impl<'a> TxOutputRef<'a> {
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    pub fn script_pubkey(&self) -> &'a [u8] {
+        self.script_pubkey
+    }
+
+    pub fn to_owned(&self) -> TxOutput {
+        TxOutput::new(self.value, Script(self.script_pubkey.to_vec()))
+    }
+}
+
+// A transaction in either wire format; `witness` is `None` for the legacy
+// encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionRef<'a> {
+    version: i32,
+    inputs: Vec<TxInputRef<'a>>,
+    outputs: Vec<TxOutputRef<'a>>,
+    witness: Option<Vec<Vec<&'a [u8]>>>,
+    lock_time: LockTime,
+}
+
+impl<'a> TransactionRef<'a> {
+    // Parse a complete transaction; trailing bytes are rejected
+    pub fn parse(data: &'a [u8]) -> Result<TransactionRef<'a>, BitcoinError> {
+        let (tx, consumed) = TransactionRef::parse_prefix(data)?;
+        if consumed != data.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        Ok(tx)
+    }
+
+    // Parse one transaction from the start of `data`, returning it and the
+    // number of bytes it took, so consecutive transactions in a block can be
+    // walked without copying
+    pub fn parse_prefix(data: &'a [u8]) -> Result<(TransactionRef<'a>, usize), BitcoinError> {
+        let mut cursor = ByteCursor::new(data);
+        let version = cursor.read_i32_le()?;
+
+        let segwit = cursor.peek_u8() == Some(SEGWIT_MARKER);
+        if segwit {
+            cursor.read_u8()?;
+            if cursor.read_u8()? != SEGWIT_FLAG {
+                return Err(BitcoinError::InvalidTransaction);
+            }
+        }
+
+        let mut inputs = Vec::new();
+        for _ in 0..cursor.read_varint()? {
+            inputs.push(TxInputRef {
+                previous_output: cursor.read_outpoint()?,
+                script_sig: cursor.read_var_bytes()?,
+                sequence: Sequence(cursor.read_u32_le()?),
+            });
+        }
+        let mut outputs = Vec::new();
+        for _ in 0..cursor.read_varint()? {
+            outputs.push(TxOutputRef {
+                value: Amount::from_sat(cursor.read_u64_le()?),
+                script_pubkey: cursor.read_var_bytes()?,
+            });
+        }
+        let witness = if segwit {
+            let mut witness = Vec::with_capacity(inputs.len());
+            for _ in 0..inputs.len() {
+                let mut stack = Vec::new();
+                for _ in 0..cursor.read_varint()? {
+                    stack.push(cursor.read_var_bytes()?);
+                }
+                witness.push(stack);
+            }
+            Some(witness)
+        } else {
+            None
+        };
+        let lock_time = LockTime(cursor.read_u32_le()?);
+
+        let tx = TransactionRef {
+            version,
+            inputs,
+            outputs,
+            witness,
+            lock_time,
+        };
+        Ok((tx, cursor.position()))
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn inputs(&self) -> &[TxInputRef<'a>] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[TxOutputRef<'a>] {
+        &self.outputs
+    }
+
+    pub fn lock_time(&self) -> LockTime {
+        self.lock_time
+    }
+
+    pub fn is_segwit(&self) -> bool {
+        self.witness.is_some()
+    }
+
+    // Witness stack of input `index`; empty for legacy transactions
+    pub fn witness(&self, index: usize) -> &[&'a [u8]] {
+        self.witness
+            .as_ref()
+            .and_then(|witness| witness.get(index))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn to_owned(&self) -> Transaction {
+        let inputs = self.inputs.iter().map(TxInputRef::to_owned).collect();
+        let outputs = self.outputs.iter().map(TxOutputRef::to_owned).collect();
+        match &self.witness {
+            Some(witness) => Transaction::SegWit(SegWitTransaction {
+                version: self.version,
+                inputs,
+                outputs,
+                witness: witness
+                    .iter()
+                    .map(|stack| stack.iter().map(|item| item.to_vec()).collect())
+                    .collect(),
+                lock_time: self.lock_time,
+            }),
+            None => Transaction::Legacy(
+                LegacyTransactionBuilder {
+                    version: self.version,
+                    inputs,
+                    outputs,
+                    lock_time: self.lock_time,
+                }
+                .build(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// Coin selection: choosing which UTXOs fund a payment at a target fee rate
+
+use crate::{
+    Amount, BitcoinError, BitcoinSerialize, FeeRate, OutPoint, TxOutput, compact_size_len,
+};
+
+// Bitcoin Core's limit on branch-and-bound search steps
+const BNB_MAX_TRIES: usize = 100_000;
+
+// A spendable output and the outpoint that identifies it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub output: TxOutput,
+}
+
+impl Utxo {
+    pub fn new(outpoint: OutPoint, output: TxOutput) -> Utxo {
+        Utxo { outpoint, output }
+    }
+
+    pub fn value(&self) -> Amount {
+        self.output.value()
+    }
+
+    // Estimated size of the signed input spending this output
+    pub fn input_vbytes(&self) -> u64 {
+        self.output.expected_input_vbytes()
+    }
+
+    // Value left after paying for its own input. `None` for outputs that cost
+    // at least as much to spend as they are worth.
+    pub fn effective_value(&self, fee_rate: FeeRate) -> Option<Amount> {
+        self.value()
+            .checked_sub(fee_rate.fee_for_vsize(self.input_vbytes()))
+            .filter(|value| *value > Amount::ZERO)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionStrategy {
+    LargestFirst,
+    BranchAndBound,
+    SingleRandomDraw,
+}
+
+// Estimated weight of a signed transaction spending `inputs` to `outputs`:
+// version, lock time and counts, the outputs as serialized, and each input at
+// its expected size. Any witness program adds the marker and flag.
+pub fn estimate_weight(inputs: &[Utxo], outputs: &[TxOutput]) -> u64 {
+    let segwit = inputs
+        .iter()
+        .any(|utxo| utxo.output.script_pubkey.witness_version().is_some());
+    let overhead =
+        (8 + compact_size_len(inputs.len() as u64) + compact_size_len(outputs.len() as u64)) as u64;
+    let outputs: u64 = outputs
+        .iter()
+        .map(|output| output.serialize().len() as u64)
+        .sum();
+    let inputs: u64 = inputs.iter().map(Utxo::input_vbytes).sum();
+    (overhead + outputs + inputs) * 4 + if segwit { 2 } else { 0 }
+}
+
+pub fn estimate_vsize(inputs: &[Utxo], outputs: &[TxOutput]) -> u64 {
+    estimate_weight(inputs, outputs).div_ceil(4)
+}
+
+// Select UTXOs whose effective values cover `target`, which should already
+// include the fee for everything but the inputs. Branch-and-bound only
+// accepts a match within `cost_of_change` of the target, the other strategies
+// ignore it.
+pub fn select(
+    strategy: SelectionStrategy,
+    utxos: &[Utxo],
+    target: Amount,
+    fee_rate: FeeRate,
+    cost_of_change: Amount,
+) -> Result<Vec<Utxo>, BitcoinError> {
+    match strategy {
+        SelectionStrategy::LargestFirst => largest_first(utxos, target, fee_rate),
+        SelectionStrategy::BranchAndBound => {
+            branch_and_bound(utxos, target, fee_rate, cost_of_change)
+                .ok_or_else(|| insufficient(utxos, target, fee_rate))
+        }
+        SelectionStrategy::SingleRandomDraw => single_random_draw(utxos, target, fee_rate),
+    }
+}
+
+// Take the largest UTXOs first until their effective values cover `target`
+pub fn largest_first(
+    utxos: &[Utxo],
+    target: Amount,
+    fee_rate: FeeRate,
+) -> Result<Vec<Utxo>, BitcoinError> {
+    let mut pool = economic(utxos, fee_rate);
+    pool.sort_by(|(a_value, a), (b_value, b)| {
+        b_value
+            .cmp(a_value)
+            .then_with(|| a.outpoint.cmp(&b.outpoint))
+    });
+    accumulate(pool, target).ok_or_else(|| insufficient(utxos, target, fee_rate))
+}
+
+// Depth-first search for a subset whose effective value lands in
+// `target..=target + cost_of_change`, so no change output is needed. Among
+// the matches found within the search limit the one with the least excess
+// wins. `None` when there is no such subset, even if funds suffice.
+pub fn branch_and_bound(
+    utxos: &[Utxo],
+    target: Amount,
+    fee_rate: FeeRate,
+    cost_of_change: Amount,
+) -> Option<Vec<Utxo>> {
+    let mut pool = economic(utxos, fee_rate);
+    pool.sort_by(|(a_value, a), (b_value, b)| {
+        b_value
+            .cmp(a_value)
+            .then_with(|| a.outpoint.cmp(&b.outpoint))
+    });
+    let values: Vec<u64> = pool.iter().map(|(value, _)| value.to_sat()).collect();
+    // remaining[i] is the total of values[i..]
+    let mut remaining = vec![0u64; values.len() + 1];
+    for i in (0..values.len()).rev() {
+        remaining[i] = remaining[i + 1].saturating_add(values[i]);
+    }
+    let target = target.to_sat();
+    let upper = target.saturating_add(cost_of_change.to_sat());
+
+    let mut best: Option<(u64, Vec<usize>)> = None;
+    let mut selected: Vec<usize> = Vec::new();
+    let mut current = 0u64;
+    let mut index = 0;
+    for _ in 0..BNB_MAX_TRIES {
+        let mut backtrack = current > upper || current.saturating_add(remaining[index]) < target;
+        if !backtrack && current >= target {
+            let excess = current - target;
+            if best
+                .as_ref()
+                .is_none_or(|(best_excess, _)| excess < *best_excess)
+            {
+                best = Some((excess, selected.clone()));
+            }
+            backtrack = true;
+        }
+
+        if backtrack {
+            // Undo the most recent inclusion and explore omitting it instead
+            let Some(last) = selected.pop() else {
+                break;
+            };
+            current -= values[last];
+            index = last + 1;
+        } else {
+            selected.push(index);
+            current += values[index];
+            index += 1;
+        }
+    }
+
+    best.map(|(_, indices)| indices.into_iter().map(|i| pool[i].1.clone()).collect())
+}
+
+// Add UTXOs in a random order until their effective values cover `target`
+pub fn single_random_draw(
+    utxos: &[Utxo],
+    target: Amount,
+    fee_rate: FeeRate,
+) -> Result<Vec<Utxo>, BitcoinError> {
+    let mut pool = economic(utxos, fee_rate);
+    // Fisher-Yates shuffle
+    for i in (1..pool.len()).rev() {
+        let mut bytes = [0u8; 8];
+        getrandom::fill(&mut bytes).map_err(|_| BitcoinError::RandomnessUnavailable)?;
+        let j = (u64::from_le_bytes(bytes) % (i as u64 + 1)) as usize;
+        pool.swap(i, j);
+    }
+    accumulate(pool, target).ok_or_else(|| insufficient(utxos, target, fee_rate))
+}
+
+// UTXOs worth spending at `fee_rate`, with their effective values
+fn economic(utxos: &[Utxo], fee_rate: FeeRate) -> Vec<(Amount, &Utxo)> {
+    utxos
+        .iter()
+        .filter_map(|utxo| Some((utxo.effective_value(fee_rate)?, utxo)))
+        .collect()
+}
+
+// The shortest prefix of `pool` covering `target`
+fn accumulate(pool: Vec<(Amount, &Utxo)>, target: Amount) -> Option<Vec<Utxo>> {
+    let mut selected = Vec::new();
+    let mut total = Amount::ZERO;
+    for (value, utxo) in pool {
+        selected.push(utxo.clone());
+        total = total.saturating_add(value);
+        if total >= target {
+            return Some(selected);
+        }
+    }
+    None
+}
+
+fn insufficient(utxos: &[Utxo], target: Amount, fee_rate: FeeRate) -> BitcoinError {
+    let available = economic(utxos, fee_rate)
+        .into_iter()
+        .fold(Amount::ZERO, |total, (value, _)| {
+            total.saturating_add(value)
+        });
+    BitcoinError::InsufficientFunds {
+        needed: target,
+        available,
+    }
+}
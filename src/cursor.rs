@@ -0,0 +1,116 @@
+// Bounds-checked little-endian decoding from a byte slice. Every read returns
+// an error carrying the offset it failed at instead of panicking on short
+// input, and byte runs borrow from the underlying slice.
+
+use crate::varint::check_canonical;
+use crate::{Amount, BitcoinError, OutPoint, Script, TxOutput, Txid};
+
+#[derive(Debug, Clone)]
+pub struct ByteCursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteCursor<'a> {
+    pub fn new(data: &'a [u8]) -> ByteCursor<'a> {
+        ByteCursor { data, pos: 0 }
+    }
+
+    // Offset of the next byte to be read
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+
+    pub fn peek_u8(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], BitcoinError> {
+        let bytes = self
+            .remaining()
+            .get(..len)
+            .ok_or(BitcoinError::UnexpectedEnd {
+                offset: self.pos,
+                needed: len,
+            })?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BitcoinError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, BitcoinError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16_le(&mut self) -> Result<u16, BitcoinError> {
+        self.read_array().map(u16::from_le_bytes)
+    }
+
+    // Port numbers in P2P addresses are the one big-endian field on the wire
+    pub fn read_u16_be(&mut self) -> Result<u16, BitcoinError> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+
+    pub fn read_u32_le(&mut self) -> Result<u32, BitcoinError> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    pub fn read_i32_le(&mut self) -> Result<i32, BitcoinError> {
+        self.read_array().map(i32::from_le_bytes)
+    }
+
+    pub fn read_u64_le(&mut self) -> Result<u64, BitcoinError> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    pub fn read_i64_le(&mut self) -> Result<i64, BitcoinError> {
+        self.read_array().map(i64::from_le_bytes)
+    }
+
+    // A CompactSize; values that fit a shorter form are rejected, matching
+    // Bitcoin Core
+    pub fn read_varint(&mut self) -> Result<u64, BitcoinError> {
+        let start = self.pos;
+        let n = match self.read_u8()? {
+            0xFD => self.read_u16_le()? as u64,
+            0xFE => self.read_u32_le()? as u64,
+            0xFF => self.read_u64_le()?,
+            n => return Ok(n as u64),
+        };
+        check_canonical(n, self.pos - start)
+            .map_err(|_| BitcoinError::NonCanonicalCompactSize { offset: start })?;
+        Ok(n)
+    }
+
+    // CompactSize length followed by that many bytes
+    pub fn read_var_bytes(&mut self) -> Result<&'a [u8], BitcoinError> {
+        let len = self.read_varint()?;
+        self.read_bytes(usize::try_from(len).unwrap_or(usize::MAX))
+    }
+
+    pub fn read_outpoint(&mut self) -> Result<OutPoint, BitcoinError> {
+        Ok(OutPoint {
+            txid: Txid(self.read_array()?),
+            vout: self.read_u32_le()?,
+        })
+    }
+
+    // An 8-byte value followed by the length-prefixed scriptPubKey
+    pub fn read_tx_output(&mut self) -> Result<TxOutput, BitcoinError> {
+        Ok(TxOutput {
+            value: Amount::from_sat(self.read_u64_le()?),
+            script_pubkey: Script(self.read_var_bytes()?.to_vec()),
+        })
+    }
+}
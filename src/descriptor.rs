@@ -0,0 +1,169 @@
+// Output descriptors (BIP-380) for single-key outputs: pkh, wpkh, sh(wpkh)
+// and key-path-only tr, with the 8-character descriptor checksum
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::address::Address;
+use crate::keys::PublicKey;
+use crate::taproot::TaprootSpendInfo;
+use crate::{BitcoinError, Script, hex};
+
+// Characters a descriptor may contain, in the order the checksum expands them
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_GENERATOR: [u64; 5] = [
+    0xf5dee51989,
+    0xa9fdca3312,
+    0x1bab10e32d,
+    0x3706b1677a,
+    0x644d626ffd,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Descriptor {
+    Pkh(PublicKey),
+    // Compressed keys only
+    Wpkh(PublicKey),
+    ShWpkh(PublicKey),
+    // x-only internal key, no script tree
+    Tr([u8; 32]),
+}
+
+impl Descriptor {
+    pub fn script_pubkey(&self) -> Result<Script, BitcoinError> {
+        Ok(self.address()?.script_pubkey())
+    }
+
+    pub fn address(&self) -> Result<Address, BitcoinError> {
+        match self {
+            Descriptor::Pkh(key) => Ok(key.p2pkh_address()),
+            Descriptor::Wpkh(key) => key.p2wpkh_address(),
+            Descriptor::ShWpkh(key) => Ok(Address::P2SH(crate::hash::hash160(
+                key.p2wpkh_script_pubkey()?.bytes(),
+            ))),
+            Descriptor::Tr(internal_key) => {
+                Ok(TaprootSpendInfo::key_path_only(*internal_key)?.address())
+            }
+        }
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> BitcoinError {
+    BitcoinError::InvalidDescriptor(reason.into())
+}
+
+// The checksum of `descriptor` (without `#`), or `None` if it contains a
+// character outside the descriptor character set
+pub fn checksum(descriptor: &str) -> Option<String> {
+    let mut symbols = Vec::with_capacity(descriptor.len() * 4 / 3 + 9);
+    let mut groups = Vec::with_capacity(3);
+    for c in descriptor.chars() {
+        let value = INPUT_CHARSET.find(c)? as u64;
+        symbols.push(value & 31);
+        groups.push(value >> 5);
+        if groups.len() == 3 {
+            symbols.push(groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+    match groups[..] {
+        [a] => symbols.push(a),
+        [a, b] => symbols.push(a * 3 + b),
+        _ => {}
+    }
+    symbols.extend([0; 8]);
+
+    let checksum = polymod(&symbols) ^ 1;
+    Some(
+        (0..8)
+            .map(|i| CHECKSUM_CHARSET[((checksum >> (5 * (7 - i))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+fn polymod(symbols: &[u64]) -> u64 {
+    let mut chk = 1u64;
+    for &value in symbols {
+        let top = chk >> 35;
+        chk = ((chk & 0x7_ffff_ffff) << 5) ^ value;
+        for (i, generator) in CHECKSUM_GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn parse_key(hex_key: &str) -> Result<PublicKey, BitcoinError> {
+    PublicKey::from_slice(&hex::decode(hex_key)?)
+}
+
+// `name(inner)` to `inner`
+fn strip_call<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    s.strip_prefix(name)?.strip_prefix('(')?.strip_suffix(')')
+}
+
+// Keys are hex public keys; tr also takes a 32-byte x-only key. A trailing
+// `#checksum` is optional but must match when present.
+impl FromStr for Descriptor {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body = match s.split_once('#') {
+            Some((body, given)) => {
+                let expected = checksum(body).ok_or_else(|| invalid("invalid character"))?;
+                if given != expected {
+                    return Err(invalid(format!(
+                        "checksum {given} does not match {expected}"
+                    )));
+                }
+                body
+            }
+            None => s,
+        };
+
+        if let Some(inner) = strip_call(body, "sh") {
+            let key = strip_call(inner, "wpkh")
+                .ok_or_else(|| invalid("only sh(wpkh(...)) is supported"))?;
+            let key = parse_key(key)?;
+            if !key.compressed {
+                return Err(BitcoinError::InvalidKey);
+            }
+            return Ok(Descriptor::ShWpkh(key));
+        }
+        if let Some(key) = strip_call(body, "wpkh") {
+            let key = parse_key(key)?;
+            if !key.compressed {
+                return Err(BitcoinError::InvalidKey);
+            }
+            return Ok(Descriptor::Wpkh(key));
+        }
+        if let Some(key) = strip_call(body, "pkh") {
+            return Ok(Descriptor::Pkh(parse_key(key)?));
+        }
+        if let Some(key) = strip_call(body, "tr") {
+            let internal_key = match hex::decode(key)?.len() {
+                32 => hex::decode_array(key)?,
+                _ => parse_key(key)?.point.x.to_be_bytes(),
+            };
+            return Ok(Descriptor::Tr(internal_key));
+        }
+        Err(invalid(format!("unsupported descriptor {body}")))
+    }
+}
+
+// With the checksum appended
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = match self {
+            Descriptor::Pkh(key) => format!("pkh({})", hex::encode(&key.to_bytes())),
+            Descriptor::Wpkh(key) => format!("wpkh({})", hex::encode(&key.to_bytes())),
+            Descriptor::ShWpkh(key) => format!("sh(wpkh({}))", hex::encode(&key.to_bytes())),
+            Descriptor::Tr(internal_key) => format!("tr({})", hex::encode(internal_key)),
+        };
+        // The body only uses descriptor characters
+        write!(f, "{body}#{}", checksum(&body).unwrap())
+    }
+}
@@ -0,0 +1,62 @@
+// Dependency-free hex codec
+
+use crate::{BitcoinError, ParseErrorCode};
+
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+// Encode bytes as lowercase hex
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() * 2);
+    for byte in data {
+        out.push(HEX_CHARS[(byte >> 4) as usize] as char);
+        out.push(HEX_CHARS[(byte & 0x0F) as usize] as char);
+    }
+    out
+}
+
+// Decode a hex string (either case) into bytes. Errors give the offset of
+// the offending character.
+pub fn decode(hex: &str) -> Result<Vec<u8>, BitcoinError> {
+    let bytes = hex.as_bytes();
+    if !bytes.len().is_multiple_of(2) {
+        return Err(BitcoinError::cli_error(
+            ParseErrorCode::InvalidHex,
+            format!("Hex string has odd length {}", bytes.len()),
+        ));
+    }
+
+    (0..bytes.len())
+        .step_by(2)
+        .map(|offset| Ok((nibble(hex, offset)? << 4) | nibble(hex, offset + 1)?))
+        .collect()
+}
+
+// Decode exactly `N` bytes, e.g. a hash
+pub fn decode_array<const N: usize>(hex: &str) -> Result<[u8; N], BitcoinError> {
+    let bytes = decode(hex)?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        BitcoinError::cli_error(
+            ParseErrorCode::InvalidHex,
+            format!("Expected {N} bytes of hex, got {}", bytes.len()),
+        )
+    })
+}
+
+fn nibble(hex: &str, offset: usize) -> Result<u8, BitcoinError> {
+    match hex.as_bytes()[offset] {
+        c @ b'0'..=b'9' => Ok(c - b'0'),
+        c @ b'a'..=b'f' => Ok(c - b'a' + 10),
+        c @ b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => {
+            // Report the whole character, which may be multi-byte
+            let c = hex
+                .get(offset..)
+                .and_then(|rest| rest.chars().next())
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            Err(BitcoinError::cli_error(
+                ParseErrorCode::InvalidHex,
+                format!("Invalid hex character {c:?} at offset {offset}"),
+            ))
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Private and public keys, WIF, and the scripts paying to a public key
+
+use std::fmt;
+
+use crate::secp256k1::{self, AffinePoint};
+use crate::{Address, BitcoinError, Network, Script, base58, hash};
+
+// WIF payloads end with this byte when the public key is compressed
+const WIF_COMPRESSED_FLAG: u8 = 0x01;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey {
+    secret: [u8; 32],
+    pub network: Network,
+    // Whether the matching public key is serialized compressed
+    pub compressed: bool,
+}
+
+impl PrivateKey {
+    // A key for a compressed public key; the secret must be in [1, n - 1]
+    pub fn new(secret: [u8; 32], network: Network) -> Result<PrivateKey, BitcoinError> {
+        secp256k1::parse_secret_key(&secret).map_err(|_| BitcoinError::InvalidKey)?;
+        Ok(PrivateKey {
+            secret,
+            network,
+            compressed: true,
+        })
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.secret
+    }
+
+    // Base58Check of the network byte, the secret and, for compressed keys,
+    // a trailing 0x01
+    pub fn to_wif(&self) -> String {
+        let mut payload = vec![self.network.wif_prefix()];
+        payload.extend_from_slice(&self.secret);
+        if self.compressed {
+            payload.push(WIF_COMPRESSED_FLAG);
+        }
+        base58::encode_check(&payload)
+    }
+
+    // The testnet version byte is shared with signet and regtest, so those
+    // keys decode as testnet
+    pub fn from_wif(wif: &str) -> Result<PrivateKey, BitcoinError> {
+        let payload = base58::decode_check(wif).map_err(|_| BitcoinError::InvalidKey)?;
+        let (prefix, rest) = payload.split_first().ok_or(BitcoinError::InvalidKey)?;
+        let network = Network::ALL
+            .into_iter()
+            .find(|network| network.wif_prefix() == *prefix)
+            .ok_or(BitcoinError::InvalidNetwork)?;
+        let (secret, compressed) = match rest {
+            [secret @ .., WIF_COMPRESSED_FLAG] if secret.len() == 32 => (secret, true),
+            secret if secret.len() == 32 => (secret, false),
+            _ => return Err(BitcoinError::InvalidKey),
+        };
+        let mut key = PrivateKey::new(secret.try_into().unwrap(), network)?;
+        key.compressed = compressed;
+        Ok(key)
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        // `new` and `from_wif` only accept secrets in range
+        let point = secp256k1::public_key(&self.secret).expect("secret key in range");
+        PublicKey {
+            point,
+            compressed: self.compressed,
+        }
+    }
+}
+
+// Keep the secret out of logs
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("network", &self.network)
+            .field("compressed", &self.compressed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey {
+    pub point: AffinePoint,
+    pub compressed: bool,
+}
+
+impl PublicKey {
+    // Parse a SEC1 compressed (33-byte) or uncompressed (65-byte) key
+    pub fn from_slice(bytes: &[u8]) -> Result<PublicKey, BitcoinError> {
+        let point = AffinePoint::from_sec1(bytes).map_err(|_| BitcoinError::InvalidKey)?;
+        Ok(PublicKey {
+            point,
+            compressed: bytes.len() == 33,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.compressed {
+            return self.point.to_compressed().to_vec();
+        }
+        let mut out = vec![0x04];
+        out.extend_from_slice(&self.point.x.to_be_bytes());
+        out.extend_from_slice(&self.point.y.to_be_bytes());
+        out
+    }
+
+    pub fn pubkey_hash(&self) -> [u8; 20] {
+        hash::hash160(&self.to_bytes())
+    }
+
+    pub fn p2pkh_address(&self) -> Address {
+        Address::P2PKH(self.pubkey_hash())
+    }
+
+    // SegWit outputs only commit to compressed keys (BIP-143)
+    pub fn p2wpkh_address(&self) -> Result<Address, BitcoinError> {
+        if !self.compressed {
+            return Err(BitcoinError::InvalidKey);
+        }
+        Ok(Address::P2WPKH(self.pubkey_hash()))
+    }
+
+    pub fn p2pkh_script_pubkey(&self) -> Script {
+        self.p2pkh_address().script_pubkey()
+    }
+
+    pub fn p2wpkh_script_pubkey(&self) -> Result<Script, BitcoinError> {
+        Ok(self.p2wpkh_address()?.script_pubkey())
+    }
+}
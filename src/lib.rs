@@ -1,5 +1,71 @@
 use thiserror::Error;
 
+pub mod address;
+pub mod amount;
+pub mod base58;
+pub mod bech32;
+pub mod bip32;
+pub mod block;
+pub mod borrowed;
+pub mod coinselect;
+pub mod cursor;
+pub mod descriptor;
+pub mod hash;
+pub mod hex;
+pub mod keys;
+pub mod mempool;
+pub mod merkle;
+pub mod network;
+pub mod p2p;
+pub mod primitives;
+pub mod psbt;
+pub mod rbf;
+pub mod reader;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod script;
+pub mod script_interpreter;
+pub mod secp256k1;
+pub mod segwit;
+#[cfg(feature = "serde")]
+mod serde_impls;
+pub mod sighash;
+pub mod signer;
+pub mod taproot;
+#[cfg(feature = "testutils")]
+pub mod testutils;
+pub mod transaction;
+pub mod validation;
+pub mod varint;
+pub mod wallet;
+
+pub use address::Address;
+pub use amount::{Amount, FeeRate, MAX_MONEY};
+pub use bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+pub use block::{Block, BlockHeader};
+pub use borrowed::{TransactionRef, TxInputRef, TxOutputRef};
+pub use coinselect::{SelectionStrategy, Utxo};
+pub use cursor::ByteCursor;
+pub use descriptor::Descriptor;
+pub use keys::{PrivateKey, PublicKey};
+pub use merkle::MerkleProof;
+pub use network::{Network, Params};
+pub use p2p::{NetworkMessage, RawNetworkMessage};
+pub use primitives::{LockTime, Sequence, Txid};
+pub use psbt::{Psbt, PsbtInput, PsbtOutput};
+pub use reader::TransactionReader;
+pub use script::{
+    Instruction, Opcode, OutputScriptType, PatternElement, Script, ScriptBuf, ScriptBuilder,
+    ScriptPattern,
+};
+pub use script_interpreter::{ScriptError, ScriptVerifyFlags};
+pub use segwit::{SegWitInputRef, SegWitTransaction, WitnessBuilder, detect_segwit};
+pub use sighash::SigHashType;
+pub use transaction::Transaction;
+pub use validation::TransactionValidator;
+pub use varint::{compact_size_len, decode_compact_size, encode_compact_size};
+pub use wallet::UtxoSet;
+
 // Custom errors for Bitcoin operations
 #[derive(Error, Debug)]
 pub enum BitcoinError {
@@ -9,8 +75,142 @@ pub enum BitcoinError {
     InvalidScript,
     #[error("Invalid amount")]
     InvalidAmount,
-    #[error("Parse error: {0}")]
-    ParseError(String),
+    #[error("Parse error: {message}")]
+    CliError {
+        code: ParseErrorCode,
+        message: String,
+    },
+    #[error("insufficient funds: need {needed}, have {available}")]
+    InsufficientFunds { needed: Amount, available: Amount },
+    #[error("dust output: {value} is below {threshold} threshold")]
+    DustOutput { value: Amount, threshold: Amount },
+    #[error("transaction weight {weight} exceeds limit {limit}")]
+    WeightLimitExceeded { weight: u64, limit: u64 },
+    #[error("non-push opcode {0:#04x} in push-only script")]
+    NonPushOpcode(u8),
+    #[error("input {input_index} sequence lock not satisfied until {required}")]
+    SequenceLockNotMet { input_index: usize, required: u32 },
+    #[error("unsupported taproot leaf version: {0:#04x}")]
+    UnsupportedTaprootLeafVersion(u8),
+    #[error("transaction check failed: {0}")]
+    CheckFailed(String),
+    #[error("system randomness unavailable")]
+    RandomnessUnavailable,
+    #[error("Invalid address")]
+    InvalidAddress,
+    #[error("Address is for a different network")]
+    InvalidNetwork,
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("transaction has no inputs")]
+    NoInputs,
+    #[error("transaction has no outputs")]
+    NoOutputs,
+    #[error("output {index} value {value} exceeds 21M BTC")]
+    OutputValueTooLarge { index: usize, value: Amount },
+    #[error("total output value exceeds 21M BTC")]
+    TotalValueTooLarge,
+    #[error("duplicate input {0:x}")]
+    DuplicateInput(OutPoint),
+    #[error("script execution failed: {0}")]
+    ScriptExecution(ScriptError),
+    #[error("missing UTXOs: {}", .0.iter().map(|o| format!("{o:x}")).collect::<Vec<_>>().join(", "))]
+    MissingUtxos(Vec<OutPoint>),
+    #[error("invalid compact target {0:#010x}")]
+    InvalidTarget(u32),
+    #[error("block hash is above the proof-of-work target")]
+    InvalidProofOfWork,
+    #[error("invalid key")]
+    InvalidKey,
+    #[error("invalid derivation path")]
+    InvalidDerivationPath,
+    #[error("hardened child {0:#x} cannot be derived from a public key")]
+    HardenedDerivationFromPublicKey(u32),
+    #[error("invalid P2P message: {0}")]
+    InvalidMessage(String),
+    #[error("invalid wallet file")]
+    InvalidWalletFile,
+    #[error("invalid descriptor: {0}")]
+    InvalidDescriptor(String),
+    #[error("unexpected end of data at offset {offset}: {needed} more bytes needed")]
+    UnexpectedEnd { offset: usize, needed: usize },
+    #[error("non-canonical CompactSize at offset {offset}")]
+    NonCanonicalCompactSize { offset: usize },
+    #[error("replacement rejected: {0}")]
+    ReplacementRejected(String),
+    #[error("RPC error {code}: {message}")]
+    RpcError { code: i64, message: String },
+    #[error("invalid RPC response: {0}")]
+    InvalidRpcResponse(String),
+}
+
+// Machine-readable category of a `CliError`, so callers can branch on the
+// kind of failure without matching on the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorCode {
+    MissingCommand,
+    UnknownCommand,
+    MissingArgument { name: &'static str },
+    InvalidAmount,
+    InvalidAddress,
+    InvalidHex,
+    InvalidNetwork,
+    // A malformed value or flag that fits none of the above
+    InvalidArgument,
+    // A wallet command run without a wallet to run it against
+    WalletRequired,
+}
+
+impl BitcoinError {
+    pub fn cli_error(code: ParseErrorCode, message: impl Into<String>) -> Self {
+        BitcoinError::CliError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    // Convert into an `anyhow::Error` for applications built on anyhow
+    #[cfg(feature = "anyhow")]
+    pub fn into_anyhow(self) -> anyhow::Error {
+        anyhow::Error::new(self)
+    }
+}
+
+// Malformed numbers in text input; amounts are still reported with
+// `InvalidAmount` by the parsers that know the field is an amount
+impl From<std::num::ParseIntError> for BitcoinError {
+    fn from(err: std::num::ParseIntError) -> Self {
+        BitcoinError::cli_error(ParseErrorCode::InvalidArgument, err.to_string())
+    }
+}
+
+impl From<std::num::ParseFloatError> for BitcoinError {
+    fn from(err: std::num::ParseFloatError) -> Self {
+        BitcoinError::cli_error(ParseErrorCode::InvalidArgument, err.to_string())
+    }
+}
+
+// Sequence values with special meaning: final, nLockTime-only, and the
+// canonical BIP-125 replaceability signal
+pub const SEQUENCE_FINAL: u32 = 0xFFFFFFFF;
+pub const SEQUENCE_ENABLE_LOCKTIME: u32 = 0xFFFFFFFE;
+pub const SEQUENCE_RBF: u32 = 0xFFFFFFFD;
+
+// Largest transaction weight relayed by default policy
+pub const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+// Largest transaction weight allowed by consensus (the block weight limit)
+pub const MAX_CONSENSUS_TX_WEIGHT: u64 = 4_000_000;
+// Legacy sigops allowed in a standard transaction (a sigop cost of 16000)
+pub const MAX_STANDARD_TX_SIGOPS: u32 = 4_000;
+// Block sigop cost limit; each legacy sigop costs 4 (the witness scale factor)
+pub const MAX_BLOCK_SIGOPS_COST: u32 = 80_000;
+
+pub(crate) fn check_weight(weight: u64, limit: u64) -> Result<(), BitcoinError> {
+    if weight > limit {
+        Err(BitcoinError::WeightLimitExceeded { weight, limit })
+    } else {
+        Ok(())
+    }
 }
 
 // Generic Point struct for Bitcoin addresses or coordinates
@@ -20,31 +220,1283 @@ pub struct Point<T> {
     pub y: T,
 }
 
-impl<T> Point<T> {
-    pub fn new(x: T, y: T) -> Self {
-        Point { x, y }
+impl<T> Point<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Point { x, y }
+    }
+}
+
+// Custom serialization for Bitcoin transaction
+pub trait BitcoinSerialize {
+    // TODO: Implement serialization to bytes
+    fn serialize(&self) -> Vec<u8>;
+
+    // Stream the serialized bytes into `writer`; implementors may override this
+    // to avoid building the intermediate buffer
+    fn serialize_to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        writer.write_all(&self.serialize())?;
+        Ok(())
+    }
+
+    // Stream the serialized bytes into `w`, returning how many were written
+    fn serialize_into(&self, w: &mut impl std::io::Write) -> std::io::Result<usize> {
+        let mut counter = CountingWriter {
+            inner: w,
+            written: 0,
+        };
+        self.serialize_to_writer(&mut counter)
+            .map_err(|err| match err {
+                BitcoinError::IoError(err) => err,
+                err => std::io::Error::other(err),
+            })?;
+        Ok(counter.written)
+    }
+
+    // P2P message checksum of the serialized bytes
+    fn checksum(&self) -> [u8; 4] {
+        hash::checksum(&self.serialize())
+    }
+}
+
+// Counts the bytes passed through to the wrapped writer
+struct CountingWriter<'a, W: std::io::Write> {
+    inner: &'a mut W,
+    written: usize,
+}
+
+impl<W: std::io::Write> std::io::Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Parse values from their consensus encoding
+pub trait BitcoinDeserialize: Sized {
+    // Read one value from `r`, leaving whatever follows it unread. Running out
+    // of bytes is an InvalidTransaction, other reader failures an IoError.
+    fn deserialize_from(r: &mut impl std::io::Read) -> Result<Self, BitcoinError>;
+
+    // Parse a complete value; trailing bytes are rejected
+    fn deserialize(data: &[u8]) -> Result<Self, BitcoinError> {
+        let mut rest = data;
+        let value = Self::deserialize_from(&mut rest)?;
+        if !rest.is_empty() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        Ok(value)
+    }
+}
+
+/// Legacy Bitcoin transaction.
+///
+/// # Migrating from 0.1
+///
+/// The fields of `LegacyTransaction` are no longer public so that invariants
+/// can be enforced in one place.
+///
+/// - Construct transactions with [`LegacyTransaction::builder`] instead of a
+///   struct literal.
+/// - Replace `tx.version` with `tx.version()` and `tx.lock_time` with
+///   `tx.lock_time()`, which now returns a [`LockTime`] (use
+///   `u32::from(tx.lock_time())` for the raw value).
+/// - Replace `tx.inputs` / `tx.outputs` with `tx.inputs()` / `tx.outputs()`,
+///   or `tx.inputs_mut()` / `tx.outputs_mut()` when mutation is required.
+///
+/// # Example
+///
+/// ```
+/// use rust_week_4_exercises::{
+///     Amount, LegacyTransaction, OutPoint, Script, TxInput, TxOutput, Txid,
+/// };
+///
+/// let outpoint = OutPoint { txid: Txid([0x11; 32]), vout: 0 };
+/// let tx = LegacyTransaction::builder()
+///     .add_input(TxInput::from_outpoint(outpoint))
+///     .add_output(TxOutput::new(Amount::from_sat(50_000), Script(vec![0x51])))
+///     .build();
+/// assert_eq!(tx.inputs()[0].sequence(), 0xFFFFFFFE);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyTransaction {
+    version: i32,
+    inputs: Vec<TxInput>,
+    outputs: Vec<TxOutput>,
+    lock_time: LockTime,
+}
+
+impl LegacyTransaction {
+    pub fn builder() -> LegacyTransactionBuilder {
+        LegacyTransactionBuilder::new()
+    }
+
+    // Spend a single UTXO worth `utxo_value` to `to`, leaving `fee` to miners.
+    // The input is unsigned; the output must be worth at least one satoshi.
+    pub fn spending_tx(
+        utxo: OutPoint,
+        utxo_value: Amount,
+        to: &Address,
+        fee: Amount,
+    ) -> Result<LegacyTransaction, BitcoinError> {
+        if utxo_value <= fee {
+            return Err(BitcoinError::InsufficientFunds {
+                needed: fee.saturating_add(Amount::from_sat(1)),
+                available: utxo_value,
+            });
+        }
+
+        Ok(LegacyTransaction {
+            version: 2,
+            inputs: vec![TxInput::from_outpoint(utxo)],
+            outputs: vec![TxOutput {
+                value: utxo_value - fee,
+                script_pubkey: to.script_pubkey(),
+            }],
+            lock_time: LockTime::ZERO,
+        })
+    }
+
+    // Whether spending outputs of these types may need SegWit signing. P2SH is
+    // included since it can wrap a witness program.
+    pub fn requires_segwit_spending(spending_types: &[OutputScriptType]) -> bool {
+        spending_types.iter().any(|script_type| {
+            matches!(
+                script_type,
+                OutputScriptType::P2WPKH
+                    | OutputScriptType::P2WSH
+                    | OutputScriptType::P2TR
+                    | OutputScriptType::P2A
+                    | OutputScriptType::P2SH
+            )
+        })
+    }
+
+    // Largest-first coin selection: pick UTXOs until they cover `target` plus
+    // the fee for a transaction spending them to a single P2PKH output
+    pub fn select_coins(
+        utxos: &UtxoSet,
+        target: Amount,
+        fee_rate: FeeRate,
+    ) -> Result<Vec<OutPoint>, BitcoinError> {
+        const OVERHEAD_VBYTES: u64 = 10;
+        const P2PKH_INPUT_VBYTES: u64 = 148;
+        const P2PKH_OUTPUT_VBYTES: u64 = 34;
+
+        let mut candidates: Vec<(&OutPoint, &TxOutput)> = utxos.iter().collect();
+        candidates
+            .sort_by(|(a_op, a), (b_op, b)| b.value.cmp(&a.value).then_with(|| a_op.cmp(b_op)));
+
+        let mut selected = Vec::new();
+        let mut available = 0u64;
+        let mut needed = target;
+        for (outpoint, output) in candidates {
+            selected.push(outpoint.clone());
+            available = available.saturating_add(output.value.to_sat());
+            let vsize =
+                OVERHEAD_VBYTES + P2PKH_INPUT_VBYTES * selected.len() as u64 + P2PKH_OUTPUT_VBYTES;
+            needed = target
+                .checked_add(fee_rate.fee_for_vsize(vsize))
+                .ok_or(BitcoinError::InvalidAmount)?;
+            if available >= needed.to_sat() {
+                return Ok(selected);
+            }
+        }
+
+        Err(BitcoinError::InsufficientFunds {
+            needed,
+            available: Amount::from_sat(available),
+        })
+    }
+
+    // How many of `available_utxos`, taken largest first, cover `target` plus
+    // the fee for spending them to a payment and a change output, both of
+    // `output_script_type`. Input sizes come from each UTXO's script type.
+    pub fn estimate_input_count_needed(
+        available_utxos: &[(&OutPoint, &TxOutput)],
+        target: Amount,
+        fee_rate: FeeRate,
+        output_script_type: OutputScriptType,
+    ) -> Result<usize, BitcoinError> {
+        const OVERHEAD_VBYTES: u64 = 10;
+
+        let mut candidates: Vec<&TxOutput> =
+            available_utxos.iter().map(|(_, output)| *output).collect();
+        candidates.sort_by_key(|output| std::cmp::Reverse(output.value));
+
+        let mut vsize = OVERHEAD_VBYTES + 2 * output_script_type.output_vbytes();
+        let mut available = Amount::from_sat(0);
+        let mut needed = target.saturating_add(fee_rate.fee_for_vsize(vsize));
+        for (count, output) in candidates.into_iter().enumerate() {
+            available = available.saturating_add(output.value);
+            vsize += output.expected_input_vbytes();
+            needed = target.saturating_add(fee_rate.fee_for_vsize(vsize));
+            if available >= needed {
+                return Ok(count + 1);
+            }
+        }
+
+        Err(BitcoinError::InsufficientFunds { needed, available })
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn inputs(&self) -> &[TxInput] {
+        &self.inputs
+    }
+
+    pub fn inputs_mut(&mut self) -> &mut Vec<TxInput> {
+        &mut self.inputs
+    }
+
+    pub fn outputs(&self) -> &[TxOutput] {
+        &self.outputs
+    }
+
+    pub fn outputs_mut(&mut self) -> &mut Vec<TxOutput> {
+        &mut self.outputs
+    }
+
+    // Standard-alphabet, padded Base64 of the serialized transaction
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(self.serialize())
+    }
+
+    #[cfg(feature = "base64")]
+    pub fn from_base64(s: &str) -> Result<LegacyTransaction, BitcoinError> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(s)
+            .map_err(|err| {
+                BitcoinError::cli_error(
+                    ParseErrorCode::InvalidArgument,
+                    format!("Invalid base64: {err}"),
+                )
+            })?;
+        LegacyTransaction::deserialize(&bytes)
+    }
+
+    pub fn remove_input(&mut self, index: usize) -> Result<TxInput, BitcoinError> {
+        remove_at(&mut self.inputs, index)
+    }
+
+    pub fn remove_output(&mut self, index: usize) -> Result<TxOutput, BitcoinError> {
+        remove_at(&mut self.outputs, index)
+    }
+
+    pub fn lock_time(&self) -> LockTime {
+        self.lock_time
+    }
+
+    // Consuming setters for producing a modified copy without the builder
+
+    pub fn with_version(self, version: i32) -> LegacyTransaction {
+        LegacyTransaction { version, ..self }
+    }
+
+    pub fn with_lock_time(self, lock_time: LockTime) -> LegacyTransaction {
+        LegacyTransaction { lock_time, ..self }
+    }
+
+    pub fn with_inputs(self, inputs: Vec<TxInput>) -> LegacyTransaction {
+        LegacyTransaction { inputs, ..self }
+    }
+
+    pub fn with_outputs(self, outputs: Vec<TxOutput>) -> LegacyTransaction {
+        LegacyTransaction { outputs, ..self }
+    }
+
+    // First input spending `outpoint`, with its index
+    pub fn input_by_outpoint(&self, outpoint: &OutPoint) -> Option<(usize, &TxInput)> {
+        self.inputs
+            .iter()
+            .enumerate()
+            .find(|(_, input)| input.previous_output == *outpoint)
+    }
+
+    // First output paying to `script`, with its index
+    pub fn output_by_script(&self, script: &Script) -> Option<(usize, &TxOutput)> {
+        self.outputs
+            .iter()
+            .enumerate()
+            .find(|(_, output)| output.script_pubkey == *script)
+    }
+
+    // Copy of the transaction with the output at `index` replaced, as used when
+    // blanking outputs for SIGHASH_SINGLE
+    pub fn with_output_replaced(
+        &self,
+        index: usize,
+        new_output: TxOutput,
+    ) -> Result<LegacyTransaction, BitcoinError> {
+        if index >= self.outputs.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let mut tx = self.clone();
+        tx.outputs[index] = new_output;
+        Ok(tx)
+    }
+
+    // Copy of the transaction keeping only the first `n` outputs
+    pub fn truncated_to_outputs(&self, n: usize) -> LegacyTransaction {
+        let mut tx = self.clone();
+        tx.outputs.truncate(n);
+        tx
+    }
+
+    // In-place stable sorts, for callers controlling order before signing
+    pub fn sort_outputs_by_value(&mut self) {
+        self.outputs.sort_by_key(|output| output.value);
+    }
+
+    pub fn sort_outputs_by_script(&mut self) {
+        self.outputs
+            .sort_by(|a, b| a.script_pubkey.bytes().cmp(b.script_pubkey.bytes()));
+    }
+
+    pub fn sort_inputs_by_outpoint(&mut self) {
+        self.inputs
+            .sort_by(|a, b| a.previous_output.cmp(&b.previous_output));
+    }
+
+    pub fn sort_inputs_by_sequence(&mut self) {
+        self.inputs.sort_by_key(|input| input.sequence);
+    }
+
+    // Non-mutating counterparts of the sorts above
+    pub fn sorted_outputs_by_value(&self) -> Vec<&TxOutput> {
+        let mut outputs: Vec<&TxOutput> = self.outputs.iter().collect();
+        outputs.sort_by_key(|output| output.value);
+        outputs
+    }
+
+    pub fn sorted_outputs_by_script(&self) -> Vec<&TxOutput> {
+        let mut outputs: Vec<&TxOutput> = self.outputs.iter().collect();
+        outputs.sort_by(|a, b| a.script_pubkey.bytes().cmp(b.script_pubkey.bytes()));
+        outputs
+    }
+
+    pub fn sorted_inputs_by_outpoint(&self) -> Vec<&TxInput> {
+        let mut inputs: Vec<&TxInput> = self.inputs.iter().collect();
+        inputs.sort_by(|a, b| a.previous_output.cmp(&b.previous_output));
+        inputs
+    }
+
+    pub fn sorted_inputs_by_sequence(&self) -> Vec<&TxInput> {
+        let mut inputs: Vec<&TxInput> = self.inputs.iter().collect();
+        inputs.sort_by_key(|input| input.sequence);
+        inputs
+    }
+
+    // True if any scriptSig or scriptPubKey is malformed or uses a disabled opcode
+    pub fn contains_invalid_scripts(&self) -> bool {
+        let script_sigs = self.inputs.iter().map(|input| &input.script_sig);
+        let script_pubkeys = self.outputs.iter().map(|output| &output.script_pubkey);
+
+        script_sigs
+            .chain(script_pubkeys)
+            .any(|script| script.contains_disabled_opcode().unwrap_or(true))
+    }
+
+    // Copy of the transaction with every scriptSig emptied, e.g. as a template
+    // for an external signer
+    pub fn clone_unsigned(&self) -> LegacyTransaction {
+        let mut tx = self.clone();
+        for input in &mut tx.inputs {
+            input.script_sig = Script::default();
+        }
+        tx
+    }
+
+    // True when every input carries a scriptSig; a "fully signed" heuristic for
+    // legacy inputs only, since native SegWit inputs have empty scriptSigs
+    pub fn has_all_scripts_populated(&self) -> bool {
+        self.inputs.iter().all(|input| !input.script_sig.is_empty())
+    }
+
+    pub fn sequences(&self) -> Vec<Sequence> {
+        self.inputs.iter().map(|input| input.sequence).collect()
+    }
+
+    // True when every input is final, which also disables nLockTime
+    pub fn all_sequences_final(&self) -> bool {
+        self.inputs.iter().all(|input| input.sequence.is_final())
+    }
+
+    // Smallest input sequence; below 0xFFFFFFFE means the transaction signals
+    // RBF, and may carry a BIP-68 relative lock time
+    pub fn min_sequence(&self) -> Option<Sequence> {
+        self.inputs.iter().map(|input| input.sequence).min()
+    }
+
+    // Inputs whose scriptSig spends from `address`: a P2PKH `<sig> <pubkey>`
+    // whose key hashes to the address, or a P2SH spend whose redeem script
+    // does. SegWit spends keep their keys in the witness and never match.
+    pub fn spending_input_indices(&self, address: &Address) -> Vec<usize> {
+        self.inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| {
+                let script_sig = input.script_sig.clone();
+                if !script_sig.is_push_only() {
+                    return false;
+                }
+                let Ok(pushes) = script_sig.decompile() else {
+                    return false;
+                };
+                let Some(Instruction::OpData(last)) = pushes.last() else {
+                    return false;
+                };
+                match address {
+                    Address::P2PKH(hash) => {
+                        pushes.len() == 2
+                            && matches!(last.len(), 33 | 65)
+                            && hash::hash160(last) == *hash
+                    }
+                    Address::P2SH(hash) => hash::hash160(last) == *hash,
+                    _ => false,
+                }
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // Outputs paying to `address`, in order; an address may be paid more than
+    // once in the same transaction
+    pub fn output_indices_paying(&self, address: &Address) -> Vec<usize> {
+        let script_pubkey = address.script_pubkey();
+        self.outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| output.script_pubkey == script_pubkey)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // BIP-125 rule 1: replaceable when any input's sequence is below
+    // 0xFFFFFFFE. 0xFFFFFFFE itself only enables nLockTime (the usual
+    // anti-fee-sniping value) and 0xFFFFFFFF is final; neither signals.
+    pub fn is_rbf_replaceable(&self) -> bool {
+        self.inputs.iter().any(|input| input.sequence.signals_rbf())
+    }
+
+    // Stricter than `is_rbf_replaceable`: some input's sequence is below the
+    // 0xFFFFFFFD opt-in value itself, so it was chosen deliberately (e.g. as a
+    // BIP-68 relative lock) rather than being one step off "not final"
+    pub fn is_explicitly_rbf(&self) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| input.sequence < Sequence::ENABLE_RBF)
+    }
+
+    // Sigops in every scriptSig and scriptPubKey, counted inaccurately
+    // (CHECKMULTISIG is always 20) as for the legacy block sigop limit
+    pub fn legacy_sigops(&self) -> u32 {
+        let script_sigs = self.inputs.iter().map(|input| &input.script_sig);
+        let script_pubkeys = self.outputs.iter().map(|output| &output.script_pubkey);
+        script_sigs
+            .chain(script_pubkeys)
+            .map(|script| script.sigops(false))
+            .sum()
+    }
+
+    // Legacy sigops plus the accurately counted sigops of every P2SH redeem
+    // script, found as the last push of the spending scriptSig (BIP-16)
+    pub fn accurate_sigops(&self, utxo_set: &UtxoSet) -> Result<u32, BitcoinError> {
+        let mut count = self.legacy_sigops();
+        if self.is_coinbase() {
+            return Ok(count);
+        }
+        for input in &self.inputs {
+            let utxo = utxo_set
+                .get(&input.previous_output)
+                .ok_or(BitcoinError::InvalidTransaction)?;
+            if !utxo.script_pubkey.is_p2sh() {
+                continue;
+            }
+            let script_sig = input.script_sig.clone();
+            if let Some(redeem_script) = Script::extract_p2sh_redeem_script(&script_sig) {
+                count += redeem_script.sigops(true);
+            }
+        }
+        Ok(count)
+    }
+
+    // Every consensus and policy problem found, rather than just the first;
+    // an empty list means the transaction passed
+    pub fn sanity_check(&self) -> Vec<BitcoinError> {
+        let fail = |reason: String| BitcoinError::CheckFailed(reason);
+        let mut issues: Vec<BitcoinError> = [
+            validation::check_not_empty(&self.inputs, &self.outputs),
+            validation::check_output_values(&self.outputs),
+            validation::check_duplicate_inputs(&self.inputs),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|err| fail(err.to_string()))
+        .collect();
+        if !(1..=3).contains(&self.version) {
+            issues.push(fail(format!("non-standard version {}", self.version)));
+        }
+
+        if self.is_coinbase() {
+            let len = self.inputs[0].script_sig.len();
+            if !(2..=100).contains(&len) {
+                issues.push(fail(format!(
+                    "coinbase scriptSig length {len} not in 2..=100"
+                )));
+            }
+        } else {
+            for (index, input) in self.inputs.iter().enumerate() {
+                if input.previous_output.is_null() {
+                    issues.push(fail(format!("input {index} spends the null outpoint")));
+                }
+            }
+        }
+
+        // nLockTime is only enforced when some input has a non-final sequence
+        if self.lock_time != LockTime::ZERO && self.all_sequences_final() {
+            issues.push(fail(format!(
+                "lock_time {} has no effect: every input is final",
+                self.lock_time
+            )));
+        }
+
+        let sigops = self.legacy_sigops();
+        if sigops > MAX_STANDARD_TX_SIGOPS {
+            issues.push(fail(format!(
+                "{sigops} sigops exceeds limit {MAX_STANDARD_TX_SIGOPS}"
+            )));
+        }
+
+        issues
+    }
+
+    // Whether a third party could change the txid by re-encoding a scriptSig
+    pub fn is_free_of_malleability(&self) -> bool {
+        self.malleability_sources().is_empty()
+    }
+
+    // (input index, reason) for every scriptSig a relaying node could rewrite
+    // without invalidating it: non-push opcodes, non-minimal pushes, and
+    // signatures that are not strict DER or have a high S value. Pushes that
+    // do not look like a DER signature are taken to be keys or scripts.
+    pub fn malleability_sources(&self) -> Vec<(usize, String)> {
+        let mut sources = Vec::new();
+        for (index, input) in self.inputs.iter().enumerate() {
+            let script_sig = input.script_sig.clone();
+            let Ok(instructions) = script_sig.decompile() else {
+                sources.push((index, "malformed scriptSig".to_string()));
+                continue;
+            };
+            if !script_sig.is_push_only() {
+                sources.push((index, "scriptSig is not push-only".to_string()));
+            }
+            if !script_sig.has_minimal_pushes() {
+                sources.push((index, "non-minimal push".to_string()));
+            }
+            let signatures = instructions
+                .iter()
+                .filter_map(|instruction| match instruction {
+                    Instruction::OpData(data) if data.first() == Some(&0x30) && data.len() >= 9 => {
+                        Some(&data[..data.len() - 1])
+                    }
+                    _ => None,
+                });
+            for (position, der) in signatures.enumerate() {
+                match secp256k1::Signature::from_der(der) {
+                    Ok(signature) if signature.to_der() == der => {
+                        if !signature.has_low_s() {
+                            sources
+                                .push((index, format!("signature {position} has a high S value")));
+                        }
+                    }
+                    _ => sources.push((index, format!("signature {position} is not strict DER"))),
+                }
+            }
+        }
+        sources
+    }
+
+    // A coinbase has exactly one input, spending the null outpoint
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    // Run input `index`'s scriptSig against the output it spends
+    pub fn verify_input(
+        &self,
+        index: usize,
+        utxo: &TxOutput,
+        flags: ScriptVerifyFlags,
+    ) -> Result<(), BitcoinError> {
+        script_interpreter::verify_input(self, index, utxo, flags)
+    }
+
+    // Legacy transactions have no witness, so this is the whole size
+    pub fn non_witness_size(&self) -> usize {
+        self.estimated_size()
+    }
+
+    // Serialized size in bytes, computed without serializing
+    pub fn estimated_size(&self) -> usize {
+        let inputs_size: usize = self
+            .inputs
+            .iter()
+            .map(|input| {
+                32 + 4
+                    + compact_size_len(input.script_sig.len() as u64)
+                    + input.script_sig.len()
+                    + 4
+            })
+            .sum();
+        let outputs_size: usize = self
+            .outputs
+            .iter()
+            .map(|output| {
+                8 + compact_size_len(output.script_pubkey.len() as u64) + output.script_pubkey.len()
+            })
+            .sum();
+
+        4 + compact_size_len(self.inputs.len() as u64)
+            + inputs_size
+            + compact_size_len(self.outputs.len() as u64)
+            + outputs_size
+            + 4
+    }
+
+    // Same as `estimated_size`, under the name other libraries use. Cheap, so
+    // this is the one to call in fee estimation loops
+    pub fn compact_size(&self) -> usize {
+        self.estimated_size()
+    }
+
+    // Exact size from actually serializing; for final verification of a
+    // finished transaction rather than for estimation
+    pub fn serialized_size(&self) -> usize {
+        self.serialize().len()
+    }
+
+    // BIP-141 weight; legacy transactions carry no witness data
+    pub fn weight(&self) -> u64 {
+        self.estimated_size() as u64 * 4
+    }
+
+    // Virtual size, ceil(weight / 4); equal to the size for legacy transactions
+    pub fn vsize(&self) -> u64 {
+        self.weight().div_ceil(4)
+    }
+
+    // Serialized size without witness data, which is the whole transaction here
+    pub fn base_size(&self) -> usize {
+        self.estimated_size()
+    }
+
+    // Serialized size once every input is signed, given the type of output
+    // each input spends (in input order). Adds the typical scriptSig to each
+    // input: 107 bytes for P2PKH, 74 for P2PK and `1 + 74 * m` for bare
+    // `m`-of-`n` multisig; other types and inputs beyond `spending_types` add
+    // nothing. Assumes the scriptSigs are still empty and under 253 bytes.
+    pub fn total_size_with_signatures(&self, spending_types: &[OutputScriptType]) -> usize {
+        let signatures: usize = spending_types
+            .iter()
+            .take(self.inputs.len())
+            .map(|script_type| match script_type {
+                OutputScriptType::P2PKH => 107,
+                OutputScriptType::P2PK => 74,
+                OutputScriptType::P2MS { required, .. } => 1 + 74 * *required as usize,
+                _ => 0,
+            })
+            .sum();
+        self.estimated_size() + signatures
+    }
+
+    // Upper bound on the signed weight, assuming every input spends P2PKH
+    pub fn max_possible_weight(&self) -> u64 {
+        self.max_possible_weight_for_input_types(&[])
+    }
+
+    // Upper bound on the signed weight given the type of output each input
+    // spends (in input order). P2SH is assumed to wrap P2WPKH; inputs beyond
+    // `spending_types`, and types without a fixed spend size, count as P2PKH.
+    pub fn max_possible_weight_for_input_types(&self, spending_types: &[OutputScriptType]) -> u64 {
+        // (scriptSig bytes, witness item bytes excluding the item count)
+        let spend_size = |script_type: Option<&OutputScriptType>| match script_type {
+            Some(OutputScriptType::P2PK) => (73, 0),
+            Some(OutputScriptType::P2SH) => (23, 107),
+            Some(OutputScriptType::P2WPKH) => (0, 107),
+            Some(OutputScriptType::P2TR) => (0, 65),
+            Some(OutputScriptType::P2A) => (0, 0),
+            _ => (107, 0),
+        };
+
+        let (script_sig_bytes, witness_bytes) = (0..self.inputs.len())
+            .map(|index| spend_size(spending_types.get(index)))
+            .fold((0u64, 0u64), |(sig, wit), (s, w)| (sig + s, wit + w));
+
+        let mut weight = self.weight() + script_sig_bytes * 4;
+        if witness_bytes > 0 {
+            // Marker and flag, plus one stack item count per input
+            weight += 2 + self.inputs.len() as u64 + witness_bytes;
+        }
+        weight
+    }
+
+    // Standard policy weight check, see `check_standard_weight_limit`
+    pub fn check_weight_limit(&self) -> Result<(), BitcoinError> {
+        self.check_standard_weight_limit()
+    }
+
+    pub fn check_standard_weight_limit(&self) -> Result<(), BitcoinError> {
+        check_weight(self.weight(), MAX_STANDARD_TX_WEIGHT)
+    }
+
+    pub fn check_consensus_weight_limit(&self) -> Result<(), BitcoinError> {
+        check_weight(self.weight(), MAX_CONSENSUS_TX_WEIGHT)
+    }
+
+    // Core's relay policy: the standard weight limit, version 1 to 3,
+    // push-only scriptSigs of at most 1650 bytes, and standard outputs above
+    // the dust threshold (anchors excepted). Reports the first violation.
+    pub fn check_standard(&self) -> Result<(), BitcoinError> {
+        self.check_standard_weight_limit()?;
+        validation::check_standard_policy(self)
+    }
+
+    pub fn is_standard(&self) -> bool {
+        self.check_standard().is_ok()
+    }
+
+    // Consensus sigop limit: the legacy sigops, at a cost of 4 each, must fit
+    // within a block's 80,000 sigop cost
+    pub fn check_sigops_limit(&self) -> Result<(), BitcoinError> {
+        if self.legacy_sigops().saturating_mul(4) > MAX_BLOCK_SIGOPS_COST {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        Ok(())
+    }
+
+    // BIP-68 relative lock times with `block_height` and `median_time_past`
+    // measured from the confirmation of the spent outputs, i.e. the number of
+    // blocks and seconds that have passed since then
+    pub fn verify_sequence_locks(
+        &self,
+        block_height: u32,
+        median_time_past: u32,
+    ) -> Result<(), BitcoinError> {
+        let coins = vec![(0, 0); self.inputs.len()];
+        self.verify_sequence_locks_for_coins(block_height, median_time_past, &coins)
+    }
+
+    // BIP-68 relative lock times given the (height, median time past) at which
+    // each input's spent output confirmed. Only version 2+ transactions are
+    // subject to BIP-68. The `required` height or time of the first immature
+    // input is reported.
+    pub fn verify_sequence_locks_for_coins(
+        &self,
+        block_height: u32,
+        median_time_past: u32,
+        coins: &[(u32, u32)],
+    ) -> Result<(), BitcoinError> {
+        // Time-based locks count in units of 512 seconds
+        const GRANULARITY: u32 = 9;
+
+        if self.version < 2 {
+            return Ok(());
+        }
+        if coins.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+
+        for (input_index, (input, &(coin_height, coin_time))) in
+            self.inputs.iter().zip(coins).enumerate()
+        {
+            let Some(value) = input.sequence.relative_lock_value() else {
+                continue;
+            };
+            let value = u32::from(value);
+            let (required, current) = if input.sequence.is_time_locked() {
+                (
+                    coin_time.saturating_add(value << GRANULARITY),
+                    median_time_past,
+                )
+            } else {
+                (coin_height.saturating_add(value), block_height)
+            };
+            if current < required {
+                return Err(BitcoinError::SequenceLockNotMet {
+                    input_index,
+                    required,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Outpoints of the outputs this transaction adds to the UTXO set;
+    // OP_RETURN outputs are provably unspendable and never enter it
+    fn created_outpoints(&self) -> impl Iterator<Item = (OutPoint, &TxOutput)> {
+        let txid = self.txid();
+        self.outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| !output.script_pubkey.is_op_return())
+            .map(move |(vout, output)| {
+                (
+                    OutPoint {
+                        txid: Txid(txid),
+                        vout: vout as u32,
+                    },
+                    output,
+                )
+            })
+    }
+
+    // Remove the outputs this transaction spends and add the ones it creates.
+    // Checks everything first, so `utxos` is untouched on error.
+    pub fn apply_to_utxo_set(&self, utxos: &mut UtxoSet) -> Result<(), BitcoinError> {
+        let spent: &[TxInput] = if self.is_coinbase() {
+            &[]
+        } else {
+            &self.inputs
+        };
+        let missing: Vec<OutPoint> = spent
+            .iter()
+            .map(|input| input.previous_output.clone())
+            .filter(|outpoint| !utxos.contains(outpoint))
+            .collect();
+        if !missing.is_empty() {
+            return Err(BitcoinError::MissingUtxos(missing));
+        }
+        let mut seen = std::collections::HashSet::new();
+        if !spent
+            .iter()
+            .all(|input| seen.insert(&input.previous_output))
+            || self
+                .created_outpoints()
+                .any(|(outpoint, _)| utxos.contains(&outpoint))
+        {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+
+        for input in spent {
+            utxos.remove(&input.previous_output);
+        }
+        let created: Vec<_> = self
+            .created_outpoints()
+            .map(|(outpoint, output)| (outpoint, output.clone()))
+            .collect();
+        for (outpoint, output) in created {
+            utxos.insert(outpoint, output);
+        }
+        Ok(())
+    }
+
+    // Undo `apply_to_utxo_set`: remove the outputs this transaction created
+    // and restore the ones it spent. `prev_outputs` holds the spent outputs in
+    // input order (empty for a coinbase). `utxos` is untouched on error.
+    pub fn unapply_to_utxo_set(
+        &self,
+        utxos: &mut UtxoSet,
+        prev_outputs: &[TxOutput],
+    ) -> Result<(), BitcoinError> {
+        let spent: &[TxInput] = if self.is_coinbase() {
+            &[]
+        } else {
+            &self.inputs
+        };
+        if prev_outputs.len() != spent.len()
+            || spent
+                .iter()
+                .any(|input| utxos.contains(&input.previous_output))
+        {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let missing: Vec<OutPoint> = self
+            .created_outpoints()
+            .map(|(outpoint, _)| outpoint)
+            .filter(|outpoint| !utxos.contains(outpoint))
+            .collect();
+        if !missing.is_empty() {
+            return Err(BitcoinError::MissingUtxos(missing));
+        }
+
+        let created: Vec<OutPoint> = self
+            .created_outpoints()
+            .map(|(outpoint, _)| outpoint)
+            .collect();
+        for outpoint in &created {
+            utxos.remove(outpoint);
+        }
+        for (input, output) in spent.iter().zip(prev_outputs) {
+            utxos.insert(input.previous_output.clone(), output.clone());
+        }
+        Ok(())
+    }
+
+    // Total value spent, with the previous outputs given as a slice parallel to
+    // the inputs (the BIP-143/BIP-341 convention)
+    pub fn input_value_from_prevouts(&self, prevouts: &[TxOutput]) -> Result<Amount, BitcoinError> {
+        if prevouts.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        prevouts
+            .iter()
+            .try_fold(Amount::ZERO, |total, prevout| {
+                total.checked_add(prevout.value())
+            })
+            .ok_or(BitcoinError::InvalidAmount)
+    }
+
+    // Input value minus output value, looking up every spent output in `utxo_set`
+    pub fn absolute_fee(&self, utxo_set: &UtxoSet) -> Result<Amount, BitcoinError> {
+        absolute_fee(&self.inputs, &self.outputs, utxo_set)
+    }
+
+    // Absolute fee divided by the virtual size
+    pub fn fee_rate(&self, utxo_set: &UtxoSet) -> Result<FeeRate, BitcoinError> {
+        let fee = self.absolute_fee(utxo_set)?;
+        Ok(FeeRate::from_fee_and_vsize(fee, self.vsize()))
+    }
+
+    // Multi-line, indented description for debugging and test output
+    pub fn debug_dump(&self) -> String {
+        let mut out = format!(
+            "LegacyTransaction {}\n  version: {}\n  inputs ({}):\n",
+            display_hex(&self.txid()),
+            self.version,
+            self.inputs.len()
+        );
+        for (index, input) in self.inputs.iter().enumerate() {
+            out.push_str(&format!(
+                "    [{index}] {}:{}\n        script_sig: {}\n        sequence: {} ({:#010x})\n",
+                input.previous_output.txid_hex(),
+                input.previous_output.vout,
+                hex::encode(input.script_sig.bytes()),
+                input.sequence,
+                input.sequence
+            ));
+        }
+        out.push_str(&format!("  outputs ({}):\n", self.outputs.len()));
+        for (index, output) in self.outputs.iter().enumerate() {
+            out.push_str(&format!(
+                "    [{index}] {} sat ({} BTC)\n        script_pubkey: {}\n        asm: {}\n",
+                output.value.to_sat(),
+                format_btc(output.value.to_sat()),
+                hex::encode(output.script_pubkey.bytes()),
+                output.script_pubkey.to_asm()
+            ));
+        }
+        out.push_str(&format!("  lock_time: {}\n", self.lock_time));
+        out
+    }
+
+    // Pretty-printed JSON (2-space indent) without depending on serde; txids
+    // are shown in display (reversed) byte order
+    pub fn to_json(&self) -> String {
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|input| {
+                format!(
+                    "    {{\n      \"txid\": \"{}\",\n      \"vout\": {},\n      \"script_sig\": \"{}\",\n      \"sequence\": {}\n    }}",
+                    input.previous_output.txid_hex(),
+                    input.previous_output.vout,
+                    hex::encode(input.script_sig.bytes()),
+                    input.sequence
+                )
+            })
+            .collect();
+        let outputs: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                // Standard scripts also get their (mainnet) address
+                let address = Address::from_script(&output.script_pubkey)
+                    .map(|address| format!(",\n      \"address\": \"{address}\""))
+                    .unwrap_or_default();
+                format!(
+                    "    {{\n      \"value\": {},\n      \"script_pubkey\": \"{}\"{}\n    }}",
+                    output.value.to_sat(),
+                    hex::encode(output.script_pubkey.bytes()),
+                    address
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\n  \"txid\": \"{}\",\n  \"version\": {},\n  \"locktime\": {},\n  \"inputs\": {},\n  \"outputs\": {}\n}}",
+            display_hex(&self.txid()),
+            self.version,
+            self.lock_time,
+            json_array(&inputs),
+            json_array(&outputs)
+        )
+    }
+
+    // JSON Schema (draft 7) for the document produced by `to_json`
+    pub const fn json_schema() -> &'static str {
+        r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "LegacyTransaction",
+  "type": "object",
+  "required": ["txid", "version", "locktime", "inputs", "outputs"],
+  "additionalProperties": false,
+  "properties": {
+    "txid": { "type": "string", "pattern": "^[0-9a-f]{64}$" },
+    "version": { "type": "integer", "minimum": -2147483648, "maximum": 2147483647 },
+    "locktime": { "type": "integer", "minimum": 0, "maximum": 4294967295 },
+    "inputs": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "required": ["txid", "vout", "script_sig", "sequence"],
+        "additionalProperties": false,
+        "properties": {
+          "txid": { "type": "string", "pattern": "^[0-9a-f]{64}$" },
+          "vout": { "type": "integer", "minimum": 0, "maximum": 4294967295 },
+          "script_sig": { "type": "string", "pattern": "^([0-9a-f]{2})*$" },
+          "sequence": { "type": "integer", "minimum": 0, "maximum": 4294967295 }
+        }
+      }
+    },
+    "outputs": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "required": ["value", "script_pubkey"],
+        "additionalProperties": false,
+        "properties": {
+          "value": { "type": "integer", "minimum": 0 },
+          "script_pubkey": { "type": "string", "pattern": "^([0-9a-f]{2})*$" },
+          "address": { "type": "string" }
+        }
+      }
+    }
+  }
+}"##
+    }
+
+    // Double SHA-256 of the serialization, in internal byte order
+    pub fn txid(&self) -> [u8; 32] {
+        hash::sha256d(&self.serialize())
+    }
+
+    // The txid as shown by explorers and RPCs: byte-reversed hex
+    pub fn txid_hex(&self) -> String {
+        display_hex(&self.txid())
+    }
+
+    // Parse a raw transaction in the legacy format; trailing bytes are
+    // rejected
+    pub fn from_hex(hex: &str) -> Result<LegacyTransaction, BitcoinError> {
+        LegacyTransaction::deserialize(&hex::decode(hex)?)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.serialize())
+    }
+
+    // Replace the OP_RETURN output at `nonce_output_index` with
+    // `OP_RETURN <8-byte little-endian nonce>`, counting the nonce up from 0
+    // until the txid in display order starts with `target_prefix`. Each
+    // attempt patches the nonce in one serialization rather than re-encoding
+    pub fn nonce_grind(
+        &self,
+        target_prefix: &[u8],
+        nonce_output_index: usize,
+    ) -> Result<LegacyTransaction, BitcoinError> {
+        let output = self
+            .outputs
+            .get(nonce_output_index)
+            .ok_or(BitcoinError::InvalidTransaction)?;
+        if !output.script_pubkey.is_op_return() || target_prefix.len() > 32 {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+
+        let mut candidate = self.clone();
+        candidate.outputs[nonce_output_index].script_pubkey = ScriptBuilder::new()
+            .push_opcode(Opcode::OpReturn)
+            .push_data(&0u64.to_le_bytes())
+            .build();
+        let mut bytes = candidate.serialize();
+        // Version, inputs, the outputs before the nonce output, then its
+        // value, script length and the OP_RETURN and push opcodes
+        let offset = 4
+            + self.inputs.serialize().len()
+            + compact_size_len(self.outputs.len() as u64)
+            + self.outputs[..nonce_output_index]
+                .iter()
+                .map(|output| output.serialize().len())
+                .sum::<usize>()
+            + 8
+            + 1
+            + 2;
+
+        for nonce in 0..=u64::MAX {
+            bytes[offset..offset + 8].copy_from_slice(&nonce.to_le_bytes());
+            let txid = hash::sha256d(&bytes);
+            if txid.iter().rev().zip(target_prefix).all(|(a, b)| a == b) {
+                candidate.outputs[nonce_output_index].script_pubkey = ScriptBuilder::new()
+                    .push_opcode(Opcode::OpReturn)
+                    .push_data(&nonce.to_le_bytes())
+                    .build();
+                return Ok(candidate);
+            }
+        }
+        Err(BitcoinError::InvalidTransaction)
+    }
+
+    // Legacy (pre-SegWit) digest signed by input `input_index`; see
+    // `sighash::legacy_signature_hash`
+    pub fn signature_hash(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        sighash_type: SigHashType,
+    ) -> Result<[u8; 32], BitcoinError> {
+        sighash::legacy_signature_hash(self, input_index, script_code, sighash_type)
+    }
+
+    // Legacy sighash of every input in one pass; `script_codes` holds the
+    // script being spent by each input, in input order
+    pub fn sighash_all_inputs_legacy(
+        &self,
+        script_codes: &[Script],
+        sighash_type: SigHashType,
+    ) -> Result<Vec<[u8; 32]>, BitcoinError> {
+        if script_codes.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        script_codes
+            .iter()
+            .enumerate()
+            .map(|(index, script_code)| {
+                sighash::legacy_signature_hash(self, index, script_code, sighash_type)
+            })
+            .collect()
+    }
+
+    // BIP-143 sighash of every input, computing hashPrevouts, hashSequence
+    // and hashOutputs once rather than per input
+    pub fn sighash_all_inputs_segwit_v0(
+        &self,
+        prevouts: &[TxOutput],
+        script_codes: &[Script],
+        sighash_type: SigHashType,
+    ) -> Result<Vec<[u8; 32]>, BitcoinError> {
+        if prevouts.len() != self.inputs.len() || script_codes.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let cache = sighash::SegwitV0Cache::new(self);
+        prevouts
+            .iter()
+            .zip(script_codes)
+            .enumerate()
+            .map(|(index, (prevout, script_code))| {
+                sighash::segwit_v0_signature_hash(
+                    self,
+                    &cache,
+                    index,
+                    script_code,
+                    prevout.value.to_sat(),
+                    sighash_type,
+                )
+            })
+            .collect()
+    }
+}
+
+impl LegacyTransaction {
+    // Moves the transaction behind an `Arc` so further clones share it
+    pub fn into_arc(self) -> ArcLegacyTransaction {
+        ArcLegacyTransaction(std::sync::Arc::new(self))
+    }
+}
+
+// Reference-counted transaction: `clone()` bumps a counter instead of copying
+// every script. Read access goes through `Deref`; mutation needs
+// `into_transaction`, which only copies when the transaction is still shared
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArcLegacyTransaction(std::sync::Arc<LegacyTransaction>);
+
+impl ArcLegacyTransaction {
+    pub fn as_transaction(&self) -> &LegacyTransaction {
+        &self.0
+    }
+
+    pub fn into_transaction(self) -> LegacyTransaction {
+        std::sync::Arc::unwrap_or_clone(self.0)
+    }
+}
+
+impl std::ops::Deref for ArcLegacyTransaction {
+    type Target = LegacyTransaction;
+
+    fn deref(&self) -> &LegacyTransaction {
+        &self.0
+    }
+}
+
+impl From<LegacyTransaction> for ArcLegacyTransaction {
+    fn from(tx: LegacyTransaction) -> Self {
+        tx.into_arc()
+    }
+}
+
+// Hashes the serialization, so it agrees with the field-wise `Eq`: two
+// transactions are equal exactly when their serializations are
+impl std::hash::Hash for LegacyTransaction {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.serialize().hash(state);
+    }
+}
+
+// One-line summary for logs: abbreviated txid, version, input and output
+// counts, total output value and lock time
+impl std::fmt::Display for LegacyTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total: u64 = self.outputs.iter().fold(0, |total, output| {
+            total.saturating_add(output.value.to_sat())
+        });
+        write!(
+            f,
+            "{}... v{} {}in {}out {}sat lock:{}",
+            &display_hex(&self.txid())[..8],
+            self.version,
+            self.inputs.len(),
+            self.outputs.len(),
+            total,
+            self.lock_time
+        )
     }
 }
 
-// Custom serialization for Bitcoin transaction
-pub trait BitcoinSerialize {
-    // TODO: Implement serialization to bytes
-    fn serialize(&self) -> Vec<u8>;
+// Hex of a hash in display (reversed) byte order
+pub(crate) fn display_hex(hash: &[u8; 32]) -> String {
+    let mut reversed = *hash;
+    reversed.reverse();
+    hex::encode(&reversed)
 }
 
-// Legacy Bitcoin transaction
-#[derive(Debug, Clone)]
-pub struct LegacyTransaction {
-    pub version: i32,
-    pub inputs: Vec<TxInput>,
-    pub outputs: Vec<TxOutput>,
-    pub lock_time: u32,
+// Satoshis as a fixed eight-decimal BTC string
+fn format_btc(sat: u64) -> String {
+    format!("{}.{:08}", sat / 100_000_000, sat % 100_000_000)
 }
 
-impl LegacyTransaction {
-    pub fn builder() -> LegacyTransactionBuilder {
-        LegacyTransactionBuilder::new()
+fn json_array(items: &[String]) -> String {
+    if items.is_empty() {
+        return "[]".to_string();
     }
+    format!("[\n{}\n  ]", items.join(",\n"))
 }
 
 // Transaction builder
@@ -52,7 +1504,7 @@ pub struct LegacyTransactionBuilder {
     pub version: i32,
     pub inputs: Vec<TxInput>,
     pub outputs: Vec<TxOutput>,
-    pub lock_time: u32,
+    pub lock_time: LockTime,
 }
 
 impl Default for LegacyTransactionBuilder {
@@ -61,7 +1513,7 @@ impl Default for LegacyTransactionBuilder {
             version: 1,
             inputs: Vec::with_capacity(1),
             outputs: Vec::with_capacity(1),
-            lock_time: 0,
+            lock_time: LockTime::ZERO,
         }
     }
 }
@@ -71,6 +1523,16 @@ impl LegacyTransactionBuilder {
         LegacyTransactionBuilder::default()
     }
 
+    // Start from a copy of an existing transaction, e.g. to bump its fee
+    pub fn from_transaction(tx: &LegacyTransaction) -> Self {
+        LegacyTransactionBuilder {
+            version: tx.version,
+            inputs: tx.inputs.clone(),
+            outputs: tx.outputs.clone(),
+            lock_time: tx.lock_time,
+        }
+    }
+
     pub fn version(mut self, version: i32) -> Self {
         self.version = version;
         self
@@ -86,11 +1548,113 @@ impl LegacyTransactionBuilder {
         self
     }
 
-    pub fn lock_time(mut self, lock_time: u32) -> Self {
-        self.lock_time = lock_time;
+    pub fn add_inputs(mut self, inputs: impl IntoIterator<Item = TxInput>) -> Self {
+        self.inputs.extend(inputs);
+        self
+    }
+
+    pub fn add_outputs(mut self, outputs: impl IntoIterator<Item = TxOutput>) -> Self {
+        self.outputs.extend(outputs);
+        self
+    }
+
+    // Replace, rather than append to, the inputs added so far
+    pub fn set_inputs(mut self, inputs: Vec<TxInput>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    pub fn set_outputs(mut self, outputs: Vec<TxOutput>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: impl Into<LockTime>) -> Self {
+        self.lock_time = lock_time.into();
         self
     }
 
+    pub fn remove_input(&mut self, index: usize) -> Result<TxInput, BitcoinError> {
+        remove_at(&mut self.inputs, index)
+    }
+
+    pub fn remove_output(&mut self, index: usize) -> Result<TxOutput, BitcoinError> {
+        remove_at(&mut self.outputs, index)
+    }
+
+    // Signal BIP-125 replaceability on every input added so far
+    pub fn enable_rbf(mut self) -> Self {
+        for input in &mut self.inputs {
+            input.sequence = Sequence::ENABLE_RBF;
+        }
+        self
+    }
+
+    // Fund the outputs added so far from `utxos` at `fee_rate`. A changeless
+    // branch-and-bound match is preferred; otherwise the largest UTXOs are
+    // taken and the change pays back to the script of the largest UTXO on
+    // offer. Change that would be dust is left to the fee. Inputs must not
+    // have been added yet, since their values are unknown.
+    pub fn select_coins(mut self, utxos: &[Utxo], fee_rate: FeeRate) -> Result<Self, BitcoinError> {
+        if !self.inputs.is_empty() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let payment = self
+            .outputs
+            .iter()
+            .try_fold(Amount::ZERO, |total, output| {
+                total.checked_add(output.value())
+            })
+            .ok_or(BitcoinError::InvalidAmount)?;
+        let target = payment
+            .checked_add(fee_rate.fee_for_vsize(coinselect::estimate_vsize(&[], &self.outputs)))
+            .ok_or(BitcoinError::InvalidAmount)?;
+
+        let change_script = utxos
+            .iter()
+            .max_by_key(|utxo| utxo.value())
+            .map(|utxo| utxo.output.script_pubkey.clone())
+            .unwrap_or_default();
+        let mut change = TxOutput::new(Amount::ZERO, change_script);
+        let change_fee = fee_rate.fee_for_vsize(change.serialize().len() as u64);
+        // Creating the change output now and spending it later
+        let cost_of_change =
+            change_fee.saturating_add(fee_rate.fee_for_vsize(change.expected_input_vbytes()));
+
+        if let Some(selected) =
+            coinselect::branch_and_bound(utxos, target, fee_rate, cost_of_change)
+        {
+            self.inputs = selected
+                .into_iter()
+                .map(|utxo| TxInput::from_outpoint(utxo.outpoint))
+                .collect();
+            return Ok(self);
+        }
+
+        let needed = target.saturating_add(change_fee);
+        let selected = coinselect::largest_first(utxos, needed, fee_rate)?;
+        let total = selected.iter().fold(Amount::ZERO, |total, utxo| {
+            total.saturating_add(utxo.value())
+        });
+        let with_change = [self.outputs.as_slice(), std::slice::from_ref(&change)].concat();
+        let fee = fee_rate.fee_for_vsize(coinselect::estimate_vsize(&selected, &with_change));
+        let change_value = total.checked_sub(payment.saturating_add(fee)).ok_or(
+            BitcoinError::InsufficientFunds {
+                needed: payment.saturating_add(fee),
+                available: total,
+            },
+        )?;
+        change.value = change_value;
+        if !change.is_dust(validation::DUST_RELAY_FEE_RATE) {
+            self.outputs.push(change);
+        }
+        self.inputs = selected
+            .into_iter()
+            .map(|utxo| TxInput::from_outpoint(utxo.outpoint))
+            .collect();
+        Ok(self)
+    }
+
     pub fn build(self) -> LegacyTransaction {
         LegacyTransaction {
             version: self.version,
@@ -99,70 +1663,1075 @@ impl LegacyTransactionBuilder {
             lock_time: self.lock_time,
         }
     }
+
+    // Build, rejecting transactions without inputs or outputs, output values
+    // above 21M BTC singly or in total, dust outputs (at the relay dust fee
+    // rate; anchors and OP_RETURN are exempt) and inputs spending the same
+    // outpoint twice
+    pub fn try_build(self) -> Result<LegacyTransaction, BitcoinError> {
+        let first = |errors: Vec<BitcoinError>| errors.into_iter().next().map_or(Ok(()), Err);
+        first(validation::check_not_empty(&self.inputs, &self.outputs))?;
+        first(validation::check_output_values(&self.outputs))?;
+        first(validation::check_dust_outputs(&self.outputs))?;
+        first(validation::check_duplicate_inputs(&self.inputs))?;
+
+        Ok(self.build())
+    }
+
+    // Build, rejecting transactions that are not standard, see
+    // `LegacyTransaction::check_standard`
+    pub fn build_validated(self) -> Result<LegacyTransaction, BitcoinError> {
+        let tx = self.build();
+        tx.check_standard()?;
+        Ok(tx)
+    }
+}
+
+// Remove `items[index]`, shifting later items down
+fn remove_at<T>(items: &mut Vec<T>, index: usize) -> Result<T, BitcoinError> {
+    if index >= items.len() {
+        return Err(BitcoinError::InvalidTransaction);
+    }
+    Ok(items.remove(index))
 }
 
 // Transaction components
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TxInput {
-    pub previous_output: OutPoint,
-    pub script_sig: Vec<u8>,
-    pub sequence: u32,
+    pub(crate) previous_output: OutPoint,
+    pub(crate) script_sig: Script,
+    pub(crate) sequence: Sequence,
 }
 
-#[derive(Debug, Clone)]
+impl TxInput {
+    pub fn new(
+        previous_output: OutPoint,
+        script_sig: Script,
+        sequence: impl Into<Sequence>,
+    ) -> TxInput {
+        TxInput {
+            previous_output,
+            script_sig,
+            sequence: sequence.into(),
+        }
+    }
+
+    // Unsigned input spending `outpoint` with nLockTime enabled (sequence
+    // 0xFFFFFFFE); this does not signal replace-by-fee
+    pub fn from_outpoint(outpoint: OutPoint) -> TxInput {
+        TxInput {
+            previous_output: outpoint,
+            script_sig: Script::default(),
+            sequence: Sequence::ENABLE_LOCKTIME,
+        }
+    }
+
+    // Unsigned input spending `outpoint` with a final sequence, which also
+    // disables nLockTime
+    pub fn from_outpoint_final(outpoint: OutPoint) -> TxInput {
+        TxInput {
+            sequence: Sequence::MAX,
+            ..TxInput::from_outpoint(outpoint)
+        }
+    }
+
+    pub fn outpoint(&self) -> &OutPoint {
+        &self.previous_output
+    }
+
+    pub fn script_sig(&self) -> &Script {
+        &self.script_sig
+    }
+
+    pub fn sequence(&self) -> Sequence {
+        self.sequence
+    }
+
+    pub fn set_script_sig(&mut self, script: Script) {
+        self.script_sig = script;
+    }
+
+    pub fn set_sequence(&mut self, seq: impl Into<Sequence>) {
+        self.sequence = seq.into();
+    }
+}
+
+/// Defaults to a coinbase-pattern input: the null outpoint, an empty
+/// `script_sig` and a final (`0xFFFFFFFF`) sequence.
+impl Default for TxInput {
+    fn default() -> Self {
+        TxInput {
+            previous_output: OutPoint::default(),
+            script_sig: Script::default(),
+            sequence: Sequence::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct TxOutput {
-    pub value: u64, // in satoshis
-    pub script_pubkey: Vec<u8>,
+    pub(crate) value: Amount,
+    pub(crate) script_pubkey: Script,
 }
 
-#[derive(Debug, Clone)]
+impl TxOutput {
+    pub fn new(value: Amount, script_pubkey: Script) -> TxOutput {
+        TxOutput {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    pub fn script_pubkey(&self) -> &Script {
+        &self.script_pubkey
+    }
+
+    // Dust threshold: 3x the fee to create and later spend an output paying to
+    // `script`. Spending costs 148 vbytes for a legacy input and 67 vbytes
+    // for a witness program (107 witness bytes, discounted with Core's integer
+    // division); unspendable OP_RETURN outputs are never dust.
+    pub fn dust_threshold_for_script(script: &Script, fee_rate: FeeRate) -> Amount {
+        if script.is_op_return() {
+            return Amount::from_sat(0);
+        }
+
+        let output_size = (8 + compact_size_len(script.len() as u64) + script.len()) as u64;
+        let spend_size = if script.witness_version().is_some() {
+            // outpoint + empty scriptSig + sequence, plus 107 witness bytes
+            32 + 4 + 1 + 4 + 107 / 4
+        } else {
+            148
+        };
+
+        let size = output_size + spend_size;
+        Amount::from_sat(3 * fee_rate.to_sat_per_kvb() * size / 1000)
+    }
+
+    pub fn dust_threshold(&self, fee_rate: FeeRate) -> Amount {
+        TxOutput::dust_threshold_for_script(&self.script_pubkey, fee_rate)
+    }
+
+    pub fn is_dust(&self, fee_rate: FeeRate) -> bool {
+        self.value < self.dust_threshold(fee_rate)
+    }
+
+    // Output paying `value` to `address`, which must belong to `network`
+    pub fn create_from_address(
+        address: &str,
+        value: Amount,
+        network: Network,
+    ) -> Result<TxOutput, BitcoinError> {
+        let address = Address::from_str_for_network(address, network)?;
+        Ok(TxOutput {
+            value,
+            script_pubkey: address.script_pubkey(),
+        })
+    }
+
+    // Value left after paying to spend this output at `fee_rate`; negative
+    // when the output costs more to spend than it is worth
+    pub fn effective_value(&self, fee_rate: FeeRate, input_vbytes: u64) -> i64 {
+        let spend_fee = fee_rate.fee_for_vsize(input_vbytes).to_sat();
+        self.value.to_sat() as i64 - spend_fee as i64
+    }
+
+    // Estimated vbytes of an input spending this output: 41 bytes of
+    // outpoint/scriptSig length/sequence plus the typical scriptSig, and
+    // witness bytes at a quarter weight (rounded up). P2WSH assumes a 2-of-3
+    // multisig; unknown scripts fall back to the P2PKH estimate.
+    pub fn expected_input_vbytes(&self) -> u64 {
+        match self.script_type() {
+            OutputScriptType::P2PK => 41 + 73,
+            OutputScriptType::P2PKH | OutputScriptType::NonStandard => 41 + 107,
+            OutputScriptType::P2SH => 41 + 23 + 107_u64.div_ceil(4),
+            OutputScriptType::P2WPKH => 41 + 107_u64.div_ceil(4),
+            OutputScriptType::P2WSH => 41 + 254_u64.div_ceil(4),
+            OutputScriptType::P2TR => 41 + 65_u64.div_ceil(4),
+            OutputScriptType::P2A => 41,
+            // OP_0 dummy plus `required` signatures
+            OutputScriptType::P2MS { required, .. } => 41 + 1 + 73 * required as u64,
+            OutputScriptType::OpReturn => 0,
+        }
+    }
+
+    // Whether the scriptPubKey matches one of the standard templates
+    pub fn is_standard(&self) -> bool {
+        self.script_type() != OutputScriptType::NonStandard
+    }
+
+    // Classify the scriptPubKey against the standard templates
+    pub fn script_type(&self) -> OutputScriptType {
+        self.script_pubkey.classify()
+    }
+}
+
+// Ordered by txid bytes, then vout
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OutPoint {
-    pub txid: [u8; 32],
+    pub txid: Txid,
     pub vout: u32,
 }
 
+impl OutPoint {
+    /// Returns true for the null outpoint (all-zero txid, vout `0xFFFFFFFF`).
+    pub fn is_null(&self) -> bool {
+        *self == OutPoint::default()
+    }
+
+    /// Builds an outpoint from a txid in display (block explorer) order.
+    pub fn from_txid_hex(txid_hex: &str, vout: u32) -> Result<OutPoint, BitcoinError> {
+        Ok(OutPoint {
+            txid: txid_hex.parse()?,
+            vout,
+        })
+    }
+
+    /// The txid in display (reversed) byte order.
+    pub fn txid_hex(&self) -> String {
+        self.txid.to_string()
+    }
+}
+
+/// Defaults to the null outpoint: an all-zero txid with vout `0xFFFFFFFF`.
+///
+/// The null outpoint does not reference a real previous output; it is the
+/// marker used by the single input of a coinbase transaction.
+impl Default for OutPoint {
+    fn default() -> Self {
+        OutPoint {
+            txid: Txid::default(),
+            vout: 0xFFFFFFFF,
+        }
+    }
+}
+
+// `txid:vout` with the txid in display (reversed) byte order
+impl std::fmt::LowerHex for OutPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.txid_hex(), self.vout)
+    }
+}
+
+impl std::fmt::UpperHex for OutPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.txid_hex().to_ascii_uppercase(), self.vout)
+    }
+}
+
+// Parses the `txid:vout` form produced by `{:x}` / `{:X}`
+impl std::str::FromStr for OutPoint {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (txid_hex, vout) = s.split_once(':').ok_or_else(|| {
+            BitcoinError::cli_error(ParseErrorCode::InvalidArgument, "Expected txid:vout")
+        })?;
+        OutPoint::from_txid_hex(txid_hex, vout.parse()?)
+    }
+}
+
 // Simple CLI argument parser
 pub fn parse_cli_args(args: &[String]) -> Result<CliCommand, BitcoinError> {
     CliCommand::try_from(args)
 }
 
+// Fields that `inspect` can extract from a transaction
+pub const INSPECT_FIELDS: [&str; 7] = [
+    "version",
+    "lock_time",
+    "input_count",
+    "output_count",
+    "txid",
+    "weight",
+    "fee_rate",
+];
+
+// Name, argument synopsis and summary of every command, from which the
+// `--help` text is generated
+pub const CLI_COMMANDS: [(&str, &str, &str); 12] = [
+    (
+        "send",
+        "<amount> <address>",
+        "Pay <amount> (satoshis, or with a BTC/sat unit) to <address> from the wallet",
+    ),
+    ("balance", "", "Show the wallet's confirmed balance"),
+    (
+        "decode",
+        "<raw_hex>",
+        "Print the fields of a legacy-format transaction",
+    ),
+    (
+        "inspect",
+        "<raw_hex> <field> [--input-value <sat>]...",
+        "Extract one field: version, lock_time, input_count, output_count, txid, weight or fee_rate",
+    ),
+    (
+        "createtx",
+        "[--input <txid:vout:script_pubkey_hex>]... [--output <address:sat>]... [--network <name>] [--locktime <n>]",
+        "Build an unsigned transaction",
+    ),
+    (
+        "rawsign",
+        "<tx_hex> <private_key_hex> [--script <script_pubkey_hex>]...",
+        "Sign every input with a raw secret key",
+    ),
+    (
+        "verify",
+        "<tx_hex> [--utxo <txid:vout:script_pubkey_hex>]...",
+        "Run every input's scripts against the spent outputs",
+    ),
+    (
+        "broadcast",
+        "<raw_hex> [--network <name>]",
+        "Frame a transaction as a P2P tx message",
+    ),
+    (
+        "genkey",
+        "[--network <name>]",
+        "Generate a key pair and its P2WPKH address",
+    ),
+    (
+        "estimate",
+        "<raw_hex> [--fee-rate <sat/vB>] [--target-blocks <n>]",
+        "Fee for a raw transaction",
+    ),
+    (
+        "estimatefee",
+        "<vsize> <sat/vB>",
+        "Fee for a transaction of <vsize> virtual bytes",
+    ),
+    (
+        "help",
+        "[command]",
+        "Show this help, or the usage of one command",
+    ),
+];
+
+// Usage text for `command`, or the overview of every command when `None`.
+// `None` is returned for an unknown command.
+pub fn cli_help(command: Option<&str>) -> Option<String> {
+    let Some(command) = command else {
+        let mut text = "Usage: <command> [arguments]\n\nCommands:".to_string();
+        for (name, args, summary) in CLI_COMMANDS {
+            text.push_str(format!("\n  {name} {args}").trim_end());
+            text.push_str(&format!("\n      {summary}"));
+        }
+        text.push_str("\n\nFlags also accept the --flag=value form.");
+        return Some(text);
+    };
+    let (name, args, summary) = CLI_COMMANDS.iter().find(|(name, ..)| *name == command)?;
+    let usage = format!("Usage: {name} {args}");
+    Some(format!("{}\n\n{summary}", usage.trim_end()))
+}
+
+// With the `serde` feature, a command is an object tagged by its CLI name,
+// e.g. `{"command": "send", "amount": 1000, "address": "..."}`
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "command", rename_all = "lowercase"))]
 pub enum CliCommand {
-    Send { amount: u64, address: String },
+    Send {
+        amount: Amount,
+        address: String,
+    },
     Balance,
+    // Values of the spent outputs are given with `--input-value <sat>`, one
+    // per input, and are only needed for `fee_rate`
+    Inspect {
+        raw_hex: String,
+        field: String,
+        input_values: Vec<u64>,
+    },
+    // Sign with a raw 32-byte secret key given as hex; `input_scripts` are the
+    // scriptPubKeys being spent, one per input
+    RawSign {
+        tx_hex: String,
+        private_key_hex: String,
+        input_scripts: Vec<String>,
+    },
+    // Frame a raw transaction as a P2P `tx` message for `network`
+    Broadcast {
+        raw_hex: String,
+        network: Network,
+    },
+    // Check every input's scriptSig against the spent outputs, each given as
+    // `txid:vout:script_pubkey_hex`
+    Verify {
+        tx_hex: String,
+        utxo_hex: Vec<String>,
+    },
+    // Build an unsigned transaction from `--input txid:vout:script_pubkey_hex`
+    // and `--output address:sat` arguments. Whether the inputs need SegWit
+    // (BIP-143) signing is reported on stderr.
+    CreateTx {
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+        network: Network,
+        locktime: u32,
+    },
+    Decode {
+        raw_hex: String,
+    },
+    // Generate a random key pair; the WIF private key and public key go to
+    // stderr and only the P2WPKH address is the result
+    #[cfg_attr(feature = "serde", serde(rename = "genkey"))]
+    GenerateKey {
+        network: Network,
+    },
+    // Fee for a raw transaction at `--fee-rate <sat/vB>`, or at the offline
+    // table rate for `--target-blocks <n>` (default 6) when no rate is given
+    Estimate {
+        raw_hex: String,
+        fee_rate: Option<FeeRate>,
+        target_blocks: u32,
+    },
+    // Fee for `size` virtual bytes at `rate`, without a transaction at hand
+    EstimateFee {
+        size: u64,
+        rate: FeeRate,
+    },
+    // Usage of one command, or of all of them
+    Help {
+        topic: Option<String>,
+    },
+}
+
+// A parsed transaction together with its derived identifiers
+#[derive(Debug, Clone)]
+pub struct DecodedTransaction {
+    pub txid: String,
+    pub weight: u64,
+    pub transaction: LegacyTransaction,
+}
+
+impl From<LegacyTransaction> for DecodedTransaction {
+    fn from(transaction: LegacyTransaction) -> Self {
+        DecodedTransaction {
+            txid: display_hex(&transaction.txid()),
+            weight: transaction.weight(),
+            transaction,
+        }
+    }
+}
+
+// Typed outcome of a CLI command, rendered with `to_text` or `to_json`
+#[derive(Debug, Clone)]
+pub enum CommandResult {
+    TransactionHex(String),
+    Balance { sat: u64, btc: String },
+    Decoded(DecodedTransaction),
+    Fee { sat: u64 },
+    // A single named value, as extracted by `inspect`
+    Field { name: String, value: String },
+    Help(String),
+    Success,
+}
+
+impl CommandResult {
+    pub fn balance(sat: u64) -> Self {
+        CommandResult::Balance {
+            sat,
+            btc: format_btc(sat),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            CommandResult::TransactionHex(hex) => hex.clone(),
+            CommandResult::Balance { sat, btc } => format!("{btc} BTC ({sat} sat)"),
+            CommandResult::Decoded(decoded) => {
+                let tx = &decoded.transaction;
+                format!(
+                    "txid: {}\nversion: {}\nlock_time: {}\ninputs: {}\noutputs: {}\nweight: {}",
+                    decoded.txid,
+                    tx.version(),
+                    tx.lock_time().to_consensus_u32(),
+                    tx.inputs().len(),
+                    tx.outputs().len(),
+                    decoded.weight
+                )
+            }
+            CommandResult::Fee { sat } => format!("{sat} sat"),
+            CommandResult::Field { value, .. } => value.clone(),
+            CommandResult::Help(text) => text.clone(),
+            CommandResult::Success => "OK".to_string(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            CommandResult::TransactionHex(hex) => format!("{{\"hex\": \"{hex}\"}}"),
+            CommandResult::Balance { sat, btc } => {
+                format!("{{\"sat\": {sat}, \"btc\": \"{btc}\"}}")
+            }
+            CommandResult::Decoded(decoded) => decoded.transaction.to_json(),
+            CommandResult::Fee { sat } => format!("{{\"fee_sat\": {sat}}}"),
+            CommandResult::Field { name, value } => format!("{{\"{name}\": \"{value}\"}}"),
+            // The help text is ASCII, for which Rust's string escapes are valid JSON
+            CommandResult::Help(text) => format!("{{\"help\": {text:?}}}"),
+            CommandResult::Success => "{\"success\": true}".to_string(),
+        }
+    }
+}
+
+impl CliCommand {
+    // Run the command, returning a typed result for the caller to render
+    pub fn execute(&self) -> Result<CommandResult, BitcoinError> {
+        match self {
+            CliCommand::Inspect {
+                raw_hex,
+                field,
+                input_values,
+            } => {
+                let bytes = hex::decode(raw_hex)?;
+                let tx = TransactionReader::new(&bytes[..]).read_transaction()?;
+
+                let value = match field.as_str() {
+                    "version" => tx.version().to_string(),
+                    "lock_time" => tx.lock_time().to_consensus_u32().to_string(),
+                    "input_count" => tx.inputs().len().to_string(),
+                    "output_count" => tx.outputs().len().to_string(),
+                    "txid" => display_hex(&tx.txid()),
+                    "weight" => tx.weight().to_string(),
+                    "fee_rate" => {
+                        if input_values.len() != tx.inputs().len() {
+                            return Err(BitcoinError::cli_error(
+                                ParseErrorCode::InvalidArgument,
+                                format!(
+                                    "fee_rate needs one --input-value per input ({} given, {} inputs)",
+                                    input_values.len(),
+                                    tx.inputs().len()
+                                ),
+                            ));
+                        }
+                        let mut utxo_set = UtxoSet::new();
+                        for (input, value) in tx.inputs().iter().zip(input_values) {
+                            utxo_set.insert(
+                                input.previous_output.clone(),
+                                TxOutput {
+                                    value: Amount::from_sat(*value),
+                                    script_pubkey: Script::default(),
+                                },
+                            );
+                        }
+                        tx.fee_rate(&utxo_set)?.to_string()
+                    }
+                    _ => {
+                        return Err(BitcoinError::cli_error(
+                            ParseErrorCode::InvalidArgument,
+                            "unknown field",
+                        ));
+                    }
+                };
+                Ok(CommandResult::Field {
+                    name: field.clone(),
+                    value,
+                })
+            }
+            CliCommand::RawSign {
+                tx_hex,
+                private_key_hex,
+                input_scripts,
+            } => {
+                let bytes = hex::decode(tx_hex)?;
+                let tx = TransactionReader::new(&bytes[..]).read_transaction()?;
+                let secret_key: [u8; 32] =
+                    hex::decode(private_key_hex)?.try_into().map_err(|_| {
+                        BitcoinError::cli_error(ParseErrorCode::InvalidHex, "Invalid private key")
+                    })?;
+                let script_codes = input_scripts
+                    .iter()
+                    .map(|script| hex::decode(script).map(Script))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let signed = signer::sign_legacy_inputs(&tx, &secret_key, &script_codes)?;
+                Ok(CommandResult::TransactionHex(hex::encode(
+                    &signed.serialize(),
+                )))
+            }
+            CliCommand::Broadcast { raw_hex, network } => {
+                let bytes = hex::decode(raw_hex)?;
+                let tx = TransactionReader::new(&bytes[..]).read_transaction()?;
+                let message = p2p::tx_message(*network, &tx)?;
+                // The framed message is the result; the txid goes to stderr so
+                // stdout can be piped straight to a peer
+                eprintln!("txid: {}", display_hex(&tx.txid()));
+                Ok(CommandResult::TransactionHex(hex::encode(&message)))
+            }
+            CliCommand::CreateTx {
+                inputs,
+                outputs,
+                network,
+                locktime,
+            } => {
+                let mut builder = LegacyTransaction::builder().version(2).lock_time(*locktime);
+                let mut spending_types = Vec::new();
+                for input in inputs {
+                    let (outpoint, script) = input.rsplit_once(':').ok_or_else(|| {
+                        BitcoinError::cli_error(
+                            ParseErrorCode::InvalidArgument,
+                            format!("Invalid input: {input}"),
+                        )
+                    })?;
+                    let spent = TxOutput {
+                        value: Amount::ZERO,
+                        script_pubkey: Script(hex::decode(script)?),
+                    };
+                    spending_types.push(spent.script_type());
+                    builder = builder.add_input(TxInput::from_outpoint(outpoint.parse()?));
+                }
+                for output in outputs {
+                    let (address, sat) = output.rsplit_once(':').ok_or_else(|| {
+                        BitcoinError::cli_error(
+                            ParseErrorCode::InvalidArgument,
+                            format!("Invalid output: {output}"),
+                        )
+                    })?;
+                    let sat = sat.parse::<u64>().map_err(|_| {
+                        BitcoinError::cli_error(
+                            ParseErrorCode::InvalidAmount,
+                            format!("Invalid amount: {sat}"),
+                        )
+                    })?;
+                    builder = builder.add_output(TxOutput::create_from_address(
+                        address,
+                        Amount::from_sat(sat),
+                        *network,
+                    )?);
+                }
+
+                // Unsigned, the bytes are the same either way: a SegWit
+                // serialization only differs once witnesses are added
+                let format = if LegacyTransaction::requires_segwit_spending(&spending_types) {
+                    "segwit (sign with BIP-143)"
+                } else {
+                    "legacy"
+                };
+                eprintln!("format: {format}");
+                Ok(CommandResult::TransactionHex(hex::encode(
+                    &builder.build().serialize(),
+                )))
+            }
+            CliCommand::GenerateKey { network } => {
+                // Retry the (astronomically unlikely) scalars outside 1..n
+                let private_key = loop {
+                    let mut secret_key = [0u8; 32];
+                    getrandom::fill(&mut secret_key)
+                        .map_err(|_| BitcoinError::RandomnessUnavailable)?;
+                    if let Ok(private_key) = PrivateKey::new(secret_key, *network) {
+                        break private_key;
+                    }
+                };
+                let pubkey = private_key.public_key();
+                let address = pubkey.p2wpkh_address()?.to_string_for_network(*network);
+
+                // Keep the private key off stdout so it is not piped or logged
+                eprintln!("private key (WIF): {}", private_key.to_wif());
+                eprintln!("public key: {}", hex::encode(&pubkey.to_bytes()));
+                Ok(CommandResult::Field {
+                    name: "address".to_string(),
+                    value: address,
+                })
+            }
+            CliCommand::Verify { tx_hex, utxo_hex } => {
+                let bytes = hex::decode(tx_hex)?;
+                let tx = TransactionReader::new(&bytes[..]).read_transaction()?;
+                let mut utxo_set = UtxoSet::new();
+                for utxo in utxo_hex {
+                    let (outpoint, script) = utxo.rsplit_once(':').ok_or_else(|| {
+                        BitcoinError::cli_error(
+                            ParseErrorCode::InvalidArgument,
+                            format!("Invalid UTXO: {utxo}"),
+                        )
+                    })?;
+                    let output = TxOutput {
+                        value: Amount::ZERO,
+                        script_pubkey: Script(hex::decode(script)?),
+                    };
+                    utxo_set.insert(outpoint.parse()?, output);
+                }
+
+                // Report every input on stderr, then fail with all the errors
+                let flags = ScriptVerifyFlags::P2SH
+                    | ScriptVerifyFlags::CHECKLOCKTIMEVERIFY
+                    | ScriptVerifyFlags::CHECKSEQUENCEVERIFY;
+                let mut failures = Vec::new();
+                for (index, input) in tx.inputs().iter().enumerate() {
+                    let result = utxo_set
+                        .get(&input.previous_output)
+                        .ok_or(BitcoinError::MissingUtxos(vec![
+                            input.previous_output.clone(),
+                        ]))
+                        .and_then(|utxo| tx.verify_input(index, utxo, flags));
+                    match result {
+                        Ok(()) => eprintln!("input {index}: OK"),
+                        Err(err) => {
+                            eprintln!("input {index}: {err}");
+                            failures.push(format!("input {index}: {err}"));
+                        }
+                    }
+                }
+
+                if failures.is_empty() {
+                    Ok(CommandResult::Field {
+                        name: "result".to_string(),
+                        value: "all inputs valid".to_string(),
+                    })
+                } else {
+                    Err(BitcoinError::CheckFailed(failures.join("; ")))
+                }
+            }
+            CliCommand::Estimate {
+                raw_hex,
+                fee_rate,
+                target_blocks,
+            } => {
+                let bytes = hex::decode(raw_hex)?;
+                let tx = TransactionReader::new(&bytes[..]).read_any_transaction()?;
+                let fee_rate =
+                    fee_rate.unwrap_or_else(|| FeeRate::from_target_blocks(*target_blocks));
+                Ok(CommandResult::Fee {
+                    sat: fee_rate.fee_for_vsize(tx.vsize()).to_sat(),
+                })
+            }
+            CliCommand::Decode { raw_hex } => {
+                let bytes = hex::decode(raw_hex)?;
+                let tx = TransactionReader::new(&bytes[..]).read_transaction()?;
+                Ok(CommandResult::Decoded(tx.into()))
+            }
+            CliCommand::EstimateFee { size, rate } => Ok(CommandResult::Fee {
+                sat: rate.fee_for_vsize(*size).to_sat(),
+            }),
+            CliCommand::Help { topic } => {
+                // The parser only accepts known commands
+                Ok(CommandResult::Help(
+                    cli_help(topic.as_deref()).unwrap_or_default(),
+                ))
+            }
+            CliCommand::Send { .. } | CliCommand::Balance => Err(BitcoinError::cli_error(
+                ParseErrorCode::WalletRequired,
+                "Command requires a wallet",
+            )),
+        }
+    }
+
+    // Run the command against `wallet`. `balance` reports the confirmed
+    // balance; `send` funds an unsigned payment from the wallet's coins at the
+    // 6-block table fee rate, with change back to the script of its largest
+    // coin, and applies it to the wallet: the spent coins are removed and the
+    // change is tracked unconfirmed. Other commands ignore the wallet.
+    pub fn execute_with_wallet(&self, wallet: &mut UtxoSet) -> Result<CommandResult, BitcoinError> {
+        match self {
+            CliCommand::Balance => Ok(CommandResult::balance(wallet.confirmed_balance().to_sat())),
+            CliCommand::Send { amount, address } => {
+                let (address, _) = Address::from_str_any_network(address)?;
+                let tx = LegacyTransaction::builder()
+                    .version(2)
+                    .add_output(TxOutput::new(*amount, address.script_pubkey()))
+                    .select_coins(&wallet.to_utxos(), FeeRate::from_target_blocks(6))?
+                    .enable_rbf()
+                    .build();
+                let watched = wallet.scripts();
+                wallet.apply_transaction(&tx, &watched, None);
+                Ok(CommandResult::TransactionHex(hex::encode(&tx.serialize())))
+            }
+            _ => self.execute(),
+        }
+    }
+
+    // `execute_with_wallet` on the UTXO set saved at `path`, which starts
+    // empty if the file does not exist yet. A `send` saves the updated set.
+    pub fn execute_with_wallet_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<CommandResult, BitcoinError> {
+        let path = path.as_ref();
+        let mut wallet = if path.exists() {
+            UtxoSet::load(path)?
+        } else {
+            UtxoSet::new()
+        };
+        let result = self.execute_with_wallet(&mut wallet)?;
+        if matches!(self, CliCommand::Send { .. }) {
+            wallet.save(path)?;
+        }
+        Ok(result)
+    }
 }
 
 impl TryFrom<&[String]> for CliCommand {
     type Error = BitcoinError;
 
     fn try_from(args: &[String]) -> Result<Self, Self::Error> {
-        use BitcoinError::ParseError;
+        use BitcoinError::CliError;
+        let missing = |name: &'static str| CliError {
+            code: ParseErrorCode::MissingArgument { name },
+            message: format!("Not enough arguments: missing {name}"),
+        };
+        let invalid = |code: ParseErrorCode, message: &str| CliError {
+            code,
+            message: message.to_string(),
+        };
+
+        // `--flag=value` is split into `--flag value`
+        let args: Vec<String> = args
+            .iter()
+            .flat_map(|arg| match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    vec![flag.to_string(), value.to_string()]
+                }
+                _ => vec![arg.clone()],
+            })
+            .collect();
 
         let mut args = args.iter();
         let commands = args
             .next()
-            .ok_or_else(|| ParseError("Not enough parameters".to_string()))?;
+            .ok_or_else(|| invalid(ParseErrorCode::MissingCommand, "Not enough parameters"))?;
+        let unknown = || CliError {
+            code: ParseErrorCode::UnknownCommand,
+            message: format!("Unknown command: {commands}"),
+        };
+
+        if matches!(commands.as_str(), "help" | "--help" | "-h") {
+            let topic = args.next().cloned();
+            if cli_help(topic.as_deref()).is_none() {
+                return Err(CliError {
+                    code: ParseErrorCode::UnknownCommand,
+                    message: format!("Unknown command: {}", topic.unwrap_or_default()),
+                });
+            }
+            return Ok(CliCommand::Help { topic });
+        }
+        // `<command> --help` anywhere among the arguments asks for its usage
+        if args.clone().any(|arg| arg == "--help") {
+            cli_help(Some(commands)).ok_or_else(unknown)?;
+            return Ok(CliCommand::Help {
+                topic: Some(commands.clone()),
+            });
+        }
 
         match commands.as_str() {
             "send" => {
-                let amount = args
-                    .next()
-                    .ok_or(ParseError("Not enough arguments".to_string()))?;
-                let address = args
-                    .next()
-                    .ok_or(ParseError("Not enough arguments".to_string()))?
-                    .to_string();
+                let amount = args.next().ok_or_else(|| missing("amount"))?;
+                let address = args.next().ok_or_else(|| missing("address"))?.to_string();
 
+                // A bare number is in satoshis; otherwise the unit is given,
+                // as in "0.001 BTC"
                 let amount = amount
                     .parse::<u64>()
-                    .map_err(|_| ParseError("Invalid amount".into()))?;
+                    .map(Amount::from_sat)
+                    .or_else(|_| amount.parse::<Amount>())
+                    .map_err(|_| invalid(ParseErrorCode::InvalidAmount, "Invalid amount"))?;
+                // Any network is accepted; the wallet decides which it pays on
+                address
+                    .parse::<Address>()
+                    .map_err(|_| invalid(ParseErrorCode::InvalidAddress, "Invalid address"))?;
 
                 Ok(CliCommand::Send { amount, address })
             }
             "balance" => Ok(CliCommand::Balance),
-            _ => Err(BitcoinError::ParseError(format!(
-                "Unknown command: {commands}"
-            ))),
+            "inspect" => {
+                let raw_hex = args.next().ok_or_else(|| missing("raw_hex"))?.to_string();
+                let field = args.next().ok_or_else(|| missing("field"))?.to_string();
+                if !INSPECT_FIELDS.contains(&field.as_str()) {
+                    return Err(invalid(ParseErrorCode::InvalidArgument, "unknown field"));
+                }
+
+                let mut input_values = Vec::new();
+                while let Some(flag) = args.next() {
+                    if flag != "--input-value" {
+                        return Err(invalid(
+                            ParseErrorCode::InvalidArgument,
+                            &format!("Unknown flag: {flag}"),
+                        ));
+                    }
+                    let value = args
+                        .next()
+                        .ok_or_else(|| missing("input_value"))?
+                        .parse::<u64>()
+                        .map_err(|_| invalid(ParseErrorCode::InvalidAmount, "Invalid amount"))?;
+                    input_values.push(value);
+                }
+
+                Ok(CliCommand::Inspect {
+                    raw_hex,
+                    field,
+                    input_values,
+                })
+            }
+            "rawsign" => {
+                let tx_hex = args.next().ok_or_else(|| missing("tx_hex"))?.to_string();
+                let private_key_hex = args
+                    .next()
+                    .ok_or_else(|| missing("private_key_hex"))?
+                    .to_string();
+                if private_key_hex.len() != 64 || hex::decode(&private_key_hex).is_err() {
+                    return Err(invalid(
+                        ParseErrorCode::InvalidHex,
+                        "Private key must be 64 hex characters",
+                    ));
+                }
+
+                let mut input_scripts = Vec::new();
+                while let Some(flag) = args.next() {
+                    if flag != "--script" {
+                        return Err(invalid(
+                            ParseErrorCode::InvalidArgument,
+                            &format!("Unknown flag: {flag}"),
+                        ));
+                    }
+                    let script = args.next().ok_or_else(|| missing("script"))?;
+                    input_scripts.push(script.to_string());
+                }
+
+                Ok(CliCommand::RawSign {
+                    tx_hex,
+                    private_key_hex,
+                    input_scripts,
+                })
+            }
+            "verify" => {
+                let tx_hex = args.next().ok_or_else(|| missing("tx_hex"))?.to_string();
+
+                let mut utxo_hex = Vec::new();
+                while let Some(flag) = args.next() {
+                    if flag != "--utxo" {
+                        return Err(invalid(
+                            ParseErrorCode::InvalidArgument,
+                            &format!("Unknown flag: {flag}"),
+                        ));
+                    }
+                    let utxo = args.next().ok_or_else(|| missing("utxo"))?;
+                    utxo_hex.push(utxo.to_string());
+                }
+
+                Ok(CliCommand::Verify { tx_hex, utxo_hex })
+            }
+            "decode" => {
+                let raw_hex = args.next().ok_or_else(|| missing("raw_hex"))?.to_string();
+                if let Some(extra) = args.next() {
+                    return Err(invalid(
+                        ParseErrorCode::InvalidArgument,
+                        &format!("Unexpected argument: {extra}"),
+                    ));
+                }
+                Ok(CliCommand::Decode { raw_hex })
+            }
+            "createtx" => {
+                let (mut inputs, mut outputs) = (Vec::new(), Vec::new());
+                let mut network = Network::Mainnet;
+                let mut locktime = 0;
+                while let Some(flag) = args.next() {
+                    let value = args.next().ok_or_else(|| missing("value"))?;
+                    match flag.as_str() {
+                        "--input" => inputs.push(value.to_string()),
+                        "--output" => outputs.push(value.to_string()),
+                        "--network" => network = value.parse()?,
+                        "--locktime" => {
+                            locktime = value.parse::<u32>().map_err(|_| {
+                                invalid(ParseErrorCode::InvalidArgument, "Invalid lock time")
+                            })?;
+                        }
+                        _ => {
+                            return Err(invalid(
+                                ParseErrorCode::InvalidArgument,
+                                &format!("Unknown flag: {flag}"),
+                            ));
+                        }
+                    }
+                }
+
+                Ok(CliCommand::CreateTx {
+                    inputs,
+                    outputs,
+                    network,
+                    locktime,
+                })
+            }
+            "broadcast" => {
+                let raw_hex = args.next().ok_or_else(|| missing("raw_hex"))?.to_string();
+
+                let mut network = Network::Mainnet;
+                while let Some(flag) = args.next() {
+                    if flag != "--network" {
+                        return Err(invalid(
+                            ParseErrorCode::InvalidArgument,
+                            &format!("Unknown flag: {flag}"),
+                        ));
+                    }
+                    network = args.next().ok_or_else(|| missing("network"))?.parse()?;
+                }
+
+                Ok(CliCommand::Broadcast { raw_hex, network })
+            }
+            "genkey" => {
+                let mut network = Network::Mainnet;
+                while let Some(flag) = args.next() {
+                    if flag != "--network" {
+                        return Err(invalid(
+                            ParseErrorCode::InvalidArgument,
+                            &format!("Unknown flag: {flag}"),
+                        ));
+                    }
+                    network = args.next().ok_or_else(|| missing("network"))?.parse()?;
+                }
+
+                Ok(CliCommand::GenerateKey { network })
+            }
+            "estimate" => {
+                let raw_hex = args.next().ok_or_else(|| missing("raw_hex"))?.to_string();
+
+                let mut fee_rate = None;
+                let mut target_blocks = 6;
+                while let Some(flag) = args.next() {
+                    let value = args.next().ok_or_else(|| missing("value"))?;
+                    match flag.as_str() {
+                        "--fee-rate" => {
+                            let sat_per_vb = value.parse::<u64>().map_err(|_| {
+                                invalid(ParseErrorCode::InvalidAmount, "Invalid fee rate")
+                            })?;
+                            fee_rate = Some(FeeRate::from_sat_per_vb(sat_per_vb));
+                        }
+                        "--target-blocks" => {
+                            target_blocks = value.parse()?;
+                        }
+                        _ => {
+                            return Err(invalid(
+                                ParseErrorCode::InvalidArgument,
+                                &format!("Unknown flag: {flag}"),
+                            ));
+                        }
+                    }
+                }
+
+                Ok(CliCommand::Estimate {
+                    raw_hex,
+                    fee_rate,
+                    target_blocks,
+                })
+            }
+            "estimatefee" => {
+                let size = args
+                    .next()
+                    .ok_or_else(|| missing("size"))?
+                    .parse::<u64>()
+                    .map_err(|_| invalid(ParseErrorCode::InvalidArgument, "Invalid size"))?;
+                let sat_per_vb = args
+                    .next()
+                    .ok_or_else(|| missing("rate"))?
+                    .parse::<u64>()
+                    .map_err(|_| invalid(ParseErrorCode::InvalidAmount, "Invalid fee rate"))?;
+                Ok(CliCommand::EstimateFee {
+                    size,
+                    rate: FeeRate::from_sat_per_vb(sat_per_vb),
+                })
+            }
+            _ => Err(unknown()),
         }
     }
 }
@@ -171,26 +2740,125 @@ impl TryFrom<&[String]> for CliCommand {
 impl TryFrom<&[u8]> for LegacyTransaction {
     type Error = BitcoinError;
 
-    // Parse binary data into a LegacyTransaction
+    // Parse a complete legacy-format transaction; truncated data and trailing
+    // bytes are both rejected
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
-        // Minimum length is 10 bytes (4 version + 4 inputs count + 4 lock_time)
-        if data.len() < 4 {
-            return Err(BitcoinError::InvalidTransaction);
+        LegacyTransaction::deserialize(data)
+    }
+}
+
+// Fee paid by spending `inputs` into `outputs`, shared by both transaction
+// formats
+pub(crate) fn absolute_fee(
+    inputs: &[TxInput],
+    outputs: &[TxOutput],
+    utxo_set: &UtxoSet,
+) -> Result<Amount, BitcoinError> {
+    let input_value = inputs
+        .iter()
+        .try_fold(Amount::from_sat(0), |total, input| {
+            let utxo = utxo_set
+                .get(&input.previous_output)
+                .ok_or(BitcoinError::InvalidTransaction)?;
+            total
+                .checked_add(utxo.value)
+                .ok_or(BitcoinError::InvalidAmount)
+        })?;
+    let output_value = outputs
+        .iter()
+        .try_fold(Amount::from_sat(0), |total, output| {
+            total.checked_add(output.value)
+        })
+        .ok_or(BitcoinError::InvalidAmount)?;
+
+    input_value
+        .checked_sub(output_value)
+        .ok_or(BitcoinError::InvalidAmount)
+}
+
+// Lists are encoded as a CompactSize count followed by each element
+impl<T: BitcoinSerialize> BitcoinSerialize for Vec<T> {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = encode_compact_size(self.len() as u64);
+        for item in self {
+            out.extend(item.serialize());
         }
-        let version = i32::from_le_bytes(data[0..4].try_into().unwrap());
+        out
+    }
+}
+
+impl BitcoinSerialize for OutPoint {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(36);
+        out.extend_from_slice(self.txid.as_bytes());
+        out.extend_from_slice(&self.vout.to_le_bytes());
+        out
+    }
+
+    fn serialize_to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        writer.write_all(self.txid.as_bytes())?;
+        writer.write_all(&self.vout.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+impl BitcoinSerialize for TxInput {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = self.previous_output.serialize();
+        out.extend(encode_compact_size(self.script_sig.len() as u64));
+        out.extend_from_slice(self.script_sig.bytes());
+        out.extend_from_slice(&self.sequence.to_consensus_u32().to_le_bytes());
+        out
+    }
+
+    fn serialize_to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        self.previous_output.serialize_to_writer(writer)?;
+        writer.write_all(&encode_compact_size(self.script_sig.len() as u64))?;
+        writer.write_all(self.script_sig.bytes())?;
+        writer.write_all(&self.sequence.to_consensus_u32().to_le_bytes())?;
+        Ok(())
+    }
+}
 
-        // TODO: do we need to parse all other fields?
-        Ok(LegacyTransaction::builder().version(version).build())
+impl BitcoinSerialize for TxOutput {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = self.value.to_sat().to_le_bytes().to_vec();
+        out.extend(encode_compact_size(self.script_pubkey.len() as u64));
+        out.extend_from_slice(self.script_pubkey.bytes());
+        out
+    }
+
+    fn serialize_to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        writer.write_all(&self.value.to_sat().to_le_bytes())?;
+        writer.write_all(&encode_compact_size(self.script_pubkey.len() as u64))?;
+        writer.write_all(self.script_pubkey.bytes())?;
+        Ok(())
     }
 }
 
 // Custom serialization for transaction
 impl BitcoinSerialize for LegacyTransaction {
-    // Serialize only version and lock_time (simplified)
     fn serialize(&self) -> Vec<u8> {
         let mut out = Vec::new();
         out.extend_from_slice(&self.version.to_le_bytes());
-        out.extend_from_slice(&self.lock_time.to_le_bytes());
+        out.extend(self.inputs.serialize());
+        out.extend(self.outputs.serialize());
+        out.extend_from_slice(&self.lock_time.to_consensus_u32().to_le_bytes());
         out
     }
+
+    // Writes field by field without building the whole transaction in memory
+    fn serialize_to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&encode_compact_size(self.inputs.len() as u64))?;
+        for input in &self.inputs {
+            input.serialize_to_writer(writer)?;
+        }
+        writer.write_all(&encode_compact_size(self.outputs.len() as u64))?;
+        for output in &self.outputs {
+            output.serialize_to_writer(writer)?;
+        }
+        writer.write_all(&self.lock_time.to_consensus_u32().to_le_bytes())?;
+        Ok(())
+    }
 }
@@ -0,0 +1,32 @@
+// Mempool statistics for fee estimation
+
+use crate::{FeeRate, LegacyTransaction};
+
+// Index of the bucket containing `fee_rate`, where bucket `i` of the sorted
+// `buckets` covers `buckets[i]` up to (not including) `buckets[i + 1]`. Rates
+// below the first boundary fall into bucket 0.
+pub fn fee_rate_bucket(fee_rate: FeeRate, buckets: &[FeeRate]) -> usize {
+    buckets
+        .partition_point(|&boundary| boundary <= fee_rate)
+        .saturating_sub(1)
+}
+
+// Total vbytes per fee-rate bucket, as `(bucket minimum, vbytes)` pairs in
+// ascending order; the boundaries need not be sorted
+pub fn build_fee_histogram(
+    transactions: &[(LegacyTransaction, FeeRate)],
+    bucket_boundaries: &[FeeRate],
+) -> Vec<(FeeRate, u64)> {
+    let mut boundaries = bucket_boundaries.to_vec();
+    boundaries.sort();
+    boundaries.dedup();
+
+    let mut histogram: Vec<(FeeRate, u64)> = boundaries.iter().map(|&b| (b, 0)).collect();
+    if histogram.is_empty() {
+        return histogram;
+    }
+    for (tx, fee_rate) in transactions {
+        histogram[fee_rate_bucket(*fee_rate, &boundaries)].1 += tx.vsize();
+    }
+    histogram
+}
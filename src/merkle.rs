@@ -0,0 +1,81 @@
+// Transaction merkle trees and SPV inclusion proofs
+
+use crate::{BlockHeader, hash};
+
+// Hash of two child nodes, both in internal byte order
+fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(left);
+    data[32..].copy_from_slice(right);
+    hash::sha256d(&data)
+}
+
+// The next level up; an odd node out is paired with itself
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| parent(&pair[0], pair.last().unwrap()))
+        .collect()
+}
+
+// Merkle root of `txids` in internal byte order, as committed to by a block
+// header. `None` for an empty list. Because the last node of an odd level is
+// duplicated, a list ending in a repeated pair has the same root as the list
+// without it (CVE-2012-2459); callers validating blocks must reject those.
+pub fn merkle_root(txids: &[[u8; 32]]) -> Option<[u8; 32]> {
+    if txids.is_empty() {
+        return None;
+    }
+    let mut level = txids.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    Some(level[0])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub txid: [u8; 32],
+    // Position of the transaction in the block, which picks the side each
+    // sibling is hashed on
+    pub index: usize,
+    // Sibling hashes from the leaf level up to just below the root
+    pub path: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    // Proof that `txids[index]` is in the tree, or `None` when out of range
+    pub fn generate(txids: &[[u8; 32]], index: usize) -> Option<MerkleProof> {
+        let txid = *txids.get(index)?;
+        let mut path = Vec::new();
+        let mut level = txids.to_vec();
+        let mut position = index;
+        while level.len() > 1 {
+            let sibling = (position ^ 1).min(level.len() - 1);
+            path.push(level[sibling]);
+            level = next_level(&level);
+            position /= 2;
+        }
+        Some(MerkleProof { txid, index, path })
+    }
+
+    // Root implied by the txid and path
+    pub fn compute_root(&self) -> [u8; 32] {
+        let (root, _) =
+            self.path
+                .iter()
+                .fold((self.txid, self.index), |(node, position), sibling| {
+                    let node = if position % 2 == 0 {
+                        parent(&node, sibling)
+                    } else {
+                        parent(sibling, &node)
+                    };
+                    (node, position / 2)
+                });
+        root
+    }
+
+    pub fn verify(&self, header: &BlockHeader) -> bool {
+        self.compute_root() == header.merkle_root
+    }
+}
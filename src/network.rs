@@ -0,0 +1,173 @@
+// Bitcoin networks and their per-network constants
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::address::Address;
+use crate::{BitcoinError, ParseErrorCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub const ALL: [Network; 4] = [
+        Network::Mainnet,
+        Network::Testnet,
+        Network::Signet,
+        Network::Regtest,
+    ];
+
+    // Network an address belongs to, from its Base58Check version byte or
+    // Bech32 HRP. Shared prefixes resolve to testnet.
+    pub fn from_address_str(address: &str) -> Result<Network, BitcoinError> {
+        Address::from_str_any_network(address).map(|(_, network)| network)
+    }
+
+    // The constants registered for this network
+    pub fn params(&self) -> &'static Params {
+        match self {
+            Network::Mainnet => &Params::MAINNET,
+            Network::Testnet => &Params::TESTNET,
+            Network::Signet => &Params::SIGNET,
+            Network::Regtest => &Params::REGTEST,
+        }
+    }
+
+    // Start bytes of every P2P message on this network
+    pub fn magic(&self) -> [u8; 4] {
+        self.params().magic
+    }
+
+    // The network whose messages start with `magic`
+    pub fn from_magic(magic: [u8; 4]) -> Option<Network> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.magic() == magic)
+    }
+
+    // Port a node listens on for P2P connections unless configured otherwise
+    pub fn default_port(&self) -> u16 {
+        self.params().default_port
+    }
+
+    // Base58Check version byte of P2PKH addresses
+    pub fn p2pkh_prefix(&self) -> u8 {
+        self.params().p2pkh_prefix
+    }
+
+    // Base58Check version byte of P2SH addresses
+    pub fn p2sh_prefix(&self) -> u8 {
+        self.params().p2sh_prefix
+    }
+
+    // Version byte of WIF-encoded private keys
+    pub fn wif_prefix(&self) -> u8 {
+        self.params().wif_prefix
+    }
+
+    // Version bytes of BIP-32 extended private keys (xprv / tprv)
+    pub fn xprv_version(&self) -> [u8; 4] {
+        self.params().xprv_version
+    }
+
+    // Version bytes of BIP-32 extended public keys (xpub / tpub)
+    pub fn xpub_version(&self) -> [u8; 4] {
+        self.params().xpub_version
+    }
+
+    // Human-readable part of SegWit addresses
+    pub fn bech32_hrp(&self) -> &'static str {
+        self.params().bech32_hrp
+    }
+}
+
+// Per-network constants used by address, key and P2P encoding. Testnet,
+// signet and regtest share their Base58 and BIP-32 version bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    pub network: Network,
+    pub p2pkh_prefix: u8,
+    pub p2sh_prefix: u8,
+    pub wif_prefix: u8,
+    pub xprv_version: [u8; 4],
+    pub xpub_version: [u8; 4],
+    pub bech32_hrp: &'static str,
+    pub magic: [u8; 4],
+    pub default_port: u16,
+}
+
+impl Params {
+    pub const MAINNET: Params = Params {
+        network: Network::Mainnet,
+        p2pkh_prefix: 0x00,
+        p2sh_prefix: 0x05,
+        wif_prefix: 0x80,
+        xprv_version: [0x04, 0x88, 0xad, 0xe4],
+        xpub_version: [0x04, 0x88, 0xb2, 0x1e],
+        bech32_hrp: "bc",
+        magic: [0xf9, 0xbe, 0xb4, 0xd9],
+        default_port: 8333,
+    };
+
+    pub const TESTNET: Params = Params {
+        network: Network::Testnet,
+        p2pkh_prefix: 0x6f,
+        p2sh_prefix: 0xc4,
+        wif_prefix: 0xef,
+        xprv_version: [0x04, 0x35, 0x83, 0x94],
+        xpub_version: [0x04, 0x35, 0x87, 0xcf],
+        bech32_hrp: "tb",
+        magic: [0x0b, 0x11, 0x09, 0x07],
+        default_port: 18333,
+    };
+
+    pub const SIGNET: Params = Params {
+        network: Network::Signet,
+        magic: [0x0a, 0x03, 0xcf, 0x40],
+        default_port: 38333,
+        ..Params::TESTNET
+    };
+
+    pub const REGTEST: Params = Params {
+        network: Network::Regtest,
+        bech32_hrp: "bcrt",
+        magic: [0xfa, 0xbf, 0xb5, 0xda],
+        default_port: 18444,
+        ..Params::TESTNET
+    };
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Network {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" | "bitcoin" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            "signet" => Ok(Network::Signet),
+            "regtest" => Ok(Network::Regtest),
+            other => Err(BitcoinError::cli_error(
+                ParseErrorCode::InvalidNetwork,
+                format!("Unknown network: {other}"),
+            )),
+        }
+    }
+}
@@ -0,0 +1,411 @@
+// Bitcoin P2P message framing and the payloads needed to hand a transaction
+// to a peer: the version/verack handshake, ping/pong keepalives, inv
+// announcements and tx. A `RawNetworkMessage` serializes into, and
+// deserializes from, any `Write`/`Read`, such as a `TcpStream`.
+
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::hash::checksum;
+use crate::network::Network;
+use crate::transaction::Transaction;
+use crate::{
+    BitcoinDeserialize, BitcoinError, BitcoinSerialize, LegacyTransaction, ParseErrorCode,
+    TransactionReader, encode_compact_size,
+};
+
+// magic (4) + command (12) + payload length (4) + checksum (4)
+pub const HEADER_SIZE: usize = 24;
+const COMMAND_SIZE: usize = 12;
+// Core's limit on a single message's payload
+pub const MAX_PAYLOAD_SIZE: usize = 4_000_000;
+// Core's limit on the entries of one inv message
+pub const MAX_INV_ENTRIES: usize = 50_000;
+// Protocol version sent in our version messages (BIP-155 era)
+pub const PROTOCOL_VERSION: i32 = 70016;
+pub const USER_AGENT: &str = concat!("/rust-week-4-exercises:", env!("CARGO_PKG_VERSION"), "/");
+
+// Wrap `payload` in a message envelope: network magic, NUL-padded ASCII
+// command, little-endian payload length and the first four bytes of
+// sha256d(payload)
+pub fn frame(network: Network, command: &str, payload: &[u8]) -> Result<Vec<u8>, BitcoinError> {
+    if command.len() > COMMAND_SIZE || !command.is_ascii() {
+        return Err(BitcoinError::cli_error(
+            ParseErrorCode::InvalidArgument,
+            format!("Invalid P2P command: {command}"),
+        ));
+    }
+    let length = u32::try_from(payload.len()).map_err(|_| BitcoinError::InvalidTransaction)?;
+    Ok(frame_with_magic(network.magic(), command, length, payload))
+}
+
+fn frame_with_magic(magic: [u8; 4], command: &str, length: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_SIZE + payload.len());
+    out.extend_from_slice(&magic);
+    let mut command_bytes = [0u8; COMMAND_SIZE];
+    command_bytes[..command.len()].copy_from_slice(command.as_bytes());
+    out.extend_from_slice(&command_bytes);
+    out.extend_from_slice(&length.to_le_bytes());
+    out.extend_from_slice(&checksum(payload));
+    out.extend_from_slice(payload);
+    out
+}
+
+// A `tx` message carrying `tx`
+pub fn tx_message(network: Network, tx: &LegacyTransaction) -> Result<Vec<u8>, BitcoinError> {
+    frame(network, "tx", &tx.serialize())
+}
+
+fn invalid(reason: impl Into<String>) -> BitcoinError {
+    BitcoinError::InvalidMessage(reason.into())
+}
+
+// A peer address as carried in version messages: service bits, an IPv6 (or
+// IPv4-mapped) address and a big-endian port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NetAddress {
+    pub services: u64,
+    pub ip: [u8; 16],
+    pub port: u16,
+}
+
+impl NetAddress {
+    pub const SIZE: usize = 26;
+
+    pub fn new(addr: SocketAddr, services: u64) -> NetAddress {
+        let ip = match addr.ip() {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+            IpAddr::V6(ip) => ip,
+        };
+        NetAddress {
+            services,
+            ip: ip.octets(),
+            port: addr.port(),
+        }
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        let ip = std::net::Ipv6Addr::from(self.ip);
+        let ip = ip.to_ipv4_mapped().map_or(IpAddr::V6(ip), IpAddr::V4);
+        SocketAddr::new(ip, self.port)
+    }
+
+    fn to_bytes(self) -> [u8; NetAddress::SIZE] {
+        let mut out = [0u8; NetAddress::SIZE];
+        out[..8].copy_from_slice(&self.services.to_le_bytes());
+        out[8..24].copy_from_slice(&self.ip);
+        out[24..].copy_from_slice(&self.port.to_be_bytes());
+        out
+    }
+
+    fn from_bytes(bytes: [u8; NetAddress::SIZE]) -> NetAddress {
+        NetAddress {
+            services: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            ip: bytes[8..24].try_into().unwrap(),
+            port: u16::from_be_bytes([bytes[24], bytes[25]]),
+        }
+    }
+}
+
+// The first message on a connection, answered by `verack`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMessage {
+    pub version: i32,
+    pub services: u64,
+    pub timestamp: i64,
+    pub receiver: NetAddress,
+    pub sender: NetAddress,
+    pub nonce: u64,
+    pub user_agent: String,
+    pub start_height: i32,
+    // BIP-37: whether the peer should announce transactions before a filter
+    // is loaded. Omitted by peers older than protocol version 70001.
+    pub relay: bool,
+}
+
+impl VersionMessage {
+    // Our version message to `receiver`: no services, no blocks, relay on.
+    // `nonce` lets a node detect connections to itself.
+    pub fn new(receiver: SocketAddr, timestamp: i64, nonce: u64) -> VersionMessage {
+        VersionMessage {
+            version: PROTOCOL_VERSION,
+            services: 0,
+            timestamp,
+            receiver: NetAddress::new(receiver, 0),
+            sender: NetAddress::default(),
+            nonce,
+            user_agent: USER_AGENT.to_string(),
+            start_height: 0,
+            relay: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryType {
+    Error,
+    Tx,
+    Block,
+    FilteredBlock,
+    CompactBlock,
+    WitnessTx,
+    WitnessBlock,
+    Unknown(u32),
+}
+
+impl InventoryType {
+    // Set on the witness variants of tx and block (BIP-144)
+    const WITNESS_FLAG: u32 = 1 << 30;
+
+    pub fn to_u32(self) -> u32 {
+        match self {
+            InventoryType::Error => 0,
+            InventoryType::Tx => 1,
+            InventoryType::Block => 2,
+            InventoryType::FilteredBlock => 3,
+            InventoryType::CompactBlock => 4,
+            InventoryType::WitnessTx => InventoryType::WITNESS_FLAG | 1,
+            InventoryType::WitnessBlock => InventoryType::WITNESS_FLAG | 2,
+            InventoryType::Unknown(value) => value,
+        }
+    }
+
+    pub fn from_u32(value: u32) -> InventoryType {
+        match value {
+            0 => InventoryType::Error,
+            1 => InventoryType::Tx,
+            2 => InventoryType::Block,
+            3 => InventoryType::FilteredBlock,
+            4 => InventoryType::CompactBlock,
+            v if v == InventoryType::WITNESS_FLAG | 1 => InventoryType::WitnessTx,
+            v if v == InventoryType::WITNESS_FLAG | 2 => InventoryType::WitnessBlock,
+            v => InventoryType::Unknown(v),
+        }
+    }
+}
+
+// An inv entry: the object's type and hash in internal byte order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inventory {
+    pub inv_type: InventoryType,
+    pub hash: [u8; 32],
+}
+
+impl Inventory {
+    pub const SIZE: usize = 36;
+
+    pub fn tx(txid: [u8; 32]) -> Inventory {
+        Inventory {
+            inv_type: InventoryType::Tx,
+            hash: txid,
+        }
+    }
+
+    pub fn block(block_hash: [u8; 32]) -> Inventory {
+        Inventory {
+            inv_type: InventoryType::Block,
+            hash: block_hash,
+        }
+    }
+}
+
+// A message payload. Serializing gives the payload bytes only; wrap it in a
+// `RawNetworkMessage` for the envelope.
+#[derive(Debug, Clone)]
+pub enum NetworkMessage {
+    Version(VersionMessage),
+    Verack,
+    Ping(u64),
+    Pong(u64),
+    Inv(Vec<Inventory>),
+    Tx(Transaction),
+}
+
+impl NetworkMessage {
+    pub fn command(&self) -> &'static str {
+        match self {
+            NetworkMessage::Version(_) => "version",
+            NetworkMessage::Verack => "verack",
+            NetworkMessage::Ping(_) => "ping",
+            NetworkMessage::Pong(_) => "pong",
+            NetworkMessage::Inv(_) => "inv",
+            NetworkMessage::Tx(_) => "tx",
+        }
+    }
+
+    // Parse the payload of a `command` message; it must be consumed exactly
+    pub fn from_payload(command: &str, payload: &[u8]) -> Result<NetworkMessage, BitcoinError> {
+        let mut rest = payload;
+        let message = match command {
+            "version" => NetworkMessage::Version(read_version(&mut rest)?),
+            "verack" => NetworkMessage::Verack,
+            "ping" => NetworkMessage::Ping(u64::from_le_bytes(take(&mut rest)?)),
+            "pong" => NetworkMessage::Pong(u64::from_le_bytes(take(&mut rest)?)),
+            "inv" => NetworkMessage::Inv(read_inventory(&mut rest)?),
+            "tx" => NetworkMessage::Tx(Transaction::deserialize_from(&mut rest)?),
+            _ => return Err(invalid(format!("unsupported command {command:?}"))),
+        };
+        if !rest.is_empty() {
+            return Err(invalid(format!(
+                "{} trailing bytes in {command}",
+                rest.len()
+            )));
+        }
+        Ok(message)
+    }
+}
+
+impl BitcoinSerialize for NetworkMessage {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            NetworkMessage::Version(version) => {
+                out.extend_from_slice(&version.version.to_le_bytes());
+                out.extend_from_slice(&version.services.to_le_bytes());
+                out.extend_from_slice(&version.timestamp.to_le_bytes());
+                out.extend_from_slice(&version.receiver.to_bytes());
+                out.extend_from_slice(&version.sender.to_bytes());
+                out.extend_from_slice(&version.nonce.to_le_bytes());
+                out.extend(encode_compact_size(version.user_agent.len() as u64));
+                out.extend_from_slice(version.user_agent.as_bytes());
+                out.extend_from_slice(&version.start_height.to_le_bytes());
+                out.push(u8::from(version.relay));
+            }
+            NetworkMessage::Verack => {}
+            NetworkMessage::Ping(nonce) | NetworkMessage::Pong(nonce) => {
+                out.extend_from_slice(&nonce.to_le_bytes());
+            }
+            NetworkMessage::Inv(inventory) => {
+                out.extend(encode_compact_size(inventory.len() as u64));
+                for entry in inventory {
+                    out.extend_from_slice(&entry.inv_type.to_u32().to_le_bytes());
+                    out.extend_from_slice(&entry.hash);
+                }
+            }
+            NetworkMessage::Tx(tx) => out.extend(tx.serialize()),
+        }
+        out
+    }
+}
+
+// Fixed-size field from the front of `rest`
+fn take<const N: usize>(rest: &mut &[u8]) -> Result<[u8; N], BitcoinError> {
+    let Some((field, tail)) = rest.split_first_chunk::<N>() else {
+        return Err(invalid("truncated payload"));
+    };
+    *rest = tail;
+    Ok(*field)
+}
+
+fn read_version(rest: &mut &[u8]) -> Result<VersionMessage, BitcoinError> {
+    let version = i32::from_le_bytes(take(rest)?);
+    let services = u64::from_le_bytes(take(rest)?);
+    let timestamp = i64::from_le_bytes(take(rest)?);
+    let receiver = NetAddress::from_bytes(take(rest)?);
+    let sender = NetAddress::from_bytes(take(rest)?);
+    let nonce = u64::from_le_bytes(take(rest)?);
+    let user_agent = TransactionReader::new(&mut *rest).read_compact_size()?;
+    let user_agent = rest
+        .split_at_checked(user_agent as usize)
+        .ok_or_else(|| invalid("truncated payload"))
+        .and_then(|(bytes, tail)| {
+            *rest = tail;
+            String::from_utf8(bytes.to_vec()).map_err(|_| invalid("user agent is not UTF-8"))
+        })?;
+    let start_height = i32::from_le_bytes(take(rest)?);
+    let relay = rest.is_empty() || take::<1>(rest)?[0] != 0;
+    Ok(VersionMessage {
+        version,
+        services,
+        timestamp,
+        receiver,
+        sender,
+        nonce,
+        user_agent,
+        start_height,
+        relay,
+    })
+}
+
+fn read_inventory(rest: &mut &[u8]) -> Result<Vec<Inventory>, BitcoinError> {
+    let count = TransactionReader::new(&mut *rest).read_compact_size()?;
+    if count > MAX_INV_ENTRIES as u64 {
+        return Err(invalid(format!("{count} inv entries")));
+    }
+    (0..count)
+        .map(|_| {
+            Ok(Inventory {
+                inv_type: InventoryType::from_u32(u32::from_le_bytes(take(rest)?)),
+                hash: take(rest)?,
+            })
+        })
+        .collect()
+}
+
+// A payload in its envelope, ready to write to or read from a peer
+#[derive(Debug, Clone)]
+pub struct RawNetworkMessage {
+    pub magic: [u8; 4],
+    pub payload: NetworkMessage,
+}
+
+impl RawNetworkMessage {
+    pub fn new(network: Network, payload: NetworkMessage) -> RawNetworkMessage {
+        RawNetworkMessage {
+            magic: network.magic(),
+            payload,
+        }
+    }
+
+    // `None` for magic bytes of a network this crate does not know
+    pub fn network(&self) -> Option<Network> {
+        Network::from_magic(self.magic)
+    }
+
+    pub fn command(&self) -> &'static str {
+        self.payload.command()
+    }
+}
+
+impl BitcoinSerialize for RawNetworkMessage {
+    // Payloads over MAX_PAYLOAD_SIZE are framed anyway; peers will reject them
+    fn serialize(&self) -> Vec<u8> {
+        let payload = self.payload.serialize();
+        frame_with_magic(self.magic, self.command(), payload.len() as u32, &payload)
+    }
+}
+
+// Reads one message: the header, then exactly `length` payload bytes, whose
+// checksum must match before the payload is parsed
+impl BitcoinDeserialize for RawNetworkMessage {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        let mut header = [0u8; HEADER_SIZE];
+        TransactionReader::new(&mut *r).read_exact(&mut header)?;
+        let magic: [u8; 4] = header[..4].try_into().unwrap();
+        let command = parse_command(&header[4..16])?;
+        let length = u32::from_le_bytes(header[16..20].try_into().unwrap()) as usize;
+        if length > MAX_PAYLOAD_SIZE {
+            return Err(invalid(format!("payload of {length} bytes")));
+        }
+
+        let mut payload = vec![0u8; length];
+        TransactionReader::new(&mut *r).read_exact(&mut payload)?;
+        if checksum(&payload) != header[20..24] {
+            return Err(invalid(format!("bad checksum for {command}")));
+        }
+        Ok(RawNetworkMessage {
+            magic,
+            payload: NetworkMessage::from_payload(command, &payload)?,
+        })
+    }
+}
+
+// ASCII, then NUL padding only
+fn parse_command(bytes: &[u8]) -> Result<&str, BitcoinError> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let (command, padding) = bytes.split_at(end);
+    if padding.iter().any(|&b| b != 0) || !command.is_ascii() {
+        return Err(invalid("malformed command"));
+    }
+    Ok(std::str::from_utf8(command).unwrap())
+}
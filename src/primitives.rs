@@ -0,0 +1,244 @@
+// Typed transaction identifiers, sequence numbers and lock times
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{
+    BitcoinError, ParseErrorCode, SEQUENCE_ENABLE_LOCKTIME, SEQUENCE_FINAL, SEQUENCE_RBF, hex,
+};
+
+// Lock times below this are block heights, at or above it unix timestamps
+pub const LOCK_TIME_THRESHOLD: u32 = 500_000_000;
+
+// A transaction id. The bytes are the sha256d hash in internal order; hex
+// parsing and display use the reversed order shown by explorers and RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Txid(pub [u8; 32]);
+
+impl Txid {
+    pub const fn from_byte_array(bytes: [u8; 32]) -> Txid {
+        Txid(bytes)
+    }
+
+    pub const fn to_byte_array(self) -> [u8; 32] {
+        self.0
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    // Same as `parse`: display (reversed) byte order
+    pub fn from_hex(hex: &str) -> Result<Txid, BitcoinError> {
+        hex.parse()
+    }
+
+    // Same as `to_string`
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl From<[u8; 32]> for Txid {
+    fn from(bytes: [u8; 32]) -> Self {
+        Txid(bytes)
+    }
+}
+
+impl From<Txid> for [u8; 32] {
+    fn from(txid: Txid) -> Self {
+        txid.0
+    }
+}
+
+impl PartialEq<[u8; 32]> for Txid {
+    fn eq(&self, other: &[u8; 32]) -> bool {
+        self.0 == *other
+    }
+}
+
+impl AsRef<[u8]> for Txid {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&crate::display_hex(&self.0))
+    }
+}
+
+// Parses the display (reversed) order produced by `Display`
+impl FromStr for Txid {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 {
+            return Err(BitcoinError::cli_error(
+                ParseErrorCode::InvalidHex,
+                "txid must be 64 hex characters",
+            ));
+        }
+        let mut bytes: [u8; 32] = hex::decode_array(s)?;
+        bytes.reverse();
+        Ok(Txid(bytes))
+    }
+}
+
+// An input's nSequence. Besides finality it carries the BIP-125
+// replaceability signal and, with the disable bit clear, a BIP-68 relative
+// lock time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sequence(pub u32);
+
+impl Sequence {
+    // Final: disables nLockTime and relative lock times
+    pub const MAX: Sequence = Sequence(SEQUENCE_FINAL);
+    pub const ENABLE_LOCKTIME: Sequence = Sequence(SEQUENCE_ENABLE_LOCKTIME);
+    pub const ENABLE_RBF: Sequence = Sequence(SEQUENCE_RBF);
+
+    // BIP-68 fields
+    const DISABLE_FLAG: u32 = 1 << 31;
+    const TYPE_FLAG: u32 = 1 << 22;
+    const VALUE_MASK: u32 = 0x0000_FFFF;
+
+    pub const fn to_consensus_u32(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_final(self) -> bool {
+        self == Sequence::MAX
+    }
+
+    // Any non-final sequence lets the transaction's nLockTime take effect
+    pub fn enables_lock_time(self) -> bool {
+        !self.is_final()
+    }
+
+    // BIP-125: below 0xFFFFFFFE signals opt-in replaceability
+    pub fn signals_rbf(self) -> bool {
+        self < Sequence::ENABLE_LOCKTIME
+    }
+
+    // Relative lock of `blocks` confirmations
+    pub const fn from_height(blocks: u16) -> Sequence {
+        Sequence(blocks as u32)
+    }
+
+    // Relative lock of `intervals` * 512 seconds
+    pub const fn from_512_second_intervals(intervals: u16) -> Sequence {
+        Sequence(Sequence::TYPE_FLAG | intervals as u32)
+    }
+
+    pub fn is_relative_lock_time(self) -> bool {
+        self.0 & Sequence::DISABLE_FLAG == 0
+    }
+
+    pub fn is_height_locked(self) -> bool {
+        self.is_relative_lock_time() && self.0 & Sequence::TYPE_FLAG == 0
+    }
+
+    pub fn is_time_locked(self) -> bool {
+        self.is_relative_lock_time() && self.0 & Sequence::TYPE_FLAG != 0
+    }
+
+    // The relative lock's value: blocks, or 512-second intervals
+    pub fn relative_lock_value(self) -> Option<u16> {
+        self.is_relative_lock_time()
+            .then_some((self.0 & Sequence::VALUE_MASK) as u16)
+    }
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Sequence::MAX
+    }
+}
+
+impl From<u32> for Sequence {
+    fn from(value: u32) -> Self {
+        Sequence(value)
+    }
+}
+
+impl From<Sequence> for u32 {
+    fn from(sequence: Sequence) -> Self {
+        sequence.0
+    }
+}
+
+impl PartialEq<u32> for Sequence {
+    fn eq(&self, other: &u32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+// Transaction lock time (block height or unix timestamp)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct LockTime(pub u32);
+
+impl LockTime {
+    pub const ZERO: LockTime = LockTime(0);
+
+    pub fn to_consensus_u32(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_block_height(self) -> bool {
+        self.0 < LOCK_TIME_THRESHOLD
+    }
+
+    pub fn is_block_time(self) -> bool {
+        !self.is_block_height()
+    }
+
+    // Whether a transaction with this lock time may be mined in the block at
+    // `height` whose median time past is `time` (the lock is exclusive)
+    pub fn is_satisfied_by(self, height: u32, time: u32) -> bool {
+        if self == LockTime::ZERO {
+            return true;
+        }
+        if self.is_block_height() {
+            self.0 < height
+        } else {
+            self.0 < time
+        }
+    }
+}
+
+impl From<u32> for LockTime {
+    fn from(value: u32) -> Self {
+        LockTime(value)
+    }
+}
+
+impl From<LockTime> for u32 {
+    fn from(lock_time: LockTime) -> Self {
+        lock_time.0
+    }
+}
+
+impl PartialEq<u32> for LockTime {
+    fn eq(&self, other: &u32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for LockTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
@@ -0,0 +1,421 @@
+// Partially signed bitcoin transactions (BIP-174)
+
+use std::collections::BTreeMap;
+
+use crate::hash::hash160;
+use crate::script::{Instruction, Script, ScriptBuilder};
+use crate::{
+    BitcoinDeserialize, BitcoinError, BitcoinSerialize, ByteCursor, LegacyTransaction, Opcode,
+    TxOutput, UtxoSet, encode_compact_size,
+};
+
+// "psbt" followed by 0xff
+const PSBT_MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+
+// Per-input signing state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PsbtInput {
+    // The full transaction being spent from, needed to sign legacy inputs
+    pub non_witness_utxo: Option<LegacyTransaction>,
+    // The output being spent, sufficient to sign SegWit inputs
+    pub witness_utxo: Option<TxOutput>,
+    // Signatures collected so far, keyed by public key
+    pub partial_sigs: BTreeMap<Vec<u8>, Vec<u8>>,
+    // Completed scriptSig, set by the finalizer
+    pub final_script_sig: Option<Vec<u8>>,
+    // Every other entry, such as BIP-32 derivations, kept verbatim by key
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PsbtInput {
+    // Finalized inputs have a scriptSig and no leftover partial signatures
+    pub fn is_finalized(&self) -> bool {
+        self.final_script_sig.is_some() && self.partial_sigs.is_empty()
+    }
+}
+
+// Per-output data. None of it is interpreted here, but BIP-174 requires it to
+// survive a round trip (e.g. change derivations for a hardware wallet).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PsbtOutput {
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Psbt {
+    unsigned_tx: LegacyTransaction,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+    // Global entries other than the unsigned transaction, such as xpubs
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Psbt {
+    // Creator role: wrap a transaction whose scriptSigs are all empty
+    pub fn from_unsigned_tx(unsigned_tx: LegacyTransaction) -> Result<Psbt, BitcoinError> {
+        if unsigned_tx
+            .inputs()
+            .iter()
+            .any(|input| !input.script_sig.is_empty())
+        {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let inputs = vec![PsbtInput::default(); unsigned_tx.inputs().len()];
+        let outputs = vec![PsbtOutput::default(); unsigned_tx.outputs().len()];
+        Ok(Psbt {
+            unsigned_tx,
+            inputs,
+            outputs,
+            unknown: BTreeMap::new(),
+        })
+    }
+
+    pub fn unsigned_tx(&self) -> &LegacyTransaction {
+        &self.unsigned_tx
+    }
+
+    // Signer role: record `signature` (DER plus sighash byte) by `pubkey`
+    pub fn add_partial_sig(
+        &mut self,
+        input_index: usize,
+        pubkey: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), BitcoinError> {
+        if pubkey.len() != 33 && pubkey.len() != 65 {
+            return Err(BitcoinError::InvalidScript);
+        }
+        let input = self
+            .inputs
+            .get_mut(input_index)
+            .ok_or(BitcoinError::InvalidTransaction)?;
+        input.partial_sigs.insert(pubkey, signature);
+        Ok(())
+    }
+
+    // Combiner role: merge what `other` knows about the same unsigned
+    // transaction. Fields already set here are kept.
+    pub fn combine(&mut self, other: &Psbt) -> Result<(), BitcoinError> {
+        if self.unsigned_tx != other.unsigned_tx
+            || self.inputs.len() != other.inputs.len()
+            || self.outputs.len() != other.outputs.len()
+        {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        merge_unknown(&mut self.unknown, &other.unknown);
+        for (ours, theirs) in self.outputs.iter_mut().zip(&other.outputs) {
+            merge_unknown(&mut ours.unknown, &theirs.unknown);
+        }
+        for (ours, theirs) in self.inputs.iter_mut().zip(&other.inputs) {
+            if ours.non_witness_utxo.is_none() {
+                ours.non_witness_utxo = theirs.non_witness_utxo.clone();
+            }
+            if ours.witness_utxo.is_none() {
+                ours.witness_utxo = theirs.witness_utxo.clone();
+            }
+            for (pubkey, signature) in &theirs.partial_sigs {
+                ours.partial_sigs
+                    .entry(pubkey.clone())
+                    .or_insert_with(|| signature.clone());
+            }
+            if ours.final_script_sig.is_none() {
+                ours.final_script_sig = theirs.final_script_sig.clone();
+            }
+            merge_unknown(&mut ours.unknown, &theirs.unknown);
+        }
+        Ok(())
+    }
+
+    // Finalizer role: build the scriptSig of every input not yet finalized
+    // from its partial signatures. Only P2PKH, P2PK and bare multisig outputs
+    // known through `non_witness_utxo` can be finalized, since the extracted
+    // transaction carries no witness. Fails without changes if any input
+    // cannot be completed.
+    pub fn finalize(&mut self) -> Result<(), BitcoinError> {
+        let mut finals = Vec::with_capacity(self.inputs.len());
+        for (input, psbt_input) in self.unsigned_tx.inputs().iter().zip(&self.inputs) {
+            if psbt_input.is_finalized() {
+                finals.push(None);
+                continue;
+            }
+            let spent = psbt_input
+                .non_witness_utxo
+                .as_ref()
+                .and_then(|tx| tx.outputs().get(input.previous_output.vout as usize))
+                .ok_or(BitcoinError::InvalidTransaction)?;
+            finals.push(Some(final_script_sig(
+                &spent.script_pubkey,
+                &psbt_input.partial_sigs,
+            )?));
+        }
+
+        for (psbt_input, script_sig) in self.inputs.iter_mut().zip(finals) {
+            if let Some(script_sig) = script_sig {
+                psbt_input.final_script_sig = Some(script_sig.into_bytes());
+                psbt_input.partial_sigs.clear();
+            }
+        }
+        Ok(())
+    }
+
+    // BIP-174 binary encoding; uninterpreted entries follow the known ones in
+    // each map
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = PSBT_MAGIC.to_vec();
+        write_pair(
+            &mut out,
+            &[PSBT_GLOBAL_UNSIGNED_TX],
+            &self.unsigned_tx.serialize(),
+        );
+        write_unknown(&mut out, &self.unknown);
+        out.push(0x00);
+
+        for input in &self.inputs {
+            if let Some(tx) = &input.non_witness_utxo {
+                write_pair(&mut out, &[PSBT_IN_NON_WITNESS_UTXO], &tx.serialize());
+            }
+            if let Some(utxo) = &input.witness_utxo {
+                write_pair(&mut out, &[PSBT_IN_WITNESS_UTXO], &utxo.serialize());
+            }
+            for (pubkey, signature) in &input.partial_sigs {
+                let key = [&[PSBT_IN_PARTIAL_SIG], pubkey.as_slice()].concat();
+                write_pair(&mut out, &key, signature);
+            }
+            if let Some(script_sig) = &input.final_script_sig {
+                write_pair(&mut out, &[PSBT_IN_FINAL_SCRIPTSIG], script_sig);
+            }
+            write_unknown(&mut out, &input.unknown);
+            out.push(0x00);
+        }
+        for output in &self.outputs {
+            write_unknown(&mut out, &output.unknown);
+            out.push(0x00);
+        }
+        out
+    }
+
+    // Parse the BIP-174 binary encoding. Entries this module doesn't
+    // interpret are kept in the `unknown` maps; duplicate keys and trailing
+    // bytes are rejected.
+    pub fn deserialize(data: &[u8]) -> Result<Psbt, BitcoinError> {
+        let data = data
+            .strip_prefix(&PSBT_MAGIC)
+            .ok_or(BitcoinError::InvalidTransaction)?;
+        let mut cursor = ByteCursor::new(data);
+
+        let mut unsigned_tx = None;
+        let mut global_unknown = BTreeMap::new();
+        for (key, value) in read_map(&mut cursor)? {
+            if key == [PSBT_GLOBAL_UNSIGNED_TX] {
+                unsigned_tx = Some(LegacyTransaction::deserialize(&value)?);
+            } else {
+                global_unknown.insert(key, value);
+            }
+        }
+        let mut psbt =
+            Psbt::from_unsigned_tx(unsigned_tx.ok_or(BitcoinError::InvalidTransaction)?)?;
+        psbt.unknown = global_unknown;
+
+        for (input, psbt_input) in psbt.unsigned_tx.inputs().iter().zip(&mut psbt.inputs) {
+            for (key, value) in read_map(&mut cursor)? {
+                match key.as_slice() {
+                    [PSBT_IN_NON_WITNESS_UTXO] => {
+                        let tx = LegacyTransaction::deserialize(&value)?;
+                        if input.previous_output.txid != tx.txid() {
+                            return Err(BitcoinError::InvalidTransaction);
+                        }
+                        psbt_input.non_witness_utxo = Some(tx);
+                    }
+                    [PSBT_IN_WITNESS_UTXO] => {
+                        psbt_input.witness_utxo = Some(parse_tx_output(&value)?);
+                    }
+                    [PSBT_IN_PARTIAL_SIG, pubkey @ ..] if matches!(pubkey.len(), 33 | 65) => {
+                        psbt_input.partial_sigs.insert(pubkey.to_vec(), value);
+                    }
+                    [PSBT_IN_FINAL_SCRIPTSIG] => psbt_input.final_script_sig = Some(value),
+                    [
+                        PSBT_IN_NON_WITNESS_UTXO | PSBT_IN_WITNESS_UTXO | PSBT_IN_PARTIAL_SIG,
+                        ..,
+                    ]
+                    | [PSBT_IN_FINAL_SCRIPTSIG, ..] => {
+                        return Err(BitcoinError::InvalidTransaction);
+                    }
+                    _ => {
+                        psbt_input.unknown.insert(key, value);
+                    }
+                }
+            }
+        }
+        for output in &mut psbt.outputs {
+            output.unknown = read_map(&mut cursor)?.into_iter().collect();
+        }
+        if !cursor.is_empty() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        Ok(psbt)
+    }
+
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(self.serialize())
+    }
+
+    #[cfg(feature = "base64")]
+    pub fn from_base64(s: &str) -> Result<Psbt, BitcoinError> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(s.trim())
+            .map_err(|_| BitcoinError::InvalidTransaction)?;
+        Psbt::deserialize(&bytes)
+    }
+
+    // Updater role: fill in `witness_utxo` for every input spending a SegWit
+    // output. A UTXO set only holds outputs, so legacy inputs keep whatever
+    // `non_witness_utxo` the caller supplied. Fails without changes if any
+    // spent output is missing.
+    pub fn update_with_utxo_set(&mut self, utxo_set: &UtxoSet) -> Result<(), BitcoinError> {
+        let spent: Vec<Option<&TxOutput>> = self
+            .unsigned_tx
+            .inputs()
+            .iter()
+            .map(|input| utxo_set.get(&input.previous_output))
+            .collect();
+        let missing: Vec<_> = self
+            .unsigned_tx
+            .inputs()
+            .iter()
+            .zip(&spent)
+            .filter(|(_, utxo)| utxo.is_none())
+            .map(|(input, _)| input.previous_output.clone())
+            .collect();
+        if !missing.is_empty() {
+            return Err(BitcoinError::MissingUtxos(missing));
+        }
+
+        for (psbt_input, utxo) in self.inputs.iter_mut().zip(spent.into_iter().flatten()) {
+            if utxo.script_pubkey.witness_version().is_some() {
+                psbt_input.witness_utxo = Some(utxo.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // Cheap check that `extract` will succeed
+    pub fn is_extractable(&self) -> bool {
+        self.inputs.len() == self.unsigned_tx.inputs().len()
+            && self.inputs.iter().all(PsbtInput::is_finalized)
+    }
+
+    // Extractor role: the network-ready transaction, with each input's
+    // scriptSig taken from its finalized PSBT input
+    pub fn extract(&self) -> Result<LegacyTransaction, BitcoinError> {
+        if !self.is_extractable() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let mut tx = self.unsigned_tx.clone();
+        for (input, psbt_input) in tx.inputs_mut().iter_mut().zip(&self.inputs) {
+            input.script_sig = Script(psbt_input.final_script_sig.clone().unwrap_or_default());
+        }
+        Ok(tx)
+    }
+}
+
+fn write_pair(out: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    out.extend(encode_compact_size(key.len() as u64));
+    out.extend_from_slice(key);
+    out.extend(encode_compact_size(value.len() as u64));
+    out.extend_from_slice(value);
+}
+
+fn write_unknown(out: &mut Vec<u8>, unknown: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    for (key, value) in unknown {
+        write_pair(out, key, value);
+    }
+}
+
+// Combiner rule for uninterpreted entries: keys already present win
+fn merge_unknown(ours: &mut BTreeMap<Vec<u8>, Vec<u8>>, theirs: &BTreeMap<Vec<u8>, Vec<u8>>) {
+    for (key, value) in theirs {
+        ours.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+}
+
+// Raw key and value of one map entry; the first key byte is its type
+type KeyValue = (Vec<u8>, Vec<u8>);
+
+// Key-value pairs up to the 0x00 terminator, advancing `cursor` past it
+fn read_map(cursor: &mut ByteCursor<'_>) -> Result<Vec<KeyValue>, BitcoinError> {
+    let mut pairs: Vec<KeyValue> = Vec::new();
+    loop {
+        let key = cursor.read_var_bytes()?;
+        if key.is_empty() {
+            return Ok(pairs);
+        }
+        if pairs.iter().any(|(existing, _)| *existing == key) {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        let value = cursor.read_var_bytes()?;
+        pairs.push((key.to_vec(), value.to_vec()));
+    }
+}
+
+fn parse_tx_output(data: &[u8]) -> Result<TxOutput, BitcoinError> {
+    let mut cursor = ByteCursor::new(data);
+    let output = cursor.read_tx_output()?;
+    if !cursor.is_empty() {
+        return Err(BitcoinError::InvalidTransaction);
+    }
+    Ok(output)
+}
+
+// scriptSig spending `script_pubkey` with the collected signatures
+fn final_script_sig(
+    script_pubkey: &Script,
+    partial_sigs: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Result<Script, BitcoinError> {
+    if script_pubkey.is_p2pkh() {
+        let hash = &script_pubkey.bytes()[3..23];
+        let (pubkey, signature) = partial_sigs
+            .iter()
+            .find(|(pubkey, _)| hash160(pubkey) == hash)
+            .ok_or(BitcoinError::InvalidScript)?;
+        return Ok(ScriptBuilder::new()
+            .push_data(signature)
+            .push_data(pubkey)
+            .build());
+    }
+    if let Some(pubkey) = script_pubkey.extract_p2pk_pubkey() {
+        let signature = partial_sigs
+            .get(pubkey)
+            .ok_or(BitcoinError::InvalidScript)?;
+        return Ok(ScriptBuilder::new().push_data(signature).build());
+    }
+    if let Some((required, _)) = script_pubkey.multisig_params() {
+        // Signatures must appear in the same order as their keys
+        let signatures: Vec<&Vec<u8>> = script_pubkey
+            .decompile()?
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::OpData(pubkey) => partial_sigs.get(pubkey),
+                _ => None,
+            })
+            .take(required as usize)
+            .collect();
+        if signatures.len() < required as usize {
+            return Err(BitcoinError::InvalidScript);
+        }
+        return Ok(signatures
+            .into_iter()
+            .fold(
+                ScriptBuilder::new().push_opcode(Opcode::Op0),
+                |builder, sig| builder.push_data(sig),
+            )
+            .build());
+    }
+    Err(BitcoinError::InvalidScript)
+}
@@ -0,0 +1,91 @@
+// BIP-125 opt-in replace-by-fee: signalling, and fee-bumping a transaction by
+// shrinking its change output
+
+use crate::validation::DUST_RELAY_FEE_RATE;
+use crate::{
+    BitcoinError, FeeRate, LegacyTransaction, LegacyTransactionBuilder, Script, TxInput, UtxoSet,
+};
+
+// Rule 4: a replacement must also pay for its own relay at this rate
+pub const INCREMENTAL_RELAY_FEE_RATE: FeeRate = FeeRate::from_sat_per_vb(1);
+
+impl TxInput {
+    pub fn signals_rbf(&self) -> bool {
+        self.sequence.signals_rbf()
+    }
+}
+
+impl LegacyTransaction {
+    // Same as `is_rbf_replaceable`
+    pub fn is_replaceable(&self) -> bool {
+        self.is_rbf_replaceable()
+    }
+}
+
+impl LegacyTransactionBuilder {
+    // Replacement for `original` paying at least `new_fee_rate`, funded by
+    // taking the extra fee out of output `change_index`. The inputs are the
+    // same (so no new unconfirmed ones, rule 2) but unsigned, since the old
+    // signatures commit to the old outputs. The fee must exceed the
+    // original's by the incremental relay fee (rules 3 and 4) and the fee rate
+    // must be higher (rule 6).
+    pub fn replace_by_fee(
+        original: &LegacyTransaction,
+        utxo_set: &UtxoSet,
+        new_fee_rate: FeeRate,
+        change_index: usize,
+    ) -> Result<LegacyTransactionBuilder, BitcoinError> {
+        let reject = |reason: String| BitcoinError::ReplacementRejected(reason);
+        if !original.is_replaceable() {
+            return Err(reject("original does not signal replaceability".into()));
+        }
+        let change = original
+            .outputs
+            .get(change_index)
+            .ok_or_else(|| reject(format!("no change output {change_index}")))?;
+
+        let vsize = original.vsize();
+        let old_fee = original.absolute_fee(utxo_set)?;
+        let old_fee_rate = FeeRate::from_fee_and_vsize(old_fee, vsize);
+        if new_fee_rate <= old_fee_rate {
+            return Err(reject(format!(
+                "fee rate {new_fee_rate} does not exceed the original {old_fee_rate}"
+            )));
+        }
+        let new_fee = new_fee_rate
+            .fee_for_vsize(vsize)
+            .max(old_fee + INCREMENTAL_RELAY_FEE_RATE.fee_for_vsize(vsize));
+        let bump = new_fee - old_fee;
+
+        let new_change = change
+            .value
+            .checked_sub(bump)
+            .ok_or(BitcoinError::InsufficientFunds {
+                needed: bump,
+                available: change.value,
+            })?;
+        let mut outputs = original.outputs.clone();
+        outputs[change_index].value = new_change;
+        if outputs[change_index].is_dust(DUST_RELAY_FEE_RATE) {
+            return Err(BitcoinError::DustOutput {
+                value: outputs[change_index].value,
+                threshold: outputs[change_index].dust_threshold(DUST_RELAY_FEE_RATE),
+            });
+        }
+
+        let inputs = original
+            .inputs
+            .iter()
+            .map(|input| TxInput {
+                script_sig: Script::default(),
+                ..input.clone()
+            })
+            .collect();
+        Ok(LegacyTransactionBuilder {
+            version: original.version,
+            inputs,
+            outputs,
+            lock_time: original.lock_time,
+        })
+    }
+}
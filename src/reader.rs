@@ -0,0 +1,205 @@
+// Streaming transaction decoding from any `std::io::Read` source
+
+use std::io::{ErrorKind, Read};
+
+use crate::segwit::{SEGWIT_MARKER, SegWitTransaction, detect_segwit};
+use crate::transaction::Transaction;
+use crate::varint::check_canonical;
+use crate::{
+    Amount, BitcoinError, LegacyTransaction, LegacyTransactionBuilder, LockTime, OutPoint, Script,
+    Sequence, TxInput, TxOutput, Txid,
+};
+
+pub struct TransactionReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> TransactionReader<R> {
+    pub fn new(reader: R) -> Self {
+        TransactionReader { reader }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Read a transaction in the legacy (non-witness) format
+    pub fn read_transaction(&mut self) -> Result<LegacyTransaction, BitcoinError> {
+        let version = self.read_i32()?;
+        let input_count = self.read_compact_size()?;
+        self.read_legacy_body(version, input_count)
+    }
+
+    // Read a transaction, detecting the SegWit marker and flag after the version
+    pub fn read_any_transaction(&mut self) -> Result<Transaction, BitcoinError> {
+        // The flag byte is only consumed after a marker, since otherwise the
+        // byte after the version already belongs to the input count
+        let mut header = [0u8; 6];
+        self.read_exact(&mut header[..5])?;
+        if header[4] == SEGWIT_MARKER {
+            header[5] = self.read_u8()?;
+        }
+        let version = i32::from_le_bytes(header[..4].try_into().unwrap());
+
+        if !detect_segwit(&header) {
+            if header[4] == SEGWIT_MARKER {
+                return Err(BitcoinError::InvalidTransaction);
+            }
+            let input_count = self.read_compact_size_with_prefix(header[4])?;
+            return self
+                .read_legacy_body(version, input_count)
+                .map(Transaction::Legacy);
+        }
+
+        let inputs = self.read_inputs()?;
+        let outputs = self.read_outputs()?;
+        let mut witness = Vec::with_capacity(inputs.len());
+        for _ in 0..inputs.len() {
+            let item_count = self.read_compact_size()?;
+            let stack = (0..item_count)
+                .map(|_| self.read_var_bytes())
+                .collect::<Result<Vec<_>, _>>()?;
+            witness.push(stack);
+        }
+        let lock_time = LockTime(self.read_u32()?);
+
+        Ok(Transaction::SegWit(SegWitTransaction {
+            version,
+            inputs,
+            outputs,
+            witness,
+            lock_time,
+        }))
+    }
+
+    fn read_legacy_body(
+        &mut self,
+        version: i32,
+        input_count: u64,
+    ) -> Result<LegacyTransaction, BitcoinError> {
+        let inputs = (0..input_count)
+            .map(|_| self.read_input())
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = self.read_outputs()?;
+        let lock_time = LockTime(self.read_u32()?);
+
+        Ok(LegacyTransactionBuilder {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        }
+        .build())
+    }
+
+    fn read_inputs(&mut self) -> Result<Vec<TxInput>, BitcoinError> {
+        let count = self.read_compact_size()?;
+        (0..count).map(|_| self.read_input()).collect()
+    }
+
+    fn read_outputs(&mut self) -> Result<Vec<TxOutput>, BitcoinError> {
+        let count = self.read_compact_size()?;
+        (0..count).map(|_| self.read_output()).collect()
+    }
+
+    pub(crate) fn read_outpoint(&mut self) -> Result<OutPoint, BitcoinError> {
+        let mut txid = [0u8; 32];
+        self.read_exact(&mut txid)?;
+        let vout = self.read_u32()?;
+        Ok(OutPoint {
+            txid: Txid(txid),
+            vout,
+        })
+    }
+
+    pub(crate) fn read_input(&mut self) -> Result<TxInput, BitcoinError> {
+        let previous_output = self.read_outpoint()?;
+        let script_sig = self.read_var_bytes()?;
+        let sequence = Sequence(self.read_u32()?);
+
+        Ok(TxInput {
+            previous_output,
+            script_sig: Script(script_sig),
+            sequence,
+        })
+    }
+
+    pub(crate) fn read_output(&mut self) -> Result<TxOutput, BitcoinError> {
+        let value = self.read_u64()?;
+        let script_pubkey = self.read_var_bytes()?;
+        Ok(TxOutput {
+            value: Amount::from_sat(value),
+            script_pubkey: Script(script_pubkey),
+        })
+    }
+
+    fn read_var_bytes(&mut self) -> Result<Vec<u8>, BitcoinError> {
+        let len = self.read_compact_size()?;
+        let mut bytes = Vec::new();
+        let read = (&mut self.reader)
+            .take(len)
+            .read_to_end(&mut bytes)
+            .map_err(io_error)?;
+        if (read as u64) < len {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_compact_size(&mut self) -> Result<u64, BitcoinError> {
+        let prefix = self.read_u8()?;
+        self.read_compact_size_with_prefix(prefix)
+    }
+
+    fn read_compact_size_with_prefix(&mut self, prefix: u8) -> Result<u64, BitcoinError> {
+        let (n, encoded_len) = match prefix {
+            0xFD => {
+                let mut buf = [0u8; 2];
+                self.read_exact(&mut buf)?;
+                (u16::from_le_bytes(buf) as u64, 3)
+            }
+            0xFE => (self.read_u32()? as u64, 5),
+            0xFF => (self.read_u64()?, 9),
+            n => return Ok(n as u64),
+        };
+        check_canonical(n, encoded_len)?;
+        Ok(n)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, BitcoinError> {
+        let mut buf = [0u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_i32(&mut self) -> Result<i32, BitcoinError> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(i32::from_le_bytes(buf))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, BitcoinError> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, BitcoinError> {
+        let mut buf = [0u8; 8];
+        self.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    pub(crate) fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), BitcoinError> {
+        self.reader.read_exact(buf).map_err(io_error)
+    }
+}
+
+// Running out of bytes means the transaction is truncated; any other
+// failure came from the underlying reader
+fn io_error(err: std::io::Error) -> BitcoinError {
+    match err.kind() {
+        ErrorKind::UnexpectedEof => BitcoinError::InvalidTransaction,
+        _ => BitcoinError::IoError(err),
+    }
+}
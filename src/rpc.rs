@@ -0,0 +1,221 @@
+// Minimal Bitcoin Core JSON-RPC client over plain HTTP, enabled with the `rpc`
+// feature. Requests are sent as HTTP/1.0 so the node closes the connection
+// after a single, unchunked reply.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use base64::Engine;
+use serde_json::{Value, json};
+
+use crate::transaction::Transaction;
+use crate::{
+    Address, Amount, BitcoinError, BitcoinSerialize, CliCommand, CommandResult, FeeRate,
+    ParseErrorCode, Txid, hex,
+};
+
+// Port of mainnet bitcoind's RPC server, used when the URL has none
+const DEFAULT_RPC_PORT: u16 = 8332;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub enum Auth {
+    None,
+    UserPass { user: String, password: String },
+    // The `.cookie` file bitcoind writes to its data directory, read on every
+    // call since the node replaces it on restart
+    CookieFile(PathBuf),
+}
+
+impl Auth {
+    fn header(&self) -> Result<Option<String>, BitcoinError> {
+        let credentials = match self {
+            Auth::None => return Ok(None),
+            Auth::UserPass { user, password } => format!("{user}:{password}"),
+            Auth::CookieFile(path) => std::fs::read_to_string(path)?.trim().to_string(),
+        };
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        Ok(Some(format!("Basic {encoded}")))
+    }
+}
+
+#[derive(Debug)]
+pub struct RpcClient {
+    // `host:port` to connect to, and the request path (`/wallet/<name>` to
+    // pick a wallet)
+    host: String,
+    path: String,
+    auth: Auth,
+    next_id: AtomicU64,
+}
+
+impl RpcClient {
+    // `url` is `http://host[:port][/path]`
+    pub fn new(url: &str, auth: Auth) -> Result<RpcClient, BitcoinError> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            BitcoinError::cli_error(
+                ParseErrorCode::InvalidArgument,
+                format!("RPC URL must start with http://: {url}"),
+            )
+        })?;
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if host.is_empty() {
+            return Err(BitcoinError::cli_error(
+                ParseErrorCode::InvalidArgument,
+                format!("RPC URL has no host: {url}"),
+            ));
+        }
+        let host = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{host}:{DEFAULT_RPC_PORT}")
+        };
+        let path = if path.is_empty() { "/" } else { path };
+        Ok(RpcClient {
+            host,
+            path: path.to_string(),
+            auth,
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    // Send one request, returning its `result`. Errors reported by the node
+    // become `RpcError`, with the node's code.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, BitcoinError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({"jsonrpc": "1.0", "id": id, "method": method, "params": params});
+        let body = body.to_string();
+
+        let mut request = format!(
+            "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            self.path,
+            self.host,
+            body.len()
+        );
+        if let Some(header) = self.auth.header()? {
+            request.push_str(&format!("Authorization: {header}\r\n"));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let mut stream = TcpStream::connect(&self.host)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        stream.write_all(request.as_bytes())?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        parse_response(&response)
+    }
+
+    // Confirmed balance of the node's wallet
+    pub fn get_balance(&self) -> Result<Amount, BitcoinError> {
+        let btc = self.call("getbalance", json!([]))?;
+        Amount::from_btc(
+            btc.as_f64()
+                .ok_or_else(|| invalid("balance is not a number"))?,
+        )
+    }
+
+    pub fn send_raw_transaction(&self, tx: &impl BitcoinSerialize) -> Result<Txid, BitcoinError> {
+        let txid = self.call("sendrawtransaction", json!([hex::encode(&tx.serialize())]))?;
+        txid.as_str()
+            .ok_or_else(|| invalid("txid is not a string"))?
+            .parse()
+    }
+
+    // Needs `-txindex` on the node unless the transaction is in its mempool or
+    // wallet
+    pub fn get_raw_transaction(&self, txid: &Txid) -> Result<Transaction, BitcoinError> {
+        let raw = self.call("getrawtransaction", json!([txid.to_string(), false]))?;
+        Transaction::from_hex(
+            raw.as_str()
+                .ok_or_else(|| invalid("raw tx is not a string"))?,
+        )
+    }
+
+    // Fee rate for confirming within `conf_target` blocks, or `None` while the
+    // node has too little data to estimate
+    pub fn estimate_smart_fee(&self, conf_target: u32) -> Result<Option<FeeRate>, BitcoinError> {
+        let estimate = self.call("estimatesmartfee", json!([conf_target]))?;
+        // Reported in BTC per 1000 virtual bytes
+        let Some(btc_per_kvb) = estimate.get("feerate").and_then(Value::as_f64) else {
+            return Ok(None);
+        };
+        let sat_per_kvb = Amount::from_btc(btc_per_kvb)?.to_sat();
+        Ok(Some(FeeRate::from_sat_per_kvb(sat_per_kvb)))
+    }
+
+    // Pay `amount` to `address` from the node's wallet, which picks the coins,
+    // signs and broadcasts
+    pub fn send_to_address(&self, address: &str, amount: Amount) -> Result<Txid, BitcoinError> {
+        let txid = self.call("sendtoaddress", json!([address, amount.to_btc()]))?;
+        txid.as_str()
+            .ok_or_else(|| invalid("txid is not a string"))?
+            .parse()
+    }
+}
+
+// Run `cmd` against the node behind `client`: `balance` asks the node's
+// wallet, `send` pays from it and `broadcast` submits the raw transaction
+// instead of framing it. Other commands run locally.
+pub fn execute(cmd: &CliCommand, client: &RpcClient) -> Result<CommandResult, BitcoinError> {
+    let txid_result = |txid: Txid| CommandResult::Field {
+        name: "txid".to_string(),
+        value: txid.to_string(),
+    };
+    match cmd {
+        CliCommand::Balance => Ok(CommandResult::balance(client.get_balance()?.to_sat())),
+        CliCommand::Send { amount, address } => {
+            Address::from_str_any_network(address)?;
+            client.send_to_address(address, *amount).map(txid_result)
+        }
+        CliCommand::Broadcast { raw_hex, .. } => {
+            let tx = Transaction::from_hex(raw_hex)?;
+            client.send_raw_transaction(&tx).map(txid_result)
+        }
+        _ => cmd.execute(),
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> BitcoinError {
+    BitcoinError::InvalidRpcResponse(reason.into())
+}
+
+fn parse_response(response: &[u8]) -> Result<Value, BitcoinError> {
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid("no HTTP header terminator"))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| invalid("malformed HTTP status line"))?;
+
+    // Core sends RPC errors as JSON with a 4xx/5xx status, but an auth
+    // failure has an empty body
+    let Ok(reply) = serde_json::from_slice::<Value>(&response[split + 4..]) else {
+        return Err(invalid(format!("HTTP {status} without a JSON body")));
+    };
+    match reply.get("error") {
+        Some(error) if !error.is_null() => Err(BitcoinError::RpcError {
+            code: error
+                .get("code")
+                .and_then(Value::as_i64)
+                .unwrap_or_default(),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        }),
+        _ => reply
+            .get("result")
+            .cloned()
+            .ok_or_else(|| invalid("reply has no result")),
+    }
+}
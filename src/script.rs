@@ -0,0 +1,979 @@
+// Bitcoin script primitives: opcodes, scripts and a script builder
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::hash::{hash160, sha256};
+use crate::{Amount, BitcoinError, TxOutput, hex};
+
+// Generates the `Opcode` enum together with its byte and ASM name mappings
+macro_rules! opcodes {
+    ($($variant:ident = $byte:literal => $name:literal,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Opcode {
+            $($variant,)*
+            // Any byte without an assigned opcode
+            Unknown(u8),
+        }
+
+        impl Opcode {
+            pub fn from_u8(byte: u8) -> Opcode {
+                match byte {
+                    $($byte => Opcode::$variant,)*
+                    other => Opcode::Unknown(other),
+                }
+            }
+
+            pub fn to_u8(self) -> u8 {
+                match self {
+                    $(Opcode::$variant => $byte,)*
+                    Opcode::Unknown(byte) => byte,
+                }
+            }
+
+            // ASM name, e.g. "OP_CHECKSIG"
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Opcode::$variant => $name,)*
+                    Opcode::Unknown(_) => "OP_UNKNOWN",
+                }
+            }
+
+            // Look up an opcode by its ASM name (case-insensitive)
+            pub fn from_name(name: &str) -> Option<Opcode> {
+                let upper = name.to_ascii_uppercase();
+                match upper.as_str() {
+                    "OP_FALSE" => return Some(Opcode::Op0),
+                    "OP_TRUE" => return Some(Opcode::Op1),
+                    _ => {}
+                }
+                [$(Opcode::$variant,)*]
+                    .into_iter()
+                    .find(|op| op.name() == upper)
+            }
+        }
+    };
+}
+
+opcodes! {
+    Op0 = 0x00 => "OP_0",
+    OpPushData1 = 0x4c => "OP_PUSHDATA1",
+    OpPushData2 = 0x4d => "OP_PUSHDATA2",
+    OpPushData4 = 0x4e => "OP_PUSHDATA4",
+    Op1Negate = 0x4f => "OP_1NEGATE",
+    OpReserved = 0x50 => "OP_RESERVED",
+    Op1 = 0x51 => "OP_1",
+    Op2 = 0x52 => "OP_2",
+    Op3 = 0x53 => "OP_3",
+    Op4 = 0x54 => "OP_4",
+    Op5 = 0x55 => "OP_5",
+    Op6 = 0x56 => "OP_6",
+    Op7 = 0x57 => "OP_7",
+    Op8 = 0x58 => "OP_8",
+    Op9 = 0x59 => "OP_9",
+    Op10 = 0x5a => "OP_10",
+    Op11 = 0x5b => "OP_11",
+    Op12 = 0x5c => "OP_12",
+    Op13 = 0x5d => "OP_13",
+    Op14 = 0x5e => "OP_14",
+    Op15 = 0x5f => "OP_15",
+    Op16 = 0x60 => "OP_16",
+    OpNop = 0x61 => "OP_NOP",
+    OpVer = 0x62 => "OP_VER",
+    OpIf = 0x63 => "OP_IF",
+    OpNotIf = 0x64 => "OP_NOTIF",
+    OpVerIf = 0x65 => "OP_VERIF",
+    OpVerNotIf = 0x66 => "OP_VERNOTIF",
+    OpElse = 0x67 => "OP_ELSE",
+    OpEndIf = 0x68 => "OP_ENDIF",
+    OpVerify = 0x69 => "OP_VERIFY",
+    OpReturn = 0x6a => "OP_RETURN",
+    OpToAltStack = 0x6b => "OP_TOALTSTACK",
+    OpFromAltStack = 0x6c => "OP_FROMALTSTACK",
+    Op2Drop = 0x6d => "OP_2DROP",
+    Op2Dup = 0x6e => "OP_2DUP",
+    Op3Dup = 0x6f => "OP_3DUP",
+    Op2Over = 0x70 => "OP_2OVER",
+    Op2Rot = 0x71 => "OP_2ROT",
+    Op2Swap = 0x72 => "OP_2SWAP",
+    OpIfDup = 0x73 => "OP_IFDUP",
+    OpDepth = 0x74 => "OP_DEPTH",
+    OpDrop = 0x75 => "OP_DROP",
+    OpDup = 0x76 => "OP_DUP",
+    OpNip = 0x77 => "OP_NIP",
+    OpOver = 0x78 => "OP_OVER",
+    OpPick = 0x79 => "OP_PICK",
+    OpRoll = 0x7a => "OP_ROLL",
+    OpRot = 0x7b => "OP_ROT",
+    OpSwap = 0x7c => "OP_SWAP",
+    OpTuck = 0x7d => "OP_TUCK",
+    OpCat = 0x7e => "OP_CAT",
+    OpSubstr = 0x7f => "OP_SUBSTR",
+    OpLeft = 0x80 => "OP_LEFT",
+    OpRight = 0x81 => "OP_RIGHT",
+    OpSize = 0x82 => "OP_SIZE",
+    OpInvert = 0x83 => "OP_INVERT",
+    OpAnd = 0x84 => "OP_AND",
+    OpOr = 0x85 => "OP_OR",
+    OpXor = 0x86 => "OP_XOR",
+    OpEqual = 0x87 => "OP_EQUAL",
+    OpEqualVerify = 0x88 => "OP_EQUALVERIFY",
+    OpReserved1 = 0x89 => "OP_RESERVED1",
+    OpReserved2 = 0x8a => "OP_RESERVED2",
+    Op1Add = 0x8b => "OP_1ADD",
+    Op1Sub = 0x8c => "OP_1SUB",
+    Op2Mul = 0x8d => "OP_2MUL",
+    Op2Div = 0x8e => "OP_2DIV",
+    OpNegate = 0x8f => "OP_NEGATE",
+    OpAbs = 0x90 => "OP_ABS",
+    OpNot = 0x91 => "OP_NOT",
+    Op0NotEqual = 0x92 => "OP_0NOTEQUAL",
+    OpAdd = 0x93 => "OP_ADD",
+    OpSub = 0x94 => "OP_SUB",
+    OpMul = 0x95 => "OP_MUL",
+    OpDiv = 0x96 => "OP_DIV",
+    OpMod = 0x97 => "OP_MOD",
+    OpLShift = 0x98 => "OP_LSHIFT",
+    OpRShift = 0x99 => "OP_RSHIFT",
+    OpBoolAnd = 0x9a => "OP_BOOLAND",
+    OpBoolOr = 0x9b => "OP_BOOLOR",
+    OpNumEqual = 0x9c => "OP_NUMEQUAL",
+    OpNumEqualVerify = 0x9d => "OP_NUMEQUALVERIFY",
+    OpNumNotEqual = 0x9e => "OP_NUMNOTEQUAL",
+    OpLessThan = 0x9f => "OP_LESSTHAN",
+    OpGreaterThan = 0xa0 => "OP_GREATERTHAN",
+    OpLessThanOrEqual = 0xa1 => "OP_LESSTHANOREQUAL",
+    OpGreaterThanOrEqual = 0xa2 => "OP_GREATERTHANOREQUAL",
+    OpMin = 0xa3 => "OP_MIN",
+    OpMax = 0xa4 => "OP_MAX",
+    OpWithin = 0xa5 => "OP_WITHIN",
+    OpRipemd160 = 0xa6 => "OP_RIPEMD160",
+    OpSha1 = 0xa7 => "OP_SHA1",
+    OpSha256 = 0xa8 => "OP_SHA256",
+    OpHash160 = 0xa9 => "OP_HASH160",
+    OpHash256 = 0xaa => "OP_HASH256",
+    OpCodeSeparator = 0xab => "OP_CODESEPARATOR",
+    OpCheckSig = 0xac => "OP_CHECKSIG",
+    OpCheckSigVerify = 0xad => "OP_CHECKSIGVERIFY",
+    OpCheckMultiSig = 0xae => "OP_CHECKMULTISIG",
+    OpCheckMultiSigVerify = 0xaf => "OP_CHECKMULTISIGVERIFY",
+    OpNop1 = 0xb0 => "OP_NOP1",
+    OpCheckLockTimeVerify = 0xb1 => "OP_CHECKLOCKTIMEVERIFY",
+    OpCheckSequenceVerify = 0xb2 => "OP_CHECKSEQUENCEVERIFY",
+    OpNop4 = 0xb3 => "OP_NOP4",
+    OpNop5 = 0xb4 => "OP_NOP5",
+    OpNop6 = 0xb5 => "OP_NOP6",
+    OpNop7 = 0xb6 => "OP_NOP7",
+    OpNop8 = 0xb7 => "OP_NOP8",
+    OpNop9 = 0xb8 => "OP_NOP9",
+    OpNop10 = 0xb9 => "OP_NOP10",
+    OpCheckSigAdd = 0xba => "OP_CHECKSIGADD",
+}
+
+impl Opcode {
+    // Opcodes disabled in 2010 (CVE-2010-5137); any script executing them fails
+    pub fn is_disabled(&self) -> bool {
+        matches!(
+            self,
+            Opcode::OpCat
+                | Opcode::OpSubstr
+                | Opcode::OpLeft
+                | Opcode::OpRight
+                | Opcode::OpInvert
+                | Opcode::OpAnd
+                | Opcode::OpOr
+                | Opcode::OpXor
+                | Opcode::Op2Mul
+                | Opcode::Op2Div
+                | Opcode::OpMul
+                | Opcode::OpDiv
+                | Opcode::OpMod
+                | Opcode::OpLShift
+                | Opcode::OpRShift
+        )
+    }
+}
+
+// Standard output script templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputScriptType {
+    P2PK,
+    P2PKH,
+    P2SH,
+    P2WPKH,
+    P2WSH,
+    P2TR,
+    // Pay-to-anchor, used for CPFP fee bumping of ephemeral anchors
+    P2A,
+    // Bare `m`-of-`n` multisig
+    P2MS { required: u8, total: u8 },
+    OpReturn,
+    NonStandard,
+}
+
+impl OutputScriptType {
+    // Serialized size of an output of this type: 8-byte value, 1-byte script
+    // length and the script. P2PK assumes a compressed key, OP_RETURN an
+    // empty payload and non-standard scripts the P2PKH size.
+    pub fn output_vbytes(&self) -> u64 {
+        let script_len = match self {
+            OutputScriptType::P2PK => 35,
+            OutputScriptType::P2PKH | OutputScriptType::NonStandard => 25,
+            OutputScriptType::P2SH => 23,
+            OutputScriptType::P2WPKH => 22,
+            OutputScriptType::P2WSH | OutputScriptType::P2TR => 34,
+            OutputScriptType::P2A => 4,
+            OutputScriptType::P2MS { total, .. } => 3 + 34 * *total as u64,
+            OutputScriptType::OpReturn => 1,
+        };
+        9 + script_len
+    }
+}
+
+// `OP_1 <0x4e73>`
+const P2A_SCRIPT: [u8; 4] = [0x51, 0x02, 0x4e, 0x73];
+
+// BIP-342 leaf version for tapscript
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+// Control block sizes (BIP-341): 33 bytes plus up to 128 merkle path hashes
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+const TAPROOT_CONTROL_MAX_NODES: usize = 128;
+
+// True for the only leaf version the interpreter knows how to execute
+pub fn is_tapscript(leaf_version: u8) -> bool {
+    leaf_version == TAPSCRIPT_LEAF_VERSION
+}
+
+// Fail with `UnsupportedTaprootLeafVersion` unless `leaf_version` is tapscript
+pub fn require_tapscript(leaf_version: u8) -> Result<(), BitcoinError> {
+    if is_tapscript(leaf_version) {
+        Ok(())
+    } else {
+        Err(BitcoinError::UnsupportedTaprootLeafVersion(leaf_version))
+    }
+}
+
+// A single decompiled script element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Op(Opcode),
+    OpData(Vec<u8>),
+}
+
+// One element of a `ScriptPattern`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternElement {
+    // Exactly this opcode
+    Op(Opcode),
+    // A data push of exactly this many bytes
+    Push(usize),
+    // A data push of any length between the bounds, inclusive
+    PushRange(usize, usize),
+    // Any single opcode or data push
+    Any,
+}
+
+// A script shape described instruction by instruction, so data pushes can be
+// matched by length rather than byte offset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptPattern(pub Vec<PatternElement>);
+
+impl ScriptPattern {
+    // `OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG`
+    pub fn p2pkh() -> Self {
+        ScriptPattern(vec![
+            PatternElement::Op(Opcode::OpDup),
+            PatternElement::Op(Opcode::OpHash160),
+            PatternElement::Push(20),
+            PatternElement::Op(Opcode::OpEqualVerify),
+            PatternElement::Op(Opcode::OpCheckSig),
+        ])
+    }
+
+    // `<33 or 65 byte key> OP_CHECKSIG`
+    pub fn p2pk() -> Self {
+        ScriptPattern(vec![
+            PatternElement::PushRange(33, 65),
+            PatternElement::Op(Opcode::OpCheckSig),
+        ])
+    }
+}
+
+// Raw script bytes (scriptPubKey, scriptSig, redeem or witness script)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Script(pub Vec<u8>);
+
+// `Script` already owns its bytes; the alias matches the name other Bitcoin
+// libraries use for owned scripts
+pub type ScriptBuf = Script;
+
+impl Script {
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    // Hex and ASM are the primary ways to build a script from external data
+    // (RPC output, test vectors, CLI arguments); `to_hex` and `to_asm` go back
+    pub fn from_hex(hex: &str) -> Result<Script, BitcoinError> {
+        hex::decode(hex).map(Script)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    pub fn from_asm(asm: &str) -> Result<Script, BitcoinError> {
+        ScriptBuilder::from_asm(asm)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn pay_to_anchor() -> Script {
+        Script(P2A_SCRIPT.to_vec())
+    }
+
+    // `<pubkey> OP_CHECKSIG` for a 33-byte compressed or 65-byte uncompressed key
+    pub fn pay_to_pubkey(pubkey: &[u8]) -> Result<Script, BitcoinError> {
+        if pubkey.len() != 33 && pubkey.len() != 65 {
+            return Err(BitcoinError::InvalidScript);
+        }
+        Ok(ScriptBuilder::new()
+            .push_data(pubkey)
+            .push_opcode(Opcode::OpCheckSig)
+            .build())
+    }
+
+    // Opcode byte that pushes `data` by length alone: OP_0 when empty, a direct
+    // push up to 75 bytes, then OP_PUSHDATA1/2/4. Small numbers are not mapped
+    // to OP_1..OP_16 here.
+    pub fn min_push_opcode(data: &[u8]) -> u8 {
+        match data.len() {
+            0 => Opcode::Op0.to_u8(),
+            len @ 1..=0x4b => len as u8,
+            0x4c..=0xff => Opcode::OpPushData1.to_u8(),
+            0x100..=0xffff => Opcode::OpPushData2.to_u8(),
+            _ => Opcode::OpPushData4.to_u8(),
+        }
+    }
+
+    // `OP_DUP OP_HASH160 <pubkey_hash> OP_EQUALVERIFY OP_CHECKSIG`
+    pub fn new_p2pkh(pubkey_hash: &[u8; 20]) -> Script {
+        ScriptBuilder::new()
+            .push_opcode(Opcode::OpDup)
+            .push_opcode(Opcode::OpHash160)
+            .push_data(pubkey_hash)
+            .push_opcode(Opcode::OpEqualVerify)
+            .push_opcode(Opcode::OpCheckSig)
+            .build()
+    }
+
+    // `OP_HASH160 <script_hash> OP_EQUAL`
+    pub fn new_p2sh(script_hash: &[u8; 20]) -> Script {
+        ScriptBuilder::new()
+            .push_opcode(Opcode::OpHash160)
+            .push_data(script_hash)
+            .push_opcode(Opcode::OpEqual)
+            .build()
+    }
+
+    // `OP_0 <pubkey_hash>`
+    pub fn new_p2wpkh(pubkey_hash: &[u8; 20]) -> Script {
+        Script::new_witness_program(Opcode::Op0, pubkey_hash)
+    }
+
+    // `OP_0 <script_hash>`
+    pub fn new_p2wsh(script_hash: &[u8; 32]) -> Script {
+        Script::new_witness_program(Opcode::Op0, script_hash)
+    }
+
+    // `OP_1 <x-only output key>`
+    pub fn new_p2tr(output_key: &[u8; 32]) -> Script {
+        Script::new_witness_program(Opcode::Op1, output_key)
+    }
+
+    fn new_witness_program(version: Opcode, program: &[u8]) -> Script {
+        ScriptBuilder::new()
+            .push_opcode(version)
+            .push_data(program)
+            .build()
+    }
+
+    // `OP_RETURN <data>`, or a bare `OP_RETURN` when `data` is empty
+    pub fn new_op_return(data: &[u8]) -> Script {
+        let builder = ScriptBuilder::new().push_opcode(Opcode::OpReturn);
+        if data.is_empty() {
+            return builder.build();
+        }
+        builder.push_data(data).build()
+    }
+
+    // `OP_HASH160 <hash160(inner)> OP_EQUAL`
+    pub fn p2sh_wrapped(inner: &Script) -> Script {
+        Script::new_p2sh(&hash160(inner.bytes()))
+    }
+
+    // `OP_0 <sha256(inner)>`
+    pub fn p2wsh_wrapped(inner: &Script) -> Script {
+        Script::new_p2wsh(&sha256(inner.bytes()))
+    }
+
+    // P2SH output wrapping `OP_0 <hash160(pubkey)>`; SegWit only allows
+    // compressed keys
+    pub fn p2sh_p2wpkh_wrapped(pubkey: &[u8]) -> Result<Script, BitcoinError> {
+        if pubkey.len() != 33 || !matches!(pubkey[0], 0x02 | 0x03) {
+            return Err(BitcoinError::InvalidScript);
+        }
+        Ok(Script::p2sh_wrapped(&Script::new_p2wpkh(&hash160(pubkey))))
+    }
+
+    // Byte-wise match where `None` is a wildcard; lengths must agree
+    pub fn matches_template(&self, template: &[Option<u8>]) -> bool {
+        self.0.len() == template.len()
+            && self
+                .0
+                .iter()
+                .zip(template)
+                .all(|(byte, expected)| expected.is_none_or(|expected| expected == *byte))
+    }
+
+    // Instruction-wise match against `pattern`; malformed scripts never match
+    pub fn matches_pattern(&self, pattern: &ScriptPattern) -> bool {
+        let Ok(instructions) = self.decompile() else {
+            return false;
+        };
+        instructions.len() == pattern.0.len()
+            && instructions
+                .iter()
+                .zip(&pattern.0)
+                .all(|(instruction, element)| match (element, instruction) {
+                    (PatternElement::Any, _) => true,
+                    (PatternElement::Op(expected), Instruction::Op(op)) => expected == op,
+                    (PatternElement::Push(len), Instruction::OpData(data)) => data.len() == *len,
+                    (PatternElement::PushRange(min, max), Instruction::OpData(data)) => {
+                        (*min..=*max).contains(&data.len())
+                    }
+                    _ => false,
+                })
+    }
+
+    // `(m, n)` for `OP_m <n pubkeys> OP_n OP_CHECKMULTISIG` with 1 <= m <= n <= 16
+    pub fn multisig_params(&self) -> Option<(u8, u8)> {
+        let instructions = self.decompile().ok()?;
+        let [
+            Instruction::Op(first),
+            keys @ ..,
+            Instruction::Op(last),
+            Instruction::Op(Opcode::OpCheckMultiSig),
+        ] = instructions.as_slice()
+        else {
+            return None;
+        };
+
+        let small_int = |op: &Opcode| {
+            let byte = op.to_u8();
+            (Opcode::Op1.to_u8()..=Opcode::Op16.to_u8())
+                .contains(&byte)
+                .then(|| byte - Opcode::Op1.to_u8() + 1)
+        };
+        let (required, total) = (small_int(first)?, small_int(last)?);
+        let keys_valid = keys.iter().all(
+            |key| matches!(key, Instruction::OpData(key) if key.len() == 33 || key.len() == 65),
+        );
+        (keys_valid && keys.len() == total as usize && required <= total)
+            .then_some((required, total))
+    }
+
+    // `OP_m <pubkeys> OP_n OP_CHECKMULTISIG`, used both as a P2SH redeem script
+    // and a P2WSH witness script. Requires 1 <= m <= n <= 16 and 33 or 65-byte keys.
+    pub fn multisig_witness_script(m: u8, pubkeys: &[Vec<u8>]) -> Result<Script, BitcoinError> {
+        let n = pubkeys.len();
+        if m == 0 || m as usize > n || n > 16 {
+            return Err(BitcoinError::InvalidScript);
+        }
+        if pubkeys.iter().any(|key| key.len() != 33 && key.len() != 65) {
+            return Err(BitcoinError::InvalidScript);
+        }
+        let small_int = |k: u8| Opcode::from_u8(Opcode::Op1.to_u8() + k - 1);
+        Ok(pubkeys
+            .iter()
+            .fold(
+                ScriptBuilder::new().push_opcode(small_int(m)),
+                |builder, key| builder.push_data(key),
+            )
+            .push_opcode(small_int(n as u8))
+            .push_opcode(Opcode::OpCheckMultiSig)
+            .build())
+    }
+
+    // P2WSH output paying to the m-of-n witness script. The value is left at
+    // zero for the caller to fill in.
+    pub fn p2wsh_multisig_output(m: u8, pubkeys: &[Vec<u8>]) -> Result<TxOutput, BitcoinError> {
+        let witness_script = Script::multisig_witness_script(m, pubkeys)?;
+        Ok(TxOutput::new(
+            Amount::ZERO,
+            Script::p2wsh_wrapped(&witness_script),
+        ))
+    }
+
+    // Whether a (redeem) script has the shape of a bare multisig. Only the
+    // structure is checked: the keys are not validated as curve points.
+    pub fn is_possible_multisig(&self) -> bool {
+        self.multisig_params().is_some()
+    }
+
+    pub fn is_p2pk(&self) -> bool {
+        self.extract_p2pk_pubkey().is_some()
+    }
+
+    pub fn extract_p2pk_pubkey(&self) -> Option<&[u8]> {
+        match self.bytes() {
+            [33, pubkey @ .., 0xac] if pubkey.len() == 33 => Some(pubkey),
+            [65, pubkey @ .., 0xac] if pubkey.len() == 65 => Some(pubkey),
+            _ => None,
+        }
+    }
+
+    // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+    pub fn is_p2pkh(&self) -> bool {
+        matches!(self.bytes(), [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20)
+    }
+
+    // OP_HASH160 <20 bytes> OP_EQUAL
+    pub fn is_p2sh(&self) -> bool {
+        matches!(self.bytes(), [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20)
+    }
+
+    pub fn is_p2wpkh(&self) -> bool {
+        self.witness_version() == Some(0) && self.0.len() == 22
+    }
+
+    pub fn is_p2wsh(&self) -> bool {
+        self.witness_version() == Some(0) && self.0.len() == 34
+    }
+
+    pub fn is_p2tr(&self) -> bool {
+        self.witness_version() == Some(1) && self.0.len() == 34
+    }
+
+    // The x-only output key of a P2TR scriptPubKey
+    pub fn p2tr_output_key(&self) -> Option<[u8; 32]> {
+        self.is_p2tr().then(|| self.0[2..].try_into().unwrap())
+    }
+
+    // Pay-to-anchor (BIP-431): the witness v1 program 0x4e73, spendable by
+    // anyone with an empty witness
+    pub fn is_p2a(&self) -> bool {
+        self.0 == P2A_SCRIPT
+    }
+
+    pub fn is_op_return(&self) -> bool {
+        self.0.first() == Some(&Opcode::OpReturn.to_u8())
+    }
+
+    // The standard template this script matches, if any
+    pub fn classify(&self) -> OutputScriptType {
+        if self.is_p2pk() {
+            OutputScriptType::P2PK
+        } else if self.is_p2pkh() {
+            OutputScriptType::P2PKH
+        } else if self.is_p2sh() {
+            OutputScriptType::P2SH
+        } else if self.is_p2wpkh() {
+            OutputScriptType::P2WPKH
+        } else if self.is_p2wsh() {
+            OutputScriptType::P2WSH
+        } else if self.is_p2tr() {
+            OutputScriptType::P2TR
+        } else if self.is_p2a() {
+            OutputScriptType::P2A
+        } else if let Some((required, total)) = self.multisig_params() {
+            OutputScriptType::P2MS { required, total }
+        } else if self.is_op_return() {
+            OutputScriptType::OpReturn
+        } else {
+            OutputScriptType::NonStandard
+        }
+    }
+
+    // Witness version of a SegWit output: `OP_0`..`OP_16` followed by a
+    // single 2-40 byte push making up the rest of the script
+    pub fn witness_version(&self) -> Option<u8> {
+        self.witness_program()?;
+        match self.0[0] {
+            0x00 => Some(0),
+            op @ 0x51..=0x60 => Some(op - 0x50),
+            _ => None,
+        }
+    }
+
+    // The pushed witness program bytes of a SegWit output
+    pub fn witness_program(&self) -> Option<&[u8]> {
+        match self.bytes() {
+            [0x00 | 0x51..=0x60, len @ 2..=40, program @ ..] if program.len() == *len as usize => {
+                Some(program)
+            }
+            _ => None,
+        }
+    }
+
+    // Leaf version of a taproot control block: the first byte with the output
+    // key parity bit cleared. None if the bytes are not a control block.
+    pub fn tapscript_leaf_version(&self) -> Option<u8> {
+        let len = self.0.len();
+        let nodes_len = len.checked_sub(TAPROOT_CONTROL_BASE_SIZE)?;
+        if !nodes_len.is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+            || nodes_len / TAPROOT_CONTROL_NODE_SIZE > TAPROOT_CONTROL_MAX_NODES
+        {
+            return None;
+        }
+        Some(self.0[0] & 0xfe)
+    }
+
+    // Redeem script of a P2SH spend: the last push of a push-only scriptSig
+    pub fn extract_p2sh_redeem_script(script_sig: &Script) -> Option<Script> {
+        if !script_sig.is_push_only() {
+            return None;
+        }
+        match script_sig.decompile().ok()?.pop()? {
+            Instruction::OpData(data) => Some(Script(data)),
+            Instruction::Op(_) => None,
+        }
+    }
+
+    // Witness script of a P2WSH spend: the last witness stack item
+    pub fn extract_p2wsh_witness_script(witness: &[Vec<u8>]) -> Option<Script> {
+        witness.last().map(|item| Script(item.clone()))
+    }
+
+    // scriptSig spending a P2SH output: `items` pushed in order, then the
+    // serialized redeem script as the final push
+    pub fn p2sh_script_sig(items: &[Vec<u8>], redeem_script: &Script) -> Script {
+        items
+            .iter()
+            .fold(ScriptBuilder::new(), |builder, item| {
+                builder.push_data(item)
+            })
+            .push_data(redeem_script.bytes())
+            .build()
+    }
+
+    // Like `p2sh_script_sig`, preceded by the OP_0 dummy that OP_CHECKMULTISIG
+    // pops in addition to its signatures
+    pub fn p2sh_multisig_script_sig(signatures: &[Vec<u8>], redeem_script: &Script) -> Script {
+        let items: Vec<Vec<u8>> = std::iter::once(Vec::new())
+            .chain(signatures.iter().cloned())
+            .collect();
+        Script::p2sh_script_sig(&items, redeem_script)
+    }
+
+    // Witness stack spending a P2WSH multisig: the empty dummy item popped by
+    // OP_CHECKMULTISIG, the signatures in key order, then the witness script
+    pub fn multisig_spend_witness(sigs: &[Vec<u8>], witness_script: &Script) -> Vec<Vec<u8>> {
+        std::iter::once(Vec::new())
+            .chain(sigs.iter().cloned())
+            .chain(std::iter::once(witness_script.bytes().to_vec()))
+            .collect()
+    }
+
+    // True if the script only pushes data: OP_0, OP_1NEGATE, OP_1..OP_16 or
+    // direct/PUSHDATA pushes. Malformed scripts are not push-only.
+    pub fn is_push_only(&self) -> bool {
+        self.check_push_only().is_ok()
+    }
+
+    // Like `is_push_only`, reporting the first non-push opcode
+    pub fn check_push_only(&self) -> Result<(), BitcoinError> {
+        for instruction in self.decompile()? {
+            match instruction {
+                Instruction::OpData(_) => {}
+                Instruction::Op(Opcode::Op0 | Opcode::Op1Negate) => {}
+                Instruction::Op(opcode)
+                    if (Opcode::Op1.to_u8()..=Opcode::Op16.to_u8()).contains(&opcode.to_u8()) => {}
+                Instruction::Op(opcode) => return Err(BitcoinError::NonPushOpcode(opcode.to_u8())),
+            }
+        }
+        Ok(())
+    }
+
+    // Signature operations as counted by Bitcoin Core's GetSigOpCount:
+    // CHECKSIG(VERIFY) counts 1 and CHECKMULTISIG(VERIFY) counts 20, or the
+    // preceding OP_N when `is_p2sh_redeemscript` asks for accurate counting.
+    // Counting stops at the first malformed push.
+    pub fn sigops(&self, is_p2sh_redeemscript: bool) -> u32 {
+        let (instructions, _) = parse_raw_instructions(&self.0);
+        let mut count = 0;
+        let mut last_opcode = None;
+        for (opcode, _) in instructions {
+            match Opcode::from_u8(opcode) {
+                Opcode::OpCheckSig | Opcode::OpCheckSigVerify => count += 1,
+                Opcode::OpCheckMultiSig | Opcode::OpCheckMultiSigVerify => {
+                    count += match last_opcode {
+                        Some(n @ 0x51..=0x60) if is_p2sh_redeemscript => u32::from(n - 0x50),
+                        _ => 20,
+                    }
+                }
+                _ => {}
+            }
+            last_opcode = Some(opcode);
+        }
+        count
+    }
+
+    // BIP-342 makes minimal OP_IF/OP_NOTIF operands a consensus rule in
+    // tapscript rather than a policy flag, so this is unconditionally true
+    pub fn tapscript_requires_minimal_if(&self) -> bool {
+        true
+    }
+
+    pub fn contains_disabled_opcode(&self) -> Result<bool, BitcoinError> {
+        Ok(self
+            .decompile()?
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::Op(op) if op.is_disabled())))
+    }
+
+    // Split the script into opcodes and data pushes
+    pub fn decompile(&self) -> Result<Vec<Instruction>, BitcoinError> {
+        Ok(raw_instructions(&self.0)?
+            .into_iter()
+            .map(|(opcode, data)| match data {
+                Some(data) => Instruction::OpData(data.to_vec()),
+                None => Instruction::Op(Opcode::from_u8(opcode)),
+            })
+            .collect())
+    }
+
+    // True if every push uses the smallest possible encoding (BIP-62 rule 3),
+    // checked without recompiling; malformed scripts are never minimal
+    pub fn has_minimal_pushes(&self) -> bool {
+        raw_instructions(&self.0).is_ok_and(|instructions| {
+            instructions.iter().all(|(opcode, data)| match data {
+                Some(data) => minimal_push_bytes(data).first() == Some(opcode),
+                None => true,
+            })
+        })
+    }
+
+    // Re-encode every data push minimally, as BIP-141 requires
+    pub fn normalize_pushes(&self) -> Result<Script, BitcoinError> {
+        if self.contains_disabled_opcode()? {
+            return Err(BitcoinError::InvalidScript);
+        }
+
+        let mut bytes = Vec::with_capacity(self.0.len());
+        for instruction in self.decompile()? {
+            match instruction {
+                Instruction::Op(op) => bytes.push(op.to_u8()),
+                Instruction::OpData(data) => bytes.extend(minimal_push_bytes(&data)),
+            }
+        }
+        Ok(Script(bytes))
+    }
+
+    // Assemble instructions into a script, using minimal pushes for data
+    pub fn compile(instructions: &[Instruction]) -> Script {
+        instructions
+            .iter()
+            .fold(
+                ScriptBuilder::new(),
+                |builder, instruction| match instruction {
+                    Instruction::Op(op) => builder.push_opcode(*op),
+                    Instruction::OpData(data) => builder.push_data(data),
+                },
+            )
+            .build()
+    }
+
+    // Human-readable ASM, e.g. "OP_DUP OP_HASH160 <hex> OP_EQUALVERIFY OP_CHECKSIG"
+    pub fn to_asm(&self) -> String {
+        match self.decompile() {
+            Ok(instructions) => instructions
+                .iter()
+                .map(|instruction| match instruction {
+                    Instruction::Op(op) => op.name().to_string(),
+                    Instruction::OpData(data) => hex::encode(data),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Err(_) => "[error]".to_string(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Script {
+    fn from(bytes: Vec<u8>) -> Self {
+        Script(bytes)
+    }
+}
+
+impl From<Script> for Vec<u8> {
+    fn from(script: Script) -> Self {
+        script.0
+    }
+}
+
+impl FromStr for Script {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Script::from_hex(s)
+    }
+}
+
+impl fmt::LowerHex for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl PartialEq<[u8]> for Script {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<Vec<u8>> for Script {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        &self.0 == other
+    }
+}
+
+// Opcode byte together with the data it pushes, if any
+type RawInstruction<'a> = (u8, Option<&'a [u8]>);
+
+// Split raw script bytes into raw instructions
+fn raw_instructions(bytes: &[u8]) -> Result<Vec<RawInstruction<'_>>, BitcoinError> {
+    match parse_raw_instructions(bytes) {
+        (instructions, true) => Ok(instructions),
+        (_, false) => Err(BitcoinError::InvalidScript),
+    }
+}
+
+// Instructions up to the first malformed push, and whether the whole script
+// parsed; consensus code such as sigop counting works on the valid prefix
+fn parse_raw_instructions(bytes: &[u8]) -> (Vec<RawInstruction<'_>>, bool) {
+    let mut instructions = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let opcode = bytes[i];
+        i += 1;
+
+        let data_len = match opcode {
+            0x01..=0x4b => Ok(opcode as usize),
+            0x4c => read_push_len(bytes, &mut i, 1),
+            0x4d => read_push_len(bytes, &mut i, 2),
+            0x4e => read_push_len(bytes, &mut i, 4),
+            _ => {
+                instructions.push((opcode, None));
+                continue;
+            }
+        };
+
+        let Some(end) = data_len
+            .ok()
+            .and_then(|len| i.checked_add(len))
+            .filter(|end| *end <= bytes.len())
+        else {
+            return (instructions, false);
+        };
+        instructions.push((opcode, Some(&bytes[i..end])));
+        i = end;
+    }
+
+    (instructions, true)
+}
+
+// Minimal encoding of a data push, using OP_0, OP_1NEGATE and OP_1..OP_16
+// for the values they represent
+fn minimal_push_bytes(data: &[u8]) -> Vec<u8> {
+    match data {
+        [] => vec![Opcode::Op0.to_u8()],
+        [n @ 1..=16] => vec![Opcode::Op1.to_u8() + n - 1],
+        [0x81] => vec![Opcode::Op1Negate.to_u8()],
+        _ => ScriptBuilder::new().push_data(data).build().into_bytes(),
+    }
+}
+
+fn read_push_len(bytes: &[u8], i: &mut usize, width: usize) -> Result<usize, BitcoinError> {
+    let end = *i + width;
+    if end > bytes.len() {
+        return Err(BitcoinError::InvalidScript);
+    }
+    let mut buf = [0u8; 4];
+    buf[..width].copy_from_slice(&bytes[*i..end]);
+    *i = end;
+    Ok(u32::from_le_bytes(buf) as usize)
+}
+
+// Incrementally assembles a script
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBuilder {
+    bytes: Vec<u8>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        ScriptBuilder::default()
+    }
+
+    pub fn push_opcode(mut self, opcode: Opcode) -> Self {
+        self.bytes.push(opcode.to_u8());
+        self
+    }
+
+    // Push data using the smallest push encoding that fits
+    pub fn push_data(mut self, data: &[u8]) -> Self {
+        let opcode = Script::min_push_opcode(data);
+        self.bytes.push(opcode);
+        let len = data.len();
+        match Opcode::from_u8(opcode) {
+            Opcode::OpPushData1 => self.bytes.push(len as u8),
+            Opcode::OpPushData2 => self.bytes.extend_from_slice(&(len as u16).to_le_bytes()),
+            Opcode::OpPushData4 => self.bytes.extend_from_slice(&(len as u32).to_le_bytes()),
+            _ => {}
+        }
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    pub fn build(self) -> Script {
+        Script(self.bytes)
+    }
+
+    // Compile ASM notation: `OP_` names (case-insensitive) become opcodes and
+    // any other token is hex data to push (optionally wrapped in `<...>`)
+    pub fn from_asm(asm: &str) -> Result<Script, BitcoinError> {
+        asm.split_whitespace()
+            .try_fold(ScriptBuilder::new(), |builder, token| {
+                // `get` rather than slicing, since a token may start with a
+                // multibyte character
+                let is_opcode = token
+                    .get(..3)
+                    .is_some_and(|p| p.eq_ignore_ascii_case("OP_"));
+                if token.len() > 3 && is_opcode {
+                    let opcode = Opcode::from_name(token).ok_or(BitcoinError::InvalidScript)?;
+                    return Ok(builder.push_opcode(opcode));
+                }
+
+                let data = token
+                    .strip_prefix('<')
+                    .and_then(|t| t.strip_suffix('>'))
+                    .unwrap_or(token);
+                let data = hex::decode(data).map_err(|_| BitcoinError::InvalidScript)?;
+                Ok(builder.push_data(&data))
+            })
+            .map(ScriptBuilder::build)
+    }
+}
@@ -0,0 +1,399 @@
+// Script execution for legacy (pre-SegWit) input verification
+
+use std::ops::BitOr;
+
+use thiserror::Error;
+
+use crate::hash::{hash160, ripemd160, sha256, sha256d};
+use crate::secp256k1::{self, AffinePoint, Signature};
+use crate::signer::{self, SIGHASH_ALL};
+use crate::{
+    BitcoinError, Instruction, LegacyTransaction, LockTime, Opcode, Script, Sequence, TxOutput,
+};
+
+// Why script execution failed, reported as `BitcoinError::ScriptExecution`.
+// Malformed scripts that cannot be decoded stay `BitcoinError::InvalidScript`.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptError {
+    #[error("stack underflow")]
+    StackUnderflow,
+    #[error("disabled opcode {0}")]
+    DisabledOpcode(&'static str),
+    #[error("unsupported opcode {0:#04x}")]
+    UnsupportedOpcode(u8),
+    #[error("OP_RETURN executed")]
+    OpReturn,
+    #[error("{0} failed")]
+    VerifyFailed(&'static str),
+    #[error("script evaluated to false")]
+    EvalFalse,
+    #[error("script number too large")]
+    NumberOverflow,
+    #[error("negative lock time")]
+    NegativeLockTime,
+    #[error("lock time not satisfied")]
+    UnsatisfiedLockTime,
+    #[error("P2SH scriptSig is not push-only")]
+    SigPushOnly,
+    #[error("OP_IF operand is not minimal")]
+    MinimalIf,
+}
+
+impl From<ScriptError> for BitcoinError {
+    fn from(err: ScriptError) -> Self {
+        BitcoinError::ScriptExecution(err)
+    }
+}
+
+// Script verification rules to enforce, combinable with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ScriptVerifyFlags(u32);
+
+impl ScriptVerifyFlags {
+    pub const NONE: ScriptVerifyFlags = ScriptVerifyFlags(0);
+    // Evaluate BIP-16 redeem scripts
+    pub const P2SH: ScriptVerifyFlags = ScriptVerifyFlags(1 << 0);
+    // Require strict DER signatures (BIP-66); always enforced by this parser
+    pub const DERSIG: ScriptVerifyFlags = ScriptVerifyFlags(1 << 2);
+    // Require the CHECKMULTISIG dummy element to be empty (BIP-147)
+    pub const NULLDUMMY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 4);
+    // Enforce OP_CHECKLOCKTIMEVERIFY (BIP-65) instead of treating it as a NOP
+    pub const CHECKLOCKTIMEVERIFY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 9);
+    // Enforce OP_CHECKSEQUENCEVERIFY (BIP-112) instead of treating it as a NOP
+    pub const CHECKSEQUENCEVERIFY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 10);
+    // Evaluate witness programs (BIP-141)
+    pub const WITNESS: ScriptVerifyFlags = ScriptVerifyFlags(1 << 11);
+
+    pub const fn empty() -> Self {
+        ScriptVerifyFlags(0)
+    }
+
+    pub const fn all() -> Self {
+        ScriptVerifyFlags(
+            Self::P2SH.0
+                | Self::DERSIG.0
+                | Self::NULLDUMMY.0
+                | Self::CHECKLOCKTIMEVERIFY.0
+                | Self::CHECKSEQUENCEVERIFY.0
+                | Self::WITNESS.0,
+        )
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(&self, other: ScriptVerifyFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: ScriptVerifyFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: ScriptVerifyFlags) {
+        self.0 &= !other.0;
+    }
+}
+
+impl BitOr for ScriptVerifyFlags {
+    type Output = ScriptVerifyFlags;
+
+    fn bitor(self, rhs: ScriptVerifyFlags) -> ScriptVerifyFlags {
+        ScriptVerifyFlags(self.0 | rhs.0)
+    }
+}
+
+// Verify that input `input_index` of `tx` may spend `utxo`: the scriptSig is
+// executed, then the scriptPubKey on the resulting stack, which must leave a
+// true value on top. With `P2SH`, pay-to-script-hash outputs additionally run
+// the serialized redeem script pushed by the scriptSig.
+pub fn verify_input(
+    tx: &LegacyTransaction,
+    input_index: usize,
+    utxo: &TxOutput,
+    flags: ScriptVerifyFlags,
+) -> Result<(), BitcoinError> {
+    let input = tx
+        .inputs()
+        .get(input_index)
+        .ok_or(BitcoinError::InvalidTransaction)?;
+    let script_sig = input.script_sig.clone();
+    let script_pubkey = utxo.script_pubkey.clone();
+    let checker = SignatureChecker { tx, input_index };
+
+    let mut stack = Vec::new();
+    execute(&script_sig, &mut stack, &checker, flags)?;
+    let p2sh_stack = stack.clone();
+    execute(&script_pubkey, &mut stack, &checker, flags)?;
+    require_true(&stack)?;
+
+    if flags.contains(ScriptVerifyFlags::P2SH) && script_pubkey.is_p2sh() {
+        if !script_sig.is_push_only() {
+            return Err(ScriptError::SigPushOnly.into());
+        }
+        let mut stack = p2sh_stack;
+        let redeem_script = Script(pop(&mut stack)?);
+        execute(&redeem_script, &mut stack, &checker, flags)?;
+        require_true(&stack)?;
+    }
+
+    Ok(())
+}
+
+// BIP-342 MINIMALIF: when `script` branches on OP_IF/OP_NOTIF, the condition
+// on top of `stack` must be exactly empty (false) or [0x01] (true)
+pub fn validate_minimal_if_operands(
+    script: &Script,
+    stack: &[Vec<u8>],
+) -> Result<(), BitcoinError> {
+    let branches = script.decompile()?.iter().any(|instruction| {
+        matches!(
+            instruction,
+            Instruction::Op(Opcode::OpIf) | Instruction::Op(Opcode::OpNotIf)
+        )
+    });
+    if !branches {
+        return Ok(());
+    }
+    match top(stack)?.as_slice() {
+        [] | [0x01] => Ok(()),
+        _ => Err(ScriptError::MinimalIf.into()),
+    }
+}
+
+// Everything OP_CHECKSIG needs to know about the spending transaction
+struct SignatureChecker<'a> {
+    tx: &'a LegacyTransaction,
+    input_index: usize,
+}
+
+impl SignatureChecker<'_> {
+    // Only SIGHASH_ALL signatures are understood; anything else is invalid
+    fn check_signature(&self, signature: &[u8], pubkey: &[u8], script_code: &Script) -> bool {
+        let Some((&hash_type, der)) = signature.split_last() else {
+            return false;
+        };
+        if u32::from(hash_type) != SIGHASH_ALL {
+            return false;
+        }
+        let (Ok(signature), Ok(pubkey)) =
+            (Signature::from_der(der), AffinePoint::from_sec1(pubkey))
+        else {
+            return false;
+        };
+        let sighash = signer::signature_hash_all(self.tx, self.input_index, script_code);
+        secp256k1::verify(&pubkey, &sighash, &signature)
+    }
+
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        // Values past u32 exceed any transaction lock time
+        let Ok(lock_time) = u32::try_from(lock_time).map(LockTime) else {
+            return false;
+        };
+        let tx_lock_time = self.tx.lock_time();
+        let same_kind = tx_lock_time.is_block_height() == lock_time.is_block_height();
+        // A final sequence disables lock time checks for the whole transaction
+        same_kind
+            && lock_time <= tx_lock_time
+            && self.tx.inputs()[self.input_index]
+                .sequence
+                .enables_lock_time()
+    }
+
+    fn check_sequence(&self, required: Sequence) -> bool {
+        if self.tx.version() < 2 {
+            return false;
+        }
+        // An input with its relative lock disabled has no value to compare
+        let actual = self.tx.inputs()[self.input_index].sequence;
+        match (required.relative_lock_value(), actual.relative_lock_value()) {
+            (Some(required_value), Some(actual_value)) => {
+                required.is_time_locked() == actual.is_time_locked()
+                    && required_value <= actual_value
+            }
+            _ => false,
+        }
+    }
+}
+
+fn execute(
+    script: &Script,
+    stack: &mut Vec<Vec<u8>>,
+    checker: &SignatureChecker,
+    flags: ScriptVerifyFlags,
+) -> Result<(), BitcoinError> {
+    for instruction in script.decompile()? {
+        let opcode = match instruction {
+            Instruction::OpData(data) => {
+                stack.push(data);
+                continue;
+            }
+            Instruction::Op(opcode) => opcode,
+        };
+
+        match opcode {
+            Opcode::Op0 => stack.push(Vec::new()),
+            Opcode::Op1Negate => stack.push(encode_num(-1)),
+            op if (Opcode::Op1.to_u8()..=Opcode::Op16.to_u8()).contains(&op.to_u8()) => {
+                stack.push(encode_num(i64::from(op.to_u8() - 0x50)))
+            }
+            Opcode::OpNop
+            | Opcode::OpNop1
+            | Opcode::OpNop4
+            | Opcode::OpNop5
+            | Opcode::OpNop6
+            | Opcode::OpNop7
+            | Opcode::OpNop8
+            | Opcode::OpNop9
+            | Opcode::OpNop10 => {}
+            Opcode::OpCheckLockTimeVerify => {
+                if flags.contains(ScriptVerifyFlags::CHECKLOCKTIMEVERIFY) {
+                    let lock_time = decode_num(top(stack)?, 5)?;
+                    if lock_time < 0 {
+                        return Err(ScriptError::NegativeLockTime.into());
+                    }
+                    if !checker.check_lock_time(lock_time) {
+                        return Err(ScriptError::UnsatisfiedLockTime.into());
+                    }
+                }
+            }
+            Opcode::OpCheckSequenceVerify => {
+                if flags.contains(ScriptVerifyFlags::CHECKSEQUENCEVERIFY) {
+                    let sequence = decode_num(top(stack)?, 5)?;
+                    if sequence < 0 {
+                        return Err(ScriptError::NegativeLockTime.into());
+                    }
+                    // Only the low 32 bits can carry BIP-68 fields
+                    let sequence = Sequence(sequence as u32);
+                    if sequence.is_relative_lock_time() && !checker.check_sequence(sequence) {
+                        return Err(ScriptError::UnsatisfiedLockTime.into());
+                    }
+                }
+            }
+            Opcode::OpVerify => {
+                if !cast_to_bool(&pop(stack)?) {
+                    return Err(ScriptError::VerifyFailed(opcode.name()).into());
+                }
+            }
+            Opcode::OpDrop => {
+                pop(stack)?;
+            }
+            Opcode::OpDup => {
+                let item = top(stack)?.clone();
+                stack.push(item);
+            }
+            Opcode::OpEqual | Opcode::OpEqualVerify => {
+                let b = pop(stack)?;
+                let a = pop(stack)?;
+                if opcode == Opcode::OpEqualVerify {
+                    if a != b {
+                        return Err(ScriptError::VerifyFailed(opcode.name()).into());
+                    }
+                } else {
+                    stack.push(encode_bool(a == b));
+                }
+            }
+            Opcode::OpRipemd160 => {
+                let item = pop(stack)?;
+                stack.push(ripemd160(&item).to_vec());
+            }
+            Opcode::OpSha256 => {
+                let item = pop(stack)?;
+                stack.push(sha256(&item).to_vec());
+            }
+            Opcode::OpHash160 => {
+                let item = pop(stack)?;
+                stack.push(hash160(&item).to_vec());
+            }
+            Opcode::OpHash256 => {
+                let item = pop(stack)?;
+                stack.push(sha256d(&item).to_vec());
+            }
+            Opcode::OpCheckSig | Opcode::OpCheckSigVerify => {
+                let pubkey = pop(stack)?;
+                let signature = pop(stack)?;
+                let valid = checker.check_signature(&signature, &pubkey, script);
+                if opcode == Opcode::OpCheckSigVerify {
+                    if !valid {
+                        return Err(ScriptError::VerifyFailed(opcode.name()).into());
+                    }
+                } else {
+                    stack.push(encode_bool(valid));
+                }
+            }
+            Opcode::OpReturn => return Err(ScriptError::OpReturn.into()),
+            op if op.is_disabled() => return Err(ScriptError::DisabledOpcode(op.name()).into()),
+            // Reserved and not yet supported opcodes
+            op => return Err(ScriptError::UnsupportedOpcode(op.to_u8()).into()),
+        }
+    }
+    Ok(())
+}
+
+fn require_true(stack: &[Vec<u8>]) -> Result<(), BitcoinError> {
+    match stack.last() {
+        Some(item) if cast_to_bool(item) => Ok(()),
+        Some(_) => Err(ScriptError::EvalFalse.into()),
+        None => Err(ScriptError::StackUnderflow.into()),
+    }
+}
+
+fn pop(stack: &mut Vec<Vec<u8>>) -> Result<Vec<u8>, BitcoinError> {
+    stack.pop().ok_or(ScriptError::StackUnderflow.into())
+}
+
+fn top(stack: &[Vec<u8>]) -> Result<&Vec<u8>, BitcoinError> {
+    stack.last().ok_or(ScriptError::StackUnderflow.into())
+}
+
+// Any non-zero value is true; negative zero (0x80 in the last byte) is false
+fn cast_to_bool(item: &[u8]) -> bool {
+    match item.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&b| b != 0) || (last & 0x7f) != 0,
+        None => false,
+    }
+}
+
+fn encode_bool(value: bool) -> Vec<u8> {
+    if value { vec![1] } else { Vec::new() }
+}
+
+// Script numbers are little-endian sign-magnitude with the sign in the top bit
+fn encode_num(value: i64) -> Vec<u8> {
+    if value == 0 {
+        return Vec::new();
+    }
+    let negative = value < 0;
+    let mut magnitude = value.unsigned_abs();
+    let mut bytes = Vec::new();
+    while magnitude > 0 {
+        bytes.push((magnitude & 0xff) as u8);
+        magnitude >>= 8;
+    }
+    if bytes.last().is_some_and(|&b| b & 0x80 != 0) {
+        bytes.push(if negative { 0x80 } else { 0 });
+    } else if negative {
+        *bytes.last_mut().unwrap() |= 0x80;
+    }
+    bytes
+}
+
+fn decode_num(bytes: &[u8], max_len: usize) -> Result<i64, BitcoinError> {
+    if bytes.len() > max_len {
+        return Err(ScriptError::NumberOverflow.into());
+    }
+    let Some((&last, _)) = bytes.split_last() else {
+        return Ok(0);
+    };
+    let mut value = 0i64;
+    for (i, &byte) in bytes.iter().enumerate() {
+        value |= i64::from(byte) << (8 * i);
+    }
+    if last & 0x80 != 0 {
+        let sign_bit = 0x80i64 << (8 * (bytes.len() - 1));
+        Ok(-(value & !sign_bit))
+    } else {
+        Ok(value)
+    }
+}
@@ -0,0 +1,728 @@
+// Pure-Rust secp256k1 arithmetic and ECDSA, written for clarity over speed
+// and without constant-time guarantees; not for production keys
+
+use std::cmp::Ordering;
+
+use crate::hash::hmac_sha256;
+use crate::{BitcoinError, Point};
+
+// 256-bit unsigned integer as four little-endian 64-bit limbs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct U256(pub [u64; 4]);
+
+impl U256 {
+    pub const ZERO: U256 = U256([0, 0, 0, 0]);
+    pub const ONE: U256 = U256([1, 0, 0, 0]);
+
+    pub fn from_be_bytes(bytes: &[u8; 32]) -> U256 {
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
+        }
+        U256(limbs)
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            out[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        out
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    pub fn bit(&self, index: usize) -> bool {
+        (self.0[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    pub fn overflowing_add(self, rhs: U256) -> (U256, bool) {
+        let mut out = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(rhs.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        (U256(out), carry)
+    }
+
+    pub fn overflowing_sub(self, rhs: U256) -> (U256, bool) {
+        let mut out = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (diff, b1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *limb = diff;
+            borrow = b1 || b2;
+        }
+        (U256(out), borrow)
+    }
+
+    // Full 512-bit product as eight little-endian limbs
+    fn mul_wide(self, rhs: U256) -> [u64; 8] {
+        let mut out = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let t = self.0[i] as u128 * rhs.0[j] as u128 + out[i + j] as u128 + carry;
+                out[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            out[i + 4] = carry as u64;
+        }
+        out
+    }
+
+    pub fn add_mod(self, rhs: U256, modulus: &U256) -> U256 {
+        let (sum, carry) = self.overflowing_add(rhs);
+        if carry || sum >= *modulus {
+            sum.overflowing_sub(*modulus).0
+        } else {
+            sum
+        }
+    }
+
+    pub fn sub_mod(self, rhs: U256, modulus: &U256) -> U256 {
+        let (diff, borrow) = self.overflowing_sub(rhs);
+        if borrow {
+            diff.overflowing_add(*modulus).0
+        } else {
+            diff
+        }
+    }
+
+    pub fn mul_mod(self, rhs: U256, modulus: &U256) -> U256 {
+        reduce_wide(&self.mul_wide(rhs), modulus)
+    }
+
+    pub fn pow_mod(self, exponent: &U256, modulus: &U256) -> U256 {
+        let mut result = U256::ONE;
+        for i in (0..256).rev() {
+            result = result.mul_mod(result, modulus);
+            if exponent.bit(i) {
+                result = result.mul_mod(self, modulus);
+            }
+        }
+        result
+    }
+
+    // Inverse modulo a prime, via Fermat's little theorem
+    pub fn inv_mod(self, modulus: &U256) -> U256 {
+        let exponent = modulus.overflowing_sub(U256([2, 0, 0, 0])).0;
+        self.pow_mod(&exponent, modulus)
+    }
+
+    // Reduce a value that may exceed the modulus
+    pub fn reduce(self, modulus: &U256) -> U256 {
+        let mut wide = [0u64; 8];
+        wide[..4].copy_from_slice(&self.0);
+        reduce_wide(&wide, modulus)
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        for i in (0..4).rev() {
+            match self.0[i].cmp(&other.0[i]) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+// Binary long division of a 512-bit value, keeping the remainder
+fn reduce_wide(wide: &[u64; 8], modulus: &U256) -> U256 {
+    let mut remainder = U256::ZERO;
+    for i in (0..512).rev() {
+        let top = remainder.0[3] >> 63;
+        let mut shifted = [0u64; 4];
+        for j in (1..4).rev() {
+            shifted[j] = (remainder.0[j] << 1) | (remainder.0[j - 1] >> 63);
+        }
+        shifted[0] = (remainder.0[0] << 1) | ((wide[i / 64] >> (i % 64)) & 1);
+        remainder = U256(shifted);
+        if top == 1 || remainder >= *modulus {
+            remainder = remainder.overflowing_sub(*modulus).0;
+        }
+    }
+    remainder
+}
+
+// Field prime p = 2^256 - 2^32 - 977
+pub const P: U256 = U256([
+    0xFFFFFFFEFFFFFC2F,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+]);
+
+// Group order n
+pub const N: U256 = U256([
+    0xBFD25E8CD0364141,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+]);
+
+// Generator point G
+pub const G_X: U256 = U256([
+    0x59F2815B16F81798,
+    0x029BFCDB2DCE28D9,
+    0x55A06295CE870B07,
+    0x79BE667EF9DCBBAC,
+]);
+pub const G_Y: U256 = U256([
+    0x9C47D08FFB10D4B8,
+    0xFD17B448A6855419,
+    0x5DA4FBFC0E1108A8,
+    0x483ADA7726A3C465,
+]);
+
+// Curve point in affine coordinates; `None` is the point at infinity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffinePoint {
+    pub x: U256,
+    pub y: U256,
+}
+
+// Jacobian coordinates (X, Y, Z) represent (X / Z^2, Y / Z^3)
+#[derive(Debug, Clone, Copy)]
+struct JacobianPoint {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+impl JacobianPoint {
+    const INFINITY: JacobianPoint = JacobianPoint {
+        x: U256::ZERO,
+        y: U256::ONE,
+        z: U256::ZERO,
+    };
+
+    fn from_affine(point: &AffinePoint) -> JacobianPoint {
+        JacobianPoint {
+            x: point.x,
+            y: point.y,
+            z: U256::ONE,
+        }
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn to_affine(self) -> Option<AffinePoint> {
+        if self.is_infinity() {
+            return None;
+        }
+        let z_inv = self.z.inv_mod(&P);
+        let z_inv2 = z_inv.mul_mod(z_inv, &P);
+        let z_inv3 = z_inv2.mul_mod(z_inv, &P);
+        Some(AffinePoint {
+            x: self.x.mul_mod(z_inv2, &P),
+            y: self.y.mul_mod(z_inv3, &P),
+        })
+    }
+
+    fn double(&self) -> JacobianPoint {
+        if self.is_infinity() || self.y.is_zero() {
+            return JacobianPoint::INFINITY;
+        }
+        let y2 = self.y.mul_mod(self.y, &P);
+        let s = self.x.mul_mod(y2, &P).mul_mod(U256([4, 0, 0, 0]), &P);
+        let m = self.x.mul_mod(self.x, &P).mul_mod(U256([3, 0, 0, 0]), &P);
+        let x3 = m.mul_mod(m, &P).sub_mod(s.add_mod(s, &P), &P);
+        let y4_8 = y2.mul_mod(y2, &P).mul_mod(U256([8, 0, 0, 0]), &P);
+        let y3 = m.mul_mod(s.sub_mod(x3, &P), &P).sub_mod(y4_8, &P);
+        let z3 = self
+            .y
+            .mul_mod(self.z, &P)
+            .add_mod(self.y.mul_mod(self.z, &P), &P);
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    fn add(&self, other: &JacobianPoint) -> JacobianPoint {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
+        }
+
+        let z1z1 = self.z.mul_mod(self.z, &P);
+        let z2z2 = other.z.mul_mod(other.z, &P);
+        let u1 = self.x.mul_mod(z2z2, &P);
+        let u2 = other.x.mul_mod(z1z1, &P);
+        let s1 = self.y.mul_mod(z2z2, &P).mul_mod(other.z, &P);
+        let s2 = other.y.mul_mod(z1z1, &P).mul_mod(self.z, &P);
+
+        if u1 == u2 {
+            return if s1 == s2 {
+                self.double()
+            } else {
+                JacobianPoint::INFINITY
+            };
+        }
+
+        let h = u2.sub_mod(u1, &P);
+        let r = s2.sub_mod(s1, &P);
+        let h2 = h.mul_mod(h, &P);
+        let h3 = h2.mul_mod(h, &P);
+        let u1h2 = u1.mul_mod(h2, &P);
+        let x3 = r
+            .mul_mod(r, &P)
+            .sub_mod(h3, &P)
+            .sub_mod(u1h2.add_mod(u1h2, &P), &P);
+        let y3 = r
+            .mul_mod(u1h2.sub_mod(x3, &P), &P)
+            .sub_mod(s1.mul_mod(h3, &P), &P);
+        let z3 = h.mul_mod(self.z, &P).mul_mod(other.z, &P);
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    fn mul(&self, scalar: &U256) -> JacobianPoint {
+        let mut result = JacobianPoint::INFINITY;
+        for i in (0..256).rev() {
+            result = result.double();
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
+
+impl AffinePoint {
+    pub const GENERATOR: AffinePoint = AffinePoint { x: G_X, y: G_Y };
+
+    pub fn is_on_curve(&self) -> bool {
+        let y2 = self.y.mul_mod(self.y, &P);
+        let x3 = self.x.mul_mod(self.x, &P).mul_mod(self.x, &P);
+        y2 == x3.add_mod(U256([7, 0, 0, 0]), &P)
+    }
+
+    // Point addition; `None` is the point at infinity
+    pub fn add(&self, other: &AffinePoint) -> Option<AffinePoint> {
+        JacobianPoint::from_affine(self)
+            .add(&JacobianPoint::from_affine(other))
+            .to_affine()
+    }
+
+    pub fn mul(&self, scalar: &U256) -> Option<AffinePoint> {
+        JacobianPoint::from_affine(self).mul(scalar).to_affine()
+    }
+
+    // SEC1 compressed encoding: parity byte followed by x
+    pub fn to_compressed(&self) -> [u8; 33] {
+        let mut out = [0u8; 33];
+        out[0] = if self.y.bit(0) { 0x03 } else { 0x02 };
+        out[1..].copy_from_slice(&self.x.to_be_bytes());
+        out
+    }
+
+    // Parse a SEC1 compressed (33-byte) or uncompressed (65-byte) public key
+    pub fn from_sec1(bytes: &[u8]) -> Result<AffinePoint, BitcoinError> {
+        let point = match bytes {
+            [prefix @ (0x02 | 0x03), x @ ..] if x.len() == 32 => {
+                let x = U256::from_be_bytes(x.try_into().unwrap());
+                let y = lift_x(&x)?;
+                let y = if y.bit(0) == (*prefix == 0x03) {
+                    y
+                } else {
+                    P.overflowing_sub(y).0
+                };
+                AffinePoint { x, y }
+            }
+            [0x04, coords @ ..] if coords.len() == 64 => AffinePoint {
+                x: U256::from_be_bytes(coords[..32].try_into().unwrap()),
+                y: U256::from_be_bytes(coords[32..].try_into().unwrap()),
+            },
+            _ => return Err(BitcoinError::InvalidScript),
+        };
+
+        if point.x >= P || point.y >= P || !point.is_on_curve() {
+            return Err(BitcoinError::InvalidScript);
+        }
+        Ok(point)
+    }
+}
+
+// Element of the field of integers modulo `P`, always kept reduced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FieldElement(U256);
+
+impl FieldElement {
+    pub const ZERO: FieldElement = FieldElement(U256::ZERO);
+    pub const ONE: FieldElement = FieldElement(U256::ONE);
+
+    pub fn new(value: U256) -> FieldElement {
+        FieldElement(value.reduce(&P))
+    }
+
+    pub fn from_u64(value: u64) -> FieldElement {
+        FieldElement(U256([value, 0, 0, 0]))
+    }
+
+    pub fn from_be_bytes(bytes: &[u8; 32]) -> FieldElement {
+        FieldElement::new(U256::from_be_bytes(bytes))
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn value(self) -> U256 {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn pow(self, exponent: &U256) -> FieldElement {
+        FieldElement(self.0.pow_mod(exponent, &P))
+    }
+
+    // Multiplicative inverse; zero has none
+    pub fn inverse(self) -> Option<FieldElement> {
+        (!self.is_zero()).then(|| FieldElement(self.0.inv_mod(&P)))
+    }
+}
+
+impl std::ops::Add for FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, rhs: FieldElement) -> FieldElement {
+        FieldElement(self.0.add_mod(rhs.0, &P))
+    }
+}
+
+impl std::ops::Sub for FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, rhs: FieldElement) -> FieldElement {
+        FieldElement(self.0.sub_mod(rhs.0, &P))
+    }
+}
+
+impl std::ops::Mul for FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, rhs: FieldElement) -> FieldElement {
+        FieldElement(self.0.mul_mod(rhs.0, &P))
+    }
+}
+
+impl std::ops::Neg for FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        FieldElement::ZERO - self
+    }
+}
+
+// A point on the curve, including the point at infinity, so that addition and
+// scalar multiplication are closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurvePoint {
+    Infinity,
+    Affine(AffinePoint),
+}
+
+impl CurvePoint {
+    pub const GENERATOR: CurvePoint = CurvePoint::Affine(AffinePoint::GENERATOR);
+
+    // Rejects coordinates that are not on the curve
+    pub fn new(x: FieldElement, y: FieldElement) -> Result<CurvePoint, BitcoinError> {
+        let point = AffinePoint { x: x.0, y: y.0 };
+        if !point.is_on_curve() {
+            return Err(BitcoinError::InvalidKey);
+        }
+        Ok(CurvePoint::Affine(point))
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        matches!(self, CurvePoint::Infinity)
+    }
+
+    // Affine coordinates, or `None` at infinity
+    pub fn coordinates(&self) -> Option<Point<FieldElement>> {
+        match self {
+            CurvePoint::Infinity => None,
+            CurvePoint::Affine(point) => {
+                Some(Point::new(FieldElement(point.x), FieldElement(point.y)))
+            }
+        }
+    }
+
+    pub fn double(&self) -> CurvePoint {
+        *self + *self
+    }
+
+    fn to_jacobian(self) -> JacobianPoint {
+        match self {
+            CurvePoint::Infinity => JacobianPoint::INFINITY,
+            CurvePoint::Affine(point) => JacobianPoint::from_affine(&point),
+        }
+    }
+
+    fn from_jacobian(point: JacobianPoint) -> CurvePoint {
+        point
+            .to_affine()
+            .map_or(CurvePoint::Infinity, CurvePoint::Affine)
+    }
+}
+
+impl From<AffinePoint> for CurvePoint {
+    fn from(point: AffinePoint) -> CurvePoint {
+        CurvePoint::Affine(point)
+    }
+}
+
+impl std::ops::Add for CurvePoint {
+    type Output = CurvePoint;
+
+    fn add(self, rhs: CurvePoint) -> CurvePoint {
+        CurvePoint::from_jacobian(self.to_jacobian().add(&rhs.to_jacobian()))
+    }
+}
+
+impl std::ops::Neg for CurvePoint {
+    type Output = CurvePoint;
+
+    fn neg(self) -> CurvePoint {
+        match self {
+            CurvePoint::Infinity => CurvePoint::Infinity,
+            CurvePoint::Affine(point) => CurvePoint::Affine(AffinePoint {
+                x: point.x,
+                y: P.overflowing_sub(point.y).0.reduce(&P),
+            }),
+        }
+    }
+}
+
+// Double-and-add scalar multiplication; the scalar is not reduced mod `N`
+impl std::ops::Mul<U256> for CurvePoint {
+    type Output = CurvePoint;
+
+    fn mul(self, scalar: U256) -> CurvePoint {
+        CurvePoint::from_jacobian(self.to_jacobian().mul(&scalar))
+    }
+}
+
+// Square root of x^3 + 7, i.e. a y coordinate for `x` (either parity)
+pub fn lift_x(x: &U256) -> Result<U256, BitcoinError> {
+    if *x >= P {
+        return Err(BitcoinError::InvalidScript);
+    }
+    let rhs = x
+        .mul_mod(*x, &P)
+        .mul_mod(*x, &P)
+        .add_mod(U256([7, 0, 0, 0]), &P);
+    // (p + 1) / 4
+    let exponent = U256([
+        0xFFFFFFFFBFFFFF0C,
+        0xFFFFFFFFFFFFFFFF,
+        0xFFFFFFFFFFFFFFFF,
+        0x3FFFFFFFFFFFFFFF,
+    ]);
+    let y = rhs.pow_mod(&exponent, &P);
+    if y.mul_mod(y, &P) != rhs {
+        return Err(BitcoinError::InvalidScript);
+    }
+    Ok(y)
+}
+
+// Secret keys must be in [1, n - 1]
+pub fn parse_secret_key(secret: &[u8; 32]) -> Result<U256, BitcoinError> {
+    let d = U256::from_be_bytes(secret);
+    if d.is_zero() || d >= N {
+        return Err(BitcoinError::InvalidScript);
+    }
+    Ok(d)
+}
+
+pub fn public_key(secret: &[u8; 32]) -> Result<AffinePoint, BitcoinError> {
+    let d = parse_secret_key(secret)?;
+    AffinePoint::GENERATOR
+        .mul(&d)
+        .ok_or(BitcoinError::InvalidScript)
+}
+
+// Compressed SEC1 public key for a secret key
+pub fn public_key_compressed(secret: &[u8; 32]) -> Result<[u8; 33], BitcoinError> {
+    Ok(public_key(secret)?.to_compressed())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub r: U256,
+    pub s: U256,
+}
+
+impl Signature {
+    // Strict DER encoding, as required inside Bitcoin scripts
+    pub fn to_der(&self) -> Vec<u8> {
+        let r = der_integer(&self.r);
+        let s = der_integer(&self.s);
+        let mut out = vec![0x30, (r.len() + s.len()) as u8];
+        out.extend(r);
+        out.extend(s);
+        out
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Signature, BitcoinError> {
+        let body = match der {
+            [0x30, len, body @ ..] if *len as usize == body.len() => body,
+            _ => return Err(BitcoinError::InvalidScript),
+        };
+        let (r, rest) = parse_der_integer(body)?;
+        let (s, rest) = parse_der_integer(rest)?;
+        if !rest.is_empty() {
+            return Err(BitcoinError::InvalidScript);
+        }
+        Ok(Signature { r, s })
+    }
+
+    // BIP-146 LOW_S: `s` in the lower half of the group order
+    pub fn has_low_s(&self) -> bool {
+        normalize_s(self.s) == self.s
+    }
+}
+
+fn der_integer(value: &U256) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let first = bytes.iter().position(|b| *b != 0).unwrap_or(31);
+    let mut digits = bytes[first..].to_vec();
+    if digits[0] & 0x80 != 0 {
+        digits.insert(0, 0x00);
+    }
+    let mut out = vec![0x02, digits.len() as u8];
+    out.extend(digits);
+    out
+}
+
+fn parse_der_integer(data: &[u8]) -> Result<(U256, &[u8]), BitcoinError> {
+    let (len, rest) = match data {
+        [0x02, len @ 1..=33, rest @ ..] if rest.len() >= *len as usize => (*len as usize, rest),
+        _ => return Err(BitcoinError::InvalidScript),
+    };
+    let digits = &rest[..len];
+    // No negative numbers and no unnecessary leading zeros
+    if digits[0] & 0x80 != 0 || (len > 1 && digits[0] == 0 && digits[1] & 0x80 == 0) {
+        return Err(BitcoinError::InvalidScript);
+    }
+    let digits = if digits[0] == 0 { &digits[1..] } else { digits };
+    if digits.len() > 32 {
+        return Err(BitcoinError::InvalidScript);
+    }
+    let mut bytes = [0u8; 32];
+    bytes[32 - digits.len()..].copy_from_slice(digits);
+    Ok((U256::from_be_bytes(&bytes), &rest[len..]))
+}
+
+// Deterministic nonce generation (RFC 6979) with HMAC-SHA256
+fn rfc6979_nonces(secret: &[u8; 32], msg_hash: &[u8; 32]) -> impl Iterator<Item = U256> {
+    let h1 = U256::from_be_bytes(msg_hash).reduce(&N).to_be_bytes();
+    let mut v = [0x01u8; 32];
+    let mut k = [0x00u8; 32];
+
+    k = hmac_sha256(&k, &[&v[..], &[0x00], secret, &h1].concat());
+    v = hmac_sha256(&k, &v);
+    k = hmac_sha256(&k, &[&v[..], &[0x01], secret, &h1].concat());
+    v = hmac_sha256(&k, &v);
+
+    std::iter::from_fn(move || {
+        v = hmac_sha256(&k, &v);
+        let candidate = U256::from_be_bytes(&v);
+        k = hmac_sha256(&k, &[&v[..], &[0x00]].concat());
+        v = hmac_sha256(&k, &v);
+        Some(candidate)
+    })
+}
+
+// ECDSA signature over a 32-byte message hash, normalized to low-S
+pub fn sign(secret: &[u8; 32], msg_hash: &[u8; 32]) -> Result<Signature, BitcoinError> {
+    let d = parse_secret_key(secret)?;
+    let z = U256::from_be_bytes(msg_hash).reduce(&N);
+
+    for k in rfc6979_nonces(secret, msg_hash) {
+        if k.is_zero() || k >= N {
+            continue;
+        }
+        let Some(point) = AffinePoint::GENERATOR.mul(&k) else {
+            continue;
+        };
+        let r = point.x.reduce(&N);
+        if r.is_zero() {
+            continue;
+        }
+        let s = k.inv_mod(&N).mul_mod(z.add_mod(r.mul_mod(d, &N), &N), &N);
+        if s.is_zero() {
+            continue;
+        }
+        return Ok(Signature {
+            r,
+            s: normalize_s(s),
+        });
+    }
+    unreachable!("the nonce iterator is infinite")
+}
+
+fn normalize_s(s: U256) -> U256 {
+    // n / 2
+    let half_n = U256([
+        0xDFE92F46681B20A0,
+        0x5D576E7357A4501D,
+        0xFFFFFFFFFFFFFFFF,
+        0x7FFFFFFFFFFFFFFF,
+    ]);
+    if s > half_n {
+        N.overflowing_sub(s).0
+    } else {
+        s
+    }
+}
+
+pub fn verify(public_key: &AffinePoint, msg_hash: &[u8; 32], signature: &Signature) -> bool {
+    let Signature { r, s } = *signature;
+    if r.is_zero() || r >= N || s.is_zero() || s >= N {
+        return false;
+    }
+    let z = U256::from_be_bytes(msg_hash).reduce(&N);
+    let s_inv = s.inv_mod(&N);
+    let u1 = z.mul_mod(s_inv, &N);
+    let u2 = r.mul_mod(s_inv, &N);
+
+    let point = JacobianPoint::from_affine(&AffinePoint::GENERATOR)
+        .mul(&u1)
+        .add(&JacobianPoint::from_affine(public_key).mul(&u2))
+        .to_affine();
+    match point {
+        Some(point) => point.x.reduce(&N) == r,
+        None => false,
+    }
+}
@@ -0,0 +1,328 @@
+// SegWit (BIP-144) transactions carrying per-input witness stacks
+
+use crate::hash::sha256d;
+use crate::validation;
+use crate::{
+    BitcoinDeserialize, BitcoinError, BitcoinSerialize, LegacyTransaction,
+    LegacyTransactionBuilder, LockTime, MAX_CONSENSUS_TX_WEIGHT, MAX_STANDARD_TX_WEIGHT, Script,
+    TxInput, TxOutput, check_weight, compact_size_len, encode_compact_size, hex,
+};
+
+// Marker and flag bytes that follow the version in the SegWit encoding
+pub const SEGWIT_MARKER: u8 = 0x00;
+pub const SEGWIT_FLAG: u8 = 0x01;
+
+// Whether serialized transaction bytes use the SegWit encoding: the marker
+// and flag directly after the 4-byte version. Too-short input is not SegWit.
+pub fn detect_segwit(data: &[u8]) -> bool {
+    matches!(data.get(4..6), Some([SEGWIT_MARKER, SEGWIT_FLAG]))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegWitTransaction {
+    pub version: i32,
+    pub inputs: Vec<TxInput>,
+    pub outputs: Vec<TxOutput>,
+    // One witness stack per input, in input order
+    pub witness: Vec<Vec<Vec<u8>>>,
+    pub lock_time: LockTime,
+}
+
+impl SegWitTransaction {
+    // Transaction id, computed over the serialization without witness data
+    pub fn txid(&self) -> [u8; 32] {
+        sha256d(&self.serialize_without_witness())
+    }
+
+    // Parse a raw transaction in the BIP-144 format
+    pub fn from_hex(hex: &str) -> Result<SegWitTransaction, BitcoinError> {
+        SegWitTransaction::deserialize(&hex::decode(hex)?)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.serialize())
+    }
+
+    // Legacy-format serialization without marker, flag or witnesses; this is
+    // what the txid commits to
+    pub fn serialize_without_witness(&self) -> Vec<u8> {
+        let mut out = self.version.to_le_bytes().to_vec();
+        out.extend(self.inputs.serialize());
+        out.extend(self.outputs.serialize());
+        out.extend_from_slice(&self.lock_time.to_consensus_u32().to_le_bytes());
+        out
+    }
+
+    // The same transaction with the witness data dropped
+    pub(crate) fn legacy_view(&self) -> LegacyTransaction {
+        LegacyTransactionBuilder {
+            version: self.version,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            lock_time: self.lock_time,
+        }
+        .build()
+    }
+
+    // A coinbase has exactly one input, spending the null outpoint
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    // Mutable handle to one input and its witness stack
+    pub fn input_mut(&mut self, index: usize) -> Result<SegWitInputRef<'_>, BitcoinError> {
+        let input = self
+            .inputs
+            .get_mut(index)
+            .ok_or(BitcoinError::InvalidTransaction)?;
+        if self.witness.len() <= index {
+            self.witness.resize_with(index + 1, Vec::new);
+        }
+        Ok(SegWitInputRef {
+            input,
+            witness: &mut self.witness[index],
+        })
+    }
+
+    // Witness stack of input `index`; inputs past the end of `witness` have an
+    // empty stack
+    fn witness_stack(&self, index: usize) -> Result<&[Vec<u8>], BitcoinError> {
+        if index >= self.inputs.len() {
+            return Err(BitcoinError::InvalidTransaction);
+        }
+        Ok(self.witness.get(index).map_or(&[], Vec::as_slice))
+    }
+
+    pub fn input_has_witness(&self, index: usize) -> Result<bool, BitcoinError> {
+        Ok(!self.witness_stack(index)?.is_empty())
+    }
+
+    pub fn witness_item_count(&self, index: usize) -> Result<usize, BitcoinError> {
+        Ok(self.witness_stack(index)?.len())
+    }
+
+    pub fn witness_item(
+        &self,
+        input_index: usize,
+        item_index: usize,
+    ) -> Result<&[u8], BitcoinError> {
+        self.witness_stack(input_index)?
+            .get(item_index)
+            .map(Vec::as_slice)
+            .ok_or(BitcoinError::InvalidTransaction)
+    }
+
+    // Set input `index`'s witness to `[sig, pubkey]`, checking that `sig` ends
+    // in a standard sighash type and `pubkey` is a compressed key
+    pub fn witness_for_p2wpkh_input(
+        &mut self,
+        index: usize,
+        sig: &[u8],
+        pubkey: &[u8],
+    ) -> Result<(), BitcoinError> {
+        if !matches!(sig.last(), Some(0x01..=0x03 | 0x81..=0x83)) {
+            return Err(BitcoinError::InvalidScript);
+        }
+        if pubkey.len() != 33 || !matches!(pubkey[0], 0x02 | 0x03) {
+            return Err(BitcoinError::InvalidScript);
+        }
+        self.input_mut(index)?
+            .set_witness(vec![sig.to_vec(), pubkey.to_vec()]);
+        Ok(())
+    }
+
+    // Set input `index`'s witness to `stack_items` followed by the witness
+    // script, as a P2WSH spend requires
+    pub fn witness_for_p2wsh_input(
+        &mut self,
+        index: usize,
+        script: Script,
+        stack_items: Vec<Vec<u8>>,
+    ) -> Result<(), BitcoinError> {
+        let mut stack = stack_items;
+        stack.push(script.into_bytes());
+        self.input_mut(index)?.set_witness(stack);
+        Ok(())
+    }
+
+    // (base size, witness size): the non-witness serialization length, and the
+    // marker, flag and witness stacks including their CompactSize prefixes
+    pub fn segwit_size_components(&self) -> (usize, usize) {
+        let base_size = self.legacy_view().estimated_size();
+
+        let stacks_size: usize = (0..self.inputs.len())
+            .map(|index| {
+                let stack = self.witness.get(index).map(Vec::as_slice).unwrap_or(&[]);
+                compact_size_len(stack.len() as u64)
+                    + stack
+                        .iter()
+                        .map(|item| compact_size_len(item.len() as u64) + item.len())
+                        .sum::<usize>()
+            })
+            .sum();
+
+        (base_size, 2 + stacks_size)
+    }
+
+    // BIP-141 weight: base size * 4 + witness size
+    pub fn weight(&self) -> u64 {
+        let (base_size, witness_size) = self.segwit_size_components();
+        (base_size * 4 + witness_size) as u64
+    }
+
+    // Standard policy weight check, see `check_standard_weight_limit`
+    pub fn check_weight_limit(&self) -> Result<(), BitcoinError> {
+        self.check_standard_weight_limit()
+    }
+
+    pub fn check_standard_weight_limit(&self) -> Result<(), BitcoinError> {
+        check_weight(self.weight(), MAX_STANDARD_TX_WEIGHT)
+    }
+
+    pub fn check_consensus_weight_limit(&self) -> Result<(), BitcoinError> {
+        check_weight(self.weight(), MAX_CONSENSUS_TX_WEIGHT)
+    }
+
+    // The legacy relay policy on the full weight and non-witness part, plus
+    // Core's standard witness item count and size
+    pub fn check_standard(&self) -> Result<(), BitcoinError> {
+        self.check_standard_weight_limit()?;
+        validation::check_standard_policy(&self.legacy_view())?;
+        validation::check_standard_witness(self)
+    }
+
+    pub fn is_standard(&self) -> bool {
+        self.check_standard().is_ok()
+    }
+
+    // Virtual size, rounded up
+    pub fn vbytes(&self) -> u64 {
+        self.weight().div_ceil(4)
+    }
+
+    // Same as `vbytes`, named to match `LegacyTransaction::vsize`
+    pub fn vsize(&self) -> u64 {
+        self.vbytes()
+    }
+
+    // Size of the serialization without marker, flag and witnesses
+    pub fn base_size(&self) -> usize {
+        self.segwit_size_components().0
+    }
+
+    // Same as `base_size`, named to pair with `witness_size`
+    pub fn non_witness_size(&self) -> usize {
+        self.base_size()
+    }
+
+    // Marker, flag and witness stacks, so that the BIP-141 weight is
+    // `4 * non_witness_size + witness_size`
+    pub fn witness_size(&self) -> usize {
+        self.segwit_size_components().1
+    }
+
+    // Witness transaction id, computed over the full serialization
+    pub fn wtxid(&self) -> [u8; 32] {
+        sha256d(&self.serialize())
+    }
+}
+
+impl BitcoinSerialize for SegWitTransaction {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.push(SEGWIT_MARKER);
+        out.push(SEGWIT_FLAG);
+        out.extend(self.inputs.serialize());
+        out.extend(self.outputs.serialize());
+        for index in 0..self.inputs.len() {
+            let stack = self.witness.get(index).map(Vec::as_slice).unwrap_or(&[]);
+            out.extend(encode_compact_size(stack.len() as u64));
+            for item in stack {
+                out.extend(encode_compact_size(item.len() as u64));
+                out.extend_from_slice(item);
+            }
+        }
+        out.extend_from_slice(&self.lock_time.to_consensus_u32().to_le_bytes());
+        out
+    }
+}
+
+// Borrowed view of a single input of a `SegWitTransaction`
+pub struct SegWitInputRef<'a> {
+    input: &'a mut TxInput,
+    witness: &'a mut Vec<Vec<u8>>,
+}
+
+impl SegWitInputRef<'_> {
+    pub fn input(&self) -> &TxInput {
+        self.input
+    }
+
+    pub fn push_witness(&mut self, item: Vec<u8>) {
+        self.witness.push(item);
+    }
+
+    pub fn clear_witness(&mut self) {
+        self.witness.clear();
+    }
+
+    pub fn witness_items(&self) -> &[Vec<u8>] {
+        self.witness
+    }
+
+    pub fn set_witness(&mut self, stack: Vec<Vec<u8>>) {
+        *self.witness = stack;
+    }
+}
+
+// Consensus limits on witness stacks: element size, witness script size and
+// the combined stack height
+const MAX_WITNESS_ITEM_SIZE: usize = 520;
+const MAX_WITNESS_SCRIPT_SIZE: usize = 10_000;
+const MAX_WITNESS_STACK_ITEMS: usize = 1000;
+
+// Builds a witness stack bottom to top. Oversized items are recorded rather
+// than rejected, so pushes chain; `try_build` reports them
+#[derive(Debug, Clone, Default)]
+pub struct WitnessBuilder {
+    items: Vec<Vec<u8>>,
+    oversized: bool,
+}
+
+impl WitnessBuilder {
+    pub fn new() -> Self {
+        WitnessBuilder::default()
+    }
+
+    pub fn push(mut self, item: Vec<u8>) -> Self {
+        self.oversized |= item.len() > MAX_WITNESS_ITEM_SIZE;
+        self.items.push(item);
+        self
+    }
+
+    // The witness script goes last in a P2WSH spend; it is bounded by the
+    // script size limit rather than the element size limit
+    pub fn push_script(mut self, script: &Script) -> Self {
+        self.oversized |= script.len() > MAX_WITNESS_SCRIPT_SIZE;
+        self.items.push(script.bytes().to_vec());
+        self
+    }
+
+    // The `[sig, pubkey]` stack of a P2WPKH spend
+    pub fn push_sig_and_pubkey(self, sig: Vec<u8>, pubkey: Vec<u8>) -> Self {
+        self.push(sig).push(pubkey)
+    }
+
+    // The stack as pushed, without checking the limits
+    pub fn build(self) -> Vec<Vec<u8>> {
+        self.items
+    }
+
+    pub fn try_build(self) -> Result<Vec<Vec<u8>>, BitcoinError> {
+        if self.oversized || self.items.len() > MAX_WITNESS_STACK_ITEMS {
+            return Err(BitcoinError::InvalidScript);
+        }
+        Ok(self.items)
+    }
+}
@@ -0,0 +1,318 @@
+// Serde support, enabled with the `serde` feature
+
+use std::collections::HashMap;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{Value, json};
+
+use crate::{
+    Amount, BitcoinError, FeeRate, LegacyTransaction, LegacyTransactionBuilder, LockTime, Network,
+    OutPoint, OutputScriptType, ParseErrorCode, Script, Sequence, TxInput, TxOutput, display_hex,
+    hex,
+};
+
+// `{"txid": "<display hex>", "vout": N}`
+impl Serialize for OutPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("OutPoint", 2)?;
+        state.serialize_field("txid", &self.txid.to_string())?;
+        state.serialize_field("vout", &self.vout)?;
+        state.end()
+    }
+}
+
+// Accepts the object form above or the combined `"<display hex>:N"` string
+impl<'de> Deserialize<'de> for OutPoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum OutPointRepr {
+            Combined(String),
+            Fields { txid: String, vout: u32 },
+        }
+
+        match OutPointRepr::deserialize(deserializer)? {
+            OutPointRepr::Combined(outpoint) => outpoint.parse().map_err(de::Error::custom),
+            OutPointRepr::Fields { txid, vout } => Ok(OutPoint {
+                txid: txid.parse().map_err(de::Error::custom)?,
+                vout,
+            }),
+        }
+    }
+}
+
+// Bitcoin Core's name for each output type in `scriptPubKey.type`
+fn rpc_script_type(script_type: OutputScriptType) -> &'static str {
+    match script_type {
+        OutputScriptType::P2PK => "pubkey",
+        OutputScriptType::P2PKH => "pubkeyhash",
+        OutputScriptType::P2SH => "scripthash",
+        OutputScriptType::P2WPKH => "witness_v0_keyhash",
+        OutputScriptType::P2WSH => "witness_v0_scripthash",
+        OutputScriptType::P2TR => "witness_v1_taproot",
+        OutputScriptType::P2A => "anchor",
+        OutputScriptType::P2MS { .. } => "multisig",
+        OutputScriptType::OpReturn => "nulldata",
+        OutputScriptType::NonStandard => "nonstandard",
+    }
+}
+
+fn rpc_error(message: &str) -> BitcoinError {
+    BitcoinError::cli_error(
+        ParseErrorCode::InvalidArgument,
+        format!("rpc json: {message}"),
+    )
+}
+
+fn rpc_field<'a>(object: &'a Value, key: &str) -> Result<&'a Value, BitcoinError> {
+    object
+        .get(key)
+        .ok_or_else(|| rpc_error(&format!("missing field `{key}`")))
+}
+
+fn rpc_u64(object: &Value, key: &str) -> Result<u64, BitcoinError> {
+    rpc_field(object, key)?
+        .as_u64()
+        .ok_or_else(|| rpc_error(&format!("`{key}` must be an unsigned integer")))
+}
+
+fn rpc_str<'a>(object: &'a Value, key: &str) -> Result<&'a str, BitcoinError> {
+    rpc_field(object, key)?
+        .as_str()
+        .ok_or_else(|| rpc_error(&format!("`{key}` must be a string")))
+}
+
+fn rpc_array<'a>(object: &'a Value, key: &str) -> Result<&'a Vec<Value>, BitcoinError> {
+    rpc_field(object, key)?
+        .as_array()
+        .ok_or_else(|| rpc_error(&format!("`{key}` must be an array")))
+}
+
+fn rpc_u32(object: &Value, key: &str) -> Result<u32, BitcoinError> {
+    u32::try_from(rpc_u64(object, key)?).map_err(|_| rpc_error(&format!("`{key}` out of range")))
+}
+
+// `"mainnet"`, `"testnet"`, ... as accepted by `--network`
+impl Serialize for Network {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Network {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+// Amounts are plain satoshi counts
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.to_sat())
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(Amount::from_sat)
+    }
+}
+
+// A number of sat/vB, with up to three decimals kept
+impl Serialize for FeeRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_sat_per_kvb() as f64 / 1000.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for FeeRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let sat_per_vb = f64::deserialize(deserializer)?;
+        if !(sat_per_vb >= 0.0 && sat_per_vb.is_finite()) {
+            return Err(de::Error::custom("fee rate must be a non-negative number"));
+        }
+        Ok(FeeRate::from_sat_per_kvb(
+            (sat_per_vb * 1000.0).round() as u64
+        ))
+    }
+}
+
+// A `decoderawtransaction` `vin` entry. A null outpoint is shown in the
+// coinbase form.
+impl Serialize for TxInput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        rpc_vin(self, self.previous_output.is_null()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TxInput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        input_from_rpc(&Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+// A `vout` entry without its index `n`, which only the transaction knows
+impl Serialize for TxOutput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        rpc_output(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TxOutput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        output_from_rpc(&Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+// The `to_rpc_json` object
+impl Serialize for LegacyTransaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let object: serde_json::Map<String, Value> = self.to_rpc_json().into_iter().collect();
+        object.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LegacyTransaction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = HashMap::<String, Value>::deserialize(deserializer)?;
+        LegacyTransaction::from_rpc_json(&map).map_err(de::Error::custom)
+    }
+}
+
+fn rpc_vin(input: &TxInput, coinbase: bool) -> Value {
+    if coinbase {
+        return json!({
+            "coinbase": hex::encode(input.script_sig.bytes()),
+            "sequence": input.sequence.to_consensus_u32(),
+        });
+    }
+    let script_sig = input.script_sig.clone();
+    json!({
+        "txid": input.previous_output.txid.to_string(),
+        "vout": input.previous_output.vout,
+        "scriptSig": {
+            "asm": script_sig.to_asm(),
+            "hex": hex::encode(input.script_sig.bytes()),
+        },
+        "sequence": input.sequence.to_consensus_u32(),
+    })
+}
+
+fn rpc_output(output: &TxOutput) -> Value {
+    json!({
+        "value": output.value.to_btc(),
+        "scriptPubKey": {
+            "asm": output.script_pubkey.to_asm(),
+            "hex": hex::encode(output.script_pubkey.bytes()),
+            "type": rpc_script_type(output.script_type()),
+        },
+    })
+}
+
+fn rpc_vout(n: usize, output: &TxOutput) -> Value {
+    let mut vout = rpc_output(output);
+    vout["n"] = json!(n);
+    vout
+}
+
+fn input_from_rpc(vin: &Value) -> Result<TxInput, BitcoinError> {
+    let sequence = Sequence(rpc_u32(vin, "sequence")?);
+    if let Some(coinbase) = vin.get("coinbase") {
+        let script_sig = coinbase
+            .as_str()
+            .ok_or_else(|| rpc_error("`coinbase` must be a string"))?;
+        return Ok(TxInput {
+            script_sig: Script(hex::decode(script_sig)?),
+            sequence,
+            ..TxInput::default()
+        });
+    }
+    Ok(TxInput {
+        previous_output: OutPoint {
+            txid: rpc_str(vin, "txid")?.parse()?,
+            vout: rpc_u32(vin, "vout")?,
+        },
+        script_sig: Script(hex::decode(rpc_str(rpc_field(vin, "scriptSig")?, "hex")?)?),
+        sequence,
+    })
+}
+
+fn output_from_rpc(vout: &Value) -> Result<TxOutput, BitcoinError> {
+    let btc = rpc_field(vout, "value")?
+        .as_f64()
+        .filter(|btc| *btc >= 0.0)
+        .ok_or_else(|| rpc_error("`value` must be a non-negative number"))?;
+    Ok(TxOutput {
+        value: Amount::from_btc(btc)?,
+        script_pubkey: Script(hex::decode(rpc_str(
+            rpc_field(vout, "scriptPubKey")?,
+            "hex",
+        )?)?),
+    })
+}
+
+impl LegacyTransaction {
+    // The object returned by Bitcoin Core's `getrawtransaction <txid> true`,
+    // minus the block-related fields
+    pub fn to_rpc_json(&self) -> HashMap<String, Value> {
+        // Display (reversed) byte order, as Bitcoin Core shows txids
+        let txid = display_hex(&self.txid());
+        let coinbase = self.is_coinbase();
+        let vin: Vec<Value> = self
+            .inputs()
+            .iter()
+            .map(|input| rpc_vin(input, coinbase))
+            .collect();
+        let vout: Vec<Value> = self
+            .outputs()
+            .iter()
+            .enumerate()
+            .map(|(n, output)| rpc_vout(n, output))
+            .collect();
+
+        HashMap::from([
+            ("txid".to_string(), json!(txid)),
+            // Legacy transactions have no witness, so wtxid == txid
+            ("hash".to_string(), json!(txid)),
+            ("version".to_string(), json!(self.version())),
+            ("size".to_string(), json!(self.estimated_size())),
+            ("vsize".to_string(), json!(self.vsize())),
+            ("weight".to_string(), json!(self.weight())),
+            (
+                "locktime".to_string(),
+                json!(self.lock_time().to_consensus_u32()),
+            ),
+            ("vin".to_string(), Value::Array(vin)),
+            ("vout".to_string(), Value::Array(vout)),
+        ])
+    }
+
+    // Rebuild a transaction from `to_rpc_json` / `getrawtransaction` output;
+    // derived fields (txid, size, asm, ...) are ignored
+    pub fn from_rpc_json(map: &HashMap<String, Value>) -> Result<LegacyTransaction, BitcoinError> {
+        let object = Value::Object(map.clone().into_iter().collect());
+        let version = rpc_field(&object, "version")?
+            .as_i64()
+            .and_then(|version| i32::try_from(version).ok())
+            .ok_or_else(|| rpc_error("`version` must be a 32-bit integer"))?;
+        let inputs = rpc_array(&object, "vin")?
+            .iter()
+            .map(input_from_rpc)
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = rpc_array(&object, "vout")?
+            .iter()
+            .map(output_from_rpc)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(LegacyTransactionBuilder {
+            version,
+            inputs,
+            outputs,
+            lock_time: LockTime(rpc_u32(&object, "locktime")?),
+        }
+        .build())
+    }
+}
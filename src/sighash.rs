@@ -0,0 +1,240 @@
+// Signature hashes: the legacy algorithm and BIP-143 for SegWit v0
+
+use crate::hash::sha256d;
+use crate::script::{Instruction, Opcode};
+use crate::{
+    Amount, BitcoinError, BitcoinSerialize, LegacyTransaction, Script, Sequence, TxOutput,
+    encode_compact_size,
+};
+
+const ANYONECANPAY: u32 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SigHashType {
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+}
+
+impl SigHashType {
+    pub fn to_u32(self) -> u32 {
+        match self {
+            SigHashType::All => 0x01,
+            SigHashType::None => 0x02,
+            SigHashType::Single => 0x03,
+            SigHashType::AllPlusAnyoneCanPay => 0x81,
+            SigHashType::NonePlusAnyoneCanPay => 0x82,
+            SigHashType::SinglePlusAnyoneCanPay => 0x83,
+        }
+    }
+
+    // Only the six standard values; consensus accepts any byte for legacy
+    // signatures but nothing else is relayed
+    pub fn from_u32(value: u32) -> Result<SigHashType, BitcoinError> {
+        match value {
+            0x01 => Ok(SigHashType::All),
+            0x02 => Ok(SigHashType::None),
+            0x03 => Ok(SigHashType::Single),
+            0x81 => Ok(SigHashType::AllPlusAnyoneCanPay),
+            0x82 => Ok(SigHashType::NonePlusAnyoneCanPay),
+            0x83 => Ok(SigHashType::SinglePlusAnyoneCanPay),
+            _ => Err(BitcoinError::InvalidScript),
+        }
+    }
+
+    pub fn anyone_can_pay(self) -> bool {
+        self.to_u32() & ANYONECANPAY != 0
+    }
+
+    // The type with ANYONECANPAY cleared
+    pub fn base(self) -> SigHashType {
+        match self {
+            SigHashType::All | SigHashType::AllPlusAnyoneCanPay => SigHashType::All,
+            SigHashType::None | SigHashType::NonePlusAnyoneCanPay => SigHashType::None,
+            SigHashType::Single | SigHashType::SinglePlusAnyoneCanPay => SigHashType::Single,
+        }
+    }
+}
+
+// Legacy digest for `input_index`. The signed input carries `script_code`
+// (with any OP_CODESEPARATORs removed) and the others an empty scriptSig;
+// NONE drops the outputs, SINGLE keeps only the matching one, and
+// ANYONECANPAY keeps only the signed input. SIGHASH_SINGLE without a
+// matching output signs the constant 1, as Bitcoin Core does.
+pub fn legacy_signature_hash(
+    tx: &LegacyTransaction,
+    input_index: usize,
+    script_code: &Script,
+    sighash_type: SigHashType,
+) -> Result<[u8; 32], BitcoinError> {
+    if input_index >= tx.inputs().len() {
+        return Err(BitcoinError::InvalidTransaction);
+    }
+    let base = sighash_type.base();
+    if base == SigHashType::Single && input_index >= tx.outputs().len() {
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        return Ok(one);
+    }
+
+    let script_code = strip_code_separators(script_code);
+    let mut tx_copy = tx.clone();
+    for (index, input) in tx_copy.inputs_mut().iter_mut().enumerate() {
+        if index == input_index {
+            input.script_sig = script_code.clone();
+        } else {
+            input.script_sig = Script::default();
+            if base != SigHashType::All {
+                input.sequence = Sequence(0);
+            }
+        }
+    }
+    if sighash_type.anyone_can_pay() {
+        let signed = tx_copy.inputs()[input_index].clone();
+        *tx_copy.inputs_mut() = vec![signed];
+    }
+    match base {
+        SigHashType::None => tx_copy.outputs_mut().clear(),
+        SigHashType::Single => {
+            let outputs = tx_copy.outputs_mut();
+            outputs.truncate(input_index + 1);
+            for output in &mut outputs[..input_index] {
+                *output = TxOutput {
+                    value: Amount::from_sat(u64::MAX),
+                    script_pubkey: Script::default(),
+                };
+            }
+        }
+        _ => {}
+    }
+
+    let mut preimage = tx_copy.serialize();
+    preimage.extend_from_slice(&sighash_type.to_u32().to_le_bytes());
+    Ok(sha256d(&preimage))
+}
+
+fn strip_code_separators(script: &Script) -> Script {
+    match script.decompile() {
+        Ok(instructions) if instructions.contains(&Instruction::Op(Opcode::OpCodeSeparator)) => {
+            let kept: Vec<_> = instructions
+                .into_iter()
+                .filter(|i| *i != Instruction::Op(Opcode::OpCodeSeparator))
+                .collect();
+            Script::compile(&kept)
+        }
+        _ => script.clone(),
+    }
+}
+
+// The BIP-143 components shared by every input of a transaction
+pub struct SegwitV0Cache {
+    hash_prevouts: [u8; 32],
+    hash_sequence: [u8; 32],
+    hash_outputs: [u8; 32],
+}
+
+impl SegwitV0Cache {
+    pub fn new(tx: &LegacyTransaction) -> Self {
+        let prevouts: Vec<u8> = tx
+            .inputs()
+            .iter()
+            .flat_map(|input| input.previous_output.serialize())
+            .collect();
+        let sequences: Vec<u8> = tx
+            .inputs()
+            .iter()
+            .flat_map(|input| input.sequence.to_consensus_u32().to_le_bytes())
+            .collect();
+        let outputs: Vec<u8> = tx.outputs().iter().flat_map(|o| o.serialize()).collect();
+        SegwitV0Cache {
+            hash_prevouts: sha256d(&prevouts),
+            hash_sequence: sha256d(&sequences),
+            hash_outputs: sha256d(&outputs),
+        }
+    }
+}
+
+// The full BIP-143 preimage for `input_index` before hashing, for inspecting
+// the sighash computation step by step
+pub fn segwit_v0_signing_data(
+    tx: &LegacyTransaction,
+    input_index: usize,
+    script_code: &Script,
+    value: Amount,
+    sighash_type: SigHashType,
+) -> Result<Vec<u8>, BitcoinError> {
+    let cache = SegwitV0Cache::new(tx);
+    segwit_v0_preimage(
+        tx,
+        &cache,
+        input_index,
+        script_code,
+        value.to_sat(),
+        sighash_type,
+    )
+}
+
+// BIP-143 digest for `input_index`, spending an output worth `amount`
+pub fn segwit_v0_signature_hash(
+    tx: &LegacyTransaction,
+    cache: &SegwitV0Cache,
+    input_index: usize,
+    script_code: &Script,
+    amount: u64,
+    sighash_type: SigHashType,
+) -> Result<[u8; 32], BitcoinError> {
+    let preimage = segwit_v0_preimage(tx, cache, input_index, script_code, amount, sighash_type)?;
+    Ok(sha256d(&preimage))
+}
+
+// The eight BIP-143 components concatenated, with the shared hashes from `cache`
+fn segwit_v0_preimage(
+    tx: &LegacyTransaction,
+    cache: &SegwitV0Cache,
+    input_index: usize,
+    script_code: &Script,
+    amount: u64,
+    sighash_type: SigHashType,
+) -> Result<Vec<u8>, BitcoinError> {
+    let input = tx
+        .inputs()
+        .get(input_index)
+        .ok_or(BitcoinError::InvalidTransaction)?;
+    let base = sighash_type.base();
+    let anyone_can_pay = sighash_type.anyone_can_pay();
+
+    let hash_prevouts = if anyone_can_pay {
+        [0; 32]
+    } else {
+        cache.hash_prevouts
+    };
+    let hash_sequence = if anyone_can_pay || base != SigHashType::All {
+        [0; 32]
+    } else {
+        cache.hash_sequence
+    };
+    let hash_outputs = match base {
+        SigHashType::All => cache.hash_outputs,
+        SigHashType::Single if input_index < tx.outputs().len() => {
+            sha256d(&tx.outputs()[input_index].serialize())
+        }
+        _ => [0; 32],
+    };
+
+    let mut preimage = Vec::with_capacity(156 + script_code.len());
+    preimage.extend_from_slice(&tx.version().to_le_bytes());
+    preimage.extend_from_slice(&hash_prevouts);
+    preimage.extend_from_slice(&hash_sequence);
+    preimage.extend(input.previous_output.serialize());
+    preimage.extend(encode_compact_size(script_code.len() as u64));
+    preimage.extend_from_slice(script_code.bytes());
+    preimage.extend_from_slice(&amount.to_le_bytes());
+    preimage.extend_from_slice(&input.sequence.to_consensus_u32().to_le_bytes());
+    preimage.extend_from_slice(&hash_outputs);
+    preimage.extend_from_slice(&tx.lock_time().to_consensus_u32().to_le_bytes());
+    preimage.extend_from_slice(&sighash_type.to_u32().to_le_bytes());
+    Ok(preimage)
+}
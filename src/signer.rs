@@ -0,0 +1,55 @@
+// Signing legacy transaction inputs with raw secret keys
+
+use crate::hash::sha256d;
+use crate::secp256k1;
+use crate::{BitcoinError, BitcoinSerialize, LegacyTransaction, Script, ScriptBuilder};
+
+pub const SIGHASH_ALL: u32 = 0x01;
+
+// Legacy SIGHASH_ALL digest for one input: every scriptSig is blanked except
+// the signed input, which carries `script_code`
+pub(crate) fn signature_hash_all(
+    tx: &LegacyTransaction,
+    input_index: usize,
+    script_code: &Script,
+) -> [u8; 32] {
+    let mut tx_copy = tx.clone();
+    for (index, input) in tx_copy.inputs_mut().iter_mut().enumerate() {
+        input.script_sig = if index == input_index {
+            script_code.clone()
+        } else {
+            Script::default()
+        };
+    }
+
+    let mut preimage = tx_copy.serialize();
+    preimage.extend_from_slice(&SIGHASH_ALL.to_le_bytes());
+    sha256d(&preimage)
+}
+
+// Sign every input as a P2PKH spend with SIGHASH_ALL, producing scriptSigs of
+// the form `<sig> <compressed pubkey>`. `script_codes` holds the scriptPubKey
+// being spent by each input, in input order.
+pub fn sign_legacy_inputs(
+    tx: &LegacyTransaction,
+    secret_key: &[u8; 32],
+    script_codes: &[Script],
+) -> Result<LegacyTransaction, BitcoinError> {
+    if script_codes.len() != tx.inputs().len() {
+        return Err(BitcoinError::InvalidTransaction);
+    }
+    let pubkey = secp256k1::public_key_compressed(secret_key)?;
+
+    let mut signed = tx.clone();
+    for (index, script_code) in script_codes.iter().enumerate() {
+        let sighash = signature_hash_all(tx, index, script_code);
+        let mut signature = secp256k1::sign(secret_key, &sighash)?.to_der();
+        signature.push(SIGHASH_ALL as u8);
+
+        signed.inputs_mut()[index].script_sig = ScriptBuilder::new()
+            .push_data(&signature)
+            .push_data(&pubkey)
+            .build();
+    }
+    Ok(signed)
+}
@@ -0,0 +1,353 @@
+// Taproot (BIP-341) key tweaking and script trees
+
+use crate::address::Address;
+use crate::hash::tagged_sha256;
+use crate::script::TAPSCRIPT_LEAF_VERSION;
+use crate::secp256k1::{self, AffinePoint, N, P, U256};
+use crate::{BitcoinError, Script, encode_compact_size};
+
+// A script leaf of a taproot tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapLeaf {
+    pub version: u8,
+    pub script: Script,
+}
+
+impl TapLeaf {
+    // A BIP-342 tapscript leaf
+    pub fn new(script: Script) -> TapLeaf {
+        TapLeaf {
+            version: TAPSCRIPT_LEAF_VERSION,
+            script,
+        }
+    }
+
+    // H_TapLeaf(version || compact_size(len) || script)
+    pub fn hash(&self) -> [u8; 32] {
+        let mut data = vec![self.version];
+        data.extend(encode_compact_size(self.script.len() as u64));
+        data.extend_from_slice(self.script.bytes());
+        tagged_sha256("TapLeaf", &data)
+    }
+}
+
+// An inner node of a taproot tree, given the hashes of its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapBranch {
+    pub left: [u8; 32],
+    pub right: [u8; 32],
+}
+
+impl TapBranch {
+    // H_TapBranch of the two child hashes in lexicographic order
+    pub fn hash(&self) -> [u8; 32] {
+        let (a, b) = if self.left <= self.right {
+            (self.left, self.right)
+        } else {
+            (self.right, self.left)
+        };
+        let mut data = a.to_vec();
+        data.extend_from_slice(&b);
+        tagged_sha256("TapBranch", &data)
+    }
+}
+
+// A leaf and its merkle proof, sibling first
+type LeafProof = (TapLeaf, Vec<[u8; 32]>);
+
+// A taproot script tree: a leaf, or a branch over two subtrees. Any shape can
+// be expressed, so more likely leaves can sit closer to the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapTree {
+    Leaf(TapLeaf),
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    pub fn branch(left: TapTree, right: TapTree) -> TapTree {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    // Pair adjacent nodes level by level, an odd node being carried up
+    // unchanged; `None` for no leaves
+    pub fn from_leaves(leaves: &[TapLeaf]) -> Option<TapTree> {
+        let mut level: Vec<TapTree> = leaves.iter().cloned().map(TapTree::Leaf).collect();
+        while level.len() > 1 {
+            let mut nodes = level.into_iter();
+            let mut next = Vec::new();
+            while let Some(left) = nodes.next() {
+                next.push(match nodes.next() {
+                    Some(right) => TapTree::branch(left, right),
+                    None => left,
+                });
+            }
+            level = next;
+        }
+        level.pop()
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf(leaf) => leaf.hash(),
+            TapTree::Branch(left, right) => TapBranch {
+                left: left.hash(),
+                right: right.hash(),
+            }
+            .hash(),
+        }
+    }
+
+    // The tree's hash and each leaf, left to right, with its merkle proof
+    fn leaves_with_proofs(&self) -> ([u8; 32], Vec<LeafProof>) {
+        match self {
+            TapTree::Leaf(leaf) => (leaf.hash(), vec![(leaf.clone(), Vec::new())]),
+            TapTree::Branch(left, right) => {
+                let (left_hash, mut left_leaves) = left.leaves_with_proofs();
+                let (right_hash, right_leaves) = right.leaves_with_proofs();
+                for (_, proof) in &mut left_leaves {
+                    proof.push(right_hash);
+                }
+                left_leaves.extend(right_leaves.into_iter().map(|(leaf, mut proof)| {
+                    proof.push(left_hash);
+                    (leaf, proof)
+                }));
+                let hash = TapBranch {
+                    left: left_hash,
+                    right: right_hash,
+                }
+                .hash();
+                (hash, left_leaves)
+            }
+        }
+    }
+}
+
+// Merkle root of `TapTree::from_leaves(leaves)`. A single leaf is its own
+// root; an empty slice gives the all-zero hash.
+pub fn taproot_merkle_root(leaves: &[TapLeaf]) -> [u8; 32] {
+    TapTree::from_leaves(leaves).map_or([0; 32], |tree| tree.hash())
+}
+
+// Everything needed to pay to and spend from a taproot output: the internal
+// key, the script tree's merkle root, the tweaked output key and its y
+// parity, and a merkle proof for every leaf
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaprootSpendInfo {
+    internal_key: [u8; 32],
+    merkle_root: Option<[u8; 32]>,
+    output_key: [u8; 32],
+    output_key_parity: bool,
+    leaves: Vec<LeafProof>,
+}
+
+impl TaprootSpendInfo {
+    // Commit to the script tree `tree`
+    pub fn new(internal_key: [u8; 32], tree: &TapTree) -> Result<Self, BitcoinError> {
+        let (root, leaves) = tree.leaves_with_proofs();
+        TaprootSpendInfo::with_leaves(internal_key, Some(root), leaves)
+    }
+
+    // No script tree: the output is key-path only (BIP-86)
+    pub fn key_path_only(internal_key: [u8; 32]) -> Result<Self, BitcoinError> {
+        TaprootSpendInfo::with_leaves(internal_key, None, Vec::new())
+    }
+
+    fn with_leaves(
+        internal_key: [u8; 32],
+        merkle_root: Option<[u8; 32]>,
+        leaves: Vec<LeafProof>,
+    ) -> Result<Self, BitcoinError> {
+        let output_point = tweaked_output_point(&internal_key, merkle_root.as_ref())?;
+        Ok(TaprootSpendInfo {
+            internal_key,
+            merkle_root,
+            output_key: output_point.x.to_be_bytes(),
+            output_key_parity: output_point.y.bit(0),
+            leaves,
+        })
+    }
+
+    pub fn internal_key(&self) -> [u8; 32] {
+        self.internal_key
+    }
+
+    pub fn merkle_root(&self) -> Option<[u8; 32]> {
+        self.merkle_root
+    }
+
+    // x-only output key, as found in the scriptPubKey
+    pub fn output_key(&self) -> [u8; 32] {
+        self.output_key
+    }
+
+    // True when the full output key has odd y
+    pub fn output_key_parity(&self) -> bool {
+        self.output_key_parity
+    }
+
+    pub fn leaves(&self) -> impl Iterator<Item = &TapLeaf> {
+        self.leaves.iter().map(|(leaf, _)| leaf)
+    }
+
+    pub fn merkle_proof(&self, leaf: &TapLeaf) -> Option<&[[u8; 32]]> {
+        self.leaves
+            .iter()
+            .find(|(candidate, _)| candidate == leaf)
+            .map(|(_, proof)| proof.as_slice())
+    }
+
+    // Control block for a script-path spend of `leaf`, as `control_block`
+    // builds it; `None` if the tree does not contain the leaf
+    pub fn control_block(&self, leaf: &TapLeaf) -> Option<Vec<u8>> {
+        let proof = self.merkle_proof(leaf)?;
+        let mut out = Vec::with_capacity(33 + 32 * proof.len());
+        out.push((leaf.version & 0xfe) | u8::from(self.output_key_parity));
+        out.extend_from_slice(&self.internal_key);
+        for node in proof {
+            out.extend_from_slice(node);
+        }
+        Some(out)
+    }
+
+    pub fn address(&self) -> Address {
+        Address::P2TR(self.output_key)
+    }
+
+    // `OP_1 <output key>`
+    pub fn script_pubkey(&self) -> Script {
+        self.address().script_pubkey()
+    }
+}
+
+// P2TR scriptPubKey for `internal_key`, committing to `leaves` (arranged as
+// by `TapTree::from_leaves`) if any
+pub fn p2tr_script_pubkey(
+    internal_key: &[u8; 32],
+    leaves: &[TapLeaf],
+) -> Result<Script, BitcoinError> {
+    let info = match TapTree::from_leaves(leaves) {
+        Some(tree) => TaprootSpendInfo::new(*internal_key, &tree)?,
+        None => TaprootSpendInfo::key_path_only(*internal_key)?,
+    };
+    Ok(info.script_pubkey())
+}
+
+// Control block for spending `leaf`: the leaf version with the output key's
+// y parity in the low bit, the internal key, then the merkle `proof` hashes
+// from the leaf's sibling up to the root
+pub fn control_block(
+    internal_key: &[u8; 32],
+    leaf: &TapLeaf,
+    proof: Vec<[u8; 32]>,
+) -> Result<Vec<u8>, BitcoinError> {
+    let merkle_root = proof.iter().fold(leaf.hash(), |node, sibling| {
+        TapBranch {
+            left: node,
+            right: *sibling,
+        }
+        .hash()
+    });
+    let output_point = tweaked_output_point(internal_key, Some(&merkle_root))?;
+
+    let mut out = Vec::with_capacity(33 + 32 * proof.len());
+    out.push((leaf.version & 0xfe) | u8::from(output_point.y.bit(0)));
+    out.extend_from_slice(internal_key);
+    for node in proof {
+        out.extend_from_slice(&node);
+    }
+    Ok(out)
+}
+
+// t = H_TapTweak(P || root), which must be a valid scalar
+fn tap_tweak(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<U256, BitcoinError> {
+    let mut data = internal_key.to_vec();
+    if let Some(root) = merkle_root {
+        data.extend_from_slice(root);
+    }
+    let tweak = U256::from_be_bytes(&tagged_sha256("TapTweak", &data));
+    if tweak >= N {
+        return Err(BitcoinError::InvalidScript);
+    }
+    Ok(tweak)
+}
+
+// The point with x coordinate `x` and even y (BIP-340 x-only keys)
+fn lift_x_even(x: &[u8; 32]) -> Result<AffinePoint, BitcoinError> {
+    let x = U256::from_be_bytes(x);
+    let y = secp256k1::lift_x(&x)?;
+    let y = if y.bit(0) { P.overflowing_sub(y).0 } else { y };
+    Ok(AffinePoint { x, y })
+}
+
+// Witness stack for a script-path spend of `leaf`: the script inputs, then
+// the leaf script, then the control block. The control block must be well
+// formed and carry `leaf`'s version.
+pub fn taproot_script_spend_witness(
+    stack_items: Vec<Vec<u8>>,
+    leaf: &TapLeaf,
+    control_block: Vec<u8>,
+) -> Result<Vec<Vec<u8>>, BitcoinError> {
+    let version = Script(control_block.clone())
+        .tapscript_leaf_version()
+        .ok_or(BitcoinError::InvalidScript)?;
+    if version != leaf.version & 0xfe {
+        return Err(BitcoinError::UnsupportedTaprootLeafVersion(
+            control_block[0],
+        ));
+    }
+
+    let mut witness = stack_items;
+    witness.push(leaf.script.bytes().to_vec());
+    witness.push(control_block);
+    Ok(witness)
+}
+
+// Output key Q = P + t * G for x-only internal key P, returned x-only.
+// Without a merkle root this is the BIP-86 key-path-only commitment.
+pub fn taproot_tweak_pubkey(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<[u8; 32], BitcoinError> {
+    Ok(tweaked_output_point(internal_key, merkle_root)?
+        .x
+        .to_be_bytes())
+}
+
+fn tweaked_output_point(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<AffinePoint, BitcoinError> {
+    let internal_point = lift_x_even(internal_key)?;
+    let tweak = tap_tweak(internal_key, merkle_root)?;
+    let tweak_point = AffinePoint::GENERATOR.mul(&tweak);
+    let output_point = match tweak_point {
+        Some(tweak_point) => internal_point.add(&tweak_point),
+        None => Some(internal_point),
+    };
+    output_point.ok_or(BitcoinError::InvalidScript)
+}
+
+// Secret key for the tweaked output key: the internal secret is negated
+// first when its public key has odd y, then t is added
+pub fn taproot_tweak_privkey(
+    internal_key_secret: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<[u8; 32], BitcoinError> {
+    let secret = secp256k1::parse_secret_key(internal_key_secret)?;
+    let internal_point = secp256k1::public_key(internal_key_secret)?;
+    let secret = if internal_point.y.bit(0) {
+        N.overflowing_sub(secret).0
+    } else {
+        secret
+    };
+
+    let tweak = tap_tweak(&internal_point.x.to_be_bytes(), merkle_root)?;
+    let tweaked = secret.add_mod(tweak, &N);
+    if tweaked.is_zero() {
+        return Err(BitcoinError::InvalidScript);
+    }
+    Ok(tweaked.to_be_bytes())
+}
@@ -0,0 +1,142 @@
+// Random value generators and a serialization round-trip check for tests.
+// The generator is a seeded SplitMix64, so a failing case can be replayed from
+// its seed without pulling in a property-testing crate.
+
+use std::fmt::Debug;
+
+use crate::{
+    Amount, BitcoinDeserialize, BitcoinSerialize, LegacyTransaction, MAX_MONEY, OutPoint, Script,
+    Sequence, TxInput, TxOutput, Txid,
+};
+
+// Largest input or output count generated for a transaction
+const MAX_GENERATED_IO: u64 = 4;
+
+#[derive(Debug, Clone)]
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Gen {
+        Gen { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    // Uniform in `0..=max`, up to a negligible modulo bias
+    pub fn up_to(&mut self, max: u64) -> u64 {
+        match max.checked_add(1) {
+            Some(bound) => self.next_u64() % bound,
+            None => self.next_u64(),
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+pub trait Arbitrary: Sized {
+    fn arbitrary(g: &mut Gen) -> Self;
+}
+
+impl Arbitrary for OutPoint {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let mut txid = [0u8; 32];
+        txid.copy_from_slice(&g.bytes(32));
+        OutPoint {
+            txid: Txid(txid),
+            vout: g.next_u32(),
+        }
+    }
+}
+
+// Mostly short scripts, with the odd one long enough to need a 0xFD length
+// prefix
+impl Arbitrary for Script {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let len = if g.up_to(7) == 0 {
+            253 + g.up_to(300)
+        } else {
+            g.up_to(40)
+        };
+        Script(g.bytes(len as usize))
+    }
+}
+
+impl Arbitrary for TxInput {
+    fn arbitrary(g: &mut Gen) -> Self {
+        TxInput::new(
+            OutPoint::arbitrary(g),
+            Script::arbitrary(g),
+            Sequence(g.next_u32()),
+        )
+    }
+}
+
+impl Arbitrary for TxOutput {
+    fn arbitrary(g: &mut Gen) -> Self {
+        TxOutput::new(
+            Amount::from_sat(g.up_to(MAX_MONEY.to_sat())),
+            Script::arbitrary(g),
+        )
+    }
+}
+
+// Always has at least one input, since an empty input list is read back as the
+// SegWit marker
+impl Arbitrary for LegacyTransaction {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let mut builder = LegacyTransaction::builder()
+            .version(g.next_u32() as i32)
+            .lock_time(g.next_u32());
+        for _ in 0..=g.up_to(MAX_GENERATED_IO - 1) {
+            builder = builder.add_input(TxInput::arbitrary(g));
+        }
+        for _ in 0..g.up_to(MAX_GENERATED_IO) {
+            builder = builder.add_output(TxOutput::arbitrary(g));
+        }
+        builder.build()
+    }
+}
+
+// Panics unless `value` serializes to bytes that parse back to an equal value
+// and re-serialize identically
+pub fn roundtrip<T>(value: &T)
+where
+    T: BitcoinSerialize + BitcoinDeserialize + PartialEq + Debug,
+{
+    let bytes = value.serialize();
+    let decoded = match T::deserialize(&bytes) {
+        Ok(decoded) => decoded,
+        Err(e) => panic!("failed to deserialize {value:?}: {e}"),
+    };
+    assert_eq!(&decoded, value, "round trip changed the value");
+    assert_eq!(
+        decoded.serialize(),
+        bytes,
+        "round trip changed the encoding"
+    );
+}
+
+// Round-trip `cases` generated values, starting from `seed`
+pub fn check_roundtrips<T>(seed: u64, cases: usize)
+where
+    T: Arbitrary + BitcoinSerialize + BitcoinDeserialize + PartialEq + Debug,
+{
+    let mut g = Gen::new(seed);
+    for _ in 0..cases {
+        roundtrip(&T::arbitrary(&mut g));
+    }
+}
@@ -0,0 +1,172 @@
+// A transaction in either wire format, with the common accessors delegated
+
+use std::io::Read;
+
+use crate::hex;
+use crate::segwit::SegWitTransaction;
+use crate::{
+    Amount, BitcoinDeserialize, BitcoinError, BitcoinSerialize, LegacyTransaction, OutPoint,
+    TransactionReader, TxInput, TxOutput, UtxoSet,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transaction {
+    Legacy(LegacyTransaction),
+    SegWit(SegWitTransaction),
+}
+
+impl Transaction {
+    pub fn txid(&self) -> [u8; 32] {
+        match self {
+            Transaction::Legacy(tx) => tx.txid(),
+            Transaction::SegWit(tx) => tx.txid(),
+        }
+    }
+
+    // Byte-reversed hex, as shown by explorers and RPCs
+    pub fn txid_hex(&self) -> String {
+        crate::display_hex(&self.txid())
+    }
+
+    // Parse a raw transaction in either format
+    pub fn from_hex(hex: &str) -> Result<Transaction, BitcoinError> {
+        Transaction::deserialize(&hex::decode(hex)?)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.serialize())
+    }
+
+    // Equal to the txid for legacy transactions, which carry no witness
+    pub fn wtxid(&self) -> [u8; 32] {
+        match self {
+            Transaction::Legacy(tx) => tx.txid(),
+            Transaction::SegWit(tx) => tx.wtxid(),
+        }
+    }
+
+    pub fn weight(&self) -> u64 {
+        match self {
+            Transaction::Legacy(tx) => tx.weight(),
+            Transaction::SegWit(tx) => tx.weight(),
+        }
+    }
+
+    pub fn vbytes(&self) -> u64 {
+        match self {
+            Transaction::Legacy(tx) => tx.vsize(),
+            Transaction::SegWit(tx) => tx.vbytes(),
+        }
+    }
+
+    // Same as `vbytes`, named to match the per-format `vsize`
+    pub fn vsize(&self) -> u64 {
+        self.vbytes()
+    }
+
+    pub fn check_standard(&self) -> Result<(), BitcoinError> {
+        match self {
+            Transaction::Legacy(tx) => tx.check_standard(),
+            Transaction::SegWit(tx) => tx.check_standard(),
+        }
+    }
+
+    pub fn is_standard(&self) -> bool {
+        self.check_standard().is_ok()
+    }
+
+    pub fn inputs(&self) -> &[TxInput] {
+        match self {
+            Transaction::Legacy(tx) => tx.inputs(),
+            Transaction::SegWit(tx) => &tx.inputs,
+        }
+    }
+
+    pub fn outputs(&self) -> &[TxOutput] {
+        match self {
+            Transaction::Legacy(tx) => tx.outputs(),
+            Transaction::SegWit(tx) => &tx.outputs,
+        }
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        match self {
+            Transaction::Legacy(tx) => tx.is_coinbase(),
+            Transaction::SegWit(tx) => tx.is_coinbase(),
+        }
+    }
+
+    // Input value minus output value, looking up every spent output in `utxo_set`
+    pub fn absolute_fee(&self, utxo_set: &UtxoSet) -> Result<Amount, BitcoinError> {
+        crate::absolute_fee(self.inputs(), self.outputs(), utxo_set)
+    }
+}
+
+impl From<LegacyTransaction> for Transaction {
+    fn from(tx: LegacyTransaction) -> Self {
+        Transaction::Legacy(tx)
+    }
+}
+
+impl From<SegWitTransaction> for Transaction {
+    fn from(tx: SegWitTransaction) -> Self {
+        Transaction::SegWit(tx)
+    }
+}
+
+impl BitcoinSerialize for Transaction {
+    fn serialize(&self) -> Vec<u8> {
+        match self {
+            Transaction::Legacy(tx) => tx.serialize(),
+            Transaction::SegWit(tx) => tx.serialize(),
+        }
+    }
+
+    fn serialize_to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<(), BitcoinError> {
+        match self {
+            Transaction::Legacy(tx) => tx.serialize_to_writer(writer),
+            Transaction::SegWit(tx) => tx.serialize_to_writer(writer),
+        }
+    }
+}
+
+// Detects the SegWit marker and flag
+impl BitcoinDeserialize for Transaction {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        TransactionReader::new(r).read_any_transaction()
+    }
+}
+
+// Only accepts the BIP-144 encoding; legacy bytes are an error
+impl BitcoinDeserialize for SegWitTransaction {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        match Transaction::deserialize_from(r)? {
+            Transaction::SegWit(tx) => Ok(tx),
+            Transaction::Legacy(_) => Err(BitcoinError::InvalidTransaction),
+        }
+    }
+}
+
+impl BitcoinDeserialize for LegacyTransaction {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        TransactionReader::new(r).read_transaction()
+    }
+}
+
+impl BitcoinDeserialize for OutPoint {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        TransactionReader::new(r).read_outpoint()
+    }
+}
+
+impl BitcoinDeserialize for TxInput {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        TransactionReader::new(r).read_input()
+    }
+}
+
+impl BitcoinDeserialize for TxOutput {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        TransactionReader::new(r).read_output()
+    }
+}
@@ -0,0 +1,283 @@
+// Composable transaction validation for different policy regimes
+
+use std::collections::HashSet;
+
+use crate::segwit::SegWitTransaction;
+use crate::transaction::Transaction;
+use crate::{
+    Amount, BitcoinError, FeeRate, LegacyTransaction, MAX_MONEY, OutputScriptType, TxInput,
+    TxOutput, UtxoSet, compact_size_len,
+};
+
+// Core's largest standard scriptSig, and the fee rate `dust_threshold` triples
+// into Core's 3 sat/vB dust relay fee
+const MAX_STANDARD_SCRIPT_SIG_SIZE: usize = 1650;
+pub(crate) const DUST_RELAY_FEE_RATE: FeeRate = FeeRate::from_sat_per_vb(1);
+// Core's limits on a standard P2WSH witness: stack items and script size
+const MAX_STANDARD_WITNESS_ITEMS: usize = 100;
+const MAX_STANDARD_WITNESS_ITEM_SIZE: usize = 3600;
+
+// Structural rules shared by `try_build`, `sanity_check` and the validator.
+// Each returns every violation in order; callers that fail fast take the first.
+
+pub(crate) fn check_not_empty(inputs: &[TxInput], outputs: &[TxOutput]) -> Vec<BitcoinError> {
+    let mut errors = Vec::new();
+    if inputs.is_empty() {
+        errors.push(BitcoinError::NoInputs);
+    }
+    if outputs.is_empty() {
+        errors.push(BitcoinError::NoOutputs);
+    }
+    errors
+}
+
+// Output values above 21M BTC, singly and then in total
+pub(crate) fn check_output_values(outputs: &[TxOutput]) -> Vec<BitcoinError> {
+    let mut errors: Vec<BitcoinError> = outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| output.value > MAX_MONEY)
+        .map(|(index, output)| BitcoinError::OutputValueTooLarge {
+            index,
+            value: output.value,
+        })
+        .collect();
+    let total = outputs.iter().try_fold(Amount::ZERO, |total, output| {
+        total.checked_add(output.value)
+    });
+    if total.is_none_or(|total| total > MAX_MONEY) {
+        errors.push(BitcoinError::TotalValueTooLarge);
+    }
+    errors
+}
+
+// Outputs below the dust limit at the relay dust fee rate. Anchors are
+// usually zero-value ephemeral dust, bumped by a child transaction in the
+// same package, so they are exempt
+pub(crate) fn check_dust_outputs(outputs: &[TxOutput]) -> Vec<BitcoinError> {
+    outputs
+        .iter()
+        .filter(|output| {
+            output.script_type() != OutputScriptType::P2A && output.is_dust(DUST_RELAY_FEE_RATE)
+        })
+        .map(|output| BitcoinError::DustOutput {
+            value: output.value,
+            threshold: output.dust_threshold(DUST_RELAY_FEE_RATE),
+        })
+        .collect()
+}
+
+// Every input spending an outpoint an earlier input already spends
+pub(crate) fn check_duplicate_inputs(inputs: &[TxInput]) -> Vec<BitcoinError> {
+    let mut seen = HashSet::new();
+    inputs
+        .iter()
+        .filter(|input| !seen.insert(&input.previous_output))
+        .map(|input| BitcoinError::DuplicateInput(input.previous_output.clone()))
+        .collect()
+}
+
+// The rules behind `require_standard`, shared with `is_standard`; the weight
+// limit is checked separately
+pub(crate) fn check_standard_policy(tx: &LegacyTransaction) -> Result<(), BitcoinError> {
+    let fail = |reason: String| Err(BitcoinError::CheckFailed(reason));
+    if !(1..=3).contains(&tx.version()) {
+        return fail(format!("non-standard version {}", tx.version()));
+    }
+    for (index, input) in tx.inputs().iter().enumerate() {
+        let script_sig = input.script_sig.clone();
+        if script_sig.len() > MAX_STANDARD_SCRIPT_SIG_SIZE {
+            return fail(format!("input {index} scriptSig is too large"));
+        }
+        script_sig.check_push_only()?;
+    }
+    for (index, output) in tx.outputs().iter().enumerate() {
+        if !output.is_standard() {
+            return fail(format!("output {index} has a non-standard script"));
+        }
+    }
+    check_dust_outputs(tx.outputs())
+        .into_iter()
+        .next()
+        .map_or(Ok(()), Err)
+}
+
+// The rules behind `require_standard_witness`
+pub(crate) fn check_standard_witness(tx: &SegWitTransaction) -> Result<(), BitcoinError> {
+    let fail = |reason: String| Err(BitcoinError::CheckFailed(reason));
+    if tx.witness.len() > tx.inputs.len() {
+        return fail(format!(
+            "{} witness stacks for {} inputs",
+            tx.witness.len(),
+            tx.inputs.len()
+        ));
+    }
+    if tx.witness.iter().all(Vec::is_empty) {
+        return fail("SegWit format without witness data".to_string());
+    }
+    for (index, stack) in tx.witness.iter().enumerate() {
+        if stack.len() > MAX_STANDARD_WITNESS_ITEMS {
+            return fail(format!("input {index} has too many witness items"));
+        }
+        if stack
+            .iter()
+            .any(|item| item.len() > MAX_STANDARD_WITNESS_ITEM_SIZE)
+        {
+            return fail(format!("input {index} has an oversized witness item"));
+        }
+    }
+    Ok(())
+}
+
+type Check = Box<dyn Fn(&LegacyTransaction, &UtxoSet) -> Option<BitcoinError>>;
+type WitnessCheck = Box<dyn Fn(&SegWitTransaction, &UtxoSet) -> Option<BitcoinError>>;
+
+// A list of checks run in the order they were added. Witness checks only run
+// for SegWit transactions, after the checks on their non-witness part
+#[derive(Default)]
+pub struct TransactionValidator {
+    checks: Vec<Check>,
+    witness_checks: Vec<WitnessCheck>,
+}
+
+impl TransactionValidator {
+    pub fn new() -> Self {
+        TransactionValidator::default()
+    }
+
+    // Relay policy for transactions without witness data
+    pub fn new_legacy() -> Self {
+        TransactionValidator::new()
+            .require_standard()
+            .require_valid_weight()
+            .require_no_duplicate_inputs()
+    }
+
+    // The legacy rules plus standard witness sizes, a witness for every input
+    // spending a witness program, and a weight covering all the witness data
+    pub fn new_segwit() -> Self {
+        TransactionValidator::new_legacy()
+            .require_standard_witness()
+            .require_witness_for_segwit_inputs()
+            .require_witness_discount()
+    }
+
+    // Add an arbitrary check on the witness data of SegWit transactions
+    pub fn require_witness(
+        mut self,
+        check: impl Fn(&SegWitTransaction, &UtxoSet) -> Option<BitcoinError> + 'static,
+    ) -> Self {
+        self.witness_checks.push(Box::new(check));
+        self
+    }
+
+    // Add an arbitrary check
+    pub fn require(
+        mut self,
+        check: impl Fn(&LegacyTransaction, &UtxoSet) -> Option<BitcoinError> + 'static,
+    ) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    // Default relay policy: version 1 to 3, small push-only scriptSigs, and
+    // standard, non-dust outputs
+    pub fn require_standard(self) -> Self {
+        self.require(|tx, _| check_standard_policy(tx).err())
+    }
+
+    // The full weight for SegWit transactions, not just the non-witness part
+    pub fn require_valid_weight(self) -> Self {
+        self.require(|tx, _| tx.check_standard_weight_limit().err())
+            .require_witness(|tx, _| tx.check_standard_weight_limit().err())
+    }
+
+    // One witness stack per input, no more than Core's standard P2WSH item
+    // count and size, and some witness data at all (otherwise the transaction
+    // should have been serialized in the legacy format)
+    pub fn require_standard_witness(self) -> Self {
+        self.require_witness(|tx, _| check_standard_witness(tx).err())
+    }
+
+    // Inputs spending a native witness program must carry a witness; inputs
+    // whose spent output is not in the UTXO set are not checked
+    pub fn require_witness_for_segwit_inputs(self) -> Self {
+        self.require_witness(|tx, utxo_set| {
+            tx.inputs.iter().enumerate().find_map(|(index, input)| {
+                let utxo = utxo_set.get(&input.previous_output)?;
+                let has_witness = tx.witness.get(index).is_some_and(|stack| !stack.is_empty());
+                (utxo.script_pubkey.witness_version().is_some() && !has_witness).then(|| {
+                    BitcoinError::CheckFailed(format!(
+                        "input {index} spends a witness program without a witness"
+                    ))
+                })
+            })
+        })
+    }
+
+    // The discounted weight must cover the witness data actually held. Only
+    // one stack per input is serialized, so extra stacks go unweighed and
+    // missing ones are weighed as empty stacks the transaction doesn't have
+    pub fn require_witness_discount(self) -> Self {
+        self.require_witness(|tx, _| {
+            let held: usize = tx
+                .witness
+                .iter()
+                .map(|stack| {
+                    compact_size_len(stack.len() as u64)
+                        + stack
+                            .iter()
+                            .map(|item| compact_size_len(item.len() as u64) + item.len())
+                            .sum::<usize>()
+                })
+                .sum();
+            let expected = 4 * tx.base_size() as u64 + 2 + held as u64;
+            (tx.weight() != expected).then(|| {
+                BitcoinError::CheckFailed(format!(
+                    "weight {} does not cover the witness data held, which weighs {expected}",
+                    tx.weight()
+                ))
+            })
+        })
+    }
+
+    pub fn require_no_duplicate_inputs(self) -> Self {
+        self.require(|tx, _| check_duplicate_inputs(tx.inputs()).into_iter().next())
+    }
+
+    // Pays at least `min_fee_rate`; the spent outputs must be in the UTXO set
+    pub fn require_min_fee(self, min_fee_rate: FeeRate) -> Self {
+        self.require(move |tx, utxo_set| match tx.fee_rate(utxo_set) {
+            Ok(fee_rate) if fee_rate < min_fee_rate => Some(BitcoinError::CheckFailed(format!(
+                "fee rate {fee_rate} is below {min_fee_rate}"
+            ))),
+            Ok(_) => None,
+            Err(err) => Some(err),
+        })
+    }
+
+    // Every violation found; empty when the transaction passes
+    pub fn validate(&self, tx: &LegacyTransaction, utxo_set: &UtxoSet) -> Vec<BitcoinError> {
+        self.checks
+            .iter()
+            .filter_map(|check| check(tx, utxo_set))
+            .collect()
+    }
+
+    // As `validate`, for either format; legacy transactions skip the witness
+    // checks
+    pub fn validate_transaction(&self, tx: &Transaction, utxo_set: &UtxoSet) -> Vec<BitcoinError> {
+        match tx {
+            Transaction::Legacy(tx) => self.validate(tx, utxo_set),
+            Transaction::SegWit(tx) => {
+                let mut errors = self.validate(&tx.legacy_view(), utxo_set);
+                errors.extend(
+                    self.witness_checks
+                        .iter()
+                        .filter_map(|check| check(tx, utxo_set)),
+                );
+                errors
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// CompactSize (varint) encoding used for counts and lengths on the wire
+
+use crate::{BitcoinError, ByteCursor};
+
+// Encode a value using the shortest CompactSize form
+pub fn encode_compact_size(n: u64) -> Vec<u8> {
+    match n {
+        0..=0xFC => vec![n as u8],
+        0xFD..=0xFFFF => {
+            let mut out = vec![0xFD];
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+            out
+        }
+        0x10000..=0xFFFF_FFFF => {
+            let mut out = vec![0xFE];
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+            out
+        }
+        _ => {
+            let mut out = vec![0xFF];
+            out.extend_from_slice(&n.to_le_bytes());
+            out
+        }
+    }
+}
+
+// Number of bytes `encode_compact_size(n)` produces, without allocating
+pub fn compact_size_len(n: u64) -> usize {
+    match n {
+        0..=0xFC => 1,
+        0xFD..=0xFFFF => 3,
+        0x10000..=0xFFFF_FFFF => 5,
+        _ => 9,
+    }
+}
+
+// Decode a CompactSize from the start of `data`, returning the value and the
+// number of bytes it took. Values that fit a shorter form are rejected as
+// non-canonical, matching Bitcoin Core.
+pub fn decode_compact_size(data: &[u8]) -> Result<(u64, usize), BitcoinError> {
+    let mut cursor = ByteCursor::new(data);
+    let n = cursor.read_varint()?;
+    Ok((n, cursor.position()))
+}
+
+// A decoded value must use the shortest form that can hold it
+pub(crate) fn check_canonical(n: u64, encoded_len: usize) -> Result<(), BitcoinError> {
+    if compact_size_len(n) != encoded_len {
+        return Err(BitcoinError::InvalidTransaction);
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+// Wallet state: the set of unspent transaction outputs
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::coinselect::Utxo;
+use crate::{
+    Amount, BitcoinDeserialize, BitcoinError, BitcoinSerialize, LegacyTransaction, OutPoint,
+    Script, TransactionReader, TxOutput, Txid, encode_compact_size,
+};
+
+// Start of a saved UTXO set, followed by a format version byte
+const FILE_MAGIC: [u8; 4] = *b"UTXO";
+const FILE_VERSION: u8 = 1;
+// Stored in place of a confirmation height for unconfirmed outputs
+const UNCONFIRMED: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UtxoSet {
+    utxos: HashMap<OutPoint, TxOutput>,
+    // Height of the block that confirmed each output; absent if unconfirmed
+    heights: HashMap<OutPoint, u32>,
+}
+
+impl UtxoSet {
+    pub fn new() -> Self {
+        UtxoSet::default()
+    }
+
+    // Add an unspent output, returning the previous entry for the outpoint.
+    // The output counts as unconfirmed until `confirm` is called.
+    pub fn insert(&mut self, outpoint: OutPoint, output: TxOutput) -> Option<TxOutput> {
+        self.heights.remove(&outpoint);
+        self.utxos.insert(outpoint, output)
+    }
+
+    // Add an output mined in the block at `height`
+    pub fn insert_confirmed(
+        &mut self,
+        outpoint: OutPoint,
+        output: TxOutput,
+        height: u32,
+    ) -> Option<TxOutput> {
+        self.heights.insert(outpoint.clone(), height);
+        self.utxos.insert(outpoint, output)
+    }
+
+    pub fn remove(&mut self, outpoint: &OutPoint) -> Option<TxOutput> {
+        self.heights.remove(outpoint);
+        self.utxos.remove(outpoint)
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&TxOutput> {
+        self.utxos.get(outpoint)
+    }
+
+    pub fn contains(&self, outpoint: &OutPoint) -> bool {
+        self.utxos.contains_key(outpoint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.utxos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utxos.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&OutPoint, &TxOutput)> {
+        self.utxos.iter()
+    }
+
+    pub fn confirmation_height(&self, outpoint: &OutPoint) -> Option<u32> {
+        self.heights.get(outpoint).copied()
+    }
+
+    // Mark the outputs of `txid` still in the set as mined at `height`,
+    // returning how many there were
+    pub fn confirm(&mut self, txid: Txid, height: u32) -> usize {
+        let outpoints: Vec<OutPoint> = self
+            .utxos
+            .keys()
+            .filter(|outpoint| outpoint.txid == txid)
+            .cloned()
+            .collect();
+        for outpoint in &outpoints {
+            self.heights.insert(outpoint.clone(), height);
+        }
+        outpoints.len()
+    }
+
+    // Wallet view of `tx`: spend whichever of its inputs are in the set and
+    // add the outputs paying one of the `watched` scripts, confirmed at
+    // `height` if it has been mined. Unlike `apply_to_utxo_set`, inputs not
+    // in the set are ignored, since a wallet only tracks its own coins.
+    // Returns the outputs spent.
+    pub fn apply_transaction(
+        &mut self,
+        tx: &LegacyTransaction,
+        watched: &HashSet<Script>,
+        height: Option<u32>,
+    ) -> Vec<TxOutput> {
+        let spent = if tx.is_coinbase() {
+            Vec::new()
+        } else {
+            tx.inputs()
+                .iter()
+                .filter_map(|input| self.remove(input.outpoint()))
+                .collect()
+        };
+
+        let txid = Txid(tx.txid());
+        for (vout, output) in tx.outputs().iter().enumerate() {
+            if !watched.contains(output.script_pubkey()) {
+                continue;
+            }
+            let outpoint = OutPoint {
+                txid,
+                vout: vout as u32,
+            };
+            match height {
+                Some(height) => self.insert_confirmed(outpoint, output.clone(), height),
+                None => self.insert(outpoint, output.clone()),
+            };
+        }
+        spent
+    }
+
+    // Total value, confirmed or not
+    pub fn balance(&self) -> Amount {
+        self.utxos.values().fold(Amount::ZERO, |total, output| {
+            total.saturating_add(output.value())
+        })
+    }
+
+    pub fn confirmed_balance(&self) -> Amount {
+        self.utxos
+            .iter()
+            .filter(|(outpoint, _)| self.heights.contains_key(outpoint))
+            .fold(Amount::ZERO, |total, (_, output)| {
+                total.saturating_add(output.value())
+            })
+    }
+
+    // The scripts this set pays to, e.g. to use as the watched set
+    pub fn scripts(&self) -> HashSet<Script> {
+        self.utxos
+            .values()
+            .map(|output| output.script_pubkey().clone())
+            .collect()
+    }
+
+    // Every entry as a `Utxo` for coin selection, ordered by outpoint
+    pub fn to_utxos(&self) -> Vec<Utxo> {
+        let mut utxos: Vec<Utxo> = self
+            .utxos
+            .iter()
+            .map(|(outpoint, output)| Utxo::new(outpoint.clone(), output.clone()))
+            .collect();
+        utxos.sort_by(|a, b| a.outpoint.cmp(&b.outpoint));
+        utxos
+    }
+
+    // Write the set to `path` in the format of `BitcoinSerialize`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BitcoinError> {
+        let mut file = std::fs::File::create(path)?;
+        self.serialize_to_writer(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<UtxoSet, BitcoinError> {
+        UtxoSet::deserialize(&std::fs::read(path)?)
+    }
+}
+
+// Magic and version, an entry count, then for each entry in outpoint order:
+// the outpoint, the confirmation height (u32::MAX if unconfirmed) and the
+// output as serialized in transactions
+impl BitcoinSerialize for UtxoSet {
+    fn serialize(&self) -> Vec<u8> {
+        let mut out = FILE_MAGIC.to_vec();
+        out.push(FILE_VERSION);
+        out.extend(encode_compact_size(self.utxos.len() as u64));
+        let mut outpoints: Vec<&OutPoint> = self.utxos.keys().collect();
+        outpoints.sort();
+        for outpoint in outpoints {
+            out.extend(outpoint.serialize());
+            let height = self.confirmation_height(outpoint).unwrap_or(UNCONFIRMED);
+            out.extend_from_slice(&height.to_le_bytes());
+            out.extend(self.utxos[outpoint].serialize());
+        }
+        out
+    }
+}
+
+impl BitcoinDeserialize for UtxoSet {
+    fn deserialize_from(r: &mut impl Read) -> Result<Self, BitcoinError> {
+        let mut reader = TransactionReader::new(r);
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != FILE_MAGIC || header[4] != FILE_VERSION {
+            return Err(BitcoinError::InvalidWalletFile);
+        }
+
+        let count = reader.read_compact_size()?;
+        let mut set = UtxoSet::new();
+        for _ in 0..count {
+            let outpoint = reader.read_outpoint()?;
+            let mut height = [0u8; 4];
+            reader.read_exact(&mut height)?;
+            let output = reader.read_output()?;
+            let previous = match u32::from_le_bytes(height) {
+                UNCONFIRMED => set.insert(outpoint, output),
+                height => set.insert_confirmed(outpoint, output, height),
+            };
+            if previous.is_some() {
+                return Err(BitcoinError::InvalidWalletFile);
+            }
+        }
+        Ok(set)
+    }
+}
@@ -1,4 +1,7 @@
+mod test_vectors;
+
 use rust_week_4_exercises::*;
+use test_vectors::*;
 
 #[test]
 fn test_point_creation() {
@@ -32,18 +35,18 @@ fn test_builder_new_uses_default() {
 fn test_builder_method_chaining() {
     let builder = LegacyTransactionBuilder::default()
         .version(2)
-        .add_input(TxInput {
-            previous_output: OutPoint {
-                txid: [0; 32],
+        .add_input(TxInput::new(
+            OutPoint {
+                txid: Txid([0; 32]),
                 vout: 0,
             },
-            script_sig: vec![],
-            sequence: 0xFFFFFFFF,
-        })
-        .add_output(TxOutput {
-            value: 50_000_000, // 0.5 BTC
-            script_pubkey: vec![],
-        })
+            Script::default(),
+            0xFFFFFFFF,
+        ))
+        .add_output(TxOutput::new(
+            Amount::from_sat(50_000_000),
+            Script::default(),
+        ))
         .lock_time(500_000);
 
     assert_eq!(builder.version, 2);
@@ -56,23 +59,89 @@ fn test_builder_method_chaining() {
 fn test_builder_build() {
     let tx = LegacyTransactionBuilder::default().version(1).build();
 
-    assert_eq!(tx.version, 1);
-    assert!(tx.inputs.is_empty());
-    assert!(tx.outputs.is_empty());
-    assert_eq!(tx.lock_time, 0);
+    assert_eq!(tx.version(), 1);
+    assert!(tx.inputs().is_empty());
+    assert!(tx.outputs().is_empty());
+    assert_eq!(tx.lock_time(), LockTime(0));
 }
 
 #[test]
 fn test_transaction_serialization() {
-    let tx = LegacyTransaction {
-        version: 1,
-        inputs: Vec::new(),
-        outputs: Vec::new(),
-        lock_time: 0,
-    };
+    let tx = LegacyTransaction::builder().version(1).lock_time(0).build();
 
     let serialized = tx.serialize();
-    assert_eq!(serialized.len(), 8); // 4 bytes version + 4 bytes lock_time
+    // 4 bytes version + 1 byte input count + 1 byte output count + 4 bytes lock_time
+    assert_eq!(serialized.len(), 10);
+    assert_eq!(serialized, [1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_vec_serialization() {
+    let empty: Vec<TxOutput> = Vec::new();
+    assert_eq!(empty.serialize(), vec![0]);
+
+    let outputs = vec![
+        TxOutput::new(Amount::from_sat(1), Script(vec![0x51])),
+        TxOutput::new(Amount::from_sat(2), Script::default()),
+    ];
+    let serialized = outputs.serialize();
+    assert_eq!(serialized[0], 2); // element count
+    assert_eq!(
+        serialized[1..],
+        [outputs[0].serialize(), outputs[1].serialize()].concat()[..]
+    );
+    assert_eq!(serialized.len(), 1 + (8 + 1 + 1) + (8 + 1));
+}
+
+#[test]
+fn test_compact_size_encoding() {
+    assert_eq!(encode_compact_size(0), vec![0x00]);
+    assert_eq!(encode_compact_size(0xFC), vec![0xFC]);
+    assert_eq!(encode_compact_size(0xFD), vec![0xFD, 0xFD, 0x00]);
+    assert_eq!(
+        encode_compact_size(0x1_0000),
+        vec![0xFE, 0x00, 0x00, 0x01, 0x00]
+    );
+    assert_eq!(
+        encode_compact_size(0x1_0000_0000),
+        vec![0xFF, 0, 0, 0, 0, 1, 0, 0, 0]
+    );
+    for n in [0, 0xFC, 0xFD, 0xFFFF, 0x1_0000, 0xFFFF_FFFF, 0x1_0000_0000] {
+        assert_eq!(compact_size_len(n), encode_compact_size(n).len());
+    }
+}
+
+#[test]
+fn test_compact_size_decoding() {
+    for n in [
+        0,
+        0xFC,
+        0xFD,
+        0xFFFF,
+        0x1_0000,
+        0xFFFF_FFFF,
+        0x1_0000_0000,
+        u64::MAX,
+    ] {
+        let mut encoded = encode_compact_size(n);
+        let len = encoded.len();
+        encoded.push(0xAA); // trailing data is left for the caller
+        assert_eq!(decode_compact_size(&encoded).unwrap(), (n, len));
+    }
+
+    // Truncated payloads
+    assert!(decode_compact_size(&[]).is_err());
+    assert!(decode_compact_size(&[0xFD, 0x00]).is_err());
+    assert!(decode_compact_size(&[0xFF, 0, 0, 0, 0, 1, 0, 0]).is_err());
+
+    // Non-canonical: each value fits a shorter form
+    assert!(decode_compact_size(&[0xFD, 0xFC, 0x00]).is_err());
+    assert!(decode_compact_size(&[0xFE, 0xFF, 0xFF, 0x00, 0x00]).is_err());
+    assert!(decode_compact_size(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]).is_err());
+
+    // The transaction reader applies the same rule to its counts
+    let data = [1, 0, 0, 0, 0xFD, 0x00, 0x00, 0, 0, 0, 0, 0];
+    assert!(LegacyTransaction::try_from(&data[..]).is_err());
 }
 
 #[test]
@@ -80,30 +149,49 @@ fn test_transaction_decoding() {
     // Version (1) + inputs count (0) + outputs count (0) + lock_time (0)
     let data = [
         1, 0, 0, 0, // version (i32)
-        0, 0, 0, 0, // inputs count (u32)
-        0, 0, 0, 0, // outputs count (u32)
+        0, // inputs count (CompactSize)
+        0, // outputs count (CompactSize)
         0, 0, 0, 0, // lock_time (u32)
     ];
     let tx = LegacyTransaction::try_from(&data[..]).unwrap();
-    assert_eq!(tx.version, 1);
-    assert_eq!(tx.lock_time, 0);
-    assert_eq!(tx.inputs.len(), 0);
-    assert_eq!(tx.outputs.len(), 0);
+    assert_eq!(tx.version(), 1);
+    assert_eq!(tx.lock_time(), LockTime(0));
+    assert_eq!(tx.inputs().len(), 0);
+    assert_eq!(tx.outputs().len(), 0);
 }
 
 #[test]
 fn test_transaction_decoding_with_inputs() {
-    // Version (1) + inputs count (1) + outputs count (0) + lock_time (0)
-    let data = [
-        1, 0, 0, 0, // version (i32)
-        1, 0, 0, 0, // inputs count (u32)
-        0, 0, 0, 0, // outputs count (u32)
-        0, 0, 0, 0, // lock_time (u32)
-    ];
+    // Version (1) + one input + one output + lock_time (500)
+    let mut data = vec![1, 0, 0, 0, 1];
+    data.extend_from_slice(&[0xab; 32]); // previous txid
+    data.extend_from_slice(&[2, 0, 0, 0]); // vout
+    data.extend_from_slice(&[2, 0x51, 0x52]); // scriptSig
+    data.extend_from_slice(&[0xfe, 0xff, 0xff, 0xff]); // sequence
+    data.push(1);
+    data.extend_from_slice(&1000u64.to_le_bytes()); // value
+    data.extend_from_slice(&[1, 0x51]); // scriptPubKey
+    data.extend_from_slice(&500u32.to_le_bytes()); // lock_time
+
     let tx = LegacyTransaction::try_from(&data[..]).unwrap();
-    assert_eq!(tx.version, 1);
-    assert_eq!(tx.inputs.capacity(), 1); // Verify we reserved space
-    assert_eq!(tx.lock_time, 0);
+    assert_eq!(tx.version(), 1);
+    assert_eq!(tx.inputs().len(), 1);
+    assert_eq!(tx.inputs()[0].outpoint().vout, 2);
+    assert_eq!(tx.inputs()[0].script_sig(), &Script(vec![0x51, 0x52]));
+    assert_eq!(tx.inputs()[0].sequence(), 0xffff_fffe);
+    assert_eq!(tx.outputs()[0].value(), Amount::from_sat(1000));
+    assert_eq!(tx.lock_time(), LockTime(500));
+    assert_eq!(tx.serialize(), data);
+
+    // Every truncation and any trailing byte is rejected
+    for len in 0..data.len() {
+        assert!(matches!(
+            LegacyTransaction::try_from(&data[..len]),
+            Err(BitcoinError::InvalidTransaction)
+        ));
+    }
+    data.push(0);
+    assert!(LegacyTransaction::try_from(&data[..]).is_err());
 }
 
 #[test]
@@ -113,21 +201,127 @@ fn test_transaction_decoding_error() {
     assert!(matches!(result, Err(BitcoinError::InvalidTransaction)));
 }
 
+#[test]
+fn test_transaction_accessors() {
+    let tx = LegacyTransaction::builder()
+        .version(2)
+        .add_output(TxOutput::new(Amount::from_sat(1_000), Script::default()))
+        .lock_time(600_000)
+        .build();
+
+    assert_eq!(tx.version(), 2);
+    assert_eq!(tx.inputs().len(), 0);
+    assert_eq!(tx.outputs().len(), 1);
+    assert_eq!(tx.outputs()[0].value().to_sat(), 1_000);
+    assert_eq!(tx.lock_time(), LockTime::from(600_000));
+    assert_eq!(u32::from(tx.lock_time()), 600_000);
+}
+
+#[test]
+fn test_component_defaults() {
+    let outpoint = OutPoint::default();
+    assert_eq!(outpoint.txid, [0; 32]);
+    assert_eq!(outpoint.vout, 0xFFFFFFFF);
+    assert!(outpoint.is_null());
+
+    let input = TxInput::default();
+    assert!(input.outpoint().is_null());
+    assert!(input.script_sig().0.is_empty());
+    assert_eq!(input.sequence(), 0xFFFFFFFF);
+
+    let output = TxOutput::default();
+    assert_eq!(output.value().to_sat(), 0);
+    assert!(output.script_pubkey().0.is_empty());
+}
+
+#[test]
+fn test_input_output_accessors() {
+    let outpoint = OutPoint {
+        txid: Txid([0x22; 32]),
+        vout: 3,
+    };
+    let mut input = TxInput::new(outpoint.clone(), Script(vec![0x51]), 0xFFFFFFFE);
+    assert_eq!(*input.outpoint(), outpoint);
+    assert_eq!(input.script_sig(), &Script(vec![0x51]));
+    assert_eq!(input.sequence(), 0xFFFFFFFE);
+
+    input.set_script_sig(Script(vec![0x52, 0x53]));
+    input.set_sequence(SEQUENCE_RBF);
+    assert_eq!(input.script_sig().0, vec![0x52, 0x53]);
+    assert_eq!(input.sequence(), SEQUENCE_RBF);
+
+    let output = TxOutput::new(Amount::from_sat(1_234), Script(vec![0x6a]));
+    assert_eq!(output.value(), Amount::from_sat(1_234));
+    assert_eq!(output.script_pubkey(), &Script(vec![0x6a]));
+}
+
 #[test]
 fn test_cli_parsing() {
     let args = vec![
         "send".to_string(),
         "1000".to_string(),
-        "address".to_string(),
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
     ];
     let cmd = parse_cli_args(&args).unwrap();
 
     if let CliCommand::Send { amount, address } = cmd {
-        assert_eq!(amount, 1000);
-        assert_eq!(address, "address");
+        assert_eq!(amount, Amount::from_sat(1000));
+        assert_eq!(address, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
     } else {
         panic!("Wrong command variant");
     }
+
+    let mut with_unit = args.clone();
+    with_unit[1] = "0.001 BTC".to_string();
+    assert!(matches!(
+        parse_cli_args(&with_unit).unwrap(),
+        CliCommand::Send { amount, .. } if amount == Amount::from_sat(100_000)
+    ));
+
+    // The address must decode, with a valid checksum
+    for address in ["address", "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"] {
+        let args = vec!["send".to_string(), "1000".to_string(), address.to_string()];
+        assert!(matches!(
+            parse_cli_args(&args),
+            Err(BitcoinError::CliError {
+                code: ParseErrorCode::InvalidAddress,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn test_address_from_str_and_display() {
+    for s in [
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+        "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+    ] {
+        let address: Address = s.parse().unwrap();
+        assert_eq!(address.to_string(), s);
+    }
+
+    // Other networks parse too, but display as mainnet
+    let testnet: Address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        .parse()
+        .unwrap();
+    assert!(matches!(testnet, Address::P2WPKH(_)));
+    assert_eq!(
+        testnet.to_string_for_network(Network::Testnet),
+        "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+    );
+    assert_eq!(
+        testnet.to_string(),
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+    );
+
+    assert!(
+        "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3"
+            .parse::<Address>()
+            .is_err()
+    );
+    assert!("".parse::<Address>().is_err());
 }
 
 #[test]
@@ -135,12 +329,24 @@ fn test_cli_parsing_errors() {
     // Test missing args
     let args = vec!["send".to_string()];
     let result = parse_cli_args(&args);
-    assert!(matches!(result, Err(BitcoinError::ParseError(_))));
+    assert!(matches!(
+        result,
+        Err(BitcoinError::CliError {
+            code: ParseErrorCode::MissingArgument { name: "amount" },
+            ..
+        })
+    ));
 
     // Test invalid command
     let args = vec!["invalid".to_string()];
     let result = parse_cli_args(&args);
-    assert!(matches!(result, Err(BitcoinError::ParseError(_))));
+    assert!(matches!(
+        result,
+        Err(BitcoinError::CliError {
+            code: ParseErrorCode::UnknownCommand,
+            ..
+        })
+    ));
 }
 
 #[test]
@@ -157,3 +363,5764 @@ fn test_generic_point() {
     assert_eq!(str_point.x, "x");
     assert_eq!(str_point.y, "y");
 }
+
+#[test]
+fn test_script_from_asm_p2pkh() {
+    let asm =
+        "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG";
+    let script = ScriptBuilder::from_asm(asm).unwrap();
+
+    let mut expected = vec![0x76, 0xa9, 0x14];
+    expected.extend(hex::decode("89abcdefabbaabbaabbaabbaabbaabbaabbaabba").unwrap());
+    expected.extend([0x88, 0xac]);
+    assert_eq!(script.0, expected);
+
+    assert_eq!(script.to_asm(), asm);
+    assert_eq!(ScriptBuilder::from_asm(&script.to_asm()).unwrap(), script);
+}
+
+#[test]
+fn test_script_from_asm_case_and_brackets() {
+    let script = ScriptBuilder::from_asm("op_return <deadbeef>").unwrap();
+    assert_eq!(script.0, vec![0x6a, 0x04, 0xde, 0xad, 0xbe, 0xef]);
+
+    let script = ScriptBuilder::from_asm("OP_FALSE OP_TRUE").unwrap();
+    assert_eq!(script.0, vec![0x00, 0x51]);
+}
+
+#[test]
+fn test_script_from_asm_errors() {
+    assert!(matches!(
+        ScriptBuilder::from_asm("OP_DUP OP_NOTANOPCODE"),
+        Err(BitcoinError::InvalidScript)
+    ));
+    assert!(matches!(
+        ScriptBuilder::from_asm("OP_RETURN xyz1"),
+        Err(BitcoinError::InvalidScript)
+    ));
+    assert!(matches!(
+        ScriptBuilder::from_asm("OP_RETURN abc"),
+        Err(BitcoinError::InvalidScript)
+    ));
+    // Multibyte characters within the first three bytes of a token
+    for asm in ["a€b", "H,€Bdo(Cr,"] {
+        assert!(matches!(
+            Script::from_asm(asm),
+            Err(BitcoinError::InvalidScript)
+        ));
+    }
+}
+
+#[test]
+fn test_script_decompile() {
+    let script = Script(vec![0x4c, 0x02, 0xaa, 0xbb, 0x87]);
+    assert_eq!(
+        script.decompile().unwrap(),
+        vec![
+            Instruction::OpData(vec![0xaa, 0xbb]),
+            Instruction::Op(Opcode::OpEqual)
+        ]
+    );
+
+    // Push runs past the end of the script
+    assert!(Script(vec![0x05, 0x01]).decompile().is_err());
+}
+
+#[test]
+fn test_sha256_known_vectors() {
+    assert_eq!(
+        hex::encode(&hash::sha256(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex::encode(&hash::sha256(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        hex::encode(&hash::sha256d(b"hello")),
+        "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+    );
+}
+
+fn sample_segwit_transaction() -> SegWitTransaction {
+    SegWitTransaction {
+        version: 2,
+        inputs: vec![TxInput::new(
+            OutPoint {
+                txid: Txid([0x11; 32]),
+                vout: 1,
+            },
+            Script::default(),
+            0xFFFFFFFD,
+        )],
+        outputs: vec![TxOutput::new(
+            Amount::from_sat(90_000),
+            Script(vec![0x00, 0x14, 0x22, 0x22]),
+        )],
+        witness: vec![vec![vec![0x30, 0x01], vec![0x02; 33]]],
+        lock_time: LockTime::ZERO,
+    }
+}
+
+#[test]
+fn test_reader_detects_segwit() {
+    let segwit = sample_segwit_transaction();
+    let bytes = segwit.serialize();
+    assert_eq!(bytes[4..6], [0x00, 0x01]);
+
+    let mut reader = TransactionReader::new(&bytes[..]);
+    match reader.read_any_transaction().unwrap() {
+        Transaction::SegWit(tx) => {
+            assert_eq!(tx, segwit);
+            assert_ne!(tx.txid(), tx.wtxid());
+        }
+        Transaction::Legacy(_) => panic!("expected a SegWit transaction"),
+    }
+}
+
+#[test]
+fn test_reader_dispatches_legacy() {
+    let segwit = sample_segwit_transaction();
+    let legacy = LegacyTransaction::builder()
+        .version(segwit.version)
+        .add_input(segwit.inputs[0].clone())
+        .add_output(segwit.outputs[0].clone())
+        .build();
+    let bytes = legacy.serialize();
+
+    let any = TransactionReader::new(&bytes[..])
+        .read_any_transaction()
+        .unwrap();
+    assert!(matches!(any, Transaction::Legacy(_)));
+    // Stripping the witness leaves the txid unchanged
+    assert_eq!(any.txid(), legacy.txid());
+    assert_eq!(any.txid(), segwit.txid());
+    assert_eq!(any.wtxid(), any.txid());
+    assert_eq!(Transaction::from(segwit.clone()).wtxid(), segwit.wtxid());
+
+    // Each concrete type only accepts its own encoding
+    assert_eq!(
+        SegWitTransaction::deserialize(&segwit.serialize()).unwrap(),
+        segwit
+    );
+    assert!(SegWitTransaction::deserialize(&bytes).is_err());
+    assert!(LegacyTransaction::deserialize(&segwit.serialize()).is_err());
+
+    // Two transactions back to back in one stream
+    let stream = [bytes.clone(), segwit.serialize()].concat();
+    let mut reader = TransactionReader::new(&stream[..]);
+    assert!(matches!(
+        reader.read_any_transaction(),
+        Ok(Transaction::Legacy(_))
+    ));
+    assert!(matches!(
+        reader.read_any_transaction(),
+        Ok(Transaction::SegWit(_))
+    ));
+}
+
+#[test]
+fn test_reader_truncated_input() {
+    let bytes = sample_segwit_transaction().serialize();
+    let mut reader = TransactionReader::new(&bytes[..bytes.len() - 1]);
+    assert!(matches!(
+        reader.read_any_transaction(),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn test_script_witness_version() {
+    let mut p2wpkh = vec![0x00, 0x14];
+    p2wpkh.extend([0xab; 20]);
+    let script = Script(p2wpkh);
+    assert_eq!(script.witness_version(), Some(0));
+    assert_eq!(script.witness_program(), Some(&[0xab; 20][..]));
+
+    let mut p2tr = vec![0x51, 0x20];
+    p2tr.extend([0xcd; 32]);
+    assert_eq!(Script(p2tr).witness_version(), Some(1));
+
+    let mut v16 = vec![0x60, 0x28];
+    v16.extend([0x01; 40]);
+    assert_eq!(Script(v16).witness_version(), Some(16));
+
+    // Program too short, too long, length mismatch, and not a version opcode
+    assert_eq!(Script(vec![0x00, 0x01, 0xff]).witness_version(), None);
+    let mut too_long = vec![0x00, 0x29];
+    too_long.extend([0x01; 41]);
+    assert_eq!(Script(too_long).witness_version(), None);
+    assert_eq!(Script(vec![0x00, 0x14, 0x01]).witness_program(), None);
+    assert_eq!(Script(vec![0x76, 0x02, 0x01, 0x02]).witness_version(), None);
+    assert_eq!(Script(vec![]).witness_version(), None);
+}
+
+fn two_output_transaction() -> LegacyTransaction {
+    LegacyTransaction::builder()
+        .add_input(TxInput::default())
+        .add_output(TxOutput::new(Amount::from_sat(1_000), Script(vec![0x51])))
+        .add_output(TxOutput::new(Amount::from_sat(2_000), Script(vec![0x52])))
+        .build()
+}
+
+#[test]
+fn test_with_output_replaced() {
+    let tx = two_output_transaction();
+    let null_output = TxOutput::new(Amount::from_sat(u64::MAX), Script::default());
+
+    let replaced = tx.with_output_replaced(0, null_output.clone()).unwrap();
+    assert_eq!(replaced.outputs()[0], null_output);
+    assert_eq!(replaced.outputs()[1], tx.outputs()[1]);
+    // The original transaction is untouched
+    assert_eq!(tx.outputs()[0].value().to_sat(), 1_000);
+
+    assert!(matches!(
+        tx.with_output_replaced(2, null_output),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn test_truncated_to_outputs() {
+    let tx = two_output_transaction();
+
+    let truncated = tx.truncated_to_outputs(1);
+    assert_eq!(truncated.outputs().len(), 1);
+    assert_eq!(truncated.outputs()[0], tx.outputs()[0]);
+    assert_eq!(tx.outputs().len(), 2);
+
+    assert_eq!(tx.truncated_to_outputs(5).outputs().len(), 2);
+    assert!(tx.truncated_to_outputs(0).outputs().is_empty());
+}
+
+#[test]
+fn test_disabled_opcodes() {
+    assert!(Opcode::OpCat.is_disabled());
+    assert!(Opcode::OpRShift.is_disabled());
+    assert!(!Opcode::OpCheckSig.is_disabled());
+    assert!(!Opcode::OpAdd.is_disabled());
+
+    let cat_script = ScriptBuilder::from_asm("01 02 OP_CAT").unwrap();
+    assert!(cat_script.contains_disabled_opcode().unwrap());
+    assert!(
+        !ScriptBuilder::from_asm("OP_DUP OP_DROP")
+            .unwrap()
+            .contains_disabled_opcode()
+            .unwrap()
+    );
+    assert!(Script(vec![0x4c]).contains_disabled_opcode().is_err());
+}
+
+#[test]
+fn test_contains_invalid_scripts() {
+    let tx = two_output_transaction();
+    assert!(!tx.contains_invalid_scripts());
+
+    let mut tx_with_cat = tx.clone();
+    tx_with_cat.outputs_mut()[1] = TxOutput::new(Amount::ZERO, Script(vec![0x7e]));
+    assert!(tx_with_cat.contains_invalid_scripts());
+
+    let mut tx_truncated_push = tx;
+    tx_truncated_push.inputs_mut()[0].set_script_sig(Script(vec![0x02, 0x01]));
+    assert!(tx_truncated_push.contains_invalid_scripts());
+}
+
+fn funding_outpoint(n: u8) -> OutPoint {
+    OutPoint {
+        txid: Txid([n; 32]),
+        vout: n as u32,
+    }
+}
+
+#[test]
+fn test_absolute_fee_and_fee_rate() {
+    let mut utxo_set = UtxoSet::new();
+    utxo_set.insert(
+        funding_outpoint(1),
+        TxOutput::new(Amount::from_sat(10_000), Script(vec![0x51])),
+    );
+    utxo_set.insert(
+        funding_outpoint(2),
+        TxOutput::new(Amount::from_sat(5_000), Script(vec![0x51])),
+    );
+
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(2)))
+        .add_output(TxOutput::new(Amount::from_sat(14_000), Script(vec![0x51])))
+        .build();
+
+    assert_eq!(tx.absolute_fee(&utxo_set).unwrap(), Amount::from_sat(1_000));
+
+    // 4 + 1 + 2 * 41 + 1 + 10 + 4 = 102 vbytes
+    let vsize = tx.serialize().len() as u64;
+    assert_eq!(vsize, 102);
+    assert_eq!(
+        tx.fee_rate(&utxo_set).unwrap(),
+        FeeRate::from_sat_per_kvb(1_000 * 1000 / 102)
+    );
+    assert_eq!(tx.fee_rate(&utxo_set).unwrap().to_sat_per_vb(), 9);
+}
+
+#[test]
+fn test_absolute_fee_missing_utxo() {
+    let utxo_set = UtxoSet::new();
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(3)))
+        .build();
+
+    assert!(matches!(
+        tx.absolute_fee(&utxo_set),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+    assert!(tx.fee_rate(&utxo_set).is_err());
+}
+
+fn block_with_fee(coinbase_value: u64) -> (Block, UtxoSet) {
+    let mut utxo_set = UtxoSet::new();
+    utxo_set.insert(
+        funding_outpoint(7),
+        TxOutput::new(Amount::from_sat(20_000), Script(vec![0x51])),
+    );
+
+    let coinbase = LegacyTransaction::builder()
+        .add_input(TxInput::default())
+        .add_output(TxOutput::new(
+            Amount::from_sat(coinbase_value),
+            Script(vec![0x51]),
+        ))
+        .build();
+    let spend = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(7)))
+        .add_output(TxOutput::new(Amount::from_sat(15_000), Script(vec![0x51])))
+        .build();
+
+    let block = Block {
+        header: BlockHeader {
+            version: 1,
+            prev_blockhash: [0; 32],
+            merkle_root: [0; 32],
+            time: 0,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        },
+        transactions: vec![coinbase.into(), spend.into()],
+    };
+    (block, utxo_set)
+}
+
+#[test]
+fn test_block_total_fees_and_claim() {
+    let (block, utxo_set) = block_with_fee(625_000_000);
+    assert!(block.transactions[0].is_coinbase());
+    assert!(!block.transactions[1].is_coinbase());
+    assert_eq!(
+        block.total_fees(&utxo_set).unwrap(),
+        Amount::from_sat(5_000)
+    );
+    assert_eq!(
+        block.coinbase_claim().unwrap(),
+        Amount::from_sat(625_000_000)
+    );
+}
+
+#[test]
+fn test_block_validate_subsidy() {
+    assert_eq!(block::block_subsidy(0), Amount::from_sat(5_000_000_000));
+    assert_eq!(block::block_subsidy(630_000), Amount::from_sat(625_000_000));
+    assert_eq!(block::block_subsidy(64 * 210_000), Amount::from_sat(0));
+
+    // Height 630_000: 6.25 BTC subsidy plus 5_000 sat of fees
+    let (block, utxo_set) = block_with_fee(625_005_000);
+    assert!(block.validate_subsidy(630_000, &utxo_set).is_ok());
+
+    let (block, utxo_set) = block_with_fee(625_005_001);
+    assert!(matches!(
+        block.validate_subsidy(630_000, &utxo_set),
+        Err(BitcoinError::InvalidAmount)
+    ));
+    // The same claim is fine before the third halving
+    assert!(block.validate_subsidy(420_000, &utxo_set).is_ok());
+}
+
+#[test]
+fn test_segwit_input_mut_witness() {
+    let mut tx = sample_segwit_transaction();
+    tx.inputs.push(TxInput::default());
+
+    {
+        let mut input = tx.input_mut(0).unwrap();
+        assert_eq!(input.witness_items().len(), 2);
+        input.clear_witness();
+        assert!(input.witness_items().is_empty());
+        input.push_witness(vec![0xaa]);
+        input.push_witness(vec![0xbb]);
+        assert_eq!(input.witness_items(), &[vec![0xaa], vec![0xbb]]);
+    }
+
+    // The second input had no witness stack yet
+    tx.input_mut(1).unwrap().set_witness(vec![vec![0x01; 3]]);
+    assert_eq!(
+        tx.witness,
+        vec![vec![vec![0xaa], vec![0xbb]], vec![vec![0x01; 3]]]
+    );
+    assert!(tx.input_mut(1).unwrap().input().outpoint().is_null());
+
+    assert!(matches!(
+        tx.input_mut(2),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn test_to_json() {
+    let mut txid = [0u8; 32];
+    txid[0] = 0xab;
+    let tx = LegacyTransaction::builder()
+        .version(2)
+        .add_input(TxInput::new(
+            OutPoint {
+                txid: Txid(txid),
+                vout: 1,
+            },
+            Script(vec![0x51]),
+            0xFFFFFFFE,
+        ))
+        .add_output(TxOutput::new(
+            Amount::from_sat(5_000),
+            Script(vec![0x6a, 0x01, 0x02]),
+        ))
+        .lock_time(10)
+        .build();
+
+    let mut display_txid = tx.txid();
+    display_txid.reverse();
+    let expected = format!(
+        r#"{{
+  "txid": "{}",
+  "version": 2,
+  "locktime": 10,
+  "inputs": [
+    {{
+      "txid": "{}ab",
+      "vout": 1,
+      "script_sig": "51",
+      "sequence": 4294967294
+    }}
+  ],
+  "outputs": [
+    {{
+      "value": 5000,
+      "script_pubkey": "6a0102"
+    }}
+  ]
+}}"#,
+        hex::encode(&display_txid),
+        "00".repeat(31)
+    );
+    assert_eq!(tx.to_json(), expected);
+}
+
+#[test]
+fn test_to_json_empty_lists() {
+    let json = LegacyTransaction::builder().build().to_json();
+    assert!(json.contains("\"inputs\": [],"));
+    assert!(json.ends_with("\"outputs\": []\n}"));
+}
+
+#[test]
+fn test_to_json_output_address() {
+    // BIP-173 P2WPKH test vector
+    let hash: [u8; 20] = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(1000),
+            Script::new_p2wpkh(&hash),
+        ))
+        .add_output(TxOutput::new(Amount::ZERO, Script(vec![0x6a])))
+        .build();
+    let json = tx.to_json();
+    assert!(json.contains(
+        "\"script_pubkey\": \"0014751e76e8199196d454941c45d1b3a323f1433bd6\",\n      \"address\": \"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4\"\n"
+    ));
+    // OP_RETURN has no address form
+    assert_eq!(json.matches("\"address\"").count(), 1);
+    assert_eq!(
+        Address::from_script(&Script::new_p2wpkh(&hash)),
+        Some(Address::P2WPKH(hash))
+    );
+}
+
+#[test]
+fn test_pay_to_pubkey() {
+    let compressed = [0x02; 33];
+    let script = Script::pay_to_pubkey(&compressed).unwrap();
+    assert_eq!(script.len(), 35);
+    assert_eq!(script.0[0], 33);
+    assert_eq!(*script.0.last().unwrap(), 0xac);
+    assert!(script.is_p2pk());
+    assert_eq!(script.extract_p2pk_pubkey(), Some(&compressed[..]));
+
+    let uncompressed = [0x04; 65];
+    let script = Script::pay_to_pubkey(&uncompressed).unwrap();
+    assert_eq!(script.extract_p2pk_pubkey(), Some(&uncompressed[..]));
+
+    assert!(matches!(
+        Script::pay_to_pubkey(&[0x02; 32]),
+        Err(BitcoinError::InvalidScript)
+    ));
+    assert!(!Script(vec![0x76, 0xac]).is_p2pk());
+}
+
+#[test]
+fn test_output_script_type() {
+    let output = |script_pubkey: Vec<u8>| TxOutput::new(Amount::from_sat(0), Script(script_pubkey));
+
+    let p2pk = Script::pay_to_pubkey(&[0x03; 33]).unwrap();
+    assert_eq!(output(p2pk.0).script_type(), OutputScriptType::P2PK);
+
+    let p2pkh = [vec![0x76, 0xa9, 0x14], vec![0; 20], vec![0x88, 0xac]].concat();
+    assert_eq!(output(p2pkh).script_type(), OutputScriptType::P2PKH);
+
+    let p2sh = [vec![0xa9, 0x14], vec![0; 20], vec![0x87]].concat();
+    assert_eq!(output(p2sh).script_type(), OutputScriptType::P2SH);
+
+    let p2wpkh = [vec![0x00, 0x14], vec![0; 20]].concat();
+    assert_eq!(output(p2wpkh).script_type(), OutputScriptType::P2WPKH);
+
+    let p2wsh = [vec![0x00, 0x20], vec![0; 32]].concat();
+    assert_eq!(output(p2wsh).script_type(), OutputScriptType::P2WSH);
+
+    let p2tr = [vec![0x51, 0x20], vec![0; 32]].concat();
+    assert_eq!(output(p2tr).script_type(), OutputScriptType::P2TR);
+
+    assert_eq!(
+        output(vec![0x6a, 0x01, 0x00]).script_type(),
+        OutputScriptType::OpReturn
+    );
+    assert_eq!(
+        output(vec![0x51]).script_type(),
+        OutputScriptType::NonStandard
+    );
+}
+
+#[test]
+fn test_estimated_size_matches_serialization() {
+    let empty = LegacyTransaction::builder().build();
+    assert_eq!(empty.estimated_size(), empty.serialize().len());
+
+    let tx = two_output_transaction();
+    assert_eq!(tx.estimated_size(), tx.serialize().len());
+
+    // Scripts long enough to need multi-byte CompactSize lengths
+    let mut large = LegacyTransaction::builder()
+        .add_input(TxInput::new(
+            OutPoint::default(),
+            Script(vec![0x01; 300]),
+            0xFFFFFFFF,
+        ))
+        .add_output(TxOutput::new(Amount::from_sat(1), Script(vec![0x02; 253])))
+        .build();
+    for _ in 0..260 {
+        large.outputs_mut().push(TxOutput::default());
+    }
+    assert_eq!(large.estimated_size(), large.serialize().len());
+    assert_eq!(large.weight(), large.serialize().len() as u64 * 4);
+}
+
+#[test]
+fn test_size_aliases_agree() {
+    let mainnet =
+        LegacyTransaction::deserialize(&hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap())
+            .unwrap();
+    assert_eq!(
+        mainnet.serialized_size(),
+        FIRST_BITCOIN_TRANSFER_TX_HEX.len() / 2
+    );
+    for tx in [
+        LegacyTransaction::builder().build(),
+        two_output_transaction(),
+        mainnet,
+    ] {
+        assert_eq!(tx.compact_size(), tx.estimated_size());
+        assert_eq!(tx.compact_size(), tx.serialized_size());
+    }
+}
+
+#[test]
+fn test_bitcoin_error_is_send_sync() {
+    fn assert_error<T: std::error::Error + Send + Sync + 'static>() {}
+    assert_error::<BitcoinError>();
+
+    // Boxing keeps the error usable across threads
+    let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(BitcoinError::cli_error(
+        ParseErrorCode::InvalidArgument,
+        "bad input",
+    ));
+    let message = std::thread::spawn(move || boxed.to_string())
+        .join()
+        .unwrap();
+    assert_eq!(message, "Parse error: bad input");
+}
+
+#[cfg(feature = "anyhow")]
+#[test]
+fn test_bitcoin_error_into_anyhow() {
+    let err = BitcoinError::InvalidScript.into_anyhow();
+    assert_eq!(err.to_string(), "Invalid script format");
+    assert!(matches!(
+        err.downcast_ref::<BitcoinError>(),
+        Some(BitcoinError::InvalidScript)
+    ));
+}
+
+#[test]
+fn test_builder_from_transaction() {
+    let tx = two_output_transaction();
+
+    let unchanged = LegacyTransactionBuilder::from_transaction(&tx).build();
+    assert_eq!(unchanged.serialize(), tx.serialize());
+
+    let modified = LegacyTransactionBuilder::from_transaction(&tx)
+        .lock_time(800_000)
+        .add_output(TxOutput::new(Amount::from_sat(3_000), Script(vec![0x53])))
+        .build();
+    assert_eq!(modified.version(), tx.version());
+    assert_eq!(modified.inputs(), tx.inputs());
+    assert_eq!(modified.outputs().len(), 3);
+    assert_eq!(modified.outputs()[..2], tx.outputs()[..]);
+    assert_eq!(modified.lock_time(), LockTime(800_000));
+}
+
+fn inspect(raw_hex: &str, field: &str, extra: &[&str]) -> Result<String, BitcoinError> {
+    let mut args = vec![
+        "inspect".to_string(),
+        raw_hex.to_string(),
+        field.to_string(),
+    ];
+    args.extend(extra.iter().map(|s| s.to_string()));
+    Ok(parse_cli_args(&args)?.execute()?.to_text())
+}
+
+#[test]
+fn test_cli_inspect_fields() {
+    let tx = LegacyTransaction::builder()
+        .version(2)
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(9_000), Script(vec![0x51])))
+        .lock_time(123)
+        .build();
+    let raw_hex = hex::encode(&tx.serialize());
+
+    assert_eq!(inspect(&raw_hex, "version", &[]).unwrap(), "2");
+    assert_eq!(inspect(&raw_hex, "lock_time", &[]).unwrap(), "123");
+    assert_eq!(inspect(&raw_hex, "input_count", &[]).unwrap(), "1");
+    assert_eq!(inspect(&raw_hex, "output_count", &[]).unwrap(), "1");
+    assert_eq!(inspect(&raw_hex, "weight", &[]).unwrap(), "244");
+
+    let mut txid = tx.txid();
+    txid.reverse();
+    assert_eq!(inspect(&raw_hex, "txid", &[]).unwrap(), hex::encode(&txid));
+
+    // 1_000 sat over 61 vbytes
+    assert_eq!(
+        inspect(&raw_hex, "fee_rate", &["--input-value", "10000"]).unwrap(),
+        "16.393 sat/vB"
+    );
+    assert!(inspect(&raw_hex, "fee_rate", &[]).is_err());
+}
+
+#[test]
+fn test_cli_inspect_errors() {
+    let args = vec![
+        "inspect".to_string(),
+        "00".to_string(),
+        "colour".to_string(),
+    ];
+    match parse_cli_args(&args) {
+        Err(BitcoinError::CliError { code, message }) => {
+            assert_eq!(code, ParseErrorCode::InvalidArgument);
+            assert_eq!(message, "unknown field");
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    let args = vec!["inspect".to_string(), "00".to_string()];
+    assert!(matches!(
+        parse_cli_args(&args),
+        Err(BitcoinError::CliError { .. })
+    ));
+
+    assert!(inspect("zz", "version", &[]).is_err());
+    assert!(inspect("0100", "version", &[]).is_err());
+}
+
+#[test]
+fn test_normalize_pushes() {
+    // OP_PUSHDATA1 0x01 0x42 fits a single-byte direct push
+    let script = Script(vec![0x4c, 0x01, 0x42, 0x87]);
+    assert!(!script.has_minimal_pushes());
+    let normalized = script.normalize_pushes().unwrap();
+    assert_eq!(normalized.0, vec![0x01, 0x42, 0x87]);
+    assert!(normalized.has_minimal_pushes());
+
+    // Small numbers and empty data use their dedicated opcodes
+    let script = Script(vec![0x01, 0x05, 0x4c, 0x00, 0x01, 0x81]);
+    assert!(!script.has_minimal_pushes());
+    assert_eq!(script.normalize_pushes().unwrap().0, vec![0x55, 0x00, 0x4f]);
+
+    // Already minimal scripts are unchanged
+    let p2pkh = ScriptBuilder::from_asm(
+        "OP_DUP OP_HASH160 0000000000000000000000000000000000000000 OP_EQUALVERIFY OP_CHECKSIG",
+    )
+    .unwrap();
+    assert!(p2pkh.has_minimal_pushes());
+    assert_eq!(p2pkh.normalize_pushes().unwrap(), p2pkh);
+
+    let mut long_push = vec![0x4d, 0x4c, 0x00];
+    long_push.extend([0xaa; 0x4c]);
+    assert_eq!(
+        Script(long_push).normalize_pushes().unwrap().0[..2],
+        [0x4c, 0x4c]
+    );
+}
+
+#[test]
+fn test_normalize_pushes_errors() {
+    assert!(matches!(
+        Script(vec![0x4c, 0x01, 0x42, 0x7e]).normalize_pushes(),
+        Err(BitcoinError::InvalidScript)
+    ));
+    assert!(Script(vec![0x4c]).normalize_pushes().is_err());
+    assert!(!Script(vec![0x4c]).has_minimal_pushes());
+}
+
+#[test]
+fn test_segwit_size_components() {
+    let tx = sample_segwit_transaction();
+    let (base_size, witness_size) = tx.segwit_size_components();
+
+    // version + 1 input (41) + 1 output (8 + 1 + 4) + lock_time
+    assert_eq!(base_size, 4 + 1 + 41 + 1 + 13 + 4);
+    // marker + flag + item count + (1 + 2) + (1 + 33)
+    assert_eq!(witness_size, 2 + 1 + 3 + 34);
+    assert_eq!(base_size + witness_size, tx.serialize().len());
+
+    assert_eq!(tx.weight(), (base_size * 4 + witness_size) as u64);
+    assert_eq!(tx.weight(), 296);
+    assert_eq!(tx.vbytes(), 74);
+
+    // Rounds up when the weight is not a multiple of four
+    let mut odd = tx.clone();
+    odd.witness[0].push(vec![0x01]);
+    assert_eq!(odd.weight(), 298);
+    assert_eq!(odd.vbytes(), 75);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_outpoint_serde_display_order() {
+    let mut txid = [0u8; 32];
+    txid[0] = 0x01;
+    txid[31] = 0xff;
+    let outpoint = OutPoint {
+        txid: Txid(txid),
+        vout: 3,
+    };
+
+    let json = serde_json::to_string(&outpoint).unwrap();
+    let expected_txid = format!("ff{}01", "00".repeat(30));
+    assert_eq!(json, format!(r#"{{"txid":"{expected_txid}","vout":3}}"#));
+
+    let parsed: OutPoint = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, outpoint);
+
+    assert!(serde_json::from_str::<OutPoint>(r#"{"txid":"abcd","vout":0}"#).is_err());
+    assert!(serde_json::from_str::<OutPoint>(r#"{"txid":"zz","vout":0}"#).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_outpoint_deserialize_string_or_object() {
+    let txid = FIRST_BITCOIN_TRANSFER_TXID;
+    let from_object: OutPoint =
+        serde_json::from_str(&format!(r#"{{"txid":"{txid}","vout":1}}"#)).unwrap();
+    let from_string: OutPoint = serde_json::from_str(&format!(r#""{txid}:1""#)).unwrap();
+    assert_eq!(from_object, from_string);
+    assert_eq!(from_string, OutPoint::from_txid_hex(txid, 1).unwrap());
+
+    assert!(serde_json::from_str::<OutPoint>(&format!(r#""{txid}""#)).is_err());
+    assert!(serde_json::from_str::<OutPoint>(&format!(r#""{txid}:x""#)).is_err());
+    assert!(serde_json::from_str::<OutPoint>(r#""abcd:0""#).is_err());
+    assert!(serde_json::from_str::<OutPoint>("7").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_transaction_serde_round_trip() {
+    let tx = LegacyTransaction::deserialize(&hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap())
+        .unwrap();
+    let json = serde_json::to_value(&tx).unwrap();
+    assert_eq!(json["txid"], FIRST_BITCOIN_TRANSFER_TXID);
+    assert_eq!(json["vin"][0]["vout"], 0);
+    assert_eq!(json["vout"][1]["n"], 1);
+    assert_eq!(json["vout"][0]["value"], 10.0);
+    let parsed: LegacyTransaction = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, tx);
+
+    // Inputs and outputs on their own use the same shapes, minus `n`
+    let input_json = serde_json::to_value(&tx.inputs()[0]).unwrap();
+    assert_eq!(input_json["txid"], tx.inputs()[0].outpoint().txid_hex());
+    assert_eq!(
+        serde_json::from_value::<TxInput>(input_json).unwrap(),
+        tx.inputs()[0]
+    );
+    let output_json = serde_json::to_value(&tx.outputs()[0]).unwrap();
+    assert!(output_json.get("n").is_none());
+    assert_eq!(
+        serde_json::from_value::<TxOutput>(output_json).unwrap(),
+        tx.outputs()[0]
+    );
+    let coinbase = serde_json::to_value(TxInput::default()).unwrap();
+    assert!(coinbase.get("coinbase").is_some());
+
+    assert!(serde_json::from_str::<LegacyTransaction>(r#"{"version":1}"#).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_cli_command_serde() {
+    let json = r#"{"command":"estimate","raw_hex":"00","fee_rate":2.5,"target_blocks":6}"#;
+    let command: CliCommand = serde_json::from_str(json).unwrap();
+    match &command {
+        CliCommand::Estimate { fee_rate, .. } => {
+            assert_eq!(*fee_rate, Some(FeeRate::from_sat_per_kvb(2_500)))
+        }
+        other => panic!("expected Estimate, got {other:?}"),
+    }
+    assert_eq!(serde_json::to_string(&command).unwrap(), json);
+
+    let command: CliCommand =
+        serde_json::from_str(r#"{"command":"genkey","network":"regtest"}"#).unwrap();
+    assert!(matches!(
+        command,
+        CliCommand::GenerateKey {
+            network: Network::Regtest
+        }
+    ));
+    assert!(
+        serde_json::from_str::<CliCommand>(r#"{"command":"genkey","network":"moon"}"#).is_err()
+    );
+    assert!(serde_json::from_str::<CliCommand>(r#"{"command":"fly"}"#).is_err());
+}
+
+fn secret_key(n: u8) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key[31] = n;
+    key
+}
+
+#[test]
+fn test_hmac_sha256_rfc4231() {
+    // RFC 4231 test case 2
+    assert_eq!(
+        hex::encode(&hash::hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[test]
+fn test_hmac_sha512_rfc4231() {
+    assert_eq!(
+        hex::encode(&hash::sha512(b"abc")),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+    // RFC 4231 test case 2
+    assert_eq!(
+        hex::encode(&hash::hmac_sha512(b"Jefe", b"what do ya want for nothing?")),
+        "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+         9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+    );
+}
+
+#[test]
+fn test_bip32_derivation_and_serialization() {
+    // BIP-32 test vector 1
+    let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+    let master = ExtendedPrivKey::new_master(Network::Mainnet, &seed).unwrap();
+    assert_eq!(
+        master.to_string(),
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"
+    );
+    assert_eq!(
+        master.to_extended_pub_key().to_string(),
+        "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8"
+    );
+
+    let path: DerivationPath = "m/0'/1".parse().unwrap();
+    assert_eq!(path.0, vec![bip32::HARDENED, 1]);
+    let child = master.derive_path(&path).unwrap();
+    assert_eq!(
+        child.to_string(),
+        "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs"
+    );
+    let xpub = child.to_extended_pub_key();
+    assert_eq!(
+        xpub.to_string(),
+        "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ"
+    );
+
+    // Public derivation matches private derivation for non-hardened steps
+    let hardened = master.derive_child(bip32::HARDENED).unwrap();
+    assert_eq!(
+        hardened.to_extended_pub_key().derive_child(1).unwrap(),
+        xpub
+    );
+    assert!(matches!(
+        xpub.derive_child(bip32::HARDENED | 3),
+        Err(BitcoinError::HardenedDerivationFromPublicKey(_))
+    ));
+    let receive = xpub.derive_path(&DerivationPath(vec![0, 5])).unwrap();
+    assert_eq!(
+        receive.public_key,
+        child
+            .derive_path(&DerivationPath(vec![0, 5]))
+            .unwrap()
+            .private_key
+            .public_key()
+    );
+    assert_eq!(receive.depth, 4);
+
+    assert_eq!(child.to_string().parse::<ExtendedPrivKey>().unwrap(), child);
+    assert_eq!(xpub.to_string().parse::<ExtendedPubKey>().unwrap(), xpub);
+    let tprv = ExtendedPrivKey::new_master(Network::Testnet, &seed).unwrap();
+    assert!(tprv.to_string().starts_with("tprv"));
+    assert_eq!(tprv.to_string().parse::<ExtendedPrivKey>().unwrap(), tprv);
+    // An xpub is not an xprv, and a master key cannot have a parent
+    assert!(xpub.to_string().parse::<ExtendedPrivKey>().is_err());
+    let mut orphan = master.encode();
+    orphan[5] = 1;
+    assert!(ExtendedPrivKey::decode(&orphan).is_err());
+    assert!(ExtendedPrivKey::new_master(Network::Mainnet, &[0; 15]).is_err());
+
+    let path: DerivationPath = "m/84'/0'/0'/0/5".parse().unwrap();
+    assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+    assert_eq!(
+        "m/84h/0H".parse::<DerivationPath>().unwrap().to_string(),
+        "m/84'/0'"
+    );
+    assert_eq!(
+        "m".parse::<DerivationPath>().unwrap(),
+        DerivationPath::default()
+    );
+    for bad in [
+        "",
+        "84'/0",
+        "m/",
+        "m//1",
+        "m/-1",
+        "m/+1",
+        "m/2147483648",
+        "m/x'",
+    ] {
+        assert!(
+            matches!(
+                bad.parse::<DerivationPath>(),
+                Err(BitcoinError::InvalidDerivationPath)
+            ),
+            "{bad}"
+        );
+    }
+}
+
+#[test]
+fn test_secp256k1_public_key() {
+    assert_eq!(
+        hex::encode(&secp256k1::public_key_compressed(&secret_key(1)).unwrap()),
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    );
+    assert_eq!(
+        hex::encode(&secp256k1::public_key_compressed(&secret_key(3)).unwrap()),
+        "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+    );
+    assert!(secp256k1::public_key_compressed(&[0u8; 32]).is_err());
+    assert!(secp256k1::public_key_compressed(&[0xff; 32]).is_err());
+}
+
+#[test]
+fn test_secp256k1_sign_rfc6979() {
+    let msg_hash = hash::sha256(b"Satoshi Nakamoto");
+    let signature = secp256k1::sign(&secret_key(1), &msg_hash).unwrap();
+    assert_eq!(
+        hex::encode(&signature.r.to_be_bytes()),
+        "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8"
+    );
+    assert_eq!(
+        hex::encode(&signature.s.to_be_bytes()),
+        "2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5"
+    );
+
+    let public_key = secp256k1::public_key(&secret_key(1)).unwrap();
+    assert!(secp256k1::verify(&public_key, &msg_hash, &signature));
+    assert!(!secp256k1::verify(
+        &public_key,
+        &hash::sha256(b"other"),
+        &signature
+    ));
+
+    let der = signature.to_der();
+    assert_eq!(secp256k1::Signature::from_der(&der).unwrap(), signature);
+}
+
+#[test]
+fn test_cli_rawsign() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(9_000), Script(vec![0x51])))
+        .build();
+    let prev_script = [vec![0x76, 0xa9, 0x14], vec![0x11; 20], vec![0x88, 0xac]].concat();
+
+    let args: Vec<String> = [
+        "rawsign",
+        &hex::encode(&tx.serialize()),
+        &hex::encode(&secret_key(7)),
+        "--script",
+        &hex::encode(&prev_script),
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let cmd = parse_cli_args(&args).unwrap();
+    assert!(matches!(&cmd, CliCommand::RawSign { input_scripts, .. } if input_scripts.len() == 1));
+
+    let signed_hex = cmd.execute().unwrap().to_text();
+    let signed_bytes = hex::decode(&signed_hex).unwrap();
+    let signed = TransactionReader::new(&signed_bytes[..])
+        .read_transaction()
+        .unwrap();
+    let script_sig = signed.inputs()[0].script_sig().clone();
+    let pushes = script_sig.decompile().unwrap();
+    assert_eq!(pushes.len(), 2);
+    match (&pushes[0], &pushes[1]) {
+        (Instruction::OpData(sig), Instruction::OpData(pubkey)) => {
+            assert_eq!(*sig.last().unwrap(), 0x01);
+            assert_eq!(
+                pubkey[..],
+                secp256k1::public_key_compressed(&secret_key(7)).unwrap()[..]
+            );
+        }
+        _ => panic!("scriptSig should be two pushes"),
+    }
+}
+
+#[test]
+fn test_cli_rawsign_rejects_bad_key() {
+    for key in ["abcd", &"zz".repeat(32), &"00".repeat(33)] {
+        let args: Vec<String> = ["rawsign", "00", key]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(matches!(
+            parse_cli_args(&args),
+            Err(BitcoinError::CliError { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_dust_threshold() {
+    let rate = FeeRate::from_sat_per_vb(1);
+
+    // 3 * (34 + 148) = 546 for P2PKH
+    let p2pkh = TxOutput::new(
+        Amount::from_sat(546),
+        Script([vec![0x76, 0xa9, 0x14], vec![0; 20], vec![0x88, 0xac]].concat()),
+    );
+    assert_eq!(p2pkh.dust_threshold(rate), Amount::from_sat(546));
+    assert!(!p2pkh.is_dust(rate));
+    assert!(
+        TxOutput::new(Amount::from_sat(545), p2pkh.clone().script_pubkey().clone()).is_dust(rate)
+    );
+
+    // 3 * (31 + 67) = 294 for P2WPKH, as Bitcoin Core computes it
+    let p2wpkh = Script([vec![0x00, 0x14], vec![0; 20]].concat());
+    assert_eq!(
+        TxOutput::dust_threshold_for_script(&p2wpkh, rate),
+        Amount::from_sat(294)
+    );
+
+    // Scales with the fee rate
+    assert_eq!(
+        p2pkh.dust_threshold(FeeRate::from_sat_per_vb(3)),
+        Amount::from_sat(1_638)
+    );
+
+    let op_return = Script(vec![0x6a, 0x01, 0x00]);
+    assert_eq!(
+        TxOutput::dust_threshold_for_script(&op_return, rate),
+        Amount::from_sat(0)
+    );
+}
+
+#[test]
+fn test_ripemd160_and_hash160() {
+    assert_eq!(
+        hex::encode(&hash::ripemd160(b"")),
+        "9c1185a5c5e9fc54612808977ee8f548b2258d31"
+    );
+    assert_eq!(
+        hex::encode(&hash::ripemd160(b"abc")),
+        "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+    );
+    // Hash160 of the generator point's compressed encoding
+    let pubkey = secp256k1::public_key_compressed(&secret_key(1)).unwrap();
+    assert_eq!(
+        hex::encode(&hash::hash160(&pubkey)),
+        "751e76e8199196d454941c45d1b3a323f1433bd6"
+    );
+}
+
+#[test]
+fn test_verify_input_p2pkh() {
+    let key = secret_key(3);
+    let pubkey = secp256k1::public_key_compressed(&key).unwrap();
+    let utxo = TxOutput::new(
+        Amount::from_sat(10_000),
+        Script(
+            [
+                vec![0x76, 0xa9, 0x14],
+                hash::hash160(&pubkey).to_vec(),
+                vec![0x88, 0xac],
+            ]
+            .concat(),
+        ),
+    );
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(9_000), Script(vec![0x51])))
+        .build();
+    let signed = signer::sign_legacy_inputs(&tx, &key, &[utxo.script_pubkey().clone()]).unwrap();
+
+    let flags = ScriptVerifyFlags::P2SH | ScriptVerifyFlags::DERSIG;
+    assert!(flags.contains(ScriptVerifyFlags::P2SH));
+    assert!(!flags.contains(ScriptVerifyFlags::WITNESS));
+    assert!(signed.verify_input(0, &utxo, flags).is_ok());
+
+    // Changing what was signed invalidates the signature
+    let tampered = signed
+        .with_output_replaced(
+            0,
+            TxOutput::new(Amount::from_sat(9_500), Script(vec![0x51])),
+        )
+        .unwrap();
+    assert!(matches!(
+        tampered.verify_input(0, &utxo, flags),
+        Err(BitcoinError::ScriptExecution(ScriptError::EvalFalse))
+    ));
+    // Unsigned input, and an input index that does not exist
+    assert!(tx.verify_input(0, &utxo, flags).is_err());
+    assert!(matches!(
+        signed.verify_input(1, &utxo, flags),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn test_script_execution_errors() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::new(funding_outpoint(2), Script::default(), 0))
+        .build();
+    let fail = |script_pubkey: Vec<u8>| {
+        let utxo = TxOutput::new(Amount::from_sat(1_000), Script(script_pubkey));
+        match tx.verify_input(0, &utxo, ScriptVerifyFlags::all()) {
+            Err(BitcoinError::ScriptExecution(err)) => err,
+            other => panic!("expected a script failure, got {other:?}"),
+        }
+    };
+
+    assert_eq!(fail(vec![0x76]), ScriptError::StackUnderflow);
+    assert_eq!(fail(vec![]), ScriptError::StackUnderflow);
+    assert_eq!(fail(vec![0x00]), ScriptError::EvalFalse);
+    assert_eq!(fail(vec![0x6a]), ScriptError::OpReturn);
+    assert_eq!(
+        fail(vec![0x51, 0x51, 0x7e]),
+        ScriptError::DisabledOpcode("OP_CAT")
+    );
+    assert_eq!(
+        fail(vec![0x00, 0x69]),
+        ScriptError::VerifyFailed("OP_VERIFY")
+    );
+    assert_eq!(
+        fail(vec![0x51, 0x52, 0x88]),
+        ScriptError::VerifyFailed("OP_EQUALVERIFY")
+    );
+    assert_eq!(fail(vec![0x89]), ScriptError::UnsupportedOpcode(0x89));
+
+    // The reason is carried through to the message
+    let err = BitcoinError::from(ScriptError::VerifyFailed("OP_EQUALVERIFY"));
+    assert_eq!(
+        err.to_string(),
+        "script execution failed: OP_EQUALVERIFY failed"
+    );
+    // Undecodable scripts remain plain InvalidScript
+    let truncated = TxOutput::new(Amount::from_sat(1_000), Script(vec![0x02, 0x01]));
+    assert!(matches!(
+        tx.verify_input(0, &truncated, ScriptVerifyFlags::all()),
+        Err(BitcoinError::InvalidScript)
+    ));
+}
+
+#[test]
+fn test_verify_input_checklocktimeverify() {
+    // <500> OP_CHECKLOCKTIMEVERIFY OP_DROP OP_1
+    let utxo = TxOutput::new(
+        Amount::from_sat(1_000),
+        Script(vec![0x02, 0xf4, 0x01, 0xb1, 0x75, 0x51]),
+    );
+    let spend = |lock_time: u32| {
+        LegacyTransaction::builder()
+            .add_input(TxInput::new(
+                funding_outpoint(2),
+                Script::default(),
+                0xFFFFFFFE,
+            ))
+            .lock_time(lock_time)
+            .build()
+    };
+
+    let flags = ScriptVerifyFlags::CHECKLOCKTIMEVERIFY;
+    assert!(spend(500).verify_input(0, &utxo, flags).is_ok());
+    assert!(spend(499).verify_input(0, &utxo, flags).is_err());
+    // Without the flag the opcode is a NOP
+    assert!(
+        spend(499)
+            .verify_input(0, &utxo, ScriptVerifyFlags::empty())
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_verify_input_checksequenceverify() {
+    // <10> OP_CHECKSEQUENCEVERIFY OP_DROP OP_1
+    let utxo = TxOutput::new(
+        Amount::from_sat(1_000),
+        Script(vec![0x01, 0x0a, 0xb2, 0x75, 0x51]),
+    );
+    let spend = |version: i32, sequence: Sequence| {
+        LegacyTransaction::builder()
+            .version(version)
+            .add_input(TxInput::new(
+                funding_outpoint(2),
+                Script::default(),
+                sequence,
+            ))
+            .build()
+    };
+
+    let flags = ScriptVerifyFlags::CHECKSEQUENCEVERIFY;
+    assert!(
+        spend(2, Sequence::from_height(10))
+            .verify_input(0, &utxo, flags)
+            .is_ok()
+    );
+    assert!(
+        spend(2, Sequence::from_height(9))
+            .verify_input(0, &utxo, flags)
+            .is_err()
+    );
+    // A time lock never satisfies a height requirement
+    assert!(
+        spend(2, Sequence::from_512_second_intervals(10))
+            .verify_input(0, &utxo, flags)
+            .is_err()
+    );
+    // Relative locks need version 2 and an input with BIP-68 enabled
+    assert!(
+        spend(1, Sequence::from_height(10))
+            .verify_input(0, &utxo, flags)
+            .is_err()
+    );
+    assert!(
+        spend(2, Sequence::MAX)
+            .verify_input(0, &utxo, flags)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_bitcoin_error_messages() {
+    let cases = [
+        (
+            BitcoinError::InvalidTransaction,
+            "Invalid transaction format",
+        ),
+        (BitcoinError::InvalidScript, "Invalid script format"),
+        (BitcoinError::InvalidAmount, "Invalid amount"),
+        (
+            BitcoinError::cli_error(ParseErrorCode::InvalidHex, "bad hex"),
+            "Parse error: bad hex",
+        ),
+        (
+            BitcoinError::InsufficientFunds {
+                needed: Amount::from_sat(5000),
+                available: Amount::from_sat(3000),
+            },
+            "insufficient funds: need 5000 sat, have 3000 sat",
+        ),
+        (
+            BitcoinError::DustOutput {
+                value: Amount::from_sat(100),
+                threshold: Amount::from_sat(546),
+            },
+            "dust output: 100 sat is below 546 sat threshold",
+        ),
+    ];
+    for (error, message) in cases {
+        assert_eq!(error.to_string(), message);
+    }
+}
+
+#[test]
+fn test_builder_try_build() {
+    let p2pkh = Address::P2PKH([3; 20]).script_pubkey();
+    let output = |sat: u64| TxOutput::new(Amount::from_sat(sat), p2pkh.clone());
+    let base =
+        || LegacyTransaction::builder().add_input(TxInput::from_outpoint(funding_outpoint(1)));
+
+    let tx = base().add_output(output(10_000)).try_build().unwrap();
+    assert_eq!(tx.outputs().len(), 1);
+    // OP_RETURN and anchor outputs are never dust
+    assert!(
+        base()
+            .add_output(TxOutput::new(Amount::ZERO, Script(vec![0x6a])))
+            .add_output(TxOutput::new(Amount::ZERO, Script::pay_to_anchor()))
+            .try_build()
+            .is_ok()
+    );
+
+    assert!(matches!(
+        LegacyTransaction::builder()
+            .add_output(output(10_000))
+            .try_build(),
+        Err(BitcoinError::NoInputs)
+    ));
+    assert!(matches!(base().try_build(), Err(BitcoinError::NoOutputs)));
+    match base()
+        .add_output(output(10_000))
+        .add_output(output(MAX_MONEY.to_sat() + 1))
+        .try_build()
+    {
+        Err(BitcoinError::OutputValueTooLarge { index, value }) => {
+            assert_eq!(index, 1);
+            assert_eq!(value, Amount::from_sat(MAX_MONEY.to_sat() + 1));
+        }
+        other => panic!("expected OutputValueTooLarge, got {other:?}"),
+    }
+    assert!(matches!(
+        base()
+            .add_output(output(MAX_MONEY.to_sat()))
+            .add_output(output(10_000))
+            .try_build(),
+        Err(BitcoinError::TotalValueTooLarge)
+    ));
+    assert!(matches!(
+        base().add_output(output(500)).try_build(),
+        Err(BitcoinError::DustOutput { .. })
+    ));
+    // Outputs at Core's dust limits, 546 sat P2PKH and 294 sat P2WPKH, relay
+    let p2wpkh = Address::P2WPKH([4; 20]).script_pubkey();
+    let at_limit = base()
+        .add_output(output(546))
+        .add_output(TxOutput::new(Amount::from_sat(294), p2wpkh.clone()))
+        .try_build()
+        .unwrap();
+    let standard = TransactionValidator::new().require_standard();
+    assert!(standard.validate(&at_limit, &UtxoSet::new()).is_empty());
+    match base()
+        .add_output(TxOutput::new(Amount::from_sat(293), p2wpkh))
+        .try_build()
+    {
+        Err(BitcoinError::DustOutput { threshold, .. }) => {
+            assert_eq!(threshold, Amount::from_sat(294))
+        }
+        other => panic!("expected DustOutput, got {other:?}"),
+    }
+    match base()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_output(output(10_000))
+        .try_build()
+    {
+        Err(BitcoinError::DuplicateInput(outpoint)) => assert_eq!(outpoint, funding_outpoint(1)),
+        other => panic!("expected DuplicateInput, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_clone_unsigned_and_scripts_populated() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::new(
+            funding_outpoint(1),
+            Script(vec![0x51]),
+            0xFFFFFFFF,
+        ))
+        .add_input(TxInput::new(
+            funding_outpoint(2),
+            Script(vec![0x52]),
+            0xFFFFFFFF,
+        ))
+        .build();
+    assert!(tx.has_all_scripts_populated());
+
+    let unsigned = tx.clone_unsigned();
+    assert!(
+        unsigned
+            .inputs()
+            .iter()
+            .all(|i| i.script_sig().0.is_empty())
+    );
+    assert_eq!(*unsigned.inputs()[1].outpoint(), funding_outpoint(2));
+    assert!(!unsigned.has_all_scripts_populated());
+    // The original is untouched
+    assert_eq!(tx.inputs()[0].script_sig().0, vec![0x51]);
+}
+
+#[test]
+fn test_select_coins_largest_first() {
+    let mut utxos = UtxoSet::new();
+    for (n, value) in [(1, 5_000), (2, 20_000), (3, 8_000)] {
+        utxos.insert(
+            funding_outpoint(n),
+            TxOutput::new(Amount::from_sat(value), Script(vec![0x51])),
+        );
+    }
+    let rate = FeeRate::from_sat_per_vb(10);
+
+    // One input: 10 + 148 + 34 = 192 vB, 1920 sat fee
+    let selected = LegacyTransaction::select_coins(&utxos, Amount::from_sat(18_000), rate).unwrap();
+    assert_eq!(selected, vec![funding_outpoint(2)]);
+
+    // 18_500 + 1920 exceeds the largest coin, so a second one is added
+    let selected = LegacyTransaction::select_coins(&utxos, Amount::from_sat(18_500), rate).unwrap();
+    assert_eq!(selected, vec![funding_outpoint(2), funding_outpoint(3)]);
+
+    let tx = selected
+        .into_iter()
+        .fold(LegacyTransaction::builder(), |builder, outpoint| {
+            builder.add_input(TxInput::from_outpoint_final(outpoint))
+        })
+        .build();
+    assert_eq!(tx.inputs().len(), 2);
+
+    // All three coins with 10 + 3 * 148 + 34 = 488 vB still fall short
+    match LegacyTransaction::select_coins(&utxos, Amount::from_sat(30_000), rate) {
+        Err(BitcoinError::InsufficientFunds { needed, available }) => {
+            assert_eq!(needed, Amount::from_sat(34_880));
+            assert_eq!(available, Amount::from_sat(33_000));
+        }
+        other => panic!("expected InsufficientFunds, got {other:?}"),
+    }
+
+    // A target too large to add the fee to is an error, not an overflow
+    assert!(matches!(
+        LegacyTransaction::select_coins(&utxos, Amount::from_sat(u64::MAX), rate),
+        Err(BitcoinError::InvalidAmount)
+    ));
+}
+
+#[test]
+fn test_tapscript_leaf_version() {
+    // Parity bit set, one merkle path node
+    let mut control_block = vec![0xc1];
+    control_block.extend_from_slice(&[0x22; 32]);
+    control_block.extend_from_slice(&[0x33; 32]);
+    let leaf_version = Script(control_block.clone()).tapscript_leaf_version();
+    assert_eq!(leaf_version, Some(0xc0));
+    assert!(script::is_tapscript(leaf_version.unwrap()));
+    assert!(script::require_tapscript(0xc0).is_ok());
+
+    // Not a whole number of path nodes, or too short
+    control_block.push(0);
+    assert_eq!(Script(control_block).tapscript_leaf_version(), None);
+    assert_eq!(Script(vec![0xc0; 32]).tapscript_leaf_version(), None);
+
+    assert!(!script::is_tapscript(0xc2));
+    let err = script::require_tapscript(0xc2).unwrap_err();
+    assert!(matches!(
+        err,
+        BitcoinError::UnsupportedTaprootLeafVersion(0xc2)
+    ));
+    assert_eq!(err.to_string(), "unsupported taproot leaf version: 0xc2");
+}
+
+#[test]
+fn test_sort_inputs_and_outputs() {
+    let input =
+        |n: u8, sequence: u32| TxInput::new(funding_outpoint(n), Script::default(), sequence);
+    let output =
+        |value: u64, script: u8| TxOutput::new(Amount::from_sat(value), Script(vec![script]));
+    let mut tx = LegacyTransaction::builder()
+        .add_input(input(3, 1))
+        .add_input(input(1, 7))
+        .add_input(input(2, 4))
+        .add_output(output(500, 0x02))
+        .add_output(output(100, 0x03))
+        .add_output(output(300, 0x01))
+        .build();
+
+    let values: Vec<u64> = tx
+        .sorted_outputs_by_value()
+        .iter()
+        .map(|o| o.value().to_sat())
+        .collect();
+    assert_eq!(values, vec![100, 300, 500]);
+    let scripts: Vec<u8> = tx
+        .sorted_outputs_by_script()
+        .iter()
+        .map(|o| o.script_pubkey().0[0])
+        .collect();
+    assert_eq!(scripts, vec![0x01, 0x02, 0x03]);
+    let sequences: Vec<u32> = tx
+        .sorted_inputs_by_sequence()
+        .iter()
+        .map(|i| i.sequence().to_consensus_u32())
+        .collect();
+    assert_eq!(sequences, vec![1, 4, 7]);
+    let outpoints: Vec<&OutPoint> = tx
+        .sorted_inputs_by_outpoint()
+        .iter()
+        .map(|i| i.outpoint())
+        .collect();
+    assert_eq!(
+        outpoints,
+        vec![
+            &funding_outpoint(1),
+            &funding_outpoint(2),
+            &funding_outpoint(3)
+        ]
+    );
+    // The non-mutating variants leave the transaction alone
+    assert_eq!(tx.outputs()[0].value().to_sat(), 500);
+
+    tx.sort_outputs_by_value();
+    assert_eq!(tx.outputs()[0].value().to_sat(), 100);
+    tx.sort_outputs_by_script();
+    assert_eq!(tx.outputs()[0].value().to_sat(), 300);
+    tx.sort_inputs_by_outpoint();
+    assert_eq!(*tx.inputs()[0].outpoint(), funding_outpoint(1));
+    tx.sort_inputs_by_sequence();
+    assert_eq!(tx.inputs()[0].sequence(), 1);
+}
+
+#[test]
+fn test_serialize_to_writer_matches_serialize() {
+    let tx = two_output_transaction();
+    let mut streamed = Vec::new();
+    tx.serialize_to_writer(&mut streamed).unwrap();
+    assert_eq!(streamed, tx.serialize());
+
+    // Types without an override fall back to the provided method
+    let segwit = sample_segwit_transaction();
+    let mut streamed = Vec::new();
+    segwit.serialize_to_writer(&mut streamed).unwrap();
+    assert_eq!(streamed, segwit.serialize());
+
+    // Writer errors are propagated
+    let mut short = [0u8; 8];
+    assert!(matches!(
+        tx.serialize_to_writer(&mut &mut short[..]),
+        Err(BitcoinError::IoError(err)) if err.kind() == std::io::ErrorKind::WriteZero
+    ));
+}
+
+#[test]
+fn test_streaming_serialize_into_and_deserialize_from() {
+    let tx = two_output_transaction();
+    let segwit = sample_segwit_transaction();
+    let mut stream = Vec::new();
+    let written = tx.serialize_into(&mut stream).unwrap();
+    assert_eq!(written, tx.serialize().len());
+    let written = segwit.serialize_into(&mut stream).unwrap();
+    assert_eq!(written, segwit.serialize().len());
+    let output = tx.outputs()[0].clone();
+    output.serialize_into(&mut stream).unwrap();
+
+    // Values are read back one after another from the same reader
+    let mut reader = std::io::Cursor::new(stream);
+    let decoded = LegacyTransaction::deserialize_from(&mut reader).unwrap();
+    assert_eq!(decoded.serialize(), tx.serialize());
+    let decoded = Transaction::deserialize_from(&mut reader).unwrap();
+    assert_eq!(decoded.serialize(), segwit.serialize());
+    assert_eq!(TxOutput::deserialize_from(&mut reader).unwrap(), output);
+    assert!(matches!(
+        TxOutput::deserialize_from(&mut reader),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+
+    let input = tx.inputs()[0].clone();
+    assert_eq!(TxInput::deserialize(&input.serialize()).unwrap(), input);
+    let outpoint = input.outpoint().clone();
+    assert_eq!(
+        OutPoint::deserialize(&outpoint.serialize()).unwrap(),
+        outpoint
+    );
+    let mut trailing = outpoint.serialize();
+    trailing.push(0);
+    assert!(OutPoint::deserialize(&trailing).is_err());
+
+    // Writer errors surface as the original io::Error
+    let mut short = [0u8; 8];
+    let err = tx.serialize_into(&mut &mut short[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn test_io_error_conversion() {
+    fn read_all(reader: &mut impl std::io::Read) -> Result<Vec<u8>, BitcoinError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    struct FailingReader;
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    let err = read_all(&mut FailingReader).unwrap_err();
+    assert!(matches!(err, BitcoinError::IoError(_)));
+    assert_eq!(err.to_string(), "I/O error: disk on fire");
+    assert!(matches!(
+        TransactionReader::new(FailingReader).read_transaction(),
+        Err(BitcoinError::IoError(_))
+    ));
+    // Truncated input still reads as a malformed transaction
+    assert!(matches!(
+        TransactionReader::new(&[0x01, 0x00][..]).read_transaction(),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn test_weight_limits() {
+    let tx = two_output_transaction();
+    assert!(tx.check_weight_limit().is_ok());
+    assert!(tx.check_consensus_weight_limit().is_ok());
+
+    // A ~110 kB scriptSig is non-standard but within consensus limits
+    let heavy = LegacyTransaction::builder()
+        .add_input(TxInput::new(
+            OutPoint::default(),
+            Script(vec![0x51; 110_000]),
+            0xFFFFFFFF,
+        ))
+        .add_output(TxOutput::default())
+        .build();
+    let weight = heavy.weight();
+    assert!(weight > MAX_STANDARD_TX_WEIGHT && weight < MAX_CONSENSUS_TX_WEIGHT);
+
+    let err = LegacyTransactionBuilder::from_transaction(&heavy)
+        .build_validated()
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        BitcoinError::WeightLimitExceeded {
+            limit: MAX_STANDARD_TX_WEIGHT,
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        format!("transaction weight {weight} exceeds limit 400000")
+    );
+    assert!(heavy.check_consensus_weight_limit().is_ok());
+
+    let mut segwit = sample_segwit_transaction();
+    assert!(segwit.check_weight_limit().is_ok());
+    segwit.witness[0].push(vec![0; 400_000]);
+    assert!(segwit.check_standard_weight_limit().is_err());
+    assert!(segwit.check_consensus_weight_limit().is_ok());
+}
+
+#[test]
+fn test_max_possible_weight() {
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(2)))
+        .add_output(TxOutput::new(Amount::from_sat(1_000), Script(vec![0x51])))
+        .build();
+    let base = unsigned.weight();
+
+    // Two P2PKH scriptSigs of 107 bytes each
+    assert_eq!(unsigned.max_possible_weight(), base + 2 * 107 * 4);
+    assert_eq!(
+        unsigned.max_possible_weight_for_input_types(&[OutputScriptType::P2PKH]),
+        unsigned.max_possible_weight()
+    );
+    // P2WPKH + P2TR: marker/flag, two stack counts, 107 + 65 witness bytes
+    assert_eq!(
+        unsigned.max_possible_weight_for_input_types(&[
+            OutputScriptType::P2WPKH,
+            OutputScriptType::P2TR
+        ]),
+        base + 2 + 2 + 107 + 65
+    );
+    // Nested P2SH-P2WPKH pays for its 23-byte scriptSig at full weight
+    assert_eq!(
+        unsigned.max_possible_weight_for_input_types(&[
+            OutputScriptType::P2SH,
+            OutputScriptType::P2WPKH
+        ]),
+        base + 23 * 4 + 2 + 2 + 107 + 107
+    );
+
+    // A real signature fits within the P2PKH bound
+    let key = secret_key(5);
+    let script_code = Script(
+        [
+            vec![0x76, 0xa9, 0x14],
+            hash::hash160(&secp256k1::public_key_compressed(&key).unwrap()).to_vec(),
+            vec![0x88, 0xac],
+        ]
+        .concat(),
+    );
+    let signed =
+        signer::sign_legacy_inputs(&unsigned, &key, &[script_code.clone(), script_code]).unwrap();
+    assert!(signed.weight() <= unsigned.max_possible_weight());
+}
+
+#[test]
+fn test_command_result_rendering() {
+    let tx = two_output_transaction();
+    let raw = hex::encode(&tx.serialize());
+    let args: Vec<String> = ["inspect", &raw, "output_count"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let result = parse_cli_args(&args).unwrap().execute().unwrap();
+    assert!(
+        matches!(&result, CommandResult::Field { name, value } if name == "output_count" && value == "2")
+    );
+    assert_eq!(result.to_json(), r#"{"output_count": "2"}"#);
+
+    let balance = CommandResult::balance(150_000_000);
+    assert_eq!(balance.to_text(), "1.50000000 BTC (150000000 sat)");
+    assert_eq!(
+        balance.to_json(),
+        r#"{"sat": 150000000, "btc": "1.50000000"}"#
+    );
+
+    assert_eq!(CommandResult::Fee { sat: 226 }.to_text(), "226 sat");
+    assert_eq!(
+        CommandResult::Fee { sat: 226 }.to_json(),
+        r#"{"fee_sat": 226}"#
+    );
+    assert_eq!(CommandResult::Success.to_text(), "OK");
+    assert_eq!(
+        CommandResult::TransactionHex("00ff".to_string()).to_json(),
+        r#"{"hex": "00ff"}"#
+    );
+
+    let decoded = CommandResult::Decoded(DecodedTransaction::from(tx.clone()));
+    assert_eq!(decoded.to_json(), tx.to_json());
+    let text = decoded.to_text();
+    assert!(text.starts_with(&format!("txid: {}\n", inspect(&raw, "txid", &[]).unwrap())));
+    assert!(text.ends_with(&format!("weight: {}", tx.weight())));
+}
+
+#[test]
+fn test_input_by_outpoint_and_output_by_script() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::new(funding_outpoint(2), Script::default(), 7))
+        .add_output(TxOutput::new(Amount::from_sat(10), Script(vec![0x51])))
+        .add_output(TxOutput::new(Amount::from_sat(20), Script(vec![0x52])))
+        .build();
+
+    let (index, input) = tx.input_by_outpoint(&funding_outpoint(2)).unwrap();
+    assert_eq!((index, input.sequence()), (1, Sequence(7)));
+    assert!(tx.input_by_outpoint(&funding_outpoint(3)).is_none());
+
+    let (index, output) = tx.output_by_script(&Script(vec![0x52])).unwrap();
+    assert_eq!((index, output.value().to_sat()), (1, 20));
+    assert!(tx.output_by_script(&Script(vec![0x53])).is_none());
+}
+
+#[test]
+fn test_tx_input_from_outpoint() {
+    let input = TxInput::from_outpoint(funding_outpoint(4));
+    assert_eq!(*input.outpoint(), funding_outpoint(4));
+    assert!(input.script_sig().0.is_empty());
+    assert_eq!(input.sequence(), 0xFFFFFFFE);
+
+    let input = TxInput::from_outpoint_final(funding_outpoint(4));
+    assert_eq!(*input.outpoint(), funding_outpoint(4));
+    assert_eq!(input.sequence(), 0xFFFFFFFF);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_rpc_json_round_trip() {
+    let raw = hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap();
+    let tx = TransactionReader::new(&raw[..]).read_transaction().unwrap();
+    let rpc = tx.to_rpc_json();
+
+    let txid = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+    assert_eq!(rpc["txid"], txid);
+    assert_eq!(rpc["hash"], txid);
+    assert_eq!(rpc["version"], 1);
+    assert_eq!(rpc["size"], 275);
+    assert_eq!(rpc["vsize"], 275);
+    assert_eq!(rpc["weight"], 1100);
+    assert_eq!(rpc["locktime"], 0);
+    assert_eq!(
+        rpc["vin"][0]["txid"],
+        "0437cd7f8525ceed2324359c2d0ba26006d92d856a9c20fa0241106ee5a597c9"
+    );
+    assert_eq!(rpc["vin"][0]["sequence"], 0xFFFFFFFFu32);
+    assert_eq!(rpc["vout"][0]["value"], 10.0);
+    assert_eq!(rpc["vout"][1]["value"], 40.0);
+    assert_eq!(rpc["vout"][1]["n"], 1);
+    assert_eq!(rpc["vout"][1]["scriptPubKey"]["type"], "pubkey");
+
+    let parsed = LegacyTransaction::from_rpc_json(&rpc).unwrap();
+    assert_eq!(parsed.serialize(), raw);
+
+    let mut missing = rpc.clone();
+    missing.remove("vout");
+    assert!(matches!(
+        LegacyTransaction::from_rpc_json(&missing),
+        Err(BitcoinError::CliError { .. })
+    ));
+}
+
+#[test]
+fn test_validate_minimal_if_operands() {
+    use script_interpreter::validate_minimal_if_operands;
+
+    // OP_IF OP_1 OP_ENDIF
+    let branching = Script(vec![0x63, 0x51, 0x68]);
+    assert!(branching.tapscript_requires_minimal_if());
+    assert!(validate_minimal_if_operands(&branching, &[vec![]]).is_ok());
+    assert!(validate_minimal_if_operands(&branching, &[vec![0x05], vec![0x01]]).is_ok());
+    for operand in [vec![0x02], vec![0x01, 0x00], vec![0x00], vec![0x80]] {
+        assert!(matches!(
+            validate_minimal_if_operands(&branching, &[operand]),
+            Err(BitcoinError::ScriptExecution(ScriptError::MinimalIf))
+        ));
+    }
+    assert!(validate_minimal_if_operands(&branching, &[]).is_err());
+
+    // Without OP_IF/OP_NOTIF the stack is irrelevant
+    let straight = Script(vec![0x75, 0x51]);
+    assert!(validate_minimal_if_operands(&straight, &[vec![0x02]]).is_ok());
+}
+
+#[test]
+fn test_outpoint_hex_formatting_round_trip() {
+    let mut txid = [0u8; 32];
+    txid[0] = 0xab;
+    txid[31] = 0x01;
+    let outpoint = OutPoint {
+        txid: Txid(txid),
+        vout: 3,
+    };
+
+    let lower = format!("{outpoint:x}");
+    assert_eq!(lower, format!("01{}ab:3", "00".repeat(30)));
+    assert_eq!(
+        format!("{outpoint:X}"),
+        format!("01{}AB:3", "00".repeat(30))
+    );
+
+    assert_eq!(lower.parse::<OutPoint>().unwrap(), outpoint);
+    assert_eq!(
+        format!("{outpoint:X}").parse::<OutPoint>().unwrap(),
+        outpoint
+    );
+    for bad in [
+        "",
+        "abcd:0",
+        &format!("{}:x", "00".repeat(32)),
+        &"00".repeat(32),
+    ] {
+        assert!(matches!(
+            bad.parse::<OutPoint>(),
+            Err(BitcoinError::CliError { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_script_sigops() {
+    let p2pkh = [vec![0x76, 0xa9, 0x14], vec![0x11; 20], vec![0x88, 0xac]].concat();
+    assert_eq!(Script(p2pkh.clone()).sigops(false), 1);
+
+    // OP_2 <pk> <pk> <pk> OP_3 OP_CHECKMULTISIG
+    let mut multisig = vec![0x52];
+    for _ in 0..3 {
+        multisig.push(33);
+        multisig.extend_from_slice(&[0x02; 33]);
+    }
+    multisig.extend_from_slice(&[0x53, 0xae]);
+    assert_eq!(Script(multisig.clone()).sigops(false), 20);
+    assert_eq!(Script(multisig.clone()).sigops(true), 3);
+
+    // CHECKMULTISIGVERIFY not preceded by OP_N, then CHECKSIGVERIFY
+    assert_eq!(Script(vec![0xaf, 0xad]).sigops(true), 21);
+    // Counting stops at a truncated push
+    assert_eq!(Script(vec![0xac, 0x4c, 0x05, 0xac]).sigops(false), 1);
+
+    // Spend a P2SH multisig output: the redeem script is the last push
+    let redeem_hash = hash::hash160(&multisig);
+    let p2sh = [vec![0xa9, 0x14], redeem_hash.to_vec(), vec![0x87]].concat();
+    let mut utxo_set = UtxoSet::new();
+    utxo_set.insert(
+        funding_outpoint(1),
+        TxOutput::new(Amount::from_sat(10_000), Script(p2sh)),
+    );
+    let script_sig = ScriptBuilder::new()
+        .push_opcode(Opcode::Op0)
+        .push_data(&multisig)
+        .build();
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::new(
+            funding_outpoint(1),
+            Script(script_sig.0),
+            0xFFFFFFFF,
+        ))
+        .add_output(TxOutput::new(Amount::from_sat(9_000), Script(p2pkh)))
+        .build();
+    // The scriptSig only pushes data, so only the P2PKH output counts
+    assert_eq!(tx.legacy_sigops(), 1);
+    assert_eq!(tx.accurate_sigops(&utxo_set).unwrap(), 4);
+    assert!(tx.accurate_sigops(&UtxoSet::new()).is_err());
+}
+
+#[test]
+fn test_vsize_and_base_size() {
+    let legacy = two_output_transaction();
+    assert_eq!(legacy.base_size(), legacy.serialize().len());
+    assert_eq!(legacy.vsize(), legacy.base_size() as u64);
+
+    // base 64 bytes, 40 witness bytes: vsize 74 is well below the full size
+    let segwit = sample_segwit_transaction();
+    assert_eq!(segwit.base_size(), 64);
+    assert_eq!(segwit.vsize(), 74);
+    assert_eq!(segwit.vsize(), segwit.vbytes());
+    assert!(segwit.vsize() < segwit.serialize().len() as u64);
+}
+
+#[test]
+fn test_verify_sequence_locks() {
+    let spend = |version: i32, sequences: &[u32]| {
+        sequences
+            .iter()
+            .enumerate()
+            .fold(
+                LegacyTransaction::builder().version(version),
+                |builder, (n, &sequence)| {
+                    builder.add_input(TxInput::new(
+                        funding_outpoint(n as u8),
+                        Script::default(),
+                        sequence,
+                    ))
+                },
+            )
+            .build()
+    };
+
+    // 10-block relative lock, plus a disabled one
+    let tx = spend(2, &[10, 0x8000_0005]);
+    assert!(tx.verify_sequence_locks(10, 0).is_ok());
+    let err = tx.verify_sequence_locks(9, 0).unwrap_err();
+    assert!(matches!(
+        err,
+        BitcoinError::SequenceLockNotMet {
+            input_index: 0,
+            required: 10
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "input 0 sequence lock not satisfied until 10"
+    );
+
+    // Time-based: 3 * 512 seconds after the coin's median time past
+    let tx = spend(2, &[0xFFFF_FFFE, (1 << 22) | 3]);
+    let coins = [(100, 1_000_000), (100, 1_000_000)];
+    assert!(
+        tx.verify_sequence_locks_for_coins(100, 1_001_536, &coins)
+            .is_ok()
+    );
+    assert!(matches!(
+        tx.verify_sequence_locks_for_coins(100, 1_001_535, &coins),
+        Err(BitcoinError::SequenceLockNotMet {
+            input_index: 1,
+            required: 1_001_536
+        })
+    ));
+
+    // BIP-68 does not apply to version 1
+    assert!(spend(1, &[10]).verify_sequence_locks(0, 0).is_ok());
+}
+
+#[test]
+fn test_script_push_only() {
+    // OP_0 <sig> OP_1NEGATE OP_16 PUSHDATA1 <2 bytes>
+    let push_only = Script(vec![
+        0x00, 0x02, 0xaa, 0xbb, 0x4f, 0x60, 0x4c, 0x02, 0x01, 0x02,
+    ]);
+    assert!(push_only.is_push_only());
+    assert!(push_only.check_push_only().is_ok());
+    assert!(Script(vec![]).is_push_only());
+
+    // <data> OP_DUP
+    let with_op = Script(vec![0x01, 0xaa, 0x76]);
+    assert!(!with_op.is_push_only());
+    let err = with_op.check_push_only().unwrap_err();
+    assert!(matches!(err, BitcoinError::NonPushOpcode(0x76)));
+    assert_eq!(err.to_string(), "non-push opcode 0x76 in push-only script");
+
+    // OP_RESERVED sits between the push opcodes but is not a push
+    assert!(!Script(vec![0x50]).is_push_only());
+    // Truncated push
+    assert!(!Script(vec![0x02, 0xaa]).is_push_only());
+    assert!(matches!(
+        Script(vec![0x02, 0xaa]).check_push_only(),
+        Err(BitcoinError::InvalidScript)
+    ));
+}
+
+#[test]
+fn test_p2sh_and_p2wsh_wrapping() {
+    let inner = Script(vec![0x51]);
+    let p2sh = Script::p2sh_wrapped(&inner);
+    assert!(p2sh.is_p2sh());
+    assert_eq!(&p2sh.0[2..22], &hash::hash160(&[0x51]));
+
+    let p2wsh = Script::p2wsh_wrapped(&inner);
+    assert!(p2wsh.is_p2wsh());
+    assert_eq!(p2wsh.witness_program(), Some(&hash::sha256(&[0x51])[..]));
+
+    // BIP-143 P2SH-P2WPKH example: the nested script hashes to 4733f37c...
+    let pubkey =
+        hex::decode("03ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a26873").unwrap();
+    let nested = Script::p2sh_p2wpkh_wrapped(&pubkey).unwrap();
+    assert_eq!(
+        hex::encode(&nested.0),
+        "a9144733f37cf4db86fbc2efed2500b4f4e49f31202387"
+    );
+
+    assert!(Script::p2sh_p2wpkh_wrapped(&[0x04; 65]).is_err());
+    assert!(Script::p2sh_p2wpkh_wrapped(&[0x05; 33]).is_err());
+}
+
+#[test]
+fn test_taproot_tweak_bip341_vectors() {
+    let bytes32 = |s: &str| -> [u8; 32] { hex::decode(s).unwrap().try_into().unwrap() };
+
+    // scriptPubKey vector 0: key-path only
+    let internal = bytes32("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+    assert_eq!(
+        hex::encode(&taproot::taproot_tweak_pubkey(&internal, None).unwrap()),
+        "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+    );
+
+    // scriptPubKey vector 1: a single script leaf
+    let internal = bytes32("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+    let root = bytes32("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21");
+    assert_eq!(
+        hex::encode(&taproot::taproot_tweak_pubkey(&internal, Some(&root)).unwrap()),
+        "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+    );
+
+    // keyPathSpending input 0
+    let secret = bytes32("6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa");
+    let tweaked = taproot::taproot_tweak_privkey(&secret, None).unwrap();
+    assert_eq!(
+        hex::encode(&tweaked),
+        "2405b971772ad26915c8dcdf10f238753a9b837e5f8e6a86fd7c0cce5b7296d9"
+    );
+    // The tweaked secret controls the tweaked public key
+    let internal = secp256k1::public_key(&secret).unwrap().x.to_be_bytes();
+    assert_eq!(
+        secp256k1::public_key(&tweaked).unwrap().x.to_be_bytes(),
+        taproot::taproot_tweak_pubkey(&internal, None).unwrap()
+    );
+
+    // x = p has no point
+    let not_on_curve = secp256k1::P.to_be_bytes();
+    assert!(taproot::taproot_tweak_pubkey(&not_on_curve, None).is_err());
+}
+
+#[test]
+fn test_taproot_tree_and_control_block() {
+    use taproot::{TapBranch, TapLeaf};
+
+    // BIP-341 scriptPubKey vector 1: one leaf, which is also the root
+    let leaf = TapLeaf {
+        version: script::TAPSCRIPT_LEAF_VERSION,
+        script: Script(
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap(),
+        ),
+    };
+    let root = taproot::taproot_merkle_root(std::slice::from_ref(&leaf));
+    assert_eq!(
+        hex::encode(&root),
+        "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+    );
+    assert_eq!(root, leaf.hash());
+
+    let internal: [u8; 32] =
+        hex::decode("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27")
+            .unwrap()
+            .try_into()
+            .unwrap();
+    let control = taproot::control_block(&internal, &leaf, vec![]).unwrap();
+    assert_eq!(control.len(), 33);
+    assert_eq!(Script(control.clone()).tapscript_leaf_version(), Some(0xc0));
+    assert_eq!(&control[1..], &internal);
+
+    // Branch hashing sorts its children
+    let other = TapLeaf {
+        version: 0xc0,
+        script: Script(vec![0x51]),
+    };
+    let branch = TapBranch {
+        left: leaf.hash(),
+        right: other.hash(),
+    };
+    let swapped = TapBranch {
+        left: other.hash(),
+        right: leaf.hash(),
+    };
+    assert_eq!(branch.hash(), swapped.hash());
+    let two_leaf_root = taproot::taproot_merkle_root(&[leaf.clone(), other.clone()]);
+    assert_eq!(two_leaf_root, branch.hash());
+
+    // With three leaves the third is paired with the first branch
+    let third = TapLeaf {
+        version: 0xc0,
+        script: Script(vec![0x52]),
+    };
+    let three_leaf_root =
+        taproot::taproot_merkle_root(&[leaf.clone(), other.clone(), third.clone()]);
+    assert_eq!(
+        three_leaf_root,
+        TapBranch {
+            left: branch.hash(),
+            right: third.hash()
+        }
+        .hash()
+    );
+
+    // The proof for `leaf` in the two-leaf tree is its sibling
+    let control = taproot::control_block(&internal, &leaf, vec![other.hash()]).unwrap();
+    assert_eq!(control.len(), 65);
+    let output_key = taproot::taproot_tweak_pubkey(&internal, Some(&two_leaf_root)).unwrap();
+    let mut compressed = vec![0x02 | (control[0] & 1)];
+    compressed.extend_from_slice(&output_key);
+    assert!(secp256k1::AffinePoint::from_sec1(&compressed).is_ok());
+    assert_eq!(taproot::taproot_merkle_root(&[]), [0; 32]);
+}
+
+#[test]
+fn test_witness_for_p2wpkh_and_p2wsh_inputs() {
+    let mut tx = sample_segwit_transaction();
+    let sig = [vec![0x30; 70], vec![0x01]].concat();
+    let pubkey = [vec![0x02], vec![0x11; 32]].concat();
+
+    tx.witness_for_p2wpkh_input(0, &sig, &pubkey).unwrap();
+    assert_eq!(tx.witness[0], vec![sig.clone(), pubkey.clone()]);
+
+    // Bad sighash byte, uncompressed key, missing input
+    let bad_sig = [vec![0x30; 70], vec![0x04]].concat();
+    assert!(tx.witness_for_p2wpkh_input(0, &bad_sig, &pubkey).is_err());
+    assert!(tx.witness_for_p2wpkh_input(0, &[], &pubkey).is_err());
+    assert!(tx.witness_for_p2wpkh_input(0, &sig, &[0x04; 65]).is_err());
+    assert!(matches!(
+        tx.witness_for_p2wpkh_input(5, &sig, &pubkey),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+    // Failed calls leave the witness alone
+    assert_eq!(tx.witness[0], vec![sig.clone(), pubkey]);
+
+    // The witness script goes last
+    let witness_script = Script(vec![0x51, 0x87]);
+    tx.witness_for_p2wsh_input(0, witness_script.clone(), vec![vec![], vec![0x01]])
+        .unwrap();
+    assert_eq!(tx.witness[0], vec![vec![], vec![0x01], witness_script.0]);
+}
+
+#[test]
+fn test_debug_dump() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::new(
+            funding_outpoint(1),
+            Script(vec![0x51]),
+            0xFFFFFFFE,
+        ))
+        .add_output(TxOutput::new(
+            Amount::from_sat(150_000_000),
+            Script(vec![0x6a, 0x01, 0xff]),
+        ))
+        .lock_time(500)
+        .build();
+
+    let expected = format!(
+        "LegacyTransaction {}
+  version: 1
+  inputs (1):
+    [0] {}:1
+        script_sig: 51
+        sequence: 4294967294 (0xfffffffe)
+  outputs (1):
+    [0] 150000000 sat (1.50000000 BTC)
+        script_pubkey: 6a01ff
+        asm: OP_RETURN ff
+  lock_time: 500
+",
+        inspect(&hex::encode(&tx.serialize()), "txid", &[]).unwrap(),
+        "01".repeat(32)
+    );
+    assert_eq!(tx.debug_dump(), expected);
+}
+
+#[test]
+fn test_min_push_opcode() {
+    let cases = [
+        (0, 0x00),
+        (1, 0x01),
+        (75, 0x4b),
+        (76, 0x4c),
+        (255, 0x4c),
+        (256, 0x4d),
+        (65_535, 0x4d),
+        (65_536, 0x4e),
+    ];
+    for (len, opcode) in cases {
+        let data = vec![0x07; len];
+        assert_eq!(Script::min_push_opcode(&data), opcode, "length {len}");
+        assert_eq!(ScriptBuilder::new().push_data(&data).build().0[0], opcode);
+    }
+}
+
+#[test]
+fn test_segwit_serialize_without_witness() {
+    let segwit = sample_segwit_transaction();
+    let stripped = segwit.serialize_without_witness();
+
+    let legacy = LegacyTransactionBuilder {
+        version: segwit.version,
+        inputs: segwit.inputs.clone(),
+        outputs: segwit.outputs.clone(),
+        lock_time: segwit.lock_time,
+    }
+    .build();
+    assert_eq!(stripped, legacy.serialize());
+    assert_eq!(stripped.len(), segwit.base_size());
+    assert_eq!(segwit.txid(), legacy.txid());
+    assert_ne!(segwit.txid(), segwit.wtxid());
+}
+
+#[test]
+fn test_p2p_frame() {
+    // verack on mainnet: empty payload, checksum of sha256d("")
+    let verack = p2p::frame(Network::Mainnet, "verack", &[]).unwrap();
+    assert_eq!(
+        hex::encode(&verack),
+        "f9beb4d976657261636b000000000000000000005df6e0e2"
+    );
+    assert!(p2p::frame(Network::Mainnet, "thirteenchars", &[]).is_err());
+
+    assert_eq!("regtest".parse::<Network>().unwrap(), Network::Regtest);
+    assert_eq!(Network::Testnet.to_string(), "testnet");
+    assert!("moonnet".parse::<Network>().is_err());
+}
+
+#[test]
+fn test_cli_broadcast() {
+    let tx = two_output_transaction();
+    let raw = hex::encode(&tx.serialize());
+    let args: Vec<String> = ["broadcast", &raw, "--network", "testnet"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let cmd = parse_cli_args(&args).unwrap();
+    assert!(matches!(
+        &cmd,
+        CliCommand::Broadcast {
+            network: Network::Testnet,
+            ..
+        }
+    ));
+
+    let framed = hex::decode(&cmd.execute().unwrap().to_text()).unwrap();
+    assert_eq!(&framed[..4], &Network::Testnet.magic());
+    assert_eq!(&framed[4..16], b"tx\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(
+        u32::from_le_bytes(framed[16..20].try_into().unwrap()) as usize,
+        tx.serialize().len()
+    );
+    assert_eq!(&framed[20..24], &hash::sha256d(&tx.serialize())[..4]);
+    assert_eq!(&framed[p2p::HEADER_SIZE..], &tx.serialize()[..]);
+
+    // Defaults to mainnet; rejects unparseable transactions and networks
+    let args: Vec<String> = ["broadcast", &raw].iter().map(|s| s.to_string()).collect();
+    assert!(matches!(
+        parse_cli_args(&args).unwrap(),
+        CliCommand::Broadcast {
+            network: Network::Mainnet,
+            ..
+        }
+    ));
+    let args: Vec<String> = ["broadcast", "0100"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert!(parse_cli_args(&args).unwrap().execute().is_err());
+    let args: Vec<String> = ["broadcast", &raw, "--network", "moonnet"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert!(parse_cli_args(&args).is_err());
+}
+
+#[test]
+fn test_tx_output_effective_value() {
+    let mut p2wpkh = vec![0x00, 0x14];
+    p2wpkh.extend_from_slice(&[0x11; 20]);
+    let mut p2pkh = vec![0x76, 0xa9, 0x14];
+    p2pkh.extend_from_slice(&[0x22; 20]);
+    p2pkh.extend_from_slice(&[0x88, 0xac]);
+
+    let segwit = TxOutput::new(Amount::from_sat(10_000), Script(p2wpkh));
+    let legacy = TxOutput::new(Amount::from_sat(1_000), Script(p2pkh));
+    assert_eq!(segwit.expected_input_vbytes(), 68);
+    assert_eq!(legacy.expected_input_vbytes(), 148);
+
+    let rate = FeeRate::from_sat_per_vb(10);
+    assert_eq!(
+        segwit.effective_value(rate, segwit.expected_input_vbytes()),
+        9_320
+    );
+    // Costs more to spend than it holds
+    assert_eq!(
+        legacy.effective_value(rate, legacy.expected_input_vbytes()),
+        -480
+    );
+    assert_eq!(
+        legacy.effective_value(FeeRate::from_sat_per_vb(0), 148),
+        1_000
+    );
+}
+
+#[test]
+fn test_spending_tx() {
+    let to = Address::P2WPKH([0x33; 20]);
+    let utxo = funding_outpoint(1);
+    let tx = LegacyTransaction::spending_tx(
+        utxo.clone(),
+        Amount::from_sat(50_000),
+        &to,
+        Amount::from_sat(1_000),
+    )
+    .unwrap();
+
+    assert_eq!(tx.inputs().len(), 1);
+    assert_eq!(*tx.inputs()[0].outpoint(), utxo);
+    assert_eq!(tx.outputs().len(), 1);
+    assert_eq!(tx.outputs()[0].value().to_sat(), 49_000);
+    assert_eq!(tx.outputs()[0].script_type(), OutputScriptType::P2WPKH);
+    assert_eq!(tx.outputs()[0].script_pubkey().0, to.script_pubkey().0);
+
+    // The fee must leave a non-zero output
+    let err =
+        LegacyTransaction::spending_tx(utxo, Amount::from_sat(1_000), &to, Amount::from_sat(1_000))
+            .unwrap_err();
+    assert!(matches!(err, BitcoinError::InsufficientFunds { .. }));
+    let huge_fee = Amount::from_sat(u64::MAX);
+    assert!(matches!(
+        LegacyTransaction::spending_tx(funding_outpoint(1), Amount::from_sat(1_000), &to, huge_fee),
+        Err(BitcoinError::InsufficientFunds { needed, .. }) if needed == huge_fee
+    ));
+}
+
+#[test]
+fn test_rbf_signalling() {
+    let with_sequence = |sequence: u32| {
+        LegacyTransaction::builder()
+            .add_input(TxInput::new(
+                funding_outpoint(1),
+                Script::default(),
+                sequence,
+            ))
+            .add_input(TxInput::from_outpoint_final(funding_outpoint(2)))
+            .build()
+    };
+
+    let final_tx = with_sequence(SEQUENCE_FINAL);
+    assert!(!final_tx.is_rbf_replaceable());
+    assert!(!final_tx.is_explicitly_rbf());
+
+    let locktime_only = with_sequence(SEQUENCE_ENABLE_LOCKTIME);
+    assert!(!locktime_only.is_rbf_replaceable());
+    assert!(!locktime_only.is_explicitly_rbf());
+
+    let signalled = with_sequence(SEQUENCE_RBF);
+    assert!(signalled.is_rbf_replaceable());
+    assert!(!signalled.is_explicitly_rbf());
+
+    let relative_lock = with_sequence(144);
+    assert!(relative_lock.is_rbf_replaceable());
+    assert!(relative_lock.is_explicitly_rbf());
+
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .enable_rbf()
+        .build();
+    assert!(
+        tx.inputs()
+            .iter()
+            .all(|input| input.sequence() == SEQUENCE_RBF)
+    );
+    assert!(tx.is_rbf_replaceable());
+}
+
+#[test]
+fn test_segwit_witness_accessors() {
+    let mut tx = sample_segwit_transaction();
+    tx.inputs.push(TxInput::from_outpoint(funding_outpoint(2)));
+
+    assert!(tx.input_has_witness(0).unwrap());
+    assert_eq!(tx.witness_item_count(0).unwrap(), 2);
+    assert_eq!(tx.witness_item(0, 1).unwrap(), &[0x02; 33][..]);
+    assert!(matches!(
+        tx.witness_item(0, 2),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+
+    // Input 1 has no witness entry at all
+    assert!(!tx.input_has_witness(1).unwrap());
+    assert_eq!(tx.witness_item_count(1).unwrap(), 0);
+    assert!(tx.witness_item(1, 0).is_err());
+
+    assert!(tx.input_has_witness(2).is_err());
+    assert!(tx.witness_item_count(2).is_err());
+}
+
+#[test]
+fn test_sanity_check_collects_all_issues() {
+    let good = LegacyTransaction::spending_tx(
+        funding_outpoint(1),
+        Amount::from_sat(10_000),
+        &Address::P2WPKH([0x33; 20]),
+        Amount::from_sat(500),
+    )
+    .unwrap();
+    assert!(good.sanity_check().is_empty());
+    // two_output_transaction spends the null outpoint with an empty scriptSig
+    assert_eq!(two_output_transaction().sanity_check().len(), 1);
+
+    let bad = LegacyTransaction::builder()
+        .version(0)
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint_final(OutPoint::default()))
+        .lock_time(500_000)
+        .build();
+    let issues: Vec<String> = bad.sanity_check().iter().map(|e| e.to_string()).collect();
+    assert_eq!(issues.len(), 5, "{issues:?}");
+    assert!(
+        issues
+            .iter()
+            .all(|issue| issue.starts_with("transaction check failed"))
+    );
+    assert!(issues.iter().any(|issue| issue.contains("no outputs")));
+    assert!(issues.iter().any(|issue| issue.contains("version 0")));
+    assert!(issues.iter().any(|issue| issue.contains("duplicate input")));
+    assert!(
+        issues
+            .iter()
+            .any(|issue| issue.contains("input 2 spends the null outpoint"))
+    );
+    assert!(
+        issues
+            .iter()
+            .any(|issue| issue.contains("lock_time 500000"))
+    );
+
+    let overpaying = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(MAX_MONEY.to_sat()),
+            Script(vec![0x51]),
+        ))
+        .add_output(TxOutput::new(
+            Amount::from_sat(MAX_MONEY.to_sat() + 1),
+            Script(vec![0x51]),
+        ))
+        .build();
+    assert_eq!(overpaying.sanity_check().len(), 2);
+}
+
+#[test]
+fn test_script_template_and_pattern_matching() {
+    let p2pkh = Address::P2PKH([0x44; 20]).script_pubkey();
+    let mut template = vec![Some(0x76), Some(0xa9), Some(0x14)];
+    template.extend([None; 20]);
+    template.extend([Some(0x88), Some(0xac)]);
+    assert!(p2pkh.matches_template(&template));
+    assert!(!p2pkh.matches_template(&template[..24]));
+    let p2sh = Address::P2SH([0x44; 20]).script_pubkey();
+    assert!(!p2sh.matches_template(&template));
+
+    assert!(p2pkh.matches_pattern(&ScriptPattern::p2pkh()));
+    assert!(!p2sh.matches_pattern(&ScriptPattern::p2pkh()));
+
+    let compressed = Script::pay_to_pubkey(&[[0x02].as_slice(), &[0x55; 32]].concat()).unwrap();
+    let uncompressed = Script::pay_to_pubkey(&[[0x04].as_slice(), &[0x55; 64]].concat()).unwrap();
+    assert!(compressed.matches_pattern(&ScriptPattern::p2pk()));
+    assert!(uncompressed.matches_pattern(&ScriptPattern::p2pk()));
+
+    let any_then_checksig = ScriptPattern(vec![
+        PatternElement::Any,
+        PatternElement::Op(Opcode::OpCheckSig),
+    ]);
+    assert!(compressed.matches_pattern(&any_then_checksig));
+    // Truncated push
+    assert!(!Script(vec![0x14, 0x00]).matches_pattern(&ScriptPattern(vec![PatternElement::Any])));
+}
+
+#[test]
+fn test_unified_transaction() {
+    let segwit = sample_segwit_transaction();
+    let legacy = LegacyTransaction::spending_tx(
+        funding_outpoint(1),
+        Amount::from_sat(10_000),
+        &Address::P2WPKH([0x33; 20]),
+        Amount::from_sat(500),
+    )
+    .unwrap();
+
+    for (tx, bytes) in [
+        (Transaction::from(legacy.clone()), legacy.serialize()),
+        (Transaction::from(segwit.clone()), segwit.serialize()),
+    ] {
+        assert_eq!(tx.serialize(), bytes);
+        let parsed = Transaction::deserialize(&bytes).unwrap();
+        assert_eq!(parsed.txid(), tx.txid());
+        assert_eq!(parsed.weight(), tx.weight());
+        assert_eq!(parsed.vbytes(), tx.vbytes());
+        assert_eq!(parsed.inputs(), tx.inputs());
+        assert_eq!(parsed.outputs(), tx.outputs());
+        assert!(!parsed.is_coinbase());
+
+        // Trailing bytes are rejected
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(Transaction::deserialize(&padded).is_err());
+    }
+
+    assert!(matches!(
+        Transaction::deserialize(&segwit.serialize()).unwrap(),
+        Transaction::SegWit(_)
+    ));
+    assert_eq!(Transaction::from(legacy.clone()).vbytes(), legacy.vsize());
+    assert!(Transaction::from(two_output_transaction()).is_coinbase());
+}
+
+#[test]
+fn test_psbt_extract() {
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .add_output(TxOutput::new(Amount::from_sat(5_000), Script(vec![0x51])))
+        .build();
+    let mut psbt = Psbt::from_unsigned_tx(unsigned.clone()).unwrap();
+    assert!(!psbt.is_extractable());
+    assert!(matches!(
+        psbt.extract(),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+
+    psbt.inputs[0].final_script_sig = Some(vec![0x51]);
+    // A leftover partial signature means input 1 is not finalized
+    psbt.inputs[1].final_script_sig = Some(vec![0x52]);
+    psbt.inputs[1]
+        .partial_sigs
+        .insert(vec![0x02; 33], vec![0x30; 71]);
+    assert!(!psbt.is_extractable());
+
+    psbt.inputs[1].partial_sigs.clear();
+    assert!(psbt.is_extractable());
+    let tx = psbt.extract().unwrap();
+    assert_eq!(tx.inputs()[0].script_sig().0, vec![0x51]);
+    assert_eq!(tx.inputs()[1].script_sig().0, vec![0x52]);
+    assert_eq!(tx.outputs(), unsigned.outputs());
+    assert!(psbt.unsigned_tx().inputs()[0].script_sig().0.is_empty());
+
+    // Signed transactions cannot seed a PSBT
+    assert!(Psbt::from_unsigned_tx(tx).is_err());
+}
+
+#[test]
+fn test_psbt_combine_finalize_and_serialize() {
+    let key = secret_key(7);
+    let pubkey = secp256k1::public_key_compressed(&key).unwrap().to_vec();
+    let p2pkh = Address::P2PKH(hash::hash160(&pubkey)).script_pubkey();
+    let funding = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(20_000), p2pkh.clone()))
+        .build();
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(OutPoint {
+            txid: funding.txid().into(),
+            vout: 0,
+        }))
+        .add_output(TxOutput::new(Amount::from_sat(19_000), Script(vec![0x51])))
+        .build();
+    let signed = signer::sign_legacy_inputs(&unsigned, &key, std::slice::from_ref(&p2pkh)).unwrap();
+    let Instruction::OpData(signature) = &signed.inputs()[0].script_sig().decompile().unwrap()[0]
+    else {
+        panic!("expected a signature push");
+    };
+
+    // One party supplies the UTXO, another the signature
+    let mut updater = Psbt::from_unsigned_tx(unsigned.clone()).unwrap();
+    updater.inputs[0].non_witness_utxo = Some(funding.clone());
+    let mut signer_psbt = Psbt::from_unsigned_tx(unsigned.clone()).unwrap();
+    signer_psbt
+        .add_partial_sig(0, pubkey.clone(), signature.clone())
+        .unwrap();
+    assert!(
+        signer_psbt
+            .add_partial_sig(1, pubkey.clone(), vec![])
+            .is_err()
+    );
+    assert!(signer_psbt.finalize().is_err());
+
+    let mut psbt = updater.clone();
+    psbt.combine(&signer_psbt).unwrap();
+    assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+    assert_eq!(psbt.inputs[0].non_witness_utxo.as_ref(), Some(&funding));
+    let other = Psbt::from_unsigned_tx(funding.clone()).unwrap();
+    assert!(psbt.clone().combine(&other).is_err());
+
+    // Binary round trip, before and after finalizing
+    let bytes = psbt.serialize();
+    assert_eq!(bytes[..5], *b"psbt\xff");
+    assert_eq!(Psbt::deserialize(&bytes).unwrap(), psbt);
+    psbt.finalize().unwrap();
+    assert!(psbt.is_extractable());
+    assert_eq!(Psbt::deserialize(&psbt.serialize()).unwrap(), psbt);
+    let tx = psbt.extract().unwrap();
+    assert_eq!(tx, signed);
+    let utxo = funding.outputs()[0].clone();
+    assert!(tx.verify_input(0, &utxo, ScriptVerifyFlags::all()).is_ok());
+
+    // Truncation, trailing bytes, bad magic and duplicate keys
+    let bytes = updater.serialize();
+    assert!(Psbt::deserialize(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Psbt::deserialize(&[bytes.clone(), vec![0]].concat()).is_err());
+    assert!(Psbt::deserialize(&bytes[1..]).is_err());
+    let unsigned_bytes = unsigned.serialize();
+    let unsigned_pair = [
+        vec![1, 0x00],
+        encode_compact_size(unsigned_bytes.len() as u64),
+        unsigned_bytes,
+    ]
+    .concat();
+    assert_eq!(bytes[5..5 + unsigned_pair.len()], unsigned_pair);
+    let duplicated = [&bytes[..5], &unsigned_pair, &bytes[5..]].concat();
+    assert!(Psbt::deserialize(&duplicated).is_err());
+    // A non-witness UTXO must be the transaction the input spends
+    let mut wrong = updater.clone();
+    wrong.inputs[0].non_witness_utxo = Some(unsigned.clone());
+    assert!(Psbt::deserialize(&wrong.serialize()).is_err());
+}
+
+#[test]
+fn test_psbt_keeps_uninterpreted_entries() {
+    let unsigned = two_output_transaction();
+    let mut psbt = Psbt::from_unsigned_tx(unsigned.clone()).unwrap();
+    assert_eq!(psbt.outputs.len(), 2);
+
+    // BIP-32 derivations (fingerprint, then m/84'/0'/0'/1/0) on an input and
+    // on the change output, plus a global xpub entry
+    let pubkey = secp256k1::public_key_compressed(&secret_key(3)).unwrap();
+    let derivation = hex::decode("d90c6a4f540000800000008000000080010000000000000000").unwrap();
+    let input_key = [&[0x06][..], &pubkey].concat();
+    let output_key = [&[0x02][..], &pubkey].concat();
+    psbt.inputs[0]
+        .unknown
+        .insert(input_key.clone(), derivation.clone());
+    psbt.outputs[1]
+        .unknown
+        .insert(output_key.clone(), derivation.clone());
+    psbt.unknown
+        .insert(vec![0x01, 0xaa], derivation[..4].to_vec());
+
+    let bytes = psbt.serialize();
+    let parsed = Psbt::deserialize(&bytes).unwrap();
+    assert_eq!(parsed, psbt);
+    assert_eq!(parsed.serialize(), bytes);
+    assert_eq!(parsed.inputs[0].unknown[&input_key], derivation);
+    assert!(parsed.outputs[0].unknown.is_empty());
+
+    // The combiner carries them over to a copy that lacks them
+    let mut bare = Psbt::from_unsigned_tx(unsigned).unwrap();
+    bare.combine(&parsed).unwrap();
+    assert_eq!(bare, psbt);
+}
+
+#[test]
+fn test_fee_rate_histogram() {
+    let rate = FeeRate::from_sat_per_vb;
+    let buckets = [rate(1), rate(5), rate(10)];
+    assert_eq!(mempool::fee_rate_bucket(rate(0), &buckets), 0);
+    assert_eq!(mempool::fee_rate_bucket(rate(1), &buckets), 0);
+    assert_eq!(mempool::fee_rate_bucket(rate(5), &buckets), 1);
+    assert_eq!(
+        mempool::fee_rate_bucket(FeeRate::from_sat_per_kvb(9_999), &buckets),
+        1
+    );
+    assert_eq!(mempool::fee_rate_bucket(rate(500), &buckets), 2);
+    assert_eq!(mempool::fee_rate_bucket(rate(5), &[]), 0);
+
+    let tx = two_output_transaction();
+    let vsize = tx.vsize();
+    let mempool_txs = [
+        (tx.clone(), rate(2)),
+        (tx.clone(), rate(3)),
+        (tx.clone(), rate(20)),
+    ];
+    let histogram = mempool::build_fee_histogram(&mempool_txs, &[rate(10), rate(1), rate(5)]);
+    assert_eq!(
+        histogram,
+        vec![(rate(1), 2 * vsize), (rate(5), 0), (rate(10), vsize)]
+    );
+    assert!(mempool::build_fee_histogram(&mempool_txs, &[]).is_empty());
+}
+
+#[test]
+fn test_sequence_accessors() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .add_input(TxInput::new(funding_outpoint(2), Script::default(), 144))
+        .build();
+    assert_eq!(tx.sequences(), vec![SEQUENCE_FINAL, 144]);
+    assert_eq!(tx.min_sequence(), Some(Sequence(144)));
+    assert!(!tx.all_sequences_final());
+
+    let final_tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .build();
+    assert!(final_tx.all_sequences_final());
+    assert_eq!(LegacyTransaction::builder().build().min_sequence(), None);
+}
+
+#[test]
+fn test_checksum_of_mainnet_transaction() {
+    let raw = hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap();
+    let tx = TransactionReader::new(&raw[..]).read_transaction().unwrap();
+    // The checksum Bitcoin Core puts in the `tx` message header: the leading
+    // bytes of the txid in internal byte order
+    assert_eq!(tx.checksum(), [0x16, 0x9e, 0x1e, 0x83]);
+    assert_eq!(tx.checksum(), tx.txid()[..4]);
+
+    let framed = p2p::tx_message(Network::Mainnet, &tx).unwrap();
+    assert_eq!(framed[20..24], tx.checksum());
+}
+
+#[test]
+fn test_total_size_with_signatures() {
+    let key = secret_key(7);
+    let pubkey = secp256k1::public_key_compressed(&key).unwrap();
+    let script_code = Address::P2PKH(hash::hash160(&pubkey)).script_pubkey();
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(9_000),
+            Script(script_code.0.clone()),
+        ))
+        .build();
+
+    let estimate = unsigned.total_size_with_signatures(&[OutputScriptType::P2PKH]);
+    assert_eq!(estimate, unsigned.estimated_size() + 107);
+    // 107 assumes a 72-byte DER signature; real ones are 70 to 72 bytes
+    let signed = signer::sign_legacy_inputs(&unsigned, &key, &[script_code]).unwrap();
+    let actual = signed.serialize().len();
+    assert!(
+        actual <= estimate && estimate - actual <= 2,
+        "{actual} vs {estimate}"
+    );
+
+    let two_inputs = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .build();
+    let multisig = OutputScriptType::P2MS {
+        required: 2,
+        total: 3,
+    };
+    assert_eq!(
+        two_inputs.total_size_with_signatures(&[OutputScriptType::P2PK, multisig]),
+        two_inputs.estimated_size() + 74 + 1 + 2 * 74
+    );
+    // Unlisted inputs are left as they are
+    assert_eq!(
+        two_inputs.total_size_with_signatures(&[]),
+        two_inputs.estimated_size()
+    );
+}
+
+#[test]
+fn test_bare_multisig_classification() {
+    let keys = [[0x02; 33], [0x03; 33], [0x02; 33]];
+    let mut builder = ScriptBuilder::new().push_opcode(Opcode::Op2);
+    for key in &keys {
+        builder = builder.push_data(key);
+    }
+    let script = builder
+        .push_opcode(Opcode::Op3)
+        .push_opcode(Opcode::OpCheckMultiSig)
+        .build();
+    assert_eq!(script.multisig_params(), Some((2, 3)));
+    let output = TxOutput::new(Amount::from_sat(1_000), Script(script.0.clone()));
+    assert_eq!(
+        output.script_type(),
+        OutputScriptType::P2MS {
+            required: 2,
+            total: 3
+        }
+    );
+
+    // Key count must match n, and m must not exceed n
+    let mut wrong_count = script.0.clone();
+    let n_index = wrong_count.len() - 2;
+    wrong_count[n_index] = Opcode::Op4.to_u8();
+    assert_eq!(Script(wrong_count).multisig_params(), None);
+    let mut too_many_required = script.0.clone();
+    too_many_required[0] = Opcode::Op4.to_u8();
+    assert_eq!(Script(too_many_required).multisig_params(), None);
+
+    assert!(script.is_possible_multisig());
+    let short_key = ScriptBuilder::new()
+        .push_opcode(Opcode::Op1)
+        .push_data(&[0x02; 20])
+        .push_opcode(Opcode::Op1)
+        .push_opcode(Opcode::OpCheckMultiSig)
+        .build();
+    assert!(!short_key.is_possible_multisig());
+    assert!(
+        !Address::P2PKH([0; 20])
+            .script_pubkey()
+            .is_possible_multisig()
+    );
+}
+
+#[test]
+fn test_tx_output_from_address() {
+    let value = Amount::from_sat(1_000);
+    let script_hex = |address: &str, network: Network| {
+        hex::encode(
+            &TxOutput::create_from_address(address, value, network)
+                .unwrap()
+                .script_pubkey()
+                .0,
+        )
+    };
+
+    assert_eq!(
+        script_hex("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs", Network::Mainnet),
+        "76a914f54a5851e9372b87810a8e60cdd2e7cfd80b6e3188ac"
+    );
+    assert_eq!(
+        script_hex("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", Network::Mainnet),
+        "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"
+    );
+    assert_eq!(
+        script_hex("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Testnet),
+        "76a914243f1394f44554f4ce3fd68649c19adc483ce92488ac"
+    );
+    assert_eq!(
+        script_hex("2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc", Network::Regtest),
+        "a9144e9f39ca4688ff102128ea4ccda34105324305b087"
+    );
+    // BIP-173 and BIP-350 test vectors
+    assert_eq!(
+        script_hex(
+            "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+            Network::Mainnet
+        ),
+        "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+    );
+    assert_eq!(
+        script_hex(
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            Network::Testnet
+        ),
+        "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"
+    );
+    assert_eq!(
+        script_hex(
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+            Network::Mainnet
+        ),
+        "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    );
+    let output = TxOutput::create_from_address(
+        "1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs",
+        value,
+        Network::Mainnet,
+    )
+    .unwrap();
+    assert_eq!(output.value().to_sat(), 1_000);
+
+    let create =
+        |address: &str, network: Network| TxOutput::create_from_address(address, value, network);
+    assert!(matches!(
+        create("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs", Network::Testnet),
+        Err(BitcoinError::InvalidNetwork)
+    ));
+    assert!(matches!(
+        create(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            Network::Regtest
+        ),
+        Err(BitcoinError::InvalidNetwork)
+    ));
+    for malformed in [
+        // Bad Base58Check checksum
+        "1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAt",
+        // Mixed case
+        "bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        // v0 program with a Bech32m checksum (BIP-350 invalid vector)
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kejj56z",
+        // Unknown HRP
+        "ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9",
+        // A valid checksum with no witness version or program
+        "c1v9gszs",
+        "",
+    ] {
+        assert!(
+            matches!(
+                create(malformed, Network::Mainnet),
+                Err(BitcoinError::InvalidAddress)
+            ),
+            "{malformed}"
+        );
+    }
+    assert!(bech32::decode_segwit("c1v9gszs").is_err());
+    let args: Vec<String> = ["send", "1000", "c1v9gszs"].map(String::from).to_vec();
+    assert!(parse_cli_args(&args).is_err());
+}
+
+#[test]
+fn test_psbt_update_with_utxo_set() {
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .build();
+    let mut psbt = Psbt::from_unsigned_tx(unsigned).unwrap();
+
+    let segwit_utxo = TxOutput::new(
+        Amount::from_sat(5_000),
+        Address::P2WPKH([0x11; 20]).script_pubkey(),
+    );
+    let legacy_utxo = TxOutput::new(
+        Amount::from_sat(6_000),
+        Address::P2PKH([0x22; 20]).script_pubkey(),
+    );
+    let mut utxos = UtxoSet::new();
+    utxos.insert(funding_outpoint(1), segwit_utxo.clone());
+
+    match psbt.update_with_utxo_set(&utxos) {
+        Err(BitcoinError::MissingUtxos(missing)) => assert_eq!(missing, vec![funding_outpoint(2)]),
+        other => panic!("expected missing UTXOs, got {other:?}"),
+    }
+    assert_eq!(psbt.inputs[0].witness_utxo, None);
+
+    utxos.insert(funding_outpoint(2), legacy_utxo);
+    psbt.update_with_utxo_set(&utxos).unwrap();
+    assert_eq!(psbt.inputs[0].witness_utxo, Some(segwit_utxo));
+    // Legacy inputs need the full previous transaction, which a UTXO set lacks
+    assert_eq!(psbt.inputs[1].witness_utxo, None);
+    assert_eq!(psbt.inputs[1].non_witness_utxo, None);
+}
+
+#[test]
+fn test_extract_redeem_and_witness_scripts() {
+    let redeem = Script(vec![0x51, 0x87]);
+    let script_sig = ScriptBuilder::new()
+        .push_opcode(Opcode::Op0)
+        .push_data(&[0x30; 71])
+        .push_data(&redeem.0)
+        .build();
+    assert_eq!(
+        Script::extract_p2sh_redeem_script(&script_sig),
+        Some(redeem.clone())
+    );
+
+    // Empty, not push-only, or ending in a small-integer opcode
+    assert_eq!(Script::extract_p2sh_redeem_script(&Script(vec![])), None);
+    let with_op = ScriptBuilder::new()
+        .push_data(&redeem.0)
+        .push_opcode(Opcode::OpDup)
+        .build();
+    assert_eq!(Script::extract_p2sh_redeem_script(&with_op), None);
+    assert_eq!(
+        Script::extract_p2sh_redeem_script(&Script(vec![0x00])),
+        None
+    );
+
+    let witness = vec![vec![], vec![0x30; 72], redeem.0.clone()];
+    assert_eq!(Script::extract_p2wsh_witness_script(&witness), Some(redeem));
+    assert_eq!(Script::extract_p2wsh_witness_script(&[]), None);
+}
+
+#[test]
+fn test_remove_input_and_output() {
+    let mut tx = two_output_transaction();
+    let removed = tx.remove_output(0).unwrap();
+    assert_eq!(removed.value().to_sat(), 1_000);
+    assert_eq!(tx.outputs().len(), 1);
+    assert_eq!(tx.outputs()[0].value().to_sat(), 2_000);
+    assert!(matches!(
+        tx.remove_output(1),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+
+    assert_eq!(tx.remove_input(0).unwrap(), TxInput::default());
+    assert!(tx.inputs().is_empty());
+    assert!(tx.remove_input(0).is_err());
+
+    let mut builder = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)));
+    let removed = builder.remove_input(0).unwrap();
+    assert_eq!(*removed.outpoint(), funding_outpoint(1));
+    assert!(builder.remove_output(0).is_err());
+    let tx = builder.build();
+    assert_eq!(tx.inputs().len(), 1);
+    assert_eq!(*tx.inputs()[0].outpoint(), funding_outpoint(2));
+}
+
+#[test]
+fn test_network_from_address_str() {
+    for (address, network) in [
+        ("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs", Network::Mainnet),
+        ("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", Network::Mainnet),
+        ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Testnet),
+        ("2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc", Network::Testnet),
+        (
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            Network::Mainnet,
+        ),
+        (
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            Network::Testnet,
+        ),
+    ] {
+        assert_eq!(
+            Network::from_address_str(address).unwrap(),
+            network,
+            "{address}"
+        );
+        let decoded = Address::from_str_for_network(address, network).unwrap();
+        assert_eq!(decoded.to_string_for_network(network), address);
+    }
+    assert!(matches!(
+        Network::from_address_str("ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9"),
+        Err(BitcoinError::InvalidAddress)
+    ));
+    assert!(Network::from_address_str("not an address").is_err());
+}
+
+#[test]
+fn test_apply_and_unapply_to_utxo_set() {
+    let funding = TxOutput::new(Amount::from_sat(10_000), Script(vec![0x51]));
+    let mut utxos = UtxoSet::new();
+    utxos.insert(funding_outpoint(1), funding.clone());
+    let original = utxos.clone();
+
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(9_000), Script(vec![0x52])))
+        .add_output(TxOutput::new(
+            Amount::from_sat(0),
+            Script(vec![0x6a, 0x01, 0xff]),
+        ))
+        .build();
+    tx.apply_to_utxo_set(&mut utxos).unwrap();
+    assert!(!utxos.contains(&funding_outpoint(1)));
+    let created = OutPoint {
+        txid: tx.txid().into(),
+        vout: 0,
+    };
+    assert_eq!(utxos.get(&created).unwrap().value().to_sat(), 9_000);
+    // OP_RETURN outputs are not added
+    assert_eq!(utxos.len(), 1);
+
+    // Applying twice fails and changes nothing
+    let after_apply = utxos.clone();
+    assert!(matches!(
+        tx.apply_to_utxo_set(&mut utxos),
+        Err(BitcoinError::MissingUtxos(_))
+    ));
+    assert_eq!(utxos, after_apply);
+
+    // Wrong number of previous outputs
+    assert!(tx.unapply_to_utxo_set(&mut utxos, &[]).is_err());
+    assert_eq!(utxos, after_apply);
+    tx.unapply_to_utxo_set(&mut utxos, &[funding]).unwrap();
+    assert_eq!(utxos, original);
+
+    // Spending the same outpoint twice is rejected before anything changes
+    let double_spend = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint_final(funding_outpoint(1)))
+        .build();
+    assert!(double_spend.apply_to_utxo_set(&mut utxos).is_err());
+    assert_eq!(utxos, original);
+}
+
+#[cfg(feature = "base64")]
+#[test]
+fn test_base64_round_trip() {
+    let raw = hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap();
+    let tx = LegacyTransaction::deserialize(&raw).unwrap();
+    let encoded = tx.to_base64();
+    assert!(encoded.starts_with("AQAAAAHJl6XlbhBBAvognGqFLdkGYKILLZw1JCPtziWFf803BAAAAABI"));
+    assert_eq!(LegacyTransaction::from_base64(&encoded).unwrap(), tx);
+
+    assert!(matches!(
+        LegacyTransaction::from_base64("not base64!"),
+        Err(BitcoinError::CliError { .. })
+    ));
+    // Valid base64 that is not a transaction
+    assert!(LegacyTransaction::from_base64("AQAA").is_err());
+}
+
+#[test]
+fn test_taproot_script_spend_witness() {
+    use taproot::TapLeaf;
+
+    let leaf = TapLeaf {
+        version: script::TAPSCRIPT_LEAF_VERSION,
+        script: Script(vec![0x51]),
+    };
+    let internal: [u8; 32] =
+        hex::decode("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27")
+            .unwrap()
+            .try_into()
+            .unwrap();
+    let control = taproot::control_block(&internal, &leaf, vec![[0x42; 32]]).unwrap();
+    let witness =
+        taproot::taproot_script_spend_witness(vec![vec![0x01], vec![]], &leaf, control.clone())
+            .unwrap();
+    assert_eq!(
+        witness,
+        vec![vec![0x01], vec![], vec![0x51], control.clone()]
+    );
+
+    // Malformed control block, and one built for another leaf version
+    assert!(matches!(
+        taproot::taproot_script_spend_witness(vec![], &leaf, control[..40].to_vec()),
+        Err(BitcoinError::InvalidScript)
+    ));
+    let mut other_version = control;
+    other_version[0] = 0xc2 | (other_version[0] & 1);
+    assert!(matches!(
+        taproot::taproot_script_spend_witness(vec![], &leaf, other_version),
+        Err(BitcoinError::UnsupportedTaprootLeafVersion(_))
+    ));
+}
+
+#[test]
+fn test_spending_input_indices() {
+    let key = secret_key(9);
+    let pubkey = secp256k1::public_key_compressed(&key).unwrap();
+    let mine = Address::P2PKH(hash::hash160(&pubkey));
+    let script_code = mine.script_pubkey();
+
+    let redeem = Script(vec![0x51]);
+    let p2sh = Address::P2SH(hash::hash160(&redeem.0));
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(3)))
+        .build();
+    let mut tx = signer::sign_legacy_inputs(
+        &unsigned,
+        &key,
+        &[script_code.clone(), script_code.clone(), script_code],
+    )
+    .unwrap();
+    tx.inputs_mut()[1].set_script_sig(ScriptBuilder::new().push_data(&redeem.0).build());
+
+    assert_eq!(tx.spending_input_indices(&mine), vec![0, 2]);
+    assert_eq!(tx.spending_input_indices(&p2sh), vec![1]);
+    assert!(
+        tx.spending_input_indices(&Address::P2PKH([0; 20]))
+            .is_empty()
+    );
+    assert!(
+        tx.spending_input_indices(&Address::P2WPKH(hash::hash160(&pubkey)))
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_output_indices_paying() {
+    let alice = Address::P2WPKH([0xaa; 20]);
+    let bob = Address::P2TR([0xbb; 32]);
+    let pay = |address: &Address, value: u64| {
+        TxOutput::new(Amount::from_sat(value), address.script_pubkey())
+    };
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(pay(&alice, 1_000))
+        .add_output(pay(&bob, 2_000))
+        .add_output(pay(&alice, 3_000))
+        .build();
+
+    assert_eq!(tx.output_indices_paying(&alice), vec![0, 2]);
+    assert_eq!(tx.output_indices_paying(&bob), vec![1]);
+    // Same hash, different script type
+    assert!(
+        tx.output_indices_paying(&Address::P2PKH([0xaa; 20]))
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_transaction_validator() {
+    let mut utxos = UtxoSet::new();
+    utxos.insert(
+        funding_outpoint(1),
+        TxOutput::new(
+            Amount::from_sat(100_000),
+            Address::P2WPKH([0x11; 20]).script_pubkey(),
+        ),
+    );
+    let pay = |value: u64| {
+        TxOutput::new(
+            Amount::from_sat(value),
+            Address::P2WPKH([0x22; 20]).script_pubkey(),
+        )
+    };
+
+    let validator = TransactionValidator::new()
+        .require_standard()
+        .require_valid_weight()
+        .require_no_duplicate_inputs()
+        .require_min_fee(FeeRate::from_sat_per_vb(1));
+
+    let good = LegacyTransaction::builder()
+        .version(2)
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(pay(99_000))
+        .build();
+    assert!(validator.validate(&good, &utxos).is_empty());
+
+    // Dust output, duplicate input and zero fee are all reported
+    let bad = LegacyTransaction::builder()
+        .version(2)
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(pay(150_000))
+        .add_output(pay(49_990))
+        .add_output(pay(10))
+        .build();
+    let issues = validator.validate(&bad, &utxos);
+    assert_eq!(issues.len(), 3, "{issues:?}");
+    assert!(matches!(issues[0], BitcoinError::DustOutput { .. }));
+    assert!(issues[1].to_string().contains("duplicate input"));
+    assert!(issues[2].to_string().contains("below"));
+
+    // An empty validator accepts anything; custom checks compose in
+    assert!(
+        TransactionValidator::new()
+            .validate(&bad, &utxos)
+            .is_empty()
+    );
+    let custom = TransactionValidator::new()
+        .require(|tx, _| (tx.outputs().len() > 2).then_some(BitcoinError::InvalidTransaction));
+    assert_eq!(custom.validate(&bad, &utxos).len(), 1);
+    assert!(custom.validate(&good, &utxos).is_empty());
+}
+
+#[test]
+fn test_cli_verify() {
+    let key = secret_key(3);
+    let pubkey = secp256k1::public_key_compressed(&key).unwrap();
+    let script_code = Address::P2PKH(hash::hash160(&pubkey)).script_pubkey();
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .add_output(TxOutput::new(Amount::from_sat(1_000), Script(vec![0x51])))
+        .build();
+    let signed =
+        signer::sign_legacy_inputs(&unsigned, &key, &[script_code.clone(), script_code.clone()])
+            .unwrap();
+    let tx_hex = hex::encode(&signed.serialize());
+    let utxo = |n: u8| format!("{:x}:{}", funding_outpoint(n), hex::encode(&script_code.0));
+
+    let run = |utxos: &[String]| {
+        let mut args = vec!["verify".to_string(), tx_hex.clone()];
+        for utxo in utxos {
+            args.push("--utxo".to_string());
+            args.push(utxo.clone());
+        }
+        parse_cli_args(&args).unwrap().execute()
+    };
+
+    assert_eq!(
+        run(&[utxo(1), utxo(2)]).unwrap().to_text(),
+        "all inputs valid"
+    );
+    // Input 1's UTXO is missing; input 0 still passes
+    let err = run(&[utxo(1)]).unwrap_err().to_string();
+    assert!(err.contains("input 1"), "{err}");
+    assert!(!err.contains("input 0"), "{err}");
+    // Input 0 spends a different key's output
+    let wrong = format!(
+        "{:x}:{}",
+        funding_outpoint(1),
+        hex::encode(&Address::P2PKH([0; 20]).script_pubkey().0)
+    );
+    assert!(
+        run(&[wrong, utxo(2)])
+            .unwrap_err()
+            .to_string()
+            .contains("input 0")
+    );
+    assert!(run(&["garbage".to_string()]).is_err());
+
+    let args: Vec<String> = ["verify", &tx_hex, "--script", "51"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert!(parse_cli_args(&args).is_err());
+}
+
+#[test]
+fn test_sighash_all_inputs() {
+    let key = secret_key(9);
+    let script_code = Address::P2PKH(hash::hash160(
+        &secp256k1::public_key_compressed(&key).unwrap(),
+    ))
+    .script_pubkey();
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .add_output(TxOutput::new(Amount::from_sat(50_000), Script(vec![0x51])))
+        .build();
+    let codes = [script_code.clone(), script_code.clone()];
+
+    // SIGHASH_ALL digests are the ones the signer commits to
+    let digests = unsigned
+        .sighash_all_inputs_legacy(&codes, SigHashType::All)
+        .unwrap();
+    let signed = signer::sign_legacy_inputs(&unsigned, &key, &codes).unwrap();
+    let public_key = secp256k1::public_key(&key).unwrap();
+    for (input, digest) in signed.inputs().iter().zip(&digests) {
+        let instructions = input.script_sig().clone().decompile().unwrap();
+        let Instruction::OpData(sig) = &instructions[0] else {
+            panic!("expected a signature push");
+        };
+        let signature = secp256k1::Signature::from_der(&sig[..sig.len() - 1]).unwrap();
+        assert!(secp256k1::verify(&public_key, digest, &signature));
+    }
+
+    // SINGLE without a matching output signs the constant 1
+    let single = unsigned
+        .sighash_all_inputs_legacy(&codes, SigHashType::Single)
+        .unwrap();
+    let mut one = [0u8; 32];
+    one[0] = 1;
+    assert_ne!(single[0], one);
+    assert_eq!(single[1], one);
+
+    // ANYONECANPAY ignores the other inputs; NONE ignores the outputs
+    let mut extended = unsigned.clone();
+    extended
+        .inputs_mut()
+        .push(TxInput::from_outpoint(funding_outpoint(3)));
+    extended
+        .outputs_mut()
+        .push(TxOutput::new(Amount::from_sat(1_000), Script(vec![0x52])));
+    let acp = |tx: &LegacyTransaction| {
+        tx.sighash_all_inputs_legacy(
+            &vec![script_code.clone(); tx.inputs().len()],
+            SigHashType::NonePlusAnyoneCanPay,
+        )
+        .unwrap()[0]
+    };
+    assert_eq!(acp(&unsigned), acp(&extended));
+    assert!(
+        unsigned
+            .sighash_all_inputs_legacy(&codes[..1], SigHashType::All)
+            .is_err()
+    );
+
+    // Single-input method agrees with the batch, and each type commits to
+    // something different
+    assert_eq!(
+        unsigned
+            .signature_hash(1, &script_code, SigHashType::All)
+            .unwrap(),
+        digests[1]
+    );
+    let all_types = [
+        SigHashType::All,
+        SigHashType::None,
+        SigHashType::Single,
+        SigHashType::AllPlusAnyoneCanPay,
+        SigHashType::NonePlusAnyoneCanPay,
+        SigHashType::SinglePlusAnyoneCanPay,
+    ];
+    let hashes: std::collections::HashSet<_> = all_types
+        .iter()
+        .map(|&ty| unsigned.signature_hash(0, &script_code, ty).unwrap())
+        .collect();
+    assert_eq!(hashes.len(), all_types.len());
+    assert!(matches!(
+        unsigned.signature_hash(2, &script_code, SigHashType::All),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+
+    // BIP-143 native P2WPKH example, second input
+    let tx = LegacyTransaction::deserialize(&hex::decode(BIP143_P2WPKH_UNSIGNED_TX_HEX).unwrap())
+        .unwrap();
+    let prevouts = [
+        TxOutput::new(
+            Amount::from_sat(625_000_000),
+            Script(
+                hex::decode(
+                    "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+                )
+                .unwrap(),
+            ),
+        ),
+        TxOutput::new(
+            Amount::from_sat(600_000_000),
+            Script(hex::decode("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap()),
+        ),
+    ];
+    let codes = [
+        prevouts[0].script_pubkey().clone(),
+        Script(hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap()),
+    ];
+    let digests = tx
+        .sighash_all_inputs_segwit_v0(&prevouts, &codes, SigHashType::All)
+        .unwrap();
+    assert_eq!(
+        hex::encode(&digests[1]),
+        "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+    );
+    assert!(
+        tx.sighash_all_inputs_segwit_v0(&prevouts[..1], &codes, SigHashType::All)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_segwit_v0_signing_data() {
+    // BIP-143 native P2WPKH example, second input
+    let tx = LegacyTransaction::deserialize(&hex::decode(BIP143_P2WPKH_UNSIGNED_TX_HEX).unwrap())
+        .unwrap();
+    let script_code =
+        Script(hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap());
+    let value = Amount::from_sat(600_000_000);
+    let preimage =
+        sighash::segwit_v0_signing_data(&tx, 1, &script_code, value, SigHashType::All).unwrap();
+    assert_eq!(
+        hex::encode(&preimage),
+        concat!(
+            "01000000",
+            "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37",
+            "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b",
+            "ef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a01000000",
+            "1976a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac",
+            "0046c32300000000",
+            "ffffffff",
+            "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5",
+            "11000000",
+            "01000000",
+        )
+    );
+
+    let cache = sighash::SegwitV0Cache::new(&tx);
+    let digest = sighash::segwit_v0_signature_hash(
+        &tx,
+        &cache,
+        1,
+        &script_code,
+        value.to_sat(),
+        SigHashType::All,
+    )
+    .unwrap();
+    assert_eq!(digest, hash::sha256d(&preimage));
+    assert!(
+        sighash::segwit_v0_signing_data(&tx, 2, &script_code, value, SigHashType::All).is_err()
+    );
+}
+
+#[test]
+fn test_amount_checked_arithmetic() {
+    const SUM: Option<Amount> = Amount::from_sat(2).checked_add(Amount::from_sat(3));
+    assert_eq!(SUM, Some(Amount::from_sat(5)));
+    let max = Amount::from_sat(u64::MAX);
+    assert_eq!(max.checked_add(Amount::from_sat(1)), None);
+    assert_eq!(max.saturating_add(Amount::from_sat(1)), max);
+    assert_eq!(
+        Amount::from_sat(5).checked_sub(Amount::from_sat(3)),
+        Some(Amount::from_sat(2))
+    );
+    assert_eq!(Amount::from_sat(3).checked_sub(Amount::from_sat(5)), None);
+    assert_eq!(
+        Amount::from_sat(3).saturating_sub(Amount::from_sat(5)),
+        Amount::from_sat(0)
+    );
+
+    // Output totals that overflow u64 are rejected rather than wrapping
+    let mut utxos = UtxoSet::new();
+    utxos.insert(
+        funding_outpoint(1),
+        TxOutput::new(Amount::from_sat(10), Script(vec![0x51])),
+    );
+    let overflowing = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(u64::MAX),
+            Script(vec![0x51]),
+        ))
+        .add_output(TxOutput::new(Amount::from_sat(2), Script(vec![0x51])))
+        .build();
+    assert!(matches!(
+        overflowing.absolute_fee(&utxos),
+        Err(BitcoinError::InvalidAmount)
+    ));
+}
+
+#[test]
+fn test_malleability_sources() {
+    let key = secret_key(4);
+    let pubkey = secp256k1::public_key_compressed(&key).unwrap();
+    let script_code = Address::P2PKH(hash::hash160(&pubkey)).script_pubkey();
+    let unsigned = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(10_000), Script(vec![0x51])))
+        .build();
+    let signed = signer::sign_legacy_inputs(&unsigned, &key, &[script_code]).unwrap();
+    assert!(signed.is_free_of_malleability());
+
+    let Instruction::OpData(sig) = &signed.inputs()[0].script_sig().clone().decompile().unwrap()[0]
+    else {
+        panic!("expected a signature push");
+    };
+    let with_script_sig = |script_sig: Vec<u8>| {
+        let mut tx = signed.clone();
+        tx.inputs_mut()[0].set_script_sig(Script(script_sig));
+        tx.malleability_sources()
+    };
+
+    // The same signature behind OP_PUSHDATA1
+    let mut non_minimal = vec![0x4c, sig.len() as u8];
+    non_minimal.extend_from_slice(sig);
+    non_minimal.push(pubkey.len() as u8);
+    non_minimal.extend_from_slice(&pubkey);
+    assert_eq!(
+        with_script_sig(non_minimal),
+        vec![(0, "non-minimal push".to_string())]
+    );
+
+    // Negating S keeps the signature valid but changes the txid
+    let mut signature = secp256k1::Signature::from_der(&sig[..sig.len() - 1]).unwrap();
+    signature.s = secp256k1::N.overflowing_sub(signature.s).0;
+    let mut high_s = signature.to_der();
+    high_s.push(0x01);
+    let script_sig = ScriptBuilder::new()
+        .push_data(&high_s)
+        .push_data(&pubkey)
+        .build();
+    assert_eq!(
+        with_script_sig(script_sig.0),
+        vec![(0, "signature 0 has a high S value".to_string())]
+    );
+
+    // Padding R with a redundant zero byte is not strict DER
+    let mut padded = vec![0x30, sig[1] + 1, 0x02, sig[3] + 1, 0x00];
+    padded.extend_from_slice(&sig[4..]);
+    let script_sig = ScriptBuilder::new()
+        .push_data(&padded)
+        .push_opcode(Opcode::OpNop)
+        .build();
+    let sources = with_script_sig(script_sig.0);
+    assert!(sources.contains(&(0, "scriptSig is not push-only".to_string())));
+    assert!(sources.contains(&(0, "signature 0 is not strict DER".to_string())));
+}
+
+#[test]
+fn test_generate_key_command() {
+    let run = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        parse_cli_args(&args)?.execute()
+    };
+
+    let CommandResult::Field { name, value } = run(&["genkey"]).unwrap() else {
+        panic!("expected an address");
+    };
+    assert_eq!(name, "address");
+    assert!(matches!(
+        Address::from_str_for_network(&value, Network::Mainnet).unwrap(),
+        Address::P2WPKH(_)
+    ));
+    assert_ne!(run(&["genkey"]).unwrap().to_text(), value);
+
+    let regtest = run(&["genkey", "--network", "regtest"]).unwrap().to_text();
+    assert!(regtest.starts_with("bcrt1q"), "{regtest}");
+    assert!(run(&["genkey", "--network", "moon"]).is_err());
+    assert!(run(&["genkey", "--testnet"]).is_err());
+}
+
+#[test]
+fn test_with_methods() {
+    let tx = two_output_transaction();
+    let outputs = vec![TxOutput::new(Amount::from_sat(1), Script(vec![0x51]))];
+    let updated = tx
+        .clone()
+        .with_version(2)
+        .with_lock_time(LockTime::from(500_000))
+        .with_inputs(vec![TxInput::from_outpoint(funding_outpoint(1))])
+        .with_outputs(outputs.clone());
+    assert_eq!(updated.version(), 2);
+    assert_eq!(updated.lock_time().to_consensus_u32(), 500_000);
+    assert_eq!(*updated.inputs()[0].outpoint(), funding_outpoint(1));
+    assert_eq!(updated.outputs(), &outputs[..]);
+    assert_ne!(updated.txid(), tx.txid());
+    assert_eq!(tx.clone().with_version(tx.version()), tx);
+}
+
+#[test]
+fn test_legacy_transaction_display() {
+    let tx = two_output_transaction().with_version(2);
+    let txid = DecodedTransaction::from(tx.clone()).txid;
+    let total: u64 = tx
+        .outputs()
+        .iter()
+        .map(|output| output.value().to_sat())
+        .sum();
+    assert_eq!(
+        tx.to_string(),
+        format!("{}... v2 1in 2out {total}sat lock:0", &txid[..8])
+    );
+}
+
+#[test]
+fn test_outpoint_from_txid_hex() {
+    let txid_hex = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+    let outpoint = OutPoint::from_txid_hex(txid_hex, 1).unwrap();
+    assert_eq!(outpoint.txid.0[0], 0x16);
+    assert_eq!(outpoint.txid.0[31], 0xf4);
+    assert_eq!(outpoint.vout, 1);
+    assert_eq!(outpoint.txid_hex(), txid_hex);
+    assert_eq!(
+        format!("{txid_hex}:1").parse::<OutPoint>().unwrap(),
+        outpoint
+    );
+
+    assert!(OutPoint::from_txid_hex(&txid_hex[2..], 0).is_err());
+    assert!(OutPoint::from_txid_hex(&format!("{txid_hex}00"), 0).is_err());
+    assert!(OutPoint::from_txid_hex(&txid_hex.replace('f', "g"), 0).is_err());
+}
+
+#[test]
+fn test_builder_batch_inputs_and_outputs() {
+    let output = |value| TxOutput::new(Amount::from_sat(value), Script(vec![0x51]));
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_inputs((2..=3).map(|n| TxInput::from_outpoint(funding_outpoint(n))))
+        .add_outputs([output(1), output(2)])
+        .build();
+    assert_eq!(tx.inputs().len(), 3);
+    assert_eq!(*tx.inputs()[2].outpoint(), funding_outpoint(3));
+    assert_eq!(tx.outputs().len(), 2);
+
+    let replaced = LegacyTransactionBuilder::from_transaction(&tx)
+        .set_inputs(vec![TxInput::from_outpoint(funding_outpoint(9))])
+        .set_outputs(vec![output(7)])
+        .build();
+    assert_eq!(replaced.inputs().len(), 1);
+    assert_eq!(*replaced.inputs()[0].outpoint(), funding_outpoint(9));
+    assert_eq!(replaced.outputs(), &[output(7)]);
+}
+
+#[test]
+fn test_script_default_is_empty() {
+    assert!(Script::default().is_empty());
+    assert_eq!(Script::default(), Script(Vec::new()));
+    assert_eq!(TxInput::default().script_sig().0, Script::default().0);
+}
+
+#[test]
+fn test_vector_txids() {
+    for (tx_hex, txid) in [
+        (GENESIS_COINBASE_TX_HEX, GENESIS_COINBASE_TXID),
+        (FIRST_BITCOIN_TRANSFER_TX_HEX, FIRST_BITCOIN_TRANSFER_TXID),
+    ] {
+        let tx = LegacyTransaction::deserialize(&hex::decode(tx_hex).unwrap()).unwrap();
+        assert_eq!(tx.txid_hex(), txid);
+        assert_eq!(Transaction::from(tx.clone()).txid_hex(), txid);
+        assert_eq!(hash::hash256(&tx.serialize()), tx.txid());
+        assert_eq!(DecodedTransaction::from(tx).txid, txid);
+    }
+
+    let genesis =
+        LegacyTransaction::deserialize(&hex::decode(GENESIS_COINBASE_TX_HEX).unwrap()).unwrap();
+    assert!(genesis.is_coinbase());
+    assert_eq!(genesis.outputs()[0].value().to_sat(), 50 * 100_000_000);
+}
+
+#[test]
+fn test_coin_selection_strategies() {
+    use coinselect::*;
+
+    let p2pkh = Address::P2PKH([5; 20]).script_pubkey();
+    let utxo = |n: u8, value: u64| {
+        Utxo::new(
+            funding_outpoint(n),
+            TxOutput::new(Amount::from_sat(value), p2pkh.clone()),
+        )
+    };
+    // Effective values of 1000, 2000, 3000 and 5000 at 1 sat/vB
+    let utxos = [
+        utxo(1, 1_148),
+        utxo(2, 2_148),
+        utxo(3, 3_148),
+        utxo(4, 5_148),
+        utxo(5, 100),
+    ];
+    let rate = FeeRate::from_sat_per_vb(1);
+    let outpoints = |selected: &[Utxo]| {
+        let mut outpoints: Vec<OutPoint> = selected.iter().map(|u| u.outpoint.clone()).collect();
+        outpoints.sort();
+        outpoints
+    };
+
+    assert_eq!(
+        utxos[0].effective_value(rate),
+        Some(Amount::from_sat(1_000))
+    );
+    assert_eq!(utxos[4].effective_value(rate), None);
+    // 10 bytes of overhead, a 148 vB input and a 34 vB output
+    assert_eq!(estimate_vsize(&utxos[..1], &[utxos[0].output.clone()]), 192);
+
+    let selected = largest_first(&utxos, Amount::from_sat(6_000), rate).unwrap();
+    assert_eq!(
+        outpoints(&selected),
+        vec![funding_outpoint(3), funding_outpoint(4)]
+    );
+
+    // Exact match without change, and the tightest match within the window
+    let selected = branch_and_bound(&utxos, Amount::from_sat(4_000), rate, Amount::ZERO).unwrap();
+    assert_eq!(
+        outpoints(&selected),
+        vec![funding_outpoint(1), funding_outpoint(3)]
+    );
+    let selected =
+        branch_and_bound(&utxos, Amount::from_sat(4_500), rate, Amount::from_sat(600)).unwrap();
+    assert_eq!(outpoints(&selected), vec![funding_outpoint(4)]);
+    assert!(
+        branch_and_bound(&utxos, Amount::from_sat(4_500), rate, Amount::from_sat(100)).is_none()
+    );
+
+    let selected = single_random_draw(&utxos, Amount::from_sat(9_000), rate).unwrap();
+    let total = selected
+        .iter()
+        .map(|u| u.effective_value(rate).unwrap().to_sat())
+        .sum::<u64>();
+    assert!(total >= 9_000);
+
+    // Uneconomic UTXOs do not count towards what is available
+    for strategy in [
+        SelectionStrategy::LargestFirst,
+        SelectionStrategy::BranchAndBound,
+        SelectionStrategy::SingleRandomDraw,
+    ] {
+        match select(
+            strategy,
+            &utxos,
+            Amount::from_sat(11_001),
+            rate,
+            Amount::ZERO,
+        ) {
+            Err(BitcoinError::InsufficientFunds { needed, available }) => {
+                assert_eq!(needed, Amount::from_sat(11_001));
+                assert_eq!(available, Amount::from_sat(11_000));
+            }
+            other => panic!("expected InsufficientFunds, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_builder_select_coins() {
+    let p2pkh = Address::P2PKH([5; 20]).script_pubkey();
+    let p2wpkh = Address::P2WPKH([6; 20]).script_pubkey();
+    let utxos: Vec<Utxo> = [(1, 1_148), (2, 2_148), (3, 3_148), (4, 5_148)]
+        .into_iter()
+        .map(|(n, value)| {
+            Utxo::new(
+                funding_outpoint(n),
+                TxOutput::new(Amount::from_sat(value), p2pkh.clone()),
+            )
+        })
+        .chain([Utxo::new(
+            funding_outpoint(5),
+            TxOutput::new(Amount::from_sat(30_000), p2wpkh.clone()),
+        )])
+        .collect();
+    let rate = FeeRate::from_sat_per_vb(1);
+    let pay = |sat: u64| {
+        LegacyTransaction::builder().add_output(TxOutput::new(Amount::from_sat(sat), p2pkh.clone()))
+    };
+    let funded_by = |tx: &LegacyTransaction| {
+        let mut outpoints: Vec<OutPoint> =
+            tx.inputs().iter().map(|i| i.outpoint().clone()).collect();
+        outpoints.sort();
+        outpoints
+    };
+
+    // 3956 + 44 vB without inputs matches 1000 + 3000 exactly: no change
+    let tx = pay(3_956).select_coins(&utxos, rate).unwrap().build();
+    assert_eq!(
+        funded_by(&tx),
+        vec![funding_outpoint(1), funding_outpoint(3)]
+    );
+    assert_eq!(tx.outputs().len(), 1);
+
+    // Change goes back to the largest UTXO's script, after a 144 vB fee
+    let tx = pay(20_000).select_coins(&utxos, rate).unwrap().build();
+    assert_eq!(funded_by(&tx), vec![funding_outpoint(5)]);
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(
+        tx.outputs()[1].value(),
+        Amount::from_sat(30_000 - 20_000 - 144)
+    );
+    assert_eq!(tx.outputs()[1].script_pubkey(), &p2wpkh);
+
+    // Dust change (256 sat, below the 294 sat P2WPKH limit) is left to the fee
+    let tx = pay(29_600).select_coins(&utxos, rate).unwrap().build();
+    assert_eq!(tx.outputs().len(), 1);
+
+    assert!(matches!(
+        pay(45_000).select_coins(&utxos, rate),
+        Err(BitcoinError::InsufficientFunds { .. })
+    ));
+    assert!(matches!(
+        pay(1_000)
+            .add_input(TxInput::from_outpoint(funding_outpoint(9)))
+            .select_coins(&utxos, rate),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn test_estimate_input_count_needed() {
+    let p2wpkh = Address::P2WPKH([7; 20]).script_pubkey().0;
+    let outputs: Vec<(OutPoint, TxOutput)> = [10_000, 5_000, 20_000]
+        .into_iter()
+        .enumerate()
+        .map(|(n, value)| {
+            (
+                funding_outpoint(n as u8),
+                TxOutput::new(Amount::from_sat(value), Script(p2wpkh.clone())),
+            )
+        })
+        .collect();
+    let utxos: Vec<(&OutPoint, &TxOutput)> = outputs.iter().map(|(o, t)| (o, t)).collect();
+    let rate = FeeRate::from_sat_per_vb(1);
+    let estimate = |target| {
+        LegacyTransaction::estimate_input_count_needed(
+            &utxos,
+            Amount::from_sat(target),
+            rate,
+            OutputScriptType::P2WPKH,
+        )
+    };
+
+    // 10 overhead + 2 * 31 output + 68 per input vbytes
+    assert_eq!(estimate(15_000).unwrap(), 1);
+    assert_eq!(estimate(25_000).unwrap(), 2);
+    assert!(matches!(
+        estimate(34_900),
+        Err(BitcoinError::InsufficientFunds { needed, available })
+            if needed == Amount::from_sat(34_900 + 276) && available == Amount::from_sat(35_000)
+    ));
+    assert!(
+        LegacyTransaction::estimate_input_count_needed(
+            &[],
+            Amount::from_sat(1),
+            rate,
+            OutputScriptType::P2PKH
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_legacy_transaction_hash() {
+    use std::collections::HashSet;
+
+    let build = |value| {
+        LegacyTransaction::builder()
+            .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+            .add_output(TxOutput::new(Amount::from_sat(value), Script(vec![0x51])))
+            .build()
+    };
+    let mut set = HashSet::new();
+    assert!(set.insert(build(1_000)));
+    assert!(!set.insert(build(1_000)));
+    assert!(set.insert(build(2_000)));
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&build(2_000)));
+}
+
+#[test]
+fn test_p2sh_script_sig() {
+    let key = secret_key(6);
+    let redeem_script =
+        Script::pay_to_pubkey(&secp256k1::public_key_compressed(&key).unwrap()).unwrap();
+    let utxo = TxOutput::new(
+        Amount::from_sat(10_000),
+        Script(
+            Address::P2SH(hash::hash160(&redeem_script.0))
+                .script_pubkey()
+                .0,
+        ),
+    );
+    let mut tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(9_000), Script(vec![0x51])))
+        .build();
+
+    let sighash = tx
+        .sighash_all_inputs_legacy(std::slice::from_ref(&redeem_script), SigHashType::All)
+        .unwrap()[0];
+    let mut signature = secp256k1::sign(&key, &sighash).unwrap().to_der();
+    signature.push(0x01);
+    let script_sig = Script::p2sh_script_sig(&[signature.clone()], &redeem_script);
+    assert_eq!(
+        Script::extract_p2sh_redeem_script(&script_sig),
+        Some(redeem_script.clone())
+    );
+    tx.inputs_mut()[0].set_script_sig(script_sig);
+    script_interpreter::verify_input(&tx, 0, &utxo, ScriptVerifyFlags::P2SH).unwrap();
+
+    // The multisig form leads with the OP_0 dummy
+    let multisig = Script::p2sh_multisig_script_sig(&[signature.clone()], &redeem_script);
+    assert_eq!(
+        multisig.decompile().unwrap(),
+        vec![
+            Instruction::Op(Opcode::Op0),
+            Instruction::OpData(signature),
+            Instruction::OpData(redeem_script.0),
+        ]
+    );
+}
+
+#[test]
+fn test_amount_parsing_and_btc_conversion() {
+    assert_eq!(
+        "0.001 BTC".parse::<Amount>().unwrap(),
+        Amount::from_sat(100_000)
+    );
+    assert_eq!(
+        "100000 sat".parse::<Amount>().unwrap(),
+        Amount::from_sat(100_000)
+    );
+    assert_eq!("21000000btc".parse::<Amount>().unwrap(), Amount::MAX);
+    assert_eq!("1 sats".parse::<Amount>().unwrap(), Amount::ONE_SAT);
+    assert_eq!("0.00000001 BTC".parse::<Amount>().unwrap(), Amount::ONE_SAT);
+    // Display round-trips through FromStr
+    let amount = Amount::from_sat(123_456);
+    assert_eq!(amount.to_string().parse::<Amount>().unwrap(), amount);
+
+    for bad in [
+        "",
+        "100",
+        "0.000000001 BTC",
+        "1.5 sat",
+        ".5 BTC",
+        "1.2.3 BTC",
+        "-1 sat",
+        "1e3 sat",
+        "10 bits",
+        "21000000.00000001 BTC",
+        "99999999999999999999 sat",
+    ] {
+        assert!(
+            matches!(bad.parse::<Amount>(), Err(BitcoinError::InvalidAmount)),
+            "{bad}"
+        );
+    }
+
+    assert_eq!(Amount::from_btc(0.001).unwrap(), Amount::from_sat(100_000));
+    assert_eq!(Amount::from_sat(150_000_000).to_btc(), 1.5);
+    assert!(Amount::from_btc(-0.1).is_err());
+    assert!(Amount::from_btc(f64::NAN).is_err());
+    assert!(Amount::from_btc(21_000_001.0).is_err());
+}
+
+#[test]
+fn test_amount_constants() {
+    assert_eq!(Amount::ZERO.to_sat(), 0);
+    assert_eq!(Amount::ONE_SAT.to_sat(), 1);
+    assert_eq!(Amount::ONE_BTC.to_sat(), 100_000_000);
+    assert_eq!(Amount::MAX, MAX_MONEY);
+    assert_eq!(Amount::MAX.to_sat(), 2_100_000_000_000_000);
+
+    assert!(Amount::from_sat(545).is_dust());
+    assert!(!Amount::DUST_LIMIT.is_dust());
+    // The fixed limit agrees with the P2PKH threshold at 1 sat/vB
+    let p2pkh = TxOutput::new(
+        Amount::from_sat(Amount::DUST_LIMIT.to_sat()),
+        Address::P2PKH([0; 20]).script_pubkey(),
+    );
+    assert!(!p2pkh.is_dust(FeeRate::from_sat_per_vb(1)));
+    assert_eq!(
+        p2pkh.dust_threshold(FeeRate::from_sat_per_vb(1)),
+        Amount::DUST_LIMIT
+    );
+}
+
+#[test]
+fn test_detect_segwit() {
+    let segwit = sample_segwit_transaction().serialize();
+    assert!(detect_segwit(&segwit));
+    let legacy = hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap();
+    assert!(!detect_segwit(&legacy));
+
+    assert!(!detect_segwit(&[]));
+    assert!(!detect_segwit(&segwit[..5]));
+    assert!(detect_segwit(&segwit[..6]));
+    assert!(!detect_segwit(&[1, 0, 0, 0, 0x00, 0x02]));
+}
+
+#[test]
+fn test_block_header_bytes() {
+    let genesis_header = hex::decode("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c").unwrap();
+    let bytes: [u8; 80] = genesis_header.clone().try_into().unwrap();
+    let header = BlockHeader::try_from_bytes(&bytes).unwrap();
+    assert_eq!(header.version, 1);
+    assert_eq!(header.time, 1_231_006_505);
+    assert_eq!(header.bits, 0x1d00ffff);
+    assert_eq!(header.nonce, 2_083_236_893);
+    // The genesis block's only transaction is its merkle root
+    let coinbase =
+        LegacyTransaction::deserialize(&hex::decode(GENESIS_COINBASE_TX_HEX).unwrap()).unwrap();
+    assert_eq!(header.merkle_root, coinbase.txid());
+
+    assert_eq!(header.serialize_header(), bytes);
+    assert_eq!(header.serialize(), genesis_header);
+    let mut block_hash = hash::sha256d(&bytes);
+    block_hash.reverse();
+    assert_eq!(
+        hex::encode(&block_hash),
+        "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+    );
+
+    let block = Block {
+        header,
+        transactions: Vec::new(),
+    };
+    assert_eq!(block.header_bytes(), bytes);
+}
+
+#[test]
+fn test_block_parsing_and_proof_of_work() {
+    let mut bytes = hex::decode("0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c").unwrap();
+    bytes.push(1);
+    bytes.extend(hex::decode(GENESIS_COINBASE_TX_HEX).unwrap());
+
+    let block = Block::deserialize(&bytes).unwrap();
+    assert_eq!(block.transactions.len(), 1);
+    assert!(block.transactions[0].is_coinbase());
+    assert_eq!(block.serialize(), bytes);
+    assert_eq!(
+        block.header.block_hash_hex(),
+        "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+    );
+    assert_eq!(
+        BlockHeader::deserialize(&bytes[..80]).unwrap(),
+        block.header
+    );
+    assert!(Block::deserialize(&bytes[..bytes.len() - 1]).is_err());
+
+    // 0x1d00ffff is the minimum difficulty: 0xffff followed by 26 zero bytes
+    let mut max_target = [0u8; 32];
+    max_target[4] = 0xff;
+    max_target[5] = 0xff;
+    assert_eq!(block::target_from_bits(0x1d00ffff).unwrap(), max_target);
+    let mut small = [0u8; 32];
+    small[31] = 0x12;
+    assert_eq!(block::target_from_bits(0x01123456).unwrap(), small);
+    for bits in [0x1d800000u32, 0x1d000000, 0x01003456, 0x21010000] {
+        assert!(matches!(
+            block::target_from_bits(bits),
+            Err(BitcoinError::InvalidTarget(b)) if b == bits
+        ));
+    }
+
+    block.header.validate_pow().unwrap();
+    let mut header = block.header.clone();
+    header.nonce += 1;
+    assert!(matches!(
+        header.validate_pow(),
+        Err(BitcoinError::InvalidProofOfWork)
+    ));
+}
+
+#[test]
+fn test_block_with_segwit_transaction() {
+    let coinbase =
+        LegacyTransaction::deserialize(&hex::decode(GENESIS_COINBASE_TX_HEX).unwrap()).unwrap();
+    let spend = sample_segwit_transaction();
+    let block = Block {
+        header: BlockHeader::try_from_bytes(&[0u8; 80]).unwrap(),
+        transactions: vec![coinbase.into(), spend.clone().into()],
+    };
+
+    // The witness survives the round trip, and the merkle root commits to txids
+    let decoded = Block::deserialize(&block.serialize()).unwrap();
+    assert_eq!(decoded, block);
+    assert_eq!(decoded.transactions[1], Transaction::SegWit(spend));
+    assert_eq!(
+        decoded.compute_merkle_root(),
+        merkle::merkle_root(&[block.transactions[0].txid(), block.transactions[1].txid()])
+    );
+}
+
+#[test]
+fn test_merkle_root_and_proofs() {
+    let txids: Vec<[u8; 32]> = (0..5u8).map(|i| hash::sha256d(&[i])).collect();
+    assert_eq!(merkle::merkle_root(&[]), None);
+    assert_eq!(merkle::merkle_root(&txids[..1]), Some(txids[0]));
+
+    // Five leaves: the odd nodes at each level are paired with themselves
+    let pair = |a: &[u8; 32], b: &[u8; 32]| hash::sha256d(&[&a[..], &b[..]].concat());
+    let ab = pair(&txids[0], &txids[1]);
+    let cd = pair(&txids[2], &txids[3]);
+    let ee = pair(&txids[4], &txids[4]);
+    let expected = pair(&pair(&ab, &cd), &pair(&ee, &ee));
+    let root = merkle::merkle_root(&txids).unwrap();
+    assert_eq!(root, expected);
+
+    let mut header = BlockHeader::try_from_bytes(&[0u8; 80]).unwrap();
+    header.merkle_root = root;
+    for index in 0..txids.len() {
+        let proof = MerkleProof::generate(&txids, index).unwrap();
+        assert_eq!(proof.path.len(), 3);
+        assert!(proof.verify(&header));
+
+        let mut moved = proof.clone();
+        moved.index ^= 1;
+        assert!(index == 4 || !moved.verify(&header));
+    }
+    assert!(MerkleProof::generate(&txids, 5).is_none());
+
+    // The genesis block's merkle root is its coinbase txid
+    let coinbase =
+        LegacyTransaction::deserialize(&hex::decode(GENESIS_COINBASE_TX_HEX).unwrap()).unwrap();
+    let block = Block {
+        header: header.clone(),
+        transactions: vec![coinbase.clone().into()],
+    };
+    assert_eq!(block.compute_merkle_root(), Some(coinbase.txid()));
+}
+
+#[test]
+fn test_pay_to_anchor() {
+    let anchor = Script::pay_to_anchor();
+    assert_eq!(anchor.0, vec![0x51, 0x02, 0x4e, 0x73]);
+    assert!(anchor.is_p2a());
+    assert_eq!(anchor.witness_version(), Some(1));
+    assert!(!anchor.is_p2tr());
+    assert!(!Script(vec![0x51, 0x02, 0x4e, 0x74]).is_p2a());
+
+    let output = TxOutput::new(Amount::from_sat(0), Script(anchor.0));
+    assert_eq!(output.script_type(), OutputScriptType::P2A);
+    assert!(output.is_standard());
+    assert!(!TxOutput::new(Amount::from_sat(0), Script(vec![0xff])).is_standard());
+
+    // A zero-value anchor alongside the payment passes relay policy
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(10_000),
+            Address::P2WPKH([1; 20]).script_pubkey(),
+        ))
+        .add_output(output)
+        .build();
+    assert!(
+        TransactionValidator::new()
+            .require_standard()
+            .validate(&tx, &UtxoSet::new())
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_non_witness_and_witness_size() {
+    let legacy = two_output_transaction();
+    assert_eq!(legacy.non_witness_size(), legacy.serialize().len());
+    assert_eq!(legacy.weight(), 4 * legacy.non_witness_size() as u64);
+
+    let segwit = sample_segwit_transaction();
+    assert_eq!(segwit.non_witness_size(), segwit.base_size());
+    assert_eq!(
+        segwit.non_witness_size() + segwit.witness_size(),
+        segwit.serialize().len()
+    );
+    assert_eq!(
+        segwit.weight(),
+        (4 * segwit.non_witness_size() + segwit.witness_size()) as u64
+    );
+}
+
+#[test]
+fn test_requires_segwit_spending_and_createtx() {
+    assert!(!LegacyTransaction::requires_segwit_spending(&[]));
+    assert!(!LegacyTransaction::requires_segwit_spending(&[
+        OutputScriptType::P2PKH,
+        OutputScriptType::P2PK
+    ]));
+    assert!(LegacyTransaction::requires_segwit_spending(&[
+        OutputScriptType::P2PKH,
+        OutputScriptType::P2WPKH
+    ]));
+    assert!(LegacyTransaction::requires_segwit_spending(&[
+        OutputScriptType::P2SH
+    ]));
+
+    let input = format!(
+        "{:x}:{}",
+        funding_outpoint(1),
+        hex::encode(&Address::P2WPKH([2; 20]).script_pubkey().0)
+    );
+    let args: Vec<String> = [
+        "createtx",
+        "--input",
+        &input,
+        "--output",
+        "1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs:5000",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let CommandResult::TransactionHex(raw) = parse_cli_args(&args).unwrap().execute().unwrap()
+    else {
+        panic!("expected a transaction");
+    };
+    let tx = LegacyTransaction::deserialize(&hex::decode(&raw).unwrap()).unwrap();
+    assert_eq!(*tx.inputs()[0].outpoint(), funding_outpoint(1));
+    assert_eq!(tx.outputs()[0].value().to_sat(), 5000);
+    assert_eq!(tx.outputs()[0].script_type(), OutputScriptType::P2PKH);
+
+    // A mainnet address on testnet, and a malformed amount
+    let mut testnet = args.clone();
+    testnet.extend(["--network".to_string(), "testnet".to_string()]);
+    assert!(parse_cli_args(&testnet).unwrap().execute().is_err());
+    let mut bad_amount = args.clone();
+    bad_amount[4] = "1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs:lots".to_string();
+    assert!(parse_cli_args(&bad_amount).unwrap().execute().is_err());
+}
+
+#[test]
+fn test_cli_decode_estimatefee_and_help() {
+    let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let run = |list: &[&str]| parse_cli_args(&args(list)).and_then(|command| command.execute());
+
+    let raw = FIRST_BITCOIN_TRANSFER_TX_HEX;
+    let Ok(CommandResult::Decoded(decoded)) = run(&["decode", raw]) else {
+        panic!("expected a decoded transaction");
+    };
+    assert_eq!(
+        decoded.txid,
+        "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"
+    );
+    assert!(run(&["decode", "zz"]).is_err());
+
+    let Ok(CommandResult::Fee { sat }) = run(&["estimatefee", "250", "4"]) else {
+        panic!("expected a fee");
+    };
+    assert_eq!(sat, 1000);
+
+    // Flags take either form
+    let Ok(CommandResult::TransactionHex(raw)) = run(&["createtx", "--locktime=500000"]) else {
+        panic!("expected a transaction");
+    };
+    let tx = LegacyTransaction::deserialize(&hex::decode(&raw).unwrap()).unwrap();
+    assert_eq!(tx.lock_time().to_consensus_u32(), 500_000);
+    assert!(matches!(
+        parse_cli_args(&args(&["createtx", "--locktime", "7"])).unwrap(),
+        CliCommand::CreateTx { locktime: 7, .. }
+    ));
+
+    let Ok(CommandResult::Help(text)) = run(&["--help"]) else {
+        panic!("expected help");
+    };
+    for (name, ..) in CLI_COMMANDS {
+        assert!(text.contains(&format!("\n  {name}")), "{name} missing");
+    }
+    let Ok(CommandResult::Help(text)) = run(&["estimatefee", "--help"]) else {
+        panic!("expected help");
+    };
+    assert!(text.starts_with("Usage: estimatefee <vsize> <sat/vB>"));
+    assert_eq!(
+        run(&["help", "balance"]).unwrap().to_text(),
+        cli_help(Some("balance")).unwrap()
+    );
+    assert!(
+        CommandResult::Help(text)
+            .to_json()
+            .starts_with("{\"help\": \"Usage:")
+    );
+
+    // Malformed input
+    let code = |list: &[&str]| match parse_cli_args(&args(list)) {
+        Err(BitcoinError::CliError { code, .. }) => code,
+        other => panic!("expected a CLI error, got {other:?}"),
+    };
+    assert_eq!(
+        code(&["decode"]),
+        ParseErrorCode::MissingArgument { name: "raw_hex" }
+    );
+    assert_eq!(
+        code(&["decode", "00", "11"]),
+        ParseErrorCode::InvalidArgument
+    );
+    assert_eq!(
+        code(&["estimatefee", "250"]),
+        ParseErrorCode::MissingArgument { name: "rate" }
+    );
+    assert_eq!(
+        code(&["estimatefee", "big", "4"]),
+        ParseErrorCode::InvalidArgument
+    );
+    assert_eq!(
+        code(&["estimatefee", "250", "-1"]),
+        ParseErrorCode::InvalidAmount
+    );
+    assert_eq!(
+        code(&["createtx", "--locktime=soon"]),
+        ParseErrorCode::InvalidArgument
+    );
+    assert_eq!(
+        code(&["createtx", "--locktime"]),
+        ParseErrorCode::MissingArgument { name: "value" }
+    );
+    assert_eq!(code(&["help", "fly"]), ParseErrorCode::UnknownCommand);
+    assert_eq!(code(&["fly", "--help"]), ParseErrorCode::UnknownCommand);
+}
+
+#[test]
+fn test_private_key_wif_and_public_key_scripts() {
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    let key = PrivateKey::new(one, Network::Mainnet).unwrap();
+    assert_eq!(
+        key.to_wif(),
+        "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"
+    );
+    assert_eq!(PrivateKey::from_wif(&key.to_wif()).unwrap(), key);
+    assert!(!format!("{key:?}").contains("secret"));
+
+    let pubkey = key.public_key();
+    assert_eq!(
+        hex::encode(&pubkey.to_bytes()),
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+    );
+    assert_eq!(PublicKey::from_slice(&pubkey.to_bytes()).unwrap(), pubkey);
+    assert_eq!(
+        pubkey.p2wpkh_address().unwrap().to_string(),
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+    );
+    assert!(pubkey.p2wpkh_script_pubkey().unwrap().is_p2wpkh());
+    assert!(pubkey.p2pkh_script_pubkey().is_p2pkh());
+
+    // Uncompressed keys have no trailing flag and their own hash
+    let uncompressed =
+        PrivateKey::from_wif("5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf").unwrap();
+    assert!(!uncompressed.compressed);
+    assert_eq!(uncompressed.secret_bytes(), one);
+    let pubkey = uncompressed.public_key();
+    assert_eq!(pubkey.to_bytes().len(), 65);
+    assert_eq!(PublicKey::from_slice(&pubkey.to_bytes()).unwrap(), pubkey);
+    assert_eq!(
+        pubkey.p2pkh_address().to_string(),
+        "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm"
+    );
+    assert!(matches!(
+        pubkey.p2wpkh_address(),
+        Err(BitcoinError::InvalidKey)
+    ));
+
+    let testnet = PrivateKey::new(one, Network::Regtest).unwrap();
+    assert_eq!(
+        PrivateKey::from_wif(&testnet.to_wif()).unwrap().network,
+        Network::Testnet
+    );
+    assert!(PrivateKey::new([0; 32], Network::Mainnet).is_err());
+    assert!(PrivateKey::from_wif("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWm").is_err());
+    let bad_flag = base58::encode_check(&[&[0x80][..], &one, &[0x02]].concat());
+    assert!(matches!(
+        PrivateKey::from_wif(&bad_flag),
+        Err(BitcoinError::InvalidKey)
+    ));
+    let mut off_field = [0xff; 33];
+    off_field[0] = 0x02;
+    assert!(PublicKey::from_slice(&off_field).is_err());
+    assert!(PublicKey::from_slice(&[0x02; 32]).is_err());
+}
+
+#[test]
+fn test_sigops_limit() {
+    // Each bare OP_CHECKMULTISIG counts as 20 legacy sigops
+    let output = |count: usize| {
+        TxOutput::new(
+            Amount::from_sat(0),
+            Script(vec![Opcode::OpCheckMultiSig.to_u8(); count]),
+        )
+    };
+    let tx = |count| {
+        LegacyTransaction::builder()
+            .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+            .add_output(output(count))
+            .build()
+    };
+
+    let at_limit = tx(1_000);
+    assert_eq!(at_limit.legacy_sigops(), 20_000);
+    assert!(at_limit.check_sigops_limit().is_ok());
+    assert!(matches!(
+        tx(1_001).check_sigops_limit(),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+
+    assert_eq!(block::block_sigops_total(&[]), 0);
+    assert_eq!(block::block_sigops_total(&[at_limit, tx(1)]), 20_020);
+}
+
+#[test]
+fn test_arc_legacy_transaction_shares_clones() {
+    let tx = two_output_transaction();
+    let shared = tx.clone().into_arc();
+    let copy = shared.clone();
+
+    assert_eq!(copy.as_transaction(), &tx);
+    assert_eq!(copy.txid(), tx.txid());
+    assert_eq!(copy.outputs().len(), 2);
+    assert!(std::ptr::eq(shared.as_transaction(), copy.as_transaction()));
+
+    drop(shared);
+    assert_eq!(copy.into_transaction(), tx);
+}
+
+#[test]
+fn test_fee_rate_from_target_blocks() {
+    assert_eq!(FeeRate::from_target_blocks(0), FeeRate::from_sat_per_vb(50));
+    assert_eq!(FeeRate::from_target_blocks(1), FeeRate::from_sat_per_vb(50));
+    assert_eq!(FeeRate::from_target_blocks(2), FeeRate::from_sat_per_vb(50));
+    assert_eq!(FeeRate::from_target_blocks(3), FeeRate::from_sat_per_vb(20));
+    assert_eq!(FeeRate::from_target_blocks(6), FeeRate::from_sat_per_vb(10));
+    assert_eq!(
+        FeeRate::from_target_blocks(100),
+        FeeRate::from_sat_per_vb(10)
+    );
+    assert_eq!(
+        FeeRate::from_target_blocks(144),
+        FeeRate::from_sat_per_vb(2)
+    );
+    assert_eq!(
+        FeeRate::from_target_blocks(1_008),
+        FeeRate::from_sat_per_vb(2)
+    );
+}
+
+#[test]
+fn test_estimate_command() {
+    let run = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        parse_cli_args(&args)?.execute()
+    };
+    let vsize = TransactionReader::new(&hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap()[..])
+        .read_transaction()
+        .unwrap()
+        .vsize();
+
+    let fee = |result: CommandResult| match result {
+        CommandResult::Fee { sat } => sat,
+        other => panic!("expected a fee, got {other:?}"),
+    };
+    assert_eq!(
+        fee(run(&["estimate", FIRST_BITCOIN_TRANSFER_TX_HEX]).unwrap()),
+        10 * vsize
+    );
+    let args = [
+        "estimate",
+        FIRST_BITCOIN_TRANSFER_TX_HEX,
+        "--target-blocks",
+        "1",
+    ];
+    assert_eq!(fee(run(&args).unwrap()), 50 * vsize);
+    let args = [
+        "estimate",
+        FIRST_BITCOIN_TRANSFER_TX_HEX,
+        "--fee-rate",
+        "3",
+        "--target-blocks",
+        "1",
+    ];
+    assert_eq!(fee(run(&args).unwrap()), 3 * vsize);
+
+    assert!(run(&["estimate"]).is_err());
+    assert!(
+        run(&[
+            "estimate",
+            FIRST_BITCOIN_TRANSFER_TX_HEX,
+            "--fee-rate",
+            "fast"
+        ])
+        .is_err()
+    );
+    assert!(run(&["estimate", FIRST_BITCOIN_TRANSFER_TX_HEX, "--target-blocks"]).is_err());
+}
+
+#[test]
+fn test_input_value_from_prevouts() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_input(TxInput::from_outpoint(funding_outpoint(2)))
+        .build();
+    let prevouts = [
+        TxOutput::new(Amount::from_sat(10_000), Script(vec![0x51])),
+        TxOutput::new(Amount::from_sat(2_500), Script(vec![0x51])),
+    ];
+    assert_eq!(
+        tx.input_value_from_prevouts(&prevouts).unwrap(),
+        Amount::from_sat(12_500)
+    );
+
+    assert!(matches!(
+        tx.input_value_from_prevouts(&prevouts[..1]),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+    let overflowing = [
+        TxOutput::new(Amount::from_sat(u64::MAX), Script::default()),
+        TxOutput::new(Amount::from_sat(1), Script::default()),
+    ];
+    assert!(matches!(
+        tx.input_value_from_prevouts(&overflowing),
+        Err(BitcoinError::InvalidAmount)
+    ));
+}
+
+#[test]
+fn test_script_bytes_accessors() {
+    let script = Script(vec![0x51, 0x52]);
+    assert_eq!(script.bytes(), &[0x51, 0x52]);
+    assert!(script == vec![0x51, 0x52]);
+    assert!(script == [0x51, 0x52][..]);
+    assert!(script != vec![0x51]);
+    assert_eq!(script.into_bytes(), vec![0x51, 0x52]);
+    assert!(Script::default().bytes().is_empty());
+}
+
+#[test]
+fn test_cli_error_codes() {
+    let code = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        match parse_cli_args(&args).and_then(|command| command.execute()) {
+            Err(BitcoinError::CliError { code, .. }) => code,
+            other => panic!("expected a CLI error, got {other:?}"),
+        }
+    };
+
+    assert_eq!(code(&[]), ParseErrorCode::MissingCommand);
+    assert_eq!(code(&["frobnicate"]), ParseErrorCode::UnknownCommand);
+    assert_eq!(
+        code(&["send", "100"]),
+        ParseErrorCode::MissingArgument { name: "address" }
+    );
+    assert_eq!(
+        code(&["send", "lots", "addr"]),
+        ParseErrorCode::InvalidAmount
+    );
+    assert_eq!(code(&["inspect", "zz", "txid"]), ParseErrorCode::InvalidHex);
+    assert_eq!(
+        code(&["genkey", "--network", "moon"]),
+        ParseErrorCode::InvalidNetwork
+    );
+    assert_eq!(
+        code(&["genkey", "--testnet"]),
+        ParseErrorCode::InvalidArgument
+    );
+}
+
+#[test]
+fn test_script_hex_and_asm_round_trips() {
+    let hex = "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac";
+    let script = Script::from_hex(hex).unwrap();
+    assert!(script.is_p2pkh());
+    assert_eq!(script.to_hex(), hex);
+    assert_eq!(Script::from_hex(&hex.to_uppercase()).unwrap(), script);
+
+    let asm = script.to_asm();
+    assert_eq!(
+        asm,
+        "OP_DUP OP_HASH160 1d0f172a0ecb48aee1be1f2687d2963ae33f71a1 OP_EQUALVERIFY OP_CHECKSIG"
+    );
+    assert_eq!(Script::from_asm(&asm).unwrap(), script);
+
+    assert!(Script::from_hex("abc").is_err());
+    assert!(Script::from_hex("zz").is_err());
+    assert!(Script::from_asm("OP_BOGUS").is_err());
+    assert_eq!(Script::from_hex("").unwrap(), Script::default());
+}
+
+#[test]
+fn test_validator_segwit_rules() {
+    let p2wpkh = Script([vec![0x00, 0x14], vec![0x22; 20]].concat());
+    let mut utxos = UtxoSet::new();
+    utxos.insert(
+        funding_outpoint(1),
+        TxOutput::new(Amount::from_sat(100_000), p2wpkh.clone()),
+    );
+    let segwit = SegWitTransaction {
+        version: 2,
+        inputs: vec![TxInput::from_outpoint(funding_outpoint(1))],
+        outputs: vec![TxOutput::new(Amount::from_sat(90_000), p2wpkh)],
+        witness: vec![vec![vec![0x30; 72], vec![0x02; 33]]],
+        lock_time: LockTime::ZERO,
+    };
+    let validator = TransactionValidator::new_segwit();
+    let tx = Transaction::from(segwit.clone());
+    assert!(validator.validate_transaction(&tx, &utxos).is_empty());
+
+    // A native SegWit input without a witness (another input keeps the
+    // transaction in the SegWit format)
+    let mut missing = segwit.clone();
+    missing
+        .inputs
+        .push(TxInput::from_outpoint(funding_outpoint(2)));
+    missing.witness = vec![vec![], vec![vec![0x51]]];
+    let issues = validator.validate_transaction(&missing.into(), &utxos);
+    assert_eq!(issues.len(), 1, "{issues:?}");
+    assert!(
+        issues[0]
+            .to_string()
+            .contains("input 0 spends a witness program")
+    );
+
+    // Oversized items, too many stacks, and no witness data at all
+    let mut oversized = segwit.clone();
+    oversized.witness[0].push(vec![0; 3601]);
+    let issues = validator.validate_transaction(&oversized.into(), &utxos);
+    assert!(issues[0].to_string().contains("oversized witness item"));
+    let mut extra = segwit.clone();
+    extra.witness.push(vec![vec![0x01]]);
+    let issues = validator.validate_transaction(&extra.into(), &utxos);
+    assert!(
+        issues[0]
+            .to_string()
+            .contains("2 witness stacks for 1 inputs")
+    );
+
+    // Witness stacks that don't line up with the inputs misstate the weight
+    let discount = TransactionValidator::new().require_witness_discount();
+    assert!(discount.validate_transaction(&tx, &utxos).is_empty());
+    let mut extra = segwit.clone();
+    extra.witness.push(vec![vec![0x01]]);
+    let issues = discount.validate_transaction(&extra.into(), &utxos);
+    assert_eq!(issues.len(), 1, "{issues:?}");
+    assert!(
+        issues[0]
+            .to_string()
+            .contains("does not cover the witness data")
+    );
+    let mut short = segwit.clone();
+    short
+        .inputs
+        .push(TxInput::from_outpoint(funding_outpoint(2)));
+    assert_eq!(
+        discount.validate_transaction(&short.into(), &utxos).len(),
+        1
+    );
+
+    let mut empty = segwit.clone();
+    empty.witness = vec![vec![]];
+    let issues = validator.validate_transaction(&empty.into(), &utxos);
+    assert!(
+        issues
+            .iter()
+            .any(|issue| issue.to_string().contains("without witness data"))
+    );
+
+    // Legacy transactions only get the legacy rules, from either constructor
+    let legacy = Transaction::from(
+        LegacyTransaction::builder()
+            .version(2)
+            .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+            .add_output(TxOutput::new(
+                Amount::from_sat(90_000),
+                Script(vec![0x51; 25]),
+            ))
+            .build(),
+    );
+    let nonstandard = TransactionValidator::new_legacy().validate_transaction(&legacy, &utxos);
+    assert_eq!(nonstandard.len(), 1);
+    assert_eq!(
+        validator.validate_transaction(&legacy, &utxos).len(),
+        nonstandard.len()
+    );
+    assert!(
+        TransactionValidator::new_legacy()
+            .validate_transaction(&tx, &utxos)
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_witness_builder() {
+    let sig = vec![0x30; 72];
+    let pubkey = vec![0x02; 33];
+    let stack = WitnessBuilder::new()
+        .push_sig_and_pubkey(sig.clone(), pubkey.clone())
+        .try_build()
+        .unwrap();
+    assert_eq!(stack, vec![sig.clone(), pubkey]);
+
+    // P2WSH: the witness script goes on top and may exceed 520 bytes
+    let script = Script(vec![0x51; 600]);
+    let stack = WitnessBuilder::new()
+        .push(Vec::new())
+        .push(sig)
+        .push_script(&script)
+        .try_build()
+        .unwrap();
+    assert_eq!(stack.len(), 3);
+    assert_eq!(stack[2], script.0);
+
+    let oversized = WitnessBuilder::new().push(vec![0; 521]);
+    assert_eq!(oversized.clone().build().len(), 1);
+    assert!(matches!(
+        oversized.try_build(),
+        Err(BitcoinError::InvalidScript)
+    ));
+    assert!(
+        WitnessBuilder::new()
+            .push_script(&Script(vec![0x51; 10_001]))
+            .try_build()
+            .is_err()
+    );
+    let tall = (0..1001).fold(WitnessBuilder::new(), |builder, _| builder.push(vec![0x01]));
+    assert!(tall.try_build().is_err());
+    assert!(WitnessBuilder::new().try_build().unwrap().is_empty());
+}
+
+#[test]
+fn test_number_parse_errors_convert() {
+    fn parse_vout(s: &str) -> Result<u32, BitcoinError> {
+        Ok(s.parse::<u32>()?)
+    }
+    fn parse_rate(s: &str) -> Result<f64, BitcoinError> {
+        Ok(s.parse::<f64>()?)
+    }
+
+    assert_eq!(parse_vout("7").unwrap(), 7);
+    assert!(matches!(
+        parse_vout("-1"),
+        Err(BitcoinError::CliError {
+            code: ParseErrorCode::InvalidArgument,
+            ..
+        })
+    ));
+    assert_eq!(parse_rate("2.5").unwrap(), 2.5);
+    assert_eq!(
+        parse_rate("fast").unwrap_err().to_string(),
+        "Parse error: invalid float literal"
+    );
+
+    // FromStr on OutPoint now reports the integer error directly
+    let txid = FIRST_BITCOIN_TRANSFER_TXID;
+    let err = format!("{txid}:x").parse::<OutPoint>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Parse error: invalid digit found in string"
+    );
+}
+
+#[test]
+fn test_json_schema_describes_to_json() {
+    let schema: serde_json::Value = serde_json::from_str(LegacyTransaction::json_schema()).unwrap();
+    assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+
+    // Every required key is present in the output, and nothing else is
+    let tx = LegacyTransaction::deserialize(&hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap())
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_str(&tx.to_json()).unwrap();
+    let keys = |value: &serde_json::Value| {
+        let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+    let required = |schema: &serde_json::Value| {
+        let mut keys: Vec<String> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| key.as_str().unwrap().to_string())
+            .collect();
+        keys.sort();
+        keys
+    };
+    let properties = &schema["properties"];
+    assert_eq!(keys(&json), required(&schema));
+    assert_eq!(
+        keys(&json["inputs"][0]),
+        required(&properties["inputs"]["items"])
+    );
+    assert_eq!(
+        keys(&json["outputs"][0]),
+        required(&properties["outputs"]["items"])
+    );
+}
+
+#[test]
+fn test_nonce_grind() {
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(10_000), Script(vec![0x51])))
+        .add_output(TxOutput::new(Amount::ZERO, Script(vec![0x6a])))
+        .build();
+
+    let ground = tx.nonce_grind(&[0xab, 0xc0], 1).unwrap();
+    // Checked against a txid computed from scratch, not the patched bytes
+    let txid = DecodedTransaction::from(ground.clone()).txid;
+    assert!(txid.starts_with("abc0"), "{txid}");
+    assert_eq!(ground.outputs()[0], tx.outputs()[0]);
+    let nonce_script = ground.outputs()[1].script_pubkey();
+    assert!(nonce_script.is_op_return());
+    assert_eq!(nonce_script.len(), 10);
+
+    assert_eq!(
+        tx.nonce_grind(&[], 1).unwrap().outputs()[1]
+            .script_pubkey()
+            .bytes()[2..],
+        [0; 8]
+    );
+    assert!(matches!(
+        tx.nonce_grind(&[0x00], 0),
+        Err(BitcoinError::InvalidTransaction)
+    ));
+    assert!(tx.nonce_grind(&[0x00], 2).is_err());
+    assert!(tx.nonce_grind(&[0x00; 33], 1).is_err());
+}
+
+#[test]
+fn test_p2wsh_multisig() {
+    let pubkeys = vec![vec![0x02; 33], vec![0x03; 33], vec![0x04; 65]];
+    let witness_script = Script::multisig_witness_script(2, &pubkeys).unwrap();
+    assert_eq!(witness_script.multisig_params(), Some((2, 3)));
+    assert_eq!(witness_script.bytes()[0], 0x52);
+    assert_eq!(
+        witness_script.bytes()[witness_script.len() - 2..],
+        [0x53, 0xae]
+    );
+
+    let output = Script::p2wsh_multisig_output(2, &pubkeys).unwrap();
+    assert_eq!(output.value(), Amount::ZERO);
+    assert!(output.script_pubkey().is_p2wsh());
+    assert_eq!(
+        output.script_pubkey(),
+        &Script::p2wsh_wrapped(&witness_script)
+    );
+
+    let sigs = vec![vec![0x30; 71], vec![0x30; 72]];
+    let witness = Script::multisig_spend_witness(&sigs, &witness_script);
+    assert_eq!(witness.len(), 4);
+    assert!(witness[0].is_empty());
+    assert_eq!(witness[1..3], sigs[..]);
+    assert_eq!(witness[3], witness_script.bytes());
+
+    assert!(Script::multisig_witness_script(0, &pubkeys).is_err());
+    assert!(Script::multisig_witness_script(4, &pubkeys).is_err());
+    assert!(Script::multisig_witness_script(1, &[vec![0x02; 32]]).is_err());
+    assert!(Script::multisig_witness_script(1, &vec![vec![0x02; 33]; 17]).is_err());
+}
+
+#[test]
+fn test_txid_sequence_and_lock_time_newtypes() {
+    let txid_hex = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+    let txid: Txid = txid_hex.parse().unwrap();
+    assert_eq!(txid.0[0], 0x16);
+    assert_eq!(txid.to_string(), txid_hex);
+    assert_eq!(Txid::from(txid.to_byte_array()), txid);
+    assert!("f418".parse::<Txid>().is_err());
+
+    assert!(Sequence::MAX.is_final());
+    assert!(!Sequence::MAX.signals_rbf());
+    assert!(Sequence::ENABLE_LOCKTIME.enables_lock_time());
+    assert!(!Sequence::ENABLE_LOCKTIME.signals_rbf());
+    assert!(Sequence::ENABLE_RBF.signals_rbf());
+    assert!(!Sequence::ENABLE_RBF.is_relative_lock_time());
+    assert_eq!(Sequence::default(), 0xFFFF_FFFF);
+
+    let blocks = Sequence::from_height(144);
+    assert!(blocks.is_height_locked());
+    assert_eq!(blocks.relative_lock_value(), Some(144));
+    let time = Sequence::from_512_second_intervals(10);
+    assert!(time.is_time_locked());
+    assert_eq!(time.to_consensus_u32(), (1 << 22) | 10);
+
+    let height = LockTime::from(800_000);
+    assert!(height.is_block_height());
+    assert!(height.is_satisfied_by(800_001, 0));
+    assert!(!height.is_satisfied_by(800_000, 0));
+    let timestamp = LockTime(1_700_000_000);
+    assert!(timestamp.is_block_time());
+    assert!(timestamp.is_satisfied_by(0, 1_700_000_001));
+
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::new(
+            OutPoint { txid, vout: 0 },
+            Script::default(),
+            Sequence::ENABLE_RBF,
+        ))
+        .lock_time(height)
+        .build();
+    assert!(tx.is_rbf_replaceable());
+    assert_eq!(tx.lock_time(), 800_000);
+    assert_eq!(tx.inputs()[0].outpoint().txid, txid);
+
+    let script: ScriptBuf = "0014abcd".parse().unwrap();
+    assert_eq!(format!("{script:x}"), "0014abcd");
+}
+
+#[test]
+fn test_transaction_weight_vsize_and_standardness() {
+    let p2wpkh = Script([vec![0x00, 0x14], vec![0x22; 20]].concat());
+    let legacy = LegacyTransaction::builder()
+        .version(2)
+        .add_input(TxInput::new(
+            funding_outpoint(1),
+            Script(vec![0x01, 0x01]),
+            Sequence::ENABLE_RBF,
+        ))
+        .add_output(TxOutput::new(Amount::from_sat(50_000), p2wpkh.clone()))
+        .build();
+    assert_eq!(legacy.weight(), legacy.serialize().len() as u64 * 4);
+    assert_eq!(legacy.vsize(), legacy.serialize().len() as u64);
+    assert!(legacy.is_standard());
+
+    let segwit = SegWitTransaction {
+        version: 2,
+        inputs: vec![TxInput::from_outpoint(funding_outpoint(2))],
+        outputs: vec![TxOutput::new(Amount::from_sat(50_000), p2wpkh.clone())],
+        witness: vec![vec![vec![0x30; 72], vec![0x02; 33]]],
+        lock_time: LockTime::ZERO,
+    };
+    let tx = Transaction::from(segwit.clone());
+    assert_eq!(tx.weight(), segwit.weight());
+    assert_eq!(tx.vsize(), segwit.weight().div_ceil(4));
+    assert!(tx.vsize() < segwit.serialize().len() as u64);
+    assert!(tx.is_standard());
+
+    // Dust, a non-push scriptSig and an oversized witness item each fail
+    let dust = LegacyTransactionBuilder::from_transaction(&legacy)
+        .add_output(TxOutput::new(Amount::from_sat(10), p2wpkh))
+        .build();
+    assert!(matches!(
+        dust.check_standard(),
+        Err(BitcoinError::DustOutput { .. })
+    ));
+    let mut non_push = legacy.clone();
+    non_push.inputs_mut()[0].set_script_sig(Script(vec![0x76]));
+    assert!(!non_push.is_standard());
+    let mut oversized = segwit;
+    oversized.witness[0].push(vec![0x00; 4_000]);
+    assert!(!Transaction::from(oversized).is_standard());
+
+    // The builder's validation applies the same policy
+    assert!(matches!(
+        LegacyTransactionBuilder::from_transaction(&dust).build_validated(),
+        Err(BitcoinError::DustOutput { .. })
+    ));
+}
+
+#[test]
+fn test_p2p_message_round_trips() {
+    use rust_week_4_exercises::p2p::{Inventory, InventoryType, VersionMessage};
+
+    let verack = RawNetworkMessage::new(Network::Mainnet, NetworkMessage::Verack);
+    assert_eq!(
+        hex::encode(&verack.serialize()),
+        "f9beb4d976657261636b000000000000000000005df6e0e2"
+    );
+
+    let peer = "127.0.0.1:8333".parse().unwrap();
+    let version = VersionMessage::new(peer, 1_700_000_000, 42);
+    assert_eq!(version.receiver.socket_addr(), peer);
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(Amount::from_sat(50_000), Script(vec![0x51])))
+        .build();
+    let messages = [
+        NetworkMessage::Version(version.clone()),
+        NetworkMessage::Ping(7),
+        NetworkMessage::Pong(7),
+        NetworkMessage::Inv(vec![Inventory::tx(tx.txid()), Inventory::block([0x33; 32])]),
+        NetworkMessage::Tx(tx.clone().into()),
+    ];
+    // Several messages back to back, as they arrive on a stream
+    let mut stream = Vec::new();
+    for message in &messages {
+        RawNetworkMessage::new(Network::Regtest, message.clone())
+            .serialize_into(&mut stream)
+            .unwrap();
+    }
+    let mut reader = &stream[..];
+    let commands: Vec<&str> = (0..messages.len())
+        .map(|_| {
+            let message = RawNetworkMessage::deserialize_from(&mut reader).unwrap();
+            assert_eq!(message.network(), Some(Network::Regtest));
+            match &message.payload {
+                NetworkMessage::Version(decoded) => assert_eq!(decoded, &version),
+                NetworkMessage::Inv(inventory) => {
+                    assert_eq!(inventory[0], Inventory::tx(tx.txid()));
+                    assert_eq!(inventory[1].inv_type, InventoryType::Block);
+                }
+                NetworkMessage::Tx(decoded) => assert_eq!(decoded.txid(), tx.txid()),
+                _ => {}
+            }
+            message.command()
+        })
+        .collect();
+    assert!(reader.is_empty());
+    assert_eq!(commands, ["version", "ping", "pong", "inv", "tx"]);
+    assert_eq!(
+        p2p::tx_message(Network::Regtest, &tx).unwrap(),
+        RawNetworkMessage::new(Network::Regtest, NetworkMessage::Tx(tx.into())).serialize()
+    );
+
+    // A corrupted payload fails the checksum; unknown commands are rejected
+    let mut corrupt = verack.serialize();
+    corrupt.push(0x00);
+    corrupt[16] = 1;
+    assert!(matches!(
+        RawNetworkMessage::deserialize(&corrupt),
+        Err(BitcoinError::InvalidMessage(_))
+    ));
+    let unknown = p2p::frame(Network::Mainnet, "getaddr", &[]).unwrap();
+    assert!(RawNetworkMessage::deserialize(&unknown).is_err());
+}
+
+#[test]
+fn test_wallet_utxo_tracking_and_persistence() {
+    let ours = Address::P2WPKH([0x11; 20]);
+    let theirs = Address::P2WPKH([0x22; 20]);
+    let watched = std::collections::HashSet::from([ours.script_pubkey()]);
+
+    // Only the output paying a watched script is tracked
+    let funding = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(100_000),
+            ours.script_pubkey(),
+        ))
+        .add_output(TxOutput::new(
+            Amount::from_sat(5_000),
+            theirs.script_pubkey(),
+        ))
+        .build();
+    let mut wallet = UtxoSet::new();
+    assert!(
+        wallet
+            .apply_transaction(&funding, &watched, None)
+            .is_empty()
+    );
+    assert_eq!(wallet.len(), 1);
+    assert_eq!(wallet.balance(), Amount::from_sat(100_000));
+    assert_eq!(wallet.confirmed_balance(), Amount::ZERO);
+    assert_eq!(wallet.confirm(Txid(funding.txid()), 800_000), 1);
+    assert_eq!(wallet.confirmed_balance(), Amount::from_sat(100_000));
+
+    // Round trip through a file
+    let path = std::env::temp_dir().join(format!("utxo-set-{}.bin", std::process::id()));
+    wallet.save(&path).unwrap();
+    assert_eq!(UtxoSet::load(&path).unwrap(), wallet);
+    assert!(matches!(
+        UtxoSet::deserialize(b"nope\x01\x00"),
+        Err(BitcoinError::InvalidWalletFile)
+    ));
+
+    // The CLI operates on the saved set: send spends the confirmed coin and
+    // leaves unconfirmed change, so the confirmed balance drops to zero
+    let run = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        CliCommand::try_from(&args[..])
+            .unwrap()
+            .execute_with_wallet_file(&path)
+            .unwrap()
+    };
+    assert_eq!(run(&["balance"]).to_text(), "0.00100000 BTC (100000 sat)");
+    let recipient = theirs.to_string_for_network(Network::Mainnet);
+    let CommandResult::TransactionHex(raw) = run(&["send", "30000", &recipient]) else {
+        panic!("send should return a transaction");
+    };
+    let tx = LegacyTransaction::deserialize(&hex::decode(&raw).unwrap()).unwrap();
+    assert_eq!(tx.inputs().len(), 1);
+    assert!(tx.is_rbf_replaceable());
+    let saved = UtxoSet::load(&path).unwrap();
+    assert_eq!(saved.confirmed_balance(), Amount::ZERO);
+    assert!(saved.balance() > Amount::ZERO && saved.balance() < Amount::from_sat(70_000));
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        CliCommand::Balance.execute(),
+        Err(BitcoinError::CliError {
+            code: ParseErrorCode::WalletRequired,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_taproot_spend_info_and_p2tr_outputs() {
+    use taproot::{TapBranch, TapLeaf, TapTree, TaprootSpendInfo};
+    let bytes32 = |s: &str| -> [u8; 32] { hex::decode(s).unwrap().try_into().unwrap() };
+
+    // BIP-341 scriptPubKey vector 0: key-path only
+    let internal = bytes32("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+    let info = TaprootSpendInfo::key_path_only(internal).unwrap();
+    assert_eq!(info.merkle_root(), None);
+    assert_eq!(
+        info.script_pubkey().to_hex(),
+        "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+    );
+    assert_eq!(
+        info.address().to_string_for_network(Network::Mainnet),
+        "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5"
+    );
+
+    // Vector 1: a single tapscript leaf
+    let internal = bytes32("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+    let leaf = TapLeaf::new(
+        Script::from_hex("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+            .unwrap(),
+    );
+    let script_pubkey =
+        taproot::p2tr_script_pubkey(&internal, std::slice::from_ref(&leaf)).unwrap();
+    assert_eq!(
+        script_pubkey.to_hex(),
+        "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+    );
+    assert_eq!(
+        script_pubkey.p2tr_output_key(),
+        Some(bytes32(
+            "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+        ))
+    );
+    assert_eq!(Script(vec![0x51]).p2tr_output_key(), None);
+
+    // Control blocks agree with building them from an explicit proof
+    let leaves = [
+        leaf.clone(),
+        TapLeaf::new(Script(vec![0x51])),
+        TapLeaf::new(Script(vec![0x52])),
+    ];
+    let info = TaprootSpendInfo::new(internal, &TapTree::from_leaves(&leaves).unwrap()).unwrap();
+    assert_eq!(
+        info.merkle_root(),
+        Some(taproot::taproot_merkle_root(&leaves))
+    );
+    assert_eq!(info.merkle_proof(&leaves[0]).unwrap().len(), 2);
+    assert_eq!(
+        info.merkle_proof(&leaves[2]).unwrap(),
+        [TapBranch {
+            left: leaves[0].hash(),
+            right: leaves[1].hash()
+        }
+        .hash()]
+    );
+
+    // An unbalanced tree (a, (b, c)): `a` sits one level from the root
+    let [a, b, c] = leaves.clone();
+    let tree = TapTree::branch(
+        TapTree::Leaf(a.clone()),
+        TapTree::branch(TapTree::Leaf(b.clone()), TapTree::Leaf(c.clone())),
+    );
+    let unbalanced = TaprootSpendInfo::new(internal, &tree).unwrap();
+    let bc = TapBranch {
+        left: b.hash(),
+        right: c.hash(),
+    }
+    .hash();
+    assert_eq!(
+        unbalanced.merkle_root(),
+        Some(
+            TapBranch {
+                left: a.hash(),
+                right: bc
+            }
+            .hash()
+        )
+    );
+    assert_eq!(unbalanced.merkle_proof(&a).unwrap(), [bc]);
+    assert_eq!(unbalanced.merkle_proof(&c).unwrap(), [b.hash(), a.hash()]);
+    assert_eq!(unbalanced.leaves().collect::<Vec<_>>(), [&a, &b, &c]);
+    for info in [&info, &unbalanced] {
+        for leaf in info.leaves() {
+            let proof = info.merkle_proof(leaf).unwrap().to_vec();
+            assert_eq!(
+                info.control_block(leaf).unwrap(),
+                taproot::control_block(&internal, leaf, proof).unwrap()
+            );
+        }
+    }
+    assert!(
+        info.control_block(&TapLeaf::new(Script(vec![0x53])))
+            .is_none()
+    );
+    assert!(
+        TxOutput::new(Amount::from_sat(1_000), info.script_pubkey()).script_type()
+            == OutputScriptType::P2TR
+    );
+}
+
+#[test]
+fn test_hex_conversions_with_offsets() {
+    let tx = two_output_transaction();
+    let raw = tx.to_hex();
+    assert_eq!(LegacyTransaction::from_hex(&raw).unwrap(), tx);
+    assert_eq!(
+        LegacyTransaction::from_hex(&raw.to_uppercase()).unwrap(),
+        tx
+    );
+    // Trailing bytes are not part of the transaction
+    assert!(LegacyTransaction::from_hex(&format!("{raw}00")).is_err());
+
+    let segwit = sample_segwit_transaction();
+    assert_eq!(
+        SegWitTransaction::from_hex(&segwit.to_hex()).unwrap(),
+        segwit
+    );
+    let any = Transaction::from_hex(&segwit.to_hex()).unwrap();
+    assert_eq!(any.to_hex(), segwit.to_hex());
+
+    let txid = Txid(tx.txid());
+    assert_eq!(txid.to_hex(), tx.txid_hex());
+    assert_eq!(Txid::from_hex(&tx.txid_hex()).unwrap(), txid);
+    let script = Script::from_hex("0014abcd").unwrap();
+    assert_eq!(script.to_hex(), "0014abcd");
+
+    let message = |result: Result<LegacyTransaction, BitcoinError>| match result {
+        Err(BitcoinError::CliError {
+            code: ParseErrorCode::InvalidHex,
+            message,
+        }) => message,
+        other => panic!("expected a hex error, got {other:?}"),
+    };
+    assert_eq!(
+        message(LegacyTransaction::from_hex("0100zz00")),
+        "Invalid hex character 'z' at offset 4"
+    );
+    assert_eq!(
+        message(LegacyTransaction::from_hex("01€0")),
+        "Invalid hex character '€' at offset 2"
+    );
+    assert_eq!(
+        message(LegacyTransaction::from_hex("010")),
+        "Hex string has odd length 3"
+    );
+    assert_eq!(hex::decode_array::<2>("beef").unwrap(), [0xbe, 0xef]);
+    assert!(hex::decode_array::<2>("be").is_err());
+}
+
+#[test]
+fn test_script_classification_and_descriptors() {
+    let hash20 = [0x11; 20];
+    let hash32 = [0x22; 32];
+    let cases = [
+        (Script::new_p2pkh(&hash20), OutputScriptType::P2PKH),
+        (Script::new_p2sh(&hash20), OutputScriptType::P2SH),
+        (Script::new_p2wpkh(&hash20), OutputScriptType::P2WPKH),
+        (Script::new_p2wsh(&hash32), OutputScriptType::P2WSH),
+        (Script::new_p2tr(&hash32), OutputScriptType::P2TR),
+        (Script::new_op_return(b"hello"), OutputScriptType::OpReturn),
+        (
+            Script::pay_to_pubkey(&[0x02; 33]).unwrap(),
+            OutputScriptType::P2PK,
+        ),
+        (Script(vec![0x51, 0x52]), OutputScriptType::NonStandard),
+    ];
+    for (script, expected) in cases {
+        assert_eq!(script.classify(), expected, "{}", script.to_asm());
+    }
+    assert_eq!(
+        Address::P2WSH(hash32).script_pubkey(),
+        Script::new_p2wsh(&hash32)
+    );
+    assert_eq!(Script::new_op_return(&[]).bytes(), [0x6a]);
+
+    // Checksums from Bitcoin Core's descriptor documentation
+    let pkh = "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)";
+    assert_eq!(descriptor::checksum(pkh).unwrap(), "8fhd9pwu");
+    let wpkh = "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)#8zl0zxma";
+    let sh_wpkh =
+        "sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))#qkrrc7je";
+
+    let descriptor: Descriptor = pkh.parse().unwrap();
+    let Descriptor::Pkh(key) = &descriptor else {
+        panic!("expected pkh");
+    };
+    assert_eq!(
+        descriptor.script_pubkey().unwrap(),
+        Script::new_p2pkh(&key.pubkey_hash())
+    );
+    assert_eq!(descriptor.to_string(), format!("{pkh}#8fhd9pwu"));
+
+    let descriptor: Descriptor = wpkh.parse().unwrap();
+    assert_eq!(descriptor.to_string(), wpkh);
+    assert_eq!(
+        descriptor.script_pubkey().unwrap().classify(),
+        OutputScriptType::P2WPKH
+    );
+    let descriptor: Descriptor = sh_wpkh.parse().unwrap();
+    let Descriptor::ShWpkh(key) = &descriptor else {
+        panic!("expected sh(wpkh)");
+    };
+    assert_eq!(
+        descriptor.script_pubkey().unwrap(),
+        Script::p2sh_p2wpkh_wrapped(&key.to_bytes()).unwrap()
+    );
+
+    let internal = "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d";
+    let descriptor: Descriptor = format!("tr({internal})").parse().unwrap();
+    assert_eq!(
+        descriptor.script_pubkey().unwrap().to_hex(),
+        "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+    );
+
+    // The descriptor's script pays straight into the builder
+    let tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(TxOutput::new(
+            Amount::from_sat(10_000),
+            descriptor.script_pubkey().unwrap(),
+        ))
+        .build();
+    assert_eq!(tx.outputs()[0].script_type(), OutputScriptType::P2TR);
+
+    assert!(matches!(
+        format!("{pkh}#8fhd9pwq").parse::<Descriptor>(),
+        Err(BitcoinError::InvalidDescriptor(_))
+    ));
+    assert!(
+        "sh(pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5))"
+            .parse::<Descriptor>()
+            .is_err()
+    );
+    assert!("combo(02c6)".parse::<Descriptor>().is_err());
+}
+
+#[cfg(feature = "testutils")]
+#[test]
+fn test_generated_values_roundtrip() {
+    use rust_week_4_exercises::testutils::{Arbitrary, Gen, check_roundtrips, roundtrip};
+
+    check_roundtrips::<OutPoint>(1, 200);
+    check_roundtrips::<TxInput>(2, 200);
+    check_roundtrips::<TxOutput>(3, 200);
+    check_roundtrips::<LegacyTransaction>(4, 200);
+
+    // The same seed replays the same values
+    let a = LegacyTransaction::arbitrary(&mut Gen::new(42));
+    let b = LegacyTransaction::arbitrary(&mut Gen::new(42));
+    assert_eq!(a, b);
+    assert!(!a.inputs().is_empty());
+
+    // Generated legacy transactions also survive the format-detecting parser
+    roundtrip(&Transaction::from(a));
+}
+
+#[test]
+fn test_borrowed_transaction_parsing() {
+    let legacy = two_output_transaction();
+    let raw = legacy.serialize();
+    let tx = TransactionRef::parse(&raw).unwrap();
+    assert!(!tx.is_segwit());
+    assert_eq!(tx.outputs().len(), 2);
+    assert_eq!(tx.outputs()[0].value(), Amount::from_sat(1_000));
+    assert_eq!(tx.to_owned(), Transaction::Legacy(legacy.clone()));
+
+    // Scripts point into the caller's buffer rather than a copy
+    let range = raw.as_ptr_range();
+    assert!(range.contains(&tx.outputs()[0].script_pubkey().as_ptr()));
+
+    let segwit = sample_segwit_transaction();
+    let raw_segwit = segwit.serialize();
+    let tx = TransactionRef::parse(&raw_segwit).unwrap();
+    assert!(tx.is_segwit());
+    assert_eq!(tx.witness(0).len(), segwit.witness[0].len());
+    assert!(tx.witness(9).is_empty());
+    assert_eq!(tx.to_owned(), Transaction::SegWit(segwit));
+
+    // Consecutive transactions are walked by offset
+    let mut both = raw.clone();
+    both.extend_from_slice(&raw_segwit);
+    let (first, used) = TransactionRef::parse_prefix(&both).unwrap();
+    assert_eq!(first.to_owned(), Transaction::Legacy(legacy));
+    assert!(TransactionRef::parse(&both[used..]).unwrap().is_segwit());
+
+    assert!(TransactionRef::parse(&both).is_err());
+    assert!(TransactionRef::parse(&raw[..raw.len() - 1]).is_err());
+}
+
+#[test]
+fn test_byte_cursor() {
+    let data = [
+        0x01, 0x00, 0x00, 0x00, // u32 1
+        0xFD, 0x03, 0x00, // non-canonical CompactSize 3
+        0x02, 0xAA, 0xBB, // two var bytes
+    ];
+    let mut cursor = ByteCursor::new(&data);
+    assert_eq!(cursor.read_u32_le().unwrap(), 1);
+    assert!(matches!(
+        cursor.clone().read_varint(),
+        Err(BitcoinError::NonCanonicalCompactSize { offset: 4 })
+    ));
+    cursor.read_bytes(3).unwrap();
+    assert_eq!(cursor.read_var_bytes().unwrap(), &[0xAA, 0xBB]);
+    assert!(cursor.is_empty());
+    assert!(matches!(
+        cursor.read_u64_le(),
+        Err(BitcoinError::UnexpectedEnd {
+            offset: 10,
+            needed: 8
+        })
+    ));
+
+    // A length prefix running past the end reports where the bytes were due
+    let mut cursor = ByteCursor::new(&[0x05, 0x01]);
+    assert!(matches!(
+        cursor.read_var_bytes(),
+        Err(BitcoinError::UnexpectedEnd {
+            offset: 1,
+            needed: 5
+        })
+    ));
+
+    let output = TxOutput::new(Amount::from_sat(1000), Script(vec![0x51]));
+    let raw = [funding_outpoint(1).serialize(), output.serialize()].concat();
+    let mut cursor = ByteCursor::new(&raw);
+    assert_eq!(cursor.read_outpoint().unwrap(), funding_outpoint(1));
+    assert_eq!(cursor.read_tx_output().unwrap(), output);
+    assert!(cursor.is_empty());
+
+    let raw = two_output_transaction().serialize();
+    assert!(matches!(
+        TransactionRef::parse(&raw[..raw.len() - 2]),
+        Err(BitcoinError::UnexpectedEnd { offset, needed: 4 }) if offset == raw.len() - 4
+    ));
+}
+
+#[test]
+fn test_field_and_curve_arithmetic() {
+    use rust_week_4_exercises::secp256k1::{CurvePoint, FieldElement, N, P, U256};
+
+    let minus_one = FieldElement::new(P.overflowing_sub(U256::ONE).0);
+    assert_eq!(minus_one + FieldElement::ONE, FieldElement::ZERO);
+    assert_eq!(-FieldElement::ONE, minus_one);
+    assert_eq!(FieldElement::new(P), FieldElement::ZERO);
+    let seven = FieldElement::from_u64(7);
+    assert_eq!(seven * seven.inverse().unwrap(), FieldElement::ONE);
+    assert_eq!(FieldElement::ZERO.inverse(), None);
+
+    let g = CurvePoint::GENERATOR;
+    let scalar = |k: u64| U256([k, 0, 0, 0]);
+    let x_hex = |point: CurvePoint| hex::encode(&point.coordinates().unwrap().x.to_be_bytes());
+
+    assert_eq!(g.double(), g + g);
+    assert_eq!(g * scalar(2), g + g);
+    assert_eq!(
+        x_hex(g * scalar(2)),
+        "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+    );
+    assert_eq!(
+        hex::encode(&(g * scalar(2)).coordinates().unwrap().y.to_be_bytes()),
+        "1ae168fea63dc339a3c58419466ceaeef7f632653266d0e1236431a950cfe52a"
+    );
+    assert_eq!(
+        x_hex(g * scalar(3)),
+        "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+    );
+    assert_eq!(g * scalar(3), g.double() + g);
+
+    // n * G is the point at infinity, and (n - 1) * G is -G
+    assert!((g * N).is_infinity());
+    assert_eq!(g * N.overflowing_sub(U256::ONE).0, -g);
+    assert!((g + -g).is_infinity());
+    assert_eq!(g + CurvePoint::Infinity, g);
+
+    let coords = g.coordinates().unwrap();
+    assert!(CurvePoint::new(coords.x, coords.y).is_ok());
+    assert!(CurvePoint::new(coords.x, coords.x).is_err());
+}
+
+#[test]
+fn test_replace_by_fee() {
+    let mut utxos = UtxoSet::new();
+    utxos.insert(
+        funding_outpoint(1),
+        TxOutput::new(Amount::from_sat(100_000), Script::new_p2pkh(&[0x11; 20])),
+    );
+    let payment = TxOutput::new(Amount::from_sat(50_000), Script::new_p2pkh(&[0x22; 20]));
+    let original = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .enable_rbf()
+        .add_output(payment.clone())
+        .add_output(TxOutput::new(
+            Amount::from_sat(49_000),
+            Script::new_p2pkh(&[0x33; 20]),
+        ))
+        .build();
+    assert!(original.inputs()[0].signals_rbf());
+    assert!(original.is_replaceable());
+
+    let old_rate = original.fee_rate(&utxos).unwrap();
+    let new_rate = FeeRate::from_sat_per_vb(20);
+    let replacement = LegacyTransactionBuilder::replace_by_fee(&original, &utxos, new_rate, 1)
+        .unwrap()
+        .build();
+    assert_eq!(replacement.outputs()[0], payment);
+    assert_eq!(replacement.inputs()[0].outpoint(), &funding_outpoint(1));
+    assert!(replacement.is_replaceable());
+    assert!(replacement.fee_rate(&utxos).unwrap() >= new_rate);
+    assert!(replacement.fee_rate(&utxos).unwrap() > old_rate);
+    assert_eq!(
+        replacement.absolute_fee(&utxos).unwrap(),
+        new_rate.fee_for_vsize(original.vsize())
+    );
+
+    // The fee rate has to go up
+    assert!(matches!(
+        LegacyTransactionBuilder::replace_by_fee(&original, &utxos, old_rate, 1),
+        Err(BitcoinError::ReplacementRejected(_))
+    ));
+    // The change output has to cover the bump
+    assert!(matches!(
+        LegacyTransactionBuilder::replace_by_fee(
+            &original,
+            &utxos,
+            FeeRate::from_sat_per_vb(1_000),
+            1
+        ),
+        Err(BitcoinError::InsufficientFunds { .. })
+    ));
+
+    let final_tx = LegacyTransaction::builder()
+        .add_input(TxInput::from_outpoint(funding_outpoint(1)))
+        .add_output(payment)
+        .build();
+    assert!(!final_tx.is_replaceable());
+    assert!(matches!(
+        LegacyTransactionBuilder::replace_by_fee(&final_tx, &utxos, new_rate, 0),
+        Err(BitcoinError::ReplacementRejected(_))
+    ));
+}
+
+#[test]
+fn test_network_params() {
+    for network in Network::ALL {
+        let params = network.params();
+        assert_eq!(params.network, network);
+        assert_eq!(network.magic(), params.magic);
+        assert_eq!(Network::from_magic(params.magic), Some(network));
+    }
+    assert_eq!(Network::Mainnet.default_port(), 8333);
+    assert_eq!(Network::Testnet.default_port(), 18333);
+    assert_eq!(Network::Signet.default_port(), 38333);
+    assert_eq!(Network::Regtest.default_port(), 18444);
+    assert_eq!(Params::SIGNET.bech32_hrp, "tb");
+    assert_eq!(Params::REGTEST.bech32_hrp, "bcrt");
+
+    // The same key and address encode per network
+    let params = Network::Testnet.params();
+    let key = PrivateKey::new(secret_key(1), Network::Testnet).unwrap();
+    assert_eq!(
+        base58::decode_check(&key.to_wif()).unwrap()[0],
+        params.wif_prefix
+    );
+    let address = Address::P2PKH([0x11; 20]);
+    let encoded = address.to_string_for_network(Network::Testnet);
+    assert_eq!(
+        base58::decode_check(&encoded).unwrap()[0],
+        params.p2pkh_prefix
+    );
+    assert!(
+        Address::P2WPKH([0x11; 20])
+            .to_string_for_network(Network::Regtest)
+            .starts_with("bcrt1")
+    );
+
+    let message = RawNetworkMessage::new(Network::Signet, NetworkMessage::Verack);
+    assert_eq!(message.serialize()[..4], Params::SIGNET.magic);
+}
+
+#[test]
+fn test_parse_and_summarize_block_transactions() {
+    use rust_week_4_exercises::block::{parse_transactions, summarize_transactions};
+
+    let txs = vec![
+        LegacyTransaction::deserialize(&hex::decode(FIRST_BITCOIN_TRANSFER_TX_HEX).unwrap())
+            .unwrap(),
+        two_output_transaction(),
+    ];
+    let data: Vec<u8> = txs.iter().flat_map(|tx| tx.serialize()).collect();
+    assert_eq!(parse_transactions(&data, 2).unwrap(), txs);
+    assert!(parse_transactions(&data, 1).is_err());
+    assert!(parse_transactions(&data, 3).is_err());
+
+    let summaries = summarize_transactions(&txs);
+    assert_eq!(summaries[0].txid.to_string(), txs[0].txid_hex());
+    assert_eq!(
+        summaries[0].output_types,
+        vec![OutputScriptType::P2PK, OutputScriptType::P2PK]
+    );
+    assert_eq!(summaries[1].output_types.len(), 2);
+
+    #[cfg(feature = "parallel")]
+    {
+        let many: Vec<_> = txs.iter().cycle().take(101).cloned().collect();
+        assert_eq!(
+            rust_week_4_exercises::block::par_summarize_transactions(&many),
+            summarize_transactions(&many)
+        );
+    }
+}
+
+// Answer `replies.len()` HTTP requests in order, passing each request body back
+#[cfg(feature = "rpc")]
+fn fake_rpc_node(replies: Vec<String>) -> (String, std::sync::mpsc::Receiver<String>) {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/wallet/test", listener.local_addr().unwrap());
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for reply in replies {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            // The body is one JSON object, complete once its braces balance
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            sender.send(String::from_utf8(request).unwrap()).unwrap();
+            let status = if reply.contains("\"error\":null") {
+                "200 OK"
+            } else {
+                "500 Internal Server Error"
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\n\r\n{reply}",
+                reply.len()
+            )
+            .unwrap();
+        }
+    });
+    (url, receiver)
+}
+
+#[cfg(feature = "rpc")]
+#[test]
+fn test_rpc_client_commands() {
+    use rust_week_4_exercises::rpc::{self, Auth, RpcClient};
+
+    let tx = two_output_transaction();
+    let txid = tx.txid_hex();
+    let raw_hex = hex::encode(&tx.serialize());
+    let (url, requests) = fake_rpc_node(vec![
+        r#"{"result":1.5,"error":null,"id":1}"#.to_string(),
+        format!(r#"{{"result":"{txid}","error":null,"id":2}}"#),
+        format!(r#"{{"result":"{raw_hex}","error":null,"id":3}}"#),
+        r#"{"result":{"feerate":0.00012,"blocks":6},"error":null,"id":4}"#.to_string(),
+        r#"{"result":null,"error":{"code":-26,"message":"txn-mempool-conflict"},"id":5}"#
+            .to_string(),
+    ]);
+    let auth = Auth::UserPass {
+        user: "alice".to_string(),
+        password: "secret".to_string(),
+    };
+    let client = RpcClient::new(&url, auth).unwrap();
+
+    let result = rpc::execute(&CliCommand::Balance, &client).unwrap();
+    assert_eq!(
+        result.to_text(),
+        CommandResult::balance(150_000_000).to_text()
+    );
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /wallet/test HTTP/1.0\r\n"));
+    // base64("alice:secret")
+    assert!(request.contains("Authorization: Basic YWxpY2U6c2VjcmV0\r\n"));
+    assert!(request.contains(r#""method":"getbalance""#));
+
+    let broadcast = CliCommand::Broadcast {
+        raw_hex,
+        network: Network::Regtest,
+    };
+    assert!(matches!(
+        rpc::execute(&broadcast, &client).unwrap(),
+        CommandResult::Field { value, .. } if value == txid
+    ));
+    assert!(requests.recv().unwrap().contains("sendrawtransaction"));
+
+    let fetched = client.get_raw_transaction(&txid.parse().unwrap()).unwrap();
+    assert_eq!(fetched, Transaction::Legacy(tx.clone()));
+    assert!(
+        requests
+            .recv()
+            .unwrap()
+            .contains(&format!(r#"["{txid}",false]"#))
+    );
+
+    assert_eq!(
+        client.estimate_smart_fee(6).unwrap(),
+        Some(FeeRate::from_sat_per_kvb(12_000))
+    );
+    requests.recv().unwrap();
+
+    assert!(matches!(
+        client.send_raw_transaction(&tx),
+        Err(BitcoinError::RpcError { code: -26, .. })
+    ));
+
+    assert!(RpcClient::new("https://localhost", Auth::None).is_err());
+}